@@ -1,40 +1,45 @@
-use crate::utils::dag_nodes::ipfs_dag_put_node_async;
+use crate::utils::journal::Journal;
+use crate::utils::metrics::Metrics;
+use crate::utils::status::DaemonStatus;
 
-use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::sync::mpsc::Receiver;
 
-use ipfs_api::IpfsClient;
+use serde::{Deserialize, Serialize};
 
+use linked_data::ipfs::{IpfsBackend, IpfsError};
 use linked_data::video::{DayNode, HourNode, MinuteNode, SecondNode, TimecodeNode};
 
 use cid::Cid;
 
+#[derive(Debug)]
 pub enum Archive {
     Chat(Cid),
-    Video(Cid),
+    /// Video node and its duration in seconds.
+    Video(Cid, f64),
     Finalize,
 }
 
-pub struct Archivist {
-    ipfs: IpfsClient,
-
-    archive_rx: UnboundedReceiver<Archive>,
-
+/// Timecode nodes being built, saved to the journal.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ArchiveState {
     video_chat_buffer: Option<SecondNode>,
 
+    /// End of the buffered video node, in seconds since the start of the stream.
+    #[serde(default)]
+    timestamp: f64,
+
     minute_node: MinuteNode,
     hour_node: HourNode,
     day_node: DayNode,
 }
 
-impl Archivist {
-    pub fn new(ipfs: IpfsClient, archive_rx: UnboundedReceiver<Archive>) -> Self {
+impl Default for ArchiveState {
+    fn default() -> Self {
         Self {
-            ipfs,
-
-            archive_rx,
-
             video_chat_buffer: None,
 
+            timestamp: 0.0,
+
             minute_node: MinuteNode {
                 links_to_seconds: Vec::with_capacity(60),
             },
@@ -48,66 +53,23 @@ impl Archivist {
             },
         }
     }
+}
 
-    pub async fn start(&mut self) {
-        println!("✅ Archive System Online");
-
-        while let Some(event) = self.archive_rx.recv().await {
-            match event {
-                Archive::Chat(cid) => self.archive_chat_message(cid),
-                Archive::Video(cid) => self.archive_video_segment(cid).await,
-                Archive::Finalize => self.finalize().await,
-            }
-        }
-
-        println!("❌ Archive System Offline");
-    }
-
-    /// Link chat message to SecondNodes.
-    fn archive_chat_message(&mut self, msg_cid: Cid) {
-        let node = match self.video_chat_buffer.as_mut() {
-            Some(node) => node,
-            None => return,
-        };
-
-        node.links_to_chat.push(msg_cid.into());
-    }
-
-    /// Buffers SecondNodes, waiting for chat messages to be linked.
-    async fn archive_video_segment(&mut self, cid: Cid) {
-        let second_node = SecondNode {
-            link_to_video: cid.into(),
-            links_to_chat: Vec::with_capacity(5),
-        };
-
-        let node = self.video_chat_buffer.take();
-
-        self.video_chat_buffer = Some(second_node);
-
-        let node = match node {
-            Some(node) => node,
-            None => return,
-        };
-
-        self.collect_second(node).await;
-
-        if self.minute_node.links_to_seconds.len() < 60 {
-            return;
-        }
-
-        self.collect_minute().await;
-
-        if self.hour_node.links_to_minutes.len() < 60 {
-            return;
-        }
-
-        self.collect_hour().await;
+impl ArchiveState {
+    /// Number of seconds already linked in the timecode tree.
+    fn linked_seconds(&self) -> usize {
+        self.day_node.links_to_hours.len() * 3600
+            + self.hour_node.links_to_minutes.len() * 60
+            + self.minute_node.links_to_seconds.len()
     }
 
     /// Create DAG node containing a link to video segment and all chat messages.
-    /// MinuteNode is then appended with the CID.
-    async fn collect_second(&mut self, node: SecondNode) {
-        let cid = match ipfs_dag_put_node_async(&self.ipfs, &node).await {
+    /// MinuteNode is then appended with the CID until the timeline reaches the end second.
+    async fn collect_second<I>(&mut self, ipfs: &I, metrics: &Metrics, node: SecondNode, end: usize)
+    where
+        I: IpfsBackend,
+    {
+        let cid = match metrics.time_dag_put(ipfs.dag_put(&node)).await {
             Ok(cid) => cid,
             Err(e) => {
                 eprintln!("❗ IPFS: dag put failed {}", e);
@@ -115,12 +77,29 @@ impl Archivist {
             }
         };
 
-        self.minute_node.links_to_seconds.push(cid.into());
+        while self.linked_seconds() < end {
+            self.minute_node.links_to_seconds.push(cid.into());
+
+            if self.minute_node.links_to_seconds.len() < 60 {
+                continue;
+            }
+
+            self.collect_minute(ipfs, metrics).await;
+
+            if self.hour_node.links_to_minutes.len() < 60 {
+                continue;
+            }
+
+            self.collect_hour(ipfs, metrics).await;
+        }
     }
 
     /// Create DAG node containing 60 SecondNode links. HourNode is then appended with the CID.
-    async fn collect_minute(&mut self) {
-        let cid = match ipfs_dag_put_node_async(&self.ipfs, &self.minute_node).await {
+    async fn collect_minute<I>(&mut self, ipfs: &I, metrics: &Metrics)
+    where
+        I: IpfsBackend,
+    {
+        let cid = match metrics.time_dag_put(ipfs.dag_put(&self.minute_node)).await {
             Ok(cid) => cid,
             Err(e) => {
                 eprintln!("❗ IPFS: dag put failed {}", e);
@@ -134,8 +113,11 @@ impl Archivist {
     }
 
     /// Create DAG node containing 60 MinuteNode links. DayNode is then appended with the CID.
-    async fn collect_hour(&mut self) {
-        let cid = match ipfs_dag_put_node_async(&self.ipfs, &self.hour_node).await {
+    async fn collect_hour<I>(&mut self, ipfs: &I, metrics: &Metrics)
+    where
+        I: IpfsBackend,
+    {
+        let cid = match metrics.time_dag_put(ipfs.dag_put(&self.hour_node)).await {
             Ok(cid) => cid,
             Err(e) => {
                 eprintln!("❗ IPFS: dag put failed {}", e);
@@ -148,54 +130,399 @@ impl Archivist {
         self.day_node.links_to_hours.push(cid.into());
     }
 
-    /// Create all remaining DAG nodes then pin and print the final CID.
-    async fn finalize(&mut self) {
-        self.archive_rx.close();
-
+    /// Create all remaining DAG nodes then pin and return the final CID, if any nodes.
+    pub async fn finalize<I>(
+        &mut self,
+        ipfs: &I,
+        metrics: &Metrics,
+    ) -> Result<Option<Cid>, IpfsError>
+    where
+        I: IpfsBackend,
+    {
         println!("Collecting Nodes...");
 
         if let Some(node) = self.video_chat_buffer.take() {
-            self.collect_second(node).await;
+            // The last node is always linked, however short.
+            let end = (self.timestamp.round() as usize).max(self.linked_seconds() + 1);
+
+            self.collect_second(ipfs, metrics, node, end).await;
         }
 
         if !self.minute_node.links_to_seconds.is_empty() {
-            self.collect_minute().await;
+            self.collect_minute(ipfs, metrics).await;
         }
 
         if !self.hour_node.links_to_minutes.is_empty() {
-            self.collect_hour().await;
+            self.collect_hour(ipfs, metrics).await;
         }
 
         if self.day_node.links_to_hours.is_empty() {
-            println!("0 Nodes Found");
-            return;
+            return Ok(None);
         }
 
-        let cid = match ipfs_dag_put_node_async(&self.ipfs, &self.day_node).await {
-            Ok(cid) => cid,
-            Err(e) => {
-                eprintln!("❗ IPFS: dag put failed {}", e);
-                return;
-            }
-        };
+        let cid = metrics.time_dag_put(ipfs.dag_put(&self.day_node)).await?;
 
         let stream = TimecodeNode {
             timecode: cid.into(),
         };
 
-        let cid = match ipfs_dag_put_node_async(&self.ipfs, &stream).await {
-            Ok(cid) => cid,
+        let cid = metrics.time_dag_put(ipfs.dag_put(&stream)).await?;
+
+        println!("Pinning Nodes...");
+
+        ipfs.pin_add(cid, true).await?;
+
+        Ok(Some(cid))
+    }
+}
+
+pub struct Archivist<I>
+where
+    I: IpfsBackend,
+{
+    ipfs: I,
+
+    archive_rx: Receiver<Archive>,
+
+    state: ArchiveState,
+
+    journal: Option<Journal>,
+
+    metrics: Metrics,
+    status: DaemonStatus,
+}
+
+impl<I> Archivist<I>
+where
+    I: IpfsBackend,
+{
+    /// Start from the state of a previous session to continue its timecode tree.
+    pub fn new(
+        ipfs: I,
+        archive_rx: Receiver<Archive>,
+        state: ArchiveState,
+        journal: Option<Journal>,
+        metrics: Metrics,
+        status: DaemonStatus,
+    ) -> Self {
+        Self {
+            ipfs,
+
+            archive_rx,
+
+            state,
+
+            journal,
+
+            metrics,
+            status,
+        }
+    }
+
+    pub async fn start(&mut self) {
+        println!("✅ Archive System Online");
+
+        while let Some(event) = self.archive_rx.recv().await {
+            match event {
+                Archive::Chat(cid) => self.archive_chat_message(cid),
+                Archive::Video(cid, duration) => {
+                    self.archive_video_segment(cid, duration).await;
+
+                    let seconds = self.state.linked_seconds();
+                    self.status
+                        .update(|status| status.archived_seconds = Some(seconds));
+
+                    // Chat linked to the buffered second is lost on crash, no write per message.
+                    self.save_journal().await;
+                }
+                Archive::Finalize => {
+                    self.finalize().await;
+                    break;
+                }
+            }
+        }
+
+        println!("❌ Archive System Offline");
+    }
+
+    /// Link chat message to SecondNodes.
+    fn archive_chat_message(&mut self, msg_cid: Cid) {
+        let node = match self.state.video_chat_buffer.as_mut() {
+            Some(node) => node,
+            None => return,
+        };
+
+        node.links_to_chat.push(msg_cid.into());
+
+        self.metrics.chat_message_archived();
+    }
+
+    /// Buffers SecondNodes, waiting for chat messages to be linked.
+    /// The previous node fills every second of the timeline it covers.
+    async fn archive_video_segment(&mut self, cid: Cid, duration: f64) {
+        let mut second_node = SecondNode {
+            link_to_video: cid.into(),
+            links_to_chat: Vec::with_capacity(5),
+        };
+
+        let node = self.state.video_chat_buffer.take();
+
+        let end = self.state.timestamp.round() as usize;
+
+        self.state.timestamp += duration;
+
+        if let Some(mut node) = node {
+            if end > self.state.linked_seconds() {
+                self.state
+                    .collect_second(&self.ipfs, &self.metrics, node, end)
+                    .await;
+            } else {
+                // Shorter than a second, the chat goes to the next node.
+                second_node.links_to_chat.append(&mut node.links_to_chat);
+            }
+        }
+
+        self.state.video_chat_buffer = Some(second_node);
+    }
+
+    /// Create all remaining DAG nodes then pin and print the final CID.
+    /// The journal is kept if it failed.
+    async fn finalize(&mut self) {
+        self.archive_rx.close();
+
+        match self.state.finalize(&self.ipfs, &self.metrics).await {
+            Ok(Some(cid)) => println!("Final Timecode-addressable Node => {}", &cid.to_string()),
+            Ok(None) => println!("0 Nodes Found"),
             Err(e) => {
-                eprintln!("❗ IPFS: dag put failed {}", e);
+                eprintln!("❗ IPFS: {}", e);
                 return;
             }
-        };
+        }
 
-        println!("Pinning Nodes...");
+        if let Some(journal) = self.journal.as_ref() {
+            if let Err(e) = journal.remove().await {
+                eprintln!("❗ Journal: remove failed {}", e);
+            }
+        }
+    }
+
+    async fn save_journal(&self) {
+        if let Some(journal) = self.journal.as_ref() {
+            if let Err(e) = journal.save(&self.state).await {
+                eprintln!("❗ Journal: save failed {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::utils::config::QueueConfig;
+    use crate::utils::queues::QueueMetrics;
+
+    use linked_data::ipfs::MemoryIpfs;
+    use linked_data::IPLDLink;
+
+    use tokio::sync::mpsc::channel;
+
+    #[tokio::test]
+    async fn finalize_links_video_and_chat() {
+        let ipfs = MemoryIpfs::new();
 
-        match self.ipfs.pin_add(&cid.to_string(), true).await {
-            Ok(_) => println!("Final Timecode-addressable Node => {}", &cid.to_string()),
-            Err(e) => eprintln!("❗ IPFS: pin add failed {}", e),
+        let (archive_tx, archive_rx) = channel(32);
+
+        let mut archivist = Archivist::new(
+            ipfs.clone(),
+            archive_rx,
+            ArchiveState::default(),
+            None,
+            Metrics::default(),
+            DaemonStatus::default(),
+        );
+
+        let mut segments = Vec::with_capacity(3);
+
+        for i in 0..3u8 {
+            let cid = ipfs.add(vec![i]).await.unwrap();
+            segments.push(cid);
+
+            archive_tx.try_send(Archive::Video(cid, 1.0)).unwrap();
         }
+
+        let chat = ipfs.dag_put("Hello").await.unwrap();
+
+        archive_tx.try_send(Archive::Chat(chat)).unwrap();
+        archive_tx.try_send(Archive::Finalize).unwrap();
+
+        archivist.start().await;
+
+        let pins = ipfs.pin_ls().await.unwrap();
+
+        assert_eq!(pins.len(), 1);
+
+        let minute: MinuteNode = ipfs
+            .dag_get(pins[0], Some("/time/hour/0/minute/0"))
+            .await
+            .unwrap();
+
+        assert_eq!(minute.links_to_seconds.len(), 3);
+
+        let second: SecondNode = ipfs
+            .dag_get(pins[0], Some("/time/hour/0/minute/0/second/2"))
+            .await
+            .unwrap();
+
+        assert_eq!(second.link_to_video.link, segments[2]);
+        assert_eq!(second.links_to_chat, vec![IPLDLink::from(chat)]);
+    }
+
+    #[tokio::test]
+    async fn resume_from_journal() {
+        let ipfs = MemoryIpfs::new();
+
+        let path =
+            std::env::temp_dir().join(format!("defluencer-resume-{}.json", std::process::id()));
+        let journal = Journal::new(&path);
+        journal.remove().await.unwrap();
+
+        // First session dies without finalizing.
+        let (archive_tx, archive_rx) = channel(32);
+
+        let mut archivist = Archivist::new(
+            ipfs.clone(),
+            archive_rx,
+            ArchiveState::default(),
+            Some(journal.clone()),
+            Metrics::default(),
+            DaemonStatus::default(),
+        );
+
+        for i in 0..2u8 {
+            let cid = ipfs.add(vec![i]).await.unwrap();
+
+            archive_tx.try_send(Archive::Video(cid, 1.0)).unwrap();
+        }
+
+        let msg = ipfs.add(b"chat".to_vec()).await.unwrap();
+
+        archive_tx.try_send(Archive::Chat(msg)).unwrap();
+
+        drop(archive_tx);
+
+        archivist.start().await;
+
+        assert!(journal.exists());
+        assert!(ipfs.pin_ls().await.unwrap().is_empty());
+
+        // Second session continues the same tree.
+        let state: ArchiveState = journal.load().await.unwrap();
+
+        assert_eq!(state.minute_node.links_to_seconds.len(), 1);
+
+        // Chat messages alone do not rewrite the journal.
+        let buffer = state.video_chat_buffer.as_ref().unwrap();
+
+        assert!(buffer.links_to_chat.is_empty());
+
+        let (archive_tx, archive_rx) = channel(32);
+
+        let mut archivist = Archivist::new(
+            ipfs.clone(),
+            archive_rx,
+            state,
+            Some(journal.clone()),
+            Metrics::default(),
+            DaemonStatus::default(),
+        );
+
+        let cid = ipfs.add(vec![2]).await.unwrap();
+
+        archive_tx.try_send(Archive::Video(cid, 1.0)).unwrap();
+        archive_tx.try_send(Archive::Finalize).unwrap();
+
+        archivist.start().await;
+
+        assert!(!journal.exists());
+
+        let pins = ipfs.pin_ls().await.unwrap();
+
+        let minute: MinuteNode = ipfs
+            .dag_get(pins[0], Some("/time/hour/0/minute/0"))
+            .await
+            .unwrap();
+
+        assert_eq!(minute.links_to_seconds.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn place_segments_by_timestamp() {
+        let ipfs = MemoryIpfs::new();
+
+        let (archive_tx, archive_rx) = channel(32);
+
+        let metrics = Metrics::default();
+
+        let mut archivist = Archivist::new(
+            ipfs.clone(),
+            archive_rx,
+            ArchiveState::default(),
+            None,
+            metrics.clone(),
+            DaemonStatus::default(),
+        );
+
+        // 16 segments of 4 seconds then a short one.
+        let mut segments = Vec::with_capacity(17);
+
+        for i in 0..17u8 {
+            let cid = ipfs.add(vec![i]).await.unwrap();
+            segments.push(cid);
+
+            let duration = if i < 16 { 4.0 } else { 0.4 };
+
+            archive_tx.try_send(Archive::Video(cid, duration)).unwrap();
+        }
+
+        archive_tx.try_send(Archive::Finalize).unwrap();
+
+        archivist.start().await;
+
+        let pins = ipfs.pin_ls().await.unwrap();
+
+        let hour: HourNode = ipfs.dag_get(pins[0], Some("/time/hour/0")).await.unwrap();
+
+        assert_eq!(hour.links_to_minutes.len(), 2);
+
+        let minute: MinuteNode = ipfs
+            .dag_get(pins[0], Some("/time/hour/0/minute/1"))
+            .await
+            .unwrap();
+
+        assert_eq!(minute.links_to_seconds.len(), 5);
+
+        for &(path, segment) in [
+            ("/time/hour/0/minute/0/second/0", 0),
+            ("/time/hour/0/minute/0/second/3", 0),
+            ("/time/hour/0/minute/0/second/4", 1),
+            ("/time/hour/0/minute/0/second/59", 14),
+            ("/time/hour/0/minute/1/second/3", 15),
+            ("/time/hour/0/minute/1/second/4", 16),
+        ]
+        .iter()
+        {
+            let second: SecondNode = ipfs.dag_get(pins[0], Some(path)).await.unwrap();
+
+            assert_eq!(second.link_to_video.link, segments[segment], "{}", path);
+        }
+
+        // 17 seconds, 2 minutes, an hour, a day and the timecode node.
+        let (tx, _rx) = channel::<()>(1);
+        let report = QueueMetrics::new(QueueConfig::default()).report::<_, _, ()>(&tx, &tx, None);
+
+        assert!(metrics
+            .render(&report)
+            .contains("defluencer_dag_put_seconds_count 22\n"));
     }
 }
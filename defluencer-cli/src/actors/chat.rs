@@ -1,44 +1,81 @@
 use crate::actors::archivist::Archive;
-use crate::cli::moderation::{BANS_KEY, MODS_KEY};
+use crate::cli::moderation::{BANS_KEY, FILTERS_KEY, MODS_KEY};
 use crate::utils::config::ChatConfig;
-use crate::utils::dag_nodes::{
-    get_from_ipns, ipfs_dag_get_node_async, ipfs_dag_put_node_async, update_ipns,
-};
+use crate::utils::dag_nodes::{get_from_ipns, update_ipns};
+use crate::utils::metrics::Metrics;
+use crate::utils::queues::QueueMetrics;
+use crate::utils::shutdown::Shutdown;
+use crate::utils::status::{ChatStatus, DaemonStatus};
 
-use tokio::sync::mpsc::UnboundedSender;
-use tokio_stream::StreamExt;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use ipfs_api::response::{Error, PubsubSubResponse};
-use ipfs_api::IpfsClient;
+use futures_util::StreamExt;
 
-use linked_data::chat::{ChatId, Message, MessageType};
-use linked_data::moderation::{Ban, Bans, ChatModerationCache, Moderators};
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::mpsc::{Receiver, Sender};
+
+use linked_data::chat::{ArchivedMessage, ChatId, Message, MessageType};
+use linked_data::filter::{Blocklist, ChatFilter};
+use linked_data::ipfs::{IpfsBackend, IpfsError, PubSubMessage};
+use linked_data::moderation::{Ban, Bans, ChatModerationCache, Moderator, Moderators, Timeout};
 use linked_data::signature::SignedMessage;
 use linked_data::PeerId;
 
-pub struct ChatAggregator {
-    ipfs: IpfsClient,
+/// Changes requested through the control API.
+#[derive(Debug)]
+pub enum ChatCommand {
+    /// Archive chat messages or only moderate.
+    Archive(bool),
+
+    /// Subscribe to this topic instead.
+    Topic(String),
+}
+
+pub struct ChatAggregator<I>
+where
+    I: IpfsBackend,
+{
+    ipfs: I,
+
+    archive_tx: Sender<Archive>,
+    archiving: bool,
 
-    archive_tx: UnboundedSender<Archive>,
+    control_rx: Receiver<ChatCommand>,
+    shutdown: Shutdown,
+
+    queues: QueueMetrics,
+    metrics: Metrics,
+    status: DaemonStatus,
 
     mod_db: ChatModerationCache,
 
+    filter: ChatFilter,
+
     topic: String,
 
     bans: Bans,
-
-    new_ban_count: usize,
+    bans_updated: bool,
 
     mods: Moderators,
+    mods_updated: bool,
 }
 
-impl ChatAggregator {
+impl<I> ChatAggregator<I>
+where
+    I: IpfsBackend + Clone,
+{
+    #[allow(clippy::too_many_arguments)]
     pub async fn new(
-        ipfs: IpfsClient,
-        archive_tx: UnboundedSender<Archive>,
+        ipfs: I,
+        archive_tx: Sender<Archive>,
+        control_rx: Receiver<ChatCommand>,
+        shutdown: Shutdown,
+        queues: QueueMetrics,
+        metrics: Metrics,
+        status: DaemonStatus,
         config: ChatConfig,
-    ) -> Result<Self, Error> {
-        let ChatConfig { topic } = config;
+    ) -> Result<Self, IpfsError> {
+        let ChatConfig { topic, slow_mode } = config;
 
         let ((_, mods), (_, bans)) = match tokio::try_join!(
             get_from_ipns(&ipfs, MODS_KEY),
@@ -50,72 +87,117 @@ impl ChatAggregator {
             }
         };
 
+        let filter = match get_from_ipns::<_, Blocklist>(&ipfs, FILTERS_KEY).await {
+            Ok((_, blocklist)) => match ChatFilter::new(&blocklist) {
+                Ok(filter) => filter,
+                Err(e) => {
+                    eprintln!("❗ Invalid Chat Filters. {}", e);
+                    ChatFilter::default()
+                }
+            },
+            Err(e) => {
+                eprintln!("❗ Cannot get Chat Filters. {}", e);
+                ChatFilter::default()
+            }
+        };
+
+        let mut mod_db = ChatModerationCache::new(100, 0);
+        mod_db.set_slow_mode(slow_mode);
+
         Ok(Self {
             ipfs,
 
             archive_tx,
+            archiving: true,
 
-            mod_db: ChatModerationCache::new(100, 0),
+            control_rx,
+            shutdown,
+
+            queues,
+            metrics,
+            status,
+
+            mod_db,
+
+            filter,
 
             topic,
 
             bans,
-
-            new_ban_count: 0,
+            bans_updated: false,
 
             mods,
+            mods_updated: false,
         })
     }
 
     pub async fn start(&mut self) {
-        let mut stream = self.ipfs.pubsub_sub(&self.topic, true);
+        let ipfs = self.ipfs.clone();
+        let mut stream = ipfs.pubsub_sub(&self.topic);
+
+        self.update_status();
 
         println!("✅ Chat System Online");
 
-        while let Some(result) = stream.next().await {
-            if self.archive_tx.is_closed() {
-                //Hacky way to shutdown
-                break;
+        loop {
+            tokio::select! {
+                _ = self.shutdown.wait() => break,
+                Some(command) = self.control_rx.recv() => match command {
+                    ChatCommand::Archive(archiving) => self.archiving = archiving,
+                    ChatCommand::Topic(topic) => {
+                        println!("Chat Topic => {}", topic);
+
+                        stream = ipfs.pubsub_sub(&topic);
+                        self.topic = topic;
+                    }
+                },
+                result = stream.next() => match result {
+                    Some(Ok(response)) => self.on_pubsub_message(response).await,
+                    Some(Err(error)) => eprintln!("{}", error),
+                    None => break,
+                },
             }
 
-            match result {
-                Ok(response) => self.on_pubsub_message(response).await,
-                Err(error) => {
-                    eprintln!("{}", error);
-                    continue;
-                }
-            }
+            self.update_status();
         }
 
-        if self.new_ban_count > 0 {
-            println!(
-                "Updating Banned List with {} New Users 👍",
-                self.new_ban_count
-            );
+        if self.bans_updated {
+            println!("Updating Banned List 👍");
 
             if let Err(e) = update_ipns(&self.ipfs, BANS_KEY, &self.bans).await {
                 eprintln!("❗ IPNS Update Failed. {}", e);
             }
         }
 
+        if self.mods_updated {
+            println!("Updating Moderator List 👍");
+
+            if let Err(e) = update_ipns(&self.ipfs, MODS_KEY, &self.mods).await {
+                eprintln!("❗ IPNS Update Failed. {}", e);
+            }
+        }
+
         println!("❌ Chat System Offline");
     }
 
-    async fn on_pubsub_message(&mut self, msg: PubsubSubResponse) {
-        let peer = match msg.from {
-            Some(from) => from,
-            None => return,
+    fn update_status(&self) {
+        let chat = ChatStatus {
+            topic: self.topic.clone(),
+            archiving: self.archiving,
+            peers: self.mod_db.peer_count(),
+            banned_peers: self.mod_db.banned_count(),
         };
 
-        if self.mod_db.is_banned(&peer) {
+        self.status.update(|status| status.chat = Some(chat));
+    }
+
+    async fn on_pubsub_message(&mut self, msg: PubSubMessage) {
+        let PubSubMessage { from: peer, data } = msg;
+
+        if self.mod_db.is_banned(&peer) || self.mod_db.is_timed_out(&peer, unix_time()) {
             return;
         }
 
-        let data = match msg.data {
-            Some(data) => data,
-            None => return,
-        };
-
         let msg: Message = match serde_json::from_slice(&data) {
             Ok(data) => data,
             Err(e) => {
@@ -132,14 +214,13 @@ impl ChatAggregator {
     }
 
     async fn get_origin(&mut self, peer: PeerId, msg: Message) {
-        let sign_msg: SignedMessage<ChatId> =
-            match ipfs_dag_get_node_async(&self.ipfs, &msg.sig.link.to_string()).await {
-                Ok(msg) => msg,
-                Err(e) => {
-                    eprintln!("❗ IPFS: dag get failed {}", e);
-                    return;
-                }
-            };
+        let sign_msg: SignedMessage<ChatId> = match self.ipfs.dag_get(msg.sig.link, None).await {
+            Ok(msg) => msg,
+            Err(e) => {
+                eprintln!("❗ IPFS: dag get failed {}", e);
+                return;
+            }
+        };
 
         self.mod_db
             .add_peer(&peer, msg.sig.link, sign_msg.address, None);
@@ -164,14 +245,40 @@ impl ChatAggregator {
 
     async fn process_msg(&mut self, peer: &str, msg: Message) {
         match msg.msg {
-            MessageType::Chat(unmsg) => self.mint_and_archive(&unmsg).await,
-            MessageType::Ban(ban) => self.update_bans(peer, ban),
-            MessageType::Mod(_) => {}
+            MessageType::Chat(text) => {
+                if self.mod_db.is_rate_limited(peer, unix_time()) {
+                    return;
+                }
+
+                let text = match self.filter.apply(peer, &text) {
+                    Some(text) => text,
+                    None => return,
+                };
+
+                let msg = Message {
+                    msg: MessageType::Chat(text),
+                    sig: msg.sig,
+                };
+
+                self.mint_and_archive(peer, msg).await
+            }
+            MessageType::Ban(ban) => self.ban(peer, ban),
+            MessageType::Unban(ban) => self.unban(peer, ban),
+            MessageType::Mod(moderator) => self.promote(peer, moderator),
+            MessageType::Unmod(moderator) => self.demote(peer, moderator),
+            MessageType::Timeout(timeout) => self.timeout(peer, timeout),
         }
     }
 
-    async fn mint_and_archive(&mut self, msg: &str) {
-        let cid = match ipfs_dag_put_node_async(&self.ipfs, &msg).await {
+    /// Archive the message with the sender and a link to its signature.
+    async fn mint_and_archive(&mut self, peer: &str, msg: Message) {
+        if !self.archiving {
+            return;
+        }
+
+        let node = ArchivedMessage::create(peer.to_owned(), msg);
+
+        let cid = match self.metrics.time_dag_put(self.ipfs.dag_put(&node)).await {
             Ok(cid) => cid,
             Err(e) => {
                 eprintln!("❗ IPFS: dag put failed {}", e);
@@ -181,21 +288,77 @@ impl ChatAggregator {
 
         let msg = Archive::Chat(cid);
 
-        if let Err(error) = self.archive_tx.send(msg) {
-            eprintln!("❗ Archive receiver hung up. {}", error);
+        // Chat must keep up with pubsub, messages are dropped when the archive is overloaded.
+        match self.archive_tx.try_send(msg) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                self.queues.chat_message_dropped();
+
+                eprintln!("❗ Archive queue full, chat message {} dropped", cid);
+            }
+            Err(error) => eprintln!("❗ Archive receiver hung up. {}", error),
         }
     }
 
-    fn update_bans(&mut self, peer: &str, ban: Ban) {
-        let address = self.mod_db.get_address(peer).unwrap();
+    /// Check that the sender is the beacon owner or a moderator.
+    fn can_moderate(&self, peer: &str) -> bool {
+        match self.mod_db.get_address(peer) {
+            Some(address) => self.mods.can_moderate(address),
+            None => false,
+        }
+    }
 
-        if !self.mods.mods.contains(address) {
+    fn ban(&mut self, peer: &str, ban: Ban) {
+        if !self.can_moderate(peer) {
             return;
         }
 
         self.mod_db.ban_peer(&ban.peer_id);
-        self.bans.banned.insert(ban.address);
 
-        self.new_ban_count += 1;
+        self.metrics.ban_issued();
+
+        self.bans_updated |= self.bans.banned.insert(ban.address);
     }
+
+    fn unban(&mut self, peer: &str, ban: Ban) {
+        if !self.can_moderate(peer) {
+            return;
+        }
+
+        self.mod_db.unban_peer(&ban.peer_id);
+
+        self.bans_updated |= self.bans.banned.remove(&ban.address);
+    }
+
+    fn promote(&mut self, peer: &str, moderator: Moderator) {
+        if !self.can_moderate(peer) {
+            return;
+        }
+
+        self.mods_updated |= self.mods.mods.insert(moderator.moderator);
+    }
+
+    fn demote(&mut self, peer: &str, moderator: Moderator) {
+        if !self.can_moderate(peer) {
+            return;
+        }
+
+        self.mods_updated |= self.mods.mods.remove(&moderator.moderator);
+    }
+
+    fn timeout(&mut self, peer: &str, timeout: Timeout) {
+        if !self.can_moderate(peer) {
+            return;
+        }
+
+        self.mod_db
+            .timeout_peer(&timeout.peer_id, unix_time(), timeout.duration);
+    }
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("SystemTime before UNIX EPOCH!")
+        .as_secs()
 }
@@ -0,0 +1,356 @@
+use crate::cli::comments::COMMENT_INDEX_KEY;
+use crate::cli::content::FEED_KEY;
+use crate::utils::dag_nodes::get_from_ipns;
+
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use futures_util::StreamExt;
+
+use tokio::signal::ctrl_c;
+use tokio::time::timeout;
+
+use linked_data::beacon::Beacon;
+use linked_data::comments::{Comment, CommentIndex};
+use linked_data::feed::{feed_pages, FeedAnchor};
+use linked_data::identity::Identity;
+use linked_data::ipfs::{IpfsBackend, IpfsError};
+use linked_data::signature::SignedMessage;
+
+use cid::Cid;
+
+/// Longest wait for a block or a name announced by anyone.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Shortest wait between two refreshes of your feed.
+const FEED_REFRESH: Duration = Duration::from_secs(60);
+
+/// Collect comments announced on the beacon topic.
+pub struct CommentIndexer<I>
+where
+    I: IpfsBackend,
+{
+    ipfs: I,
+
+    topic: String,
+
+    index_cid: Cid,
+    index: CommentIndex,
+
+    /// Content of your feed, only comments on it are indexed.
+    media: HashSet<Cid>,
+    media_updated: Instant,
+
+    feed_refresh: Duration,
+}
+
+impl<I> CommentIndexer<I>
+where
+    I: IpfsBackend + Clone,
+{
+    pub async fn new(ipfs: I, topic: String) -> Result<Self, IpfsError> {
+        let (index_cid, index) = get_from_ipns(&ipfs, COMMENT_INDEX_KEY).await?;
+
+        let mut indexer = Self {
+            ipfs,
+
+            topic,
+
+            index_cid,
+            index,
+
+            media: HashSet::new(),
+            media_updated: Instant::now(),
+
+            feed_refresh: FEED_REFRESH,
+        };
+
+        indexer.update_feed().await?;
+
+        Ok(indexer)
+    }
+
+    pub async fn start(&mut self) {
+        let ipfs = self.ipfs.clone();
+        let mut stream = ipfs.pubsub_sub(&self.topic);
+
+        let shutdown = ctrl_c();
+        tokio::pin!(shutdown);
+
+        println!("✅ Comment Indexer Online");
+
+        loop {
+            tokio::select! {
+                _ = &mut shutdown => break,
+                result = stream.next() => match result {
+                    Some(Ok(msg)) => self.log_announcement(&msg.data).await,
+                    Some(Err(e)) => eprintln!("{}", e),
+                    None => break,
+                },
+            }
+        }
+
+        println!("❌ Comment Indexer Offline");
+    }
+
+    async fn log_announcement(&mut self, data: &[u8]) {
+        match self.on_announcement(data).await {
+            Ok(Some(cid)) => println!("✅ Indexed Comment {}", cid),
+            Ok(None) => {}
+            Err(e) => eprintln!("❗ Comment Rejected. {}", e),
+        }
+    }
+
+    /// Verify and index an announced comment. Return its CID if new.
+    async fn on_announcement(&mut self, data: &[u8]) -> Result<Option<Cid>, IpfsError> {
+        let cid = std::str::from_utf8(data).map_err(IpfsError::new)?;
+        let cid = Cid::try_from(cid.trim())?;
+
+        if self.index.authors.contains_key(&cid) {
+            return Ok(None);
+        }
+
+        let signed: SignedMessage<Comment> =
+            fetch(FETCH_TIMEOUT, self.ipfs.dag_get(cid, None)).await?;
+
+        if !signed.verify() {
+            return Err(IpfsError::new("Invalid Signature"));
+        }
+
+        let origin = signed.data.origin.link;
+
+        if !self.media.contains(&origin) {
+            // Anyone can announce unknown content, only refresh the feed once in a while.
+            if self.media_updated.elapsed() >= self.feed_refresh {
+                self.update_feed().await?;
+            }
+
+            if !self.media.contains(&origin) {
+                return Err(IpfsError::new(format!("Unknown Content {}", origin)));
+            }
+        }
+
+        let beacon: Beacon = fetch(
+            FETCH_TIMEOUT,
+            self.ipfs.dag_get(signed.data.author.link, None),
+        )
+        .await?;
+        let identity = fetch(FETCH_TIMEOUT, self.ipfs.name_resolve(beacon.identity)).await?;
+        let identity: Identity = fetch(FETCH_TIMEOUT, self.ipfs.dag_get(identity, None)).await?;
+
+        if identity.eth_address != Some(signed.address) {
+            return Err(IpfsError::new("Signer Is Not The Author"));
+        }
+
+        if let Err(e) = self.ipfs.pin_add(cid, false).await {
+            eprintln!("❗ IPFS could not pin {}. Error: {}", cid, e);
+        }
+
+        self.index.insert(cid, &signed.data);
+
+        self.publish_index().await?;
+
+        Ok(Some(cid))
+    }
+
+    async fn update_feed(&mut self) -> Result<(), IpfsError> {
+        self.media_updated = Instant::now();
+
+        let (head, _) = fetch(
+            FETCH_TIMEOUT,
+            get_from_ipns::<I, FeedAnchor>(&self.ipfs, FEED_KEY),
+        )
+        .await?;
+
+        self.media = fetch(FETCH_TIMEOUT, feed_pages(&self.ipfs, head))
+            .await?
+            .into_iter()
+            .flat_map(|(_, page)| page.content)
+            .map(|ipld| ipld.link)
+            .collect();
+
+        Ok(())
+    }
+
+    async fn publish_index(&mut self) -> Result<(), IpfsError> {
+        let cid = self.ipfs.dag_put(&self.index).await?;
+
+        if let Err(e) = self.ipfs.pin_add(cid, false).await {
+            eprintln!("❗ IPFS could not pin {}. Error: {}", cid, e);
+        }
+
+        self.ipfs.name_publish(cid, COMMENT_INDEX_KEY).await?;
+
+        let old_index_cid = std::mem::replace(&mut self.index_cid, cid);
+
+        if let Err(e) = self.ipfs.pin_rm(old_index_cid, false).await {
+            eprintln!("❗ IPFS could not unpin {}. Error: {}", old_index_cid, e);
+        }
+
+        Ok(())
+    }
+}
+
+/// Give up on blocks or names nobody serves.
+async fn fetch<F, T>(duration: Duration, future: F) -> Result<T, IpfsError>
+where
+    F: Future<Output = Result<T, IpfsError>>,
+{
+    match timeout(duration, future).await {
+        Ok(result) => result,
+        Err(_) => Err(IpfsError::new("Timed Out")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::cli::identity::IDENTITY_KEY;
+    use crate::utils::dag_nodes::update_ipns;
+
+    use linked_data::ipfs::MemoryIpfs;
+    use linked_data::signature::eth_address;
+
+    #[tokio::test]
+    async fn index_announced_comments() {
+        let ipfs = MemoryIpfs::new();
+
+        let key = [0x4c; 32];
+
+        let avatar = ipfs.add(b"avatar".to_vec()).await.unwrap();
+        let video = ipfs.add(b"video".to_vec()).await.unwrap();
+
+        ipfs.key_gen(FEED_KEY).await.unwrap();
+        update_ipns(
+            &ipfs,
+            FEED_KEY,
+            &FeedAnchor {
+                content: vec![video.into()],
+                previous: None,
+            },
+        )
+        .await
+        .unwrap();
+
+        ipfs.key_gen(COMMENT_INDEX_KEY).await.unwrap();
+        update_ipns(&ipfs, COMMENT_INDEX_KEY, &CommentIndex::default())
+            .await
+            .unwrap();
+
+        let identity = ipfs.key_gen(IDENTITY_KEY).await.unwrap();
+        let identity_node = Identity {
+            display_name: "Bob".to_owned(),
+            avatar: avatar.into(),
+            eth_address: Some(eth_address(&key).unwrap()),
+        };
+        update_ipns(&ipfs, IDENTITY_KEY, &identity_node)
+            .await
+            .unwrap();
+
+        let beacon = Beacon {
+            identity,
+            ..Default::default()
+        };
+        let beacon = ipfs.dag_put(&beacon).await.unwrap();
+
+        let mut indexer = CommentIndexer::new(ipfs.clone(), "comments".to_owned())
+            .await
+            .unwrap();
+
+        let comment = Comment::create(beacon, video, "Nice!".to_owned());
+        let comment = SignedMessage::sign_typed(comment, &key).unwrap();
+        let comment = ipfs.dag_put(&comment).await.unwrap();
+
+        let announcement = comment.to_string();
+
+        assert_eq!(
+            indexer
+                .on_announcement(announcement.as_bytes())
+                .await
+                .unwrap(),
+            Some(comment)
+        );
+        assert_eq!(
+            indexer
+                .on_announcement(announcement.as_bytes())
+                .await
+                .unwrap(),
+            None
+        );
+
+        let (_, index) = get_from_ipns::<_, CommentIndex>(&ipfs, COMMENT_INDEX_KEY)
+            .await
+            .unwrap();
+
+        assert_eq!(index.comments[&video], vec![comment.into()]);
+        assert_eq!(index.authors[&comment], beacon.into());
+        assert!(ipfs.is_pinned(&comment));
+
+        // Signed by someone else
+        let forged = Comment::create(beacon, video, "Scam!".to_owned());
+        let forged = SignedMessage::sign_typed(forged, &[0x11; 32]).unwrap();
+        let forged = ipfs.dag_put(&forged).await.unwrap();
+
+        assert!(indexer
+            .on_announcement(forged.to_string().as_bytes())
+            .await
+            .is_err());
+
+        // Not on your content
+        let other = Comment::create(beacon, avatar, "Hi!".to_owned());
+        let other = SignedMessage::sign_typed(other, &key).unwrap();
+        let other = ipfs.dag_put(&other).await.unwrap();
+
+        assert!(indexer
+            .on_announcement(other.to_string().as_bytes())
+            .await
+            .is_err());
+
+        // New content is only seen once the feed can be refreshed.
+        let clip = ipfs.add(b"clip".to_vec()).await.unwrap();
+
+        update_ipns(
+            &ipfs,
+            FEED_KEY,
+            &FeedAnchor {
+                content: vec![video.into(), clip.into()],
+                previous: None,
+            },
+        )
+        .await
+        .unwrap();
+
+        let late = Comment::create(beacon, clip, "First!".to_owned());
+        let late = SignedMessage::sign_typed(late, &key).unwrap();
+        let late = ipfs.dag_put(&late).await.unwrap();
+
+        assert!(indexer
+            .on_announcement(late.to_string().as_bytes())
+            .await
+            .is_err());
+
+        indexer.feed_refresh = Duration::ZERO;
+
+        assert_eq!(
+            indexer
+                .on_announcement(late.to_string().as_bytes())
+                .await
+                .unwrap(),
+            Some(late)
+        );
+    }
+
+    #[tokio::test]
+    async fn fetch_timeout() {
+        let never = futures_util::future::pending::<Result<Cid, IpfsError>>();
+
+        assert!(fetch(Duration::from_millis(10), never).await.is_err());
+
+        let ready = futures_util::future::ready(Ok::<_, IpfsError>(1));
+
+        assert_eq!(fetch(Duration::from_millis(10), ready).await.unwrap(), 1);
+    }
+}
@@ -1,10 +1,12 @@
 mod archivist;
 mod chat;
+mod indexer;
 mod setup;
 mod video;
 
 pub use archivist::Archive;
-pub use archivist::Archivist;
-pub use chat::ChatAggregator;
+pub use archivist::{ArchiveState, Archivist};
+pub use chat::{ChatAggregator, ChatCommand};
+pub use indexer::CommentIndexer;
 pub use setup::{SetupAggregator, SetupData};
-pub use video::{VideoAggregator, VideoData};
+pub use video::{VideoAggregator, VideoData, VideoState};
@@ -1,14 +1,11 @@
 use crate::actors::VideoData;
-use crate::utils::dag_nodes::ipfs_dag_put_node_async;
 
 use std::collections::HashMap;
 use std::path::Path;
-use std::path::PathBuf;
 
-use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
-
-use ipfs_api::IpfsClient;
+use tokio::sync::mpsc::{Receiver, Sender};
 
+use linked_data::ipfs::IpfsBackend;
 use linked_data::video::{SetupNode, Track};
 use linked_data::IPLDLink;
 
@@ -20,27 +17,40 @@ type TrackData = (Option<String>, Option<usize>, Option<IPLDLink>);
 
 #[derive(Debug)]
 pub enum SetupData {
+    /// HLS master playlist describing all tracks.
     Playlist(MasterPlaylist),
-    Segment((PathBuf, Cid)),
+
+    /// One track of a direct fMP4 ingest.
+    Track {
+        name: String,
+        codecs: String,
+        bandwidth: usize,
+        track_count: usize,
+    },
+
+    /// Initialization segment.
+    Segment { track: String, cid: Cid },
 }
 
-pub struct SetupAggregator {
-    ipfs: IpfsClient,
+pub struct SetupAggregator<I>
+where
+    I: IpfsBackend,
+{
+    ipfs: I,
 
-    service_rx: UnboundedReceiver<SetupData>,
-    video_tx: UnboundedSender<VideoData>,
+    service_rx: Receiver<SetupData>,
+    video_tx: Sender<VideoData>,
 
     track_len: usize,
 
     map: HashMap<String, TrackData>,
 }
 
-impl SetupAggregator {
-    pub fn new(
-        ipfs: IpfsClient,
-        service_rx: UnboundedReceiver<SetupData>,
-        video_tx: UnboundedSender<VideoData>,
-    ) -> Self {
+impl<I> SetupAggregator<I>
+where
+    I: IpfsBackend,
+{
+    pub fn new(ipfs: I, service_rx: Receiver<SetupData>, video_tx: Sender<VideoData>) -> Self {
         Self {
             ipfs,
 
@@ -59,7 +69,16 @@ impl SetupAggregator {
         while let Some(msg) = self.service_rx.recv().await {
             match msg {
                 SetupData::Playlist(pl) => self.process_master_playlist(pl).await,
-                SetupData::Segment((path, cid)) => self.init_seg(path, cid).await,
+                SetupData::Track {
+                    name,
+                    codecs,
+                    bandwidth,
+                    track_count,
+                } => {
+                    self.process_track(name, codecs, bandwidth, track_count)
+                        .await
+                }
+                SetupData::Segment { track, cid } => self.init_seg(track, cid).await,
             }
         }
 
@@ -67,21 +86,35 @@ impl SetupAggregator {
     }
 
     /// Update track with initialization segments then try to mint node.
-    async fn init_seg(&mut self, path: PathBuf, cid: Cid) {
-        let name = path
-            .parent()
-            .expect("Orphan path!")
-            .file_name()
-            .expect("Dir with no name!")
-            .to_str()
-            .expect("Invalid Unicode");
-
+    async fn init_seg(&mut self, name: String, cid: Cid) {
         let link = Some(cid.into());
 
-        if let Some((_, _, init_seg)) = self.map.get_mut(name) {
+        if let Some((_, _, init_seg)) = self.map.get_mut(&name) {
             *init_seg = link;
         } else {
-            self.map.insert(name.to_owned(), (None, None, link));
+            self.map.insert(name, (None, None, link));
+        }
+
+        self.try_mint_setup_node().await;
+    }
+
+    /// Create or update one track then try to mint node.
+    async fn process_track(
+        &mut self,
+        name: String,
+        codecs: String,
+        bandwidth: usize,
+        track_count: usize,
+    ) {
+        self.track_len = track_count;
+
+        let v_codec = Some(mime_type(&name, &codecs));
+
+        if let Some((codec, v_bandwidth, _)) = self.map.get_mut(&name) {
+            *codec = v_codec;
+            *v_bandwidth = Some(bandwidth);
+        } else {
+            self.map.insert(name, (v_codec, Some(bandwidth), None));
         }
 
         self.try_mint_setup_node().await;
@@ -92,30 +125,21 @@ impl SetupAggregator {
         #[cfg(debug_assertions)]
         println!("{:#?}", pl);
 
-        self.track_len = pl.variants.len();
+        self.track_len = 0;
 
         for variant in pl.variants.into_iter().rev() {
-            let path = Path::new(&variant.uri);
-
-            let v_name = path
-                .parent()
-                .expect("Orphan path!")
-                .file_name()
-                .expect("Dir with no name!")
-                .to_str()
-                .expect("Invalid Unicode");
-
-            let v_codec = match variant.codecs {
-                Some(codec) => {
-                    if v_name == "audio" {
-                        Some(format!(r#"audio/mp4; codecs="{}""#, codec))
-                    } else {
-                        Some(format!(r#"video/mp4; codecs="{}""#, codec))
-                    }
+            let v_name = match track_name(Path::new(&variant.uri)) {
+                Some(name) => name,
+                None => {
+                    eprintln!("❗ Variant {} is not in a track directory", variant.uri);
+                    continue;
                 }
-                None => None,
             };
 
+            self.track_len += 1;
+
+            let v_codec = variant.codecs.map(|codec| mime_type(v_name, &codec));
+
             let v_bandwidth = variant.bandwidth.parse::<usize>().ok();
 
             if let Some((codec, bandwidth, _)) = self.map.get_mut(v_name) {
@@ -167,7 +191,9 @@ impl SetupAggregator {
 
         let setup_node = SetupNode { tracks };
 
-        let cid = ipfs_dag_put_node_async(&self.ipfs, &setup_node)
+        let cid = self
+            .ipfs
+            .dag_put(&setup_node)
             .await
             .expect("IPFS: SetupNode dag put failed"); // Panic because can't be recovered from anyway
 
@@ -175,10 +201,23 @@ impl SetupAggregator {
 
         let msg = VideoData::Setup((cid.into(), self.track_len));
 
-        if let Err(error) = self.video_tx.send(msg) {
+        if let Err(error) = self.video_tx.send(msg).await {
             eprintln!("❗ Video receiver hung up! Error: {}", error);
         }
 
         self.service_rx.close();
     }
 }
+
+/// Name of the directory containing the file.
+fn track_name(path: &Path) -> Option<&str> {
+    path.parent()?.file_name()?.to_str()
+}
+
+fn mime_type(name: &str, codecs: &str) -> String {
+    if name == "audio" {
+        format!(r#"audio/mp4; codecs="{}""#, codecs)
+    } else {
+        format!(r#"video/mp4; codecs="{}""#, codecs)
+    }
+}
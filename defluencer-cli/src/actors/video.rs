@@ -1,48 +1,92 @@
 use crate::actors::archivist::Archive;
 use crate::utils::config::VideoConfig;
-use crate::utils::dag_nodes::ipfs_dag_put_node_async;
+use crate::utils::journal::Journal;
+use crate::utils::metrics::Metrics;
+use crate::utils::queues::QueueMetrics;
+use crate::utils::status::DaemonStatus;
 
 use std::collections::{HashMap, VecDeque};
-use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+use tokio::sync::mpsc::{Receiver, Sender};
 
-use ipfs_api::IpfsClient;
+use serde::{Deserialize, Serialize};
 
+use linked_data::ipfs::IpfsBackend;
+use linked_data::live::VideoAnnouncement;
+use linked_data::signature::SignedMessage;
 use linked_data::video::VideoNode;
 use linked_data::IPLDLink;
 
 use cid::Cid;
 
-pub struct VideoAggregator {
-    ipfs: IpfsClient,
-
-    service_rx: UnboundedReceiver<VideoData>,
-    archive_tx: Option<UnboundedSender<Archive>>,
-
-    config: VideoConfig,
-
+/// Position in the chain of video nodes, saved to the journal.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct VideoState {
     track_len: usize,
     setup_link: Option<IPLDLink>,
 
     node_mint_count: usize,
-    video_nodes: VecDeque<VideoNode>,
 
     previous: Option<IPLDLink>,
 }
 
+pub struct VideoAggregator<I>
+where
+    I: IpfsBackend,
+{
+    ipfs: I,
+
+    service_rx: Receiver<VideoData>,
+    archive_tx: Option<Sender<Archive>>,
+
+    config: VideoConfig,
+
+    queues: QueueMetrics,
+    metrics: Metrics,
+    status: DaemonStatus,
+
+    state: VideoState,
+    journal: Option<Journal>,
+
+    /// Segment indices restart at zero when resuming.
+    index_offset: usize,
+
+    video_nodes: VecDeque<VideoNode>,
+}
+
 #[derive(Debug)]
 pub enum VideoData {
-    Segment((PathBuf, Cid)),
+    /// Media segment of a track at this absolute index.
+    Segment {
+        track: String,
+        index: usize,
+        cid: Cid,
+        /// Length in seconds, if the segment could be parsed.
+        duration: Option<f64>,
+    },
     Setup((IPLDLink, usize)),
+
+    /// Announce video nodes on this topic instead.
+    Topic(String),
 }
 
-impl VideoAggregator {
+impl<I> VideoAggregator<I>
+where
+    I: IpfsBackend,
+{
+    /// Start from the state of a previous session to continue its chain of video nodes.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
-        ipfs: IpfsClient,
-        service_rx: UnboundedReceiver<VideoData>,
-        archive_tx: Option<UnboundedSender<Archive>>,
+        ipfs: I,
+        service_rx: Receiver<VideoData>,
+        archive_tx: Option<Sender<Archive>>,
         config: VideoConfig,
+        queues: QueueMetrics,
+        metrics: Metrics,
+        status: DaemonStatus,
+        state: VideoState,
+        journal: Option<Journal>,
     ) -> Self {
         Self {
             ipfs,
@@ -52,68 +96,92 @@ impl VideoAggregator {
 
             config,
 
-            track_len: 0,
-            setup_link: None,
+            queues,
+            metrics,
+            status,
+
+            index_offset: state.node_mint_count,
+
+            state,
+            journal,
 
-            node_mint_count: 0,
             video_nodes: VecDeque::with_capacity(5),
-            previous: None,
         }
     }
 
     pub async fn start(&mut self) {
+        let topic = self.config.pubsub_topic.clone();
+        self.status.update(|status| status.video_topic = topic);
+
         println!("✅ Video System Online");
 
         while let Some(msg) = self.service_rx.recv().await {
             match msg {
-                VideoData::Segment((path, cid)) => self.media_seg(path, cid).await,
+                VideoData::Segment {
+                    track,
+                    index,
+                    cid,
+                    duration,
+                } => self.media_seg(track, index, cid, duration).await,
                 VideoData::Setup((link, len)) => {
-                    self.track_len = len;
-                    self.setup_link = Some(link);
+                    self.state.track_len = len;
+                    self.state.setup_link = Some(link);
+
+                    self.save_journal().await;
+                }
+                VideoData::Topic(topic) => {
+                    println!("Video Topic => {}", topic);
+
+                    self.config.pubsub_topic = topic.clone();
+                    self.status.update(|status| status.video_topic = topic);
                 }
             }
         }
 
+        // Clean shutdown, nothing to resume.
+        if let Some(journal) = self.journal.as_ref() {
+            if let Err(e) = journal.remove().await {
+                eprintln!("❗ Journal: remove failed {}", e);
+            }
+        }
+
         println!("❌ Video System Offline");
     }
 
     /// Update or create VideoNode in queue then try to mint one.
-    async fn media_seg(&mut self, path: PathBuf, cid: Cid) {
-        let quality = path
-            .parent()
-            .expect("Orphan path!")
-            .file_name()
-            .expect("Dir with no name!")
-            .to_str()
-            .expect("Invalid Unicode");
-
-        //absolute index from ffmpeg
-        let index = path
-            .file_stem()
-            .expect("Not file stem")
-            .to_str()
-            .expect("Invalid Unicode")
-            .parse::<usize>()
-            .expect("Not a number");
+    async fn media_seg(&mut self, quality: String, index: usize, cid: Cid, duration: Option<f64>) {
+        let index = index + self.index_offset;
 
         // relative index for in memory video nodes
-        let buffer_index = index - self.node_mint_count;
+        let buffer_index = match index.checked_sub(self.state.node_mint_count) {
+            Some(i) => i,
+            None => {
+                eprintln!("❗ Segment {} of track {} arrived too late", index, quality);
+                return;
+            }
+        };
 
         if let Some(node) = self.video_nodes.get_mut(buffer_index) {
-            node.tracks.insert(quality.to_owned(), cid.into());
+            node.tracks.insert(quality, cid.into());
 
-            node.setup = self.setup_link;
+            // Tracks may differ slightly, the longest one sets the timeline.
+            node.duration = match (node.duration, duration) {
+                (Some(a), Some(b)) => Some(a.max(b)),
+                (a, b) => a.or(b),
+            };
+
+            node.setup = self.state.setup_link;
 
             // Set previous field only for the next node to be minted
             if buffer_index == 0 {
-                node.previous = self.previous;
+                node.previous = self.state.previous;
             }
         } else {
             let mut tracks = HashMap::with_capacity(4);
 
-            tracks.insert(quality.to_owned(), cid.into());
+            tracks.insert(quality, cid.into());
 
-            let setup = self.setup_link;
+            let setup = self.state.setup_link;
 
             let previous = None;
 
@@ -121,17 +189,33 @@ impl VideoAggregator {
                 tracks,
                 setup,
                 previous,
+                duration,
             };
 
             self.video_nodes.push_back(node);
+
+            // Overloaded, the oldest node is never minted but keeps its index.
+            if self.video_nodes.len() > self.queues.config.video_nodes {
+                self.video_nodes.pop_front();
+                self.state.node_mint_count += 1;
+
+                self.queues.video_node_dropped();
+
+                eprintln!(
+                    "❗ Video node {} dropped, too many incomplete nodes",
+                    self.state.node_mint_count - 1
+                );
+            }
         }
 
+        let mint_count = self.state.node_mint_count;
+
         // try to mint in case something failed previously
-        while let Some(cid) = self.mint_video_node().await {
+        while let Some((cid, duration)) = self.mint_video_node().await {
             if let Some(archive_tx) = self.archive_tx.as_ref() {
-                let msg = Archive::Video(cid);
+                let msg = Archive::Video(cid, duration.unwrap_or(1.0));
 
-                if let Err(error) = archive_tx.send(msg) {
+                if let Err(error) = archive_tx.send(msg).await {
                     eprintln!("❗ Archive receiver hung up! Error: {}", error);
                 }
             }
@@ -139,33 +223,84 @@ impl VideoAggregator {
             if self.config.pubsub_enable {
                 let topic = &self.config.pubsub_topic;
 
-                if let Err(e) = self.ipfs.pubsub_pub(topic, &cid.to_string()).await {
+                let msg = self.announcement(cid);
+
+                if let Err(e) = self.ipfs.pubsub_pub(topic, &msg).await {
+                    self.metrics.pubsub_failed();
+
                     eprintln!("❗ IPFS: pubsub pub failed {}", e);
                 }
             }
         }
 
+        if self.state.node_mint_count > mint_count {
+            self.save_journal().await;
+        }
+
+        self.queues.set_video_nodes(self.video_nodes.len());
+
         #[cfg(debug_assertions)]
         println!("Video: {} buffered nodes", self.video_nodes.len());
     }
 
+    async fn save_journal(&self) {
+        if let Some(journal) = self.journal.as_ref() {
+            if let Err(e) = journal.save(&self.state).await {
+                eprintln!("❗ Journal: save failed {}", e);
+            }
+        }
+    }
+
+    /// Signed announcement of the new node or only the CID if no key was provided.
+    fn announcement(&self, cid: Cid) -> String {
+        let key = match self.config.signing_key.as_ref() {
+            Some(key) => key,
+            None => return cid.to_string(),
+        };
+
+        let announcement = VideoAnnouncement {
+            video: cid.into(),
+            sequence: self.state.node_mint_count as u64 - 1,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .expect("SystemTime before UNIX EPOCH!")
+                .as_secs(),
+        };
+
+        let signed = match SignedMessage::sign(announcement, key) {
+            Ok(signed) => signed,
+            Err(e) => {
+                eprintln!("❗ Video announcement signing failed {}", e);
+                return cid.to_string();
+            }
+        };
+
+        serde_json::to_string(&signed).expect("Cannot Serialize")
+    }
+
     /// Mint the first VideoNode in queue if it meets all requirements.
-    async fn mint_video_node(&mut self) -> Option<Cid> {
+    /// Return the CID and duration of the node.
+    async fn mint_video_node(&mut self) -> Option<(Cid, Option<f64>)> {
         let node = self.video_nodes.front_mut()?;
 
-        node.setup = self.setup_link;
+        node.setup = self.state.setup_link;
 
         node.setup?;
 
-        if node.tracks.len() != self.track_len {
+        if node.tracks.len() != self.state.track_len {
             return None;
         }
 
-        if node.previous.is_none() && self.node_mint_count > 0 {
+        // The first node in queue is always the next one in the chain.
+        if node.previous.is_none() {
+            node.previous = self.state.previous;
+        }
+
+        if node.previous.is_none() && self.state.node_mint_count > 0 {
             return None;
         }
 
-        let cid = match ipfs_dag_put_node_async(&self.ipfs, node).await {
+        let cid = match self.metrics.time_dag_put(self.ipfs.dag_put(node)).await {
             Ok(res) => res,
             Err(e) => {
                 eprintln!("❗ IPFS: dag put failed {}", e);
@@ -173,12 +308,26 @@ impl VideoAggregator {
             }
         };
 
+        let duration = node.duration;
+
+        let mut tracks: Vec<String> = node.tracks.keys().cloned().collect();
+        tracks.sort_unstable();
+
         self.video_nodes.pop_front();
-        self.node_mint_count += 1;
-        self.previous = Some(cid.into());
+        self.state.node_mint_count += 1;
+        self.state.previous = Some(cid.into());
+
+        let count = self.state.node_mint_count;
+        self.status.update(|status| {
+            status.tracks = tracks;
+            status.last_video_node = Some(cid.to_string());
+            status.video_node_count = count;
+        });
+
+        self.metrics.video_node_minted();
 
         println!("Video Node Minted => {}", &cid.to_string());
 
-        Some(cid)
+        Some((cid, duration))
     }
 }
@@ -0,0 +1,238 @@
+use crate::actors::ArchiveState;
+use crate::utils::ipfs::IpfsDaemon;
+use crate::utils::journal::{Journal, ARCHIVE_JOURNAL, VIDEO_JOURNAL};
+use crate::utils::metrics::Metrics;
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use linked_data::chat::{ArchivedMessage, ChatId};
+use linked_data::ipfs::{IpfsBackend, IpfsError};
+use linked_data::signature::SignedMessage;
+use linked_data::video::{DayNode, HourNode, MinuteNode, SecondNode};
+
+use structopt::StructOpt;
+
+use cid::Cid;
+
+#[derive(Debug, StructOpt)]
+pub struct VerifyArchive {
+    /// Timecode CID of the video.
+    #[structopt(short, long)]
+    cid: Cid,
+}
+
+pub async fn verify_archive_cli(args: VerifyArchive) {
+    let ipfs = IpfsDaemon::default();
+
+    println!("Verifying Chat Archive...");
+
+    match verify_archive(&ipfs, args.cid).await {
+        Ok(report) if report.verified == report.total => {
+            println!("✅ {} Messages Verified", report.total)
+        }
+        Ok(report) => eprintln!(
+            "❗ {} of {} Messages Failed Verification",
+            report.total - report.verified,
+            report.total
+        ),
+        Err(e) => eprintln!("❗ IPFS: {:#?}", e),
+    }
+}
+
+#[derive(Debug, StructOpt)]
+pub struct FinalizeArchive {
+    /// Path to the archive journal of the session. Defaults to archive_journal.json
+    #[structopt(long, parse(from_os_str))]
+    journal: Option<PathBuf>,
+}
+
+/// Build the timecode node of a session that did not shutdown.
+pub async fn finalize_archive_cli(args: FinalizeArchive) {
+    let ipfs = IpfsDaemon::default();
+
+    let journal = match args.journal {
+        Some(path) => Journal::new(path),
+        None => Journal::new(ARCHIVE_JOURNAL),
+    };
+
+    let mut state: ArchiveState = match journal.load().await {
+        Ok(state) => state,
+        Err(e) => {
+            eprintln!("❗ Cannot load archive journal. {}", e);
+            return;
+        }
+    };
+
+    match state.finalize(&ipfs, &Metrics::default()).await {
+        Ok(Some(cid)) => println!("Final Timecode-addressable Node => {}", &cid.to_string()),
+        Ok(None) => println!("0 Nodes Found"),
+        Err(e) => {
+            eprintln!("❗ IPFS: {:#?}", e);
+            return;
+        }
+    }
+
+    // The session is over, nothing left to resume.
+    for journal in [journal, Journal::new(VIDEO_JOURNAL)].iter() {
+        if let Err(e) = journal.remove().await {
+            eprintln!("❗ Journal: remove failed {}", e);
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct ArchiveReport {
+    total: usize,
+    verified: usize,
+}
+
+/// Walk every SecondNode of the video and check the signature of each chat message.
+async fn verify_archive<I>(ipfs: &I, cid: Cid) -> Result<ArchiveReport, IpfsError>
+where
+    I: IpfsBackend,
+{
+    let day: DayNode = ipfs.dag_get(cid, Some("/time")).await?;
+
+    let mut report = ArchiveReport::default();
+
+    // Signed messages are shared by all messages of a user.
+    let mut signatures: HashMap<Cid, Result<SignedMessage<ChatId>, String>> = HashMap::new();
+
+    for (h, hour) in day.links_to_hours.iter().enumerate() {
+        let hour: HourNode = ipfs.dag_get(hour.link, None).await?;
+
+        for (m, minute) in hour.links_to_minutes.iter().enumerate() {
+            let minute: MinuteNode = ipfs.dag_get(minute.link, None).await?;
+
+            for (s, second) in minute.links_to_seconds.iter().enumerate() {
+                let second: SecondNode = ipfs.dag_get(second.link, None).await?;
+
+                for chat in second.links_to_chat {
+                    report.total += 1;
+
+                    let result = verify_message(ipfs, chat.link, &mut signatures).await;
+
+                    match result {
+                        Ok(()) => report.verified += 1,
+                        Err(reason) => eprintln!(
+                            "❗ {:02}:{:02}:{:02} Message {} {}",
+                            h, m, s, chat.link, reason
+                        ),
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+async fn verify_message<I>(
+    ipfs: &I,
+    cid: Cid,
+    signatures: &mut HashMap<Cid, Result<SignedMessage<ChatId>, String>>,
+) -> Result<(), String>
+where
+    I: IpfsBackend,
+{
+    let msg: ArchivedMessage = match ipfs.dag_get(cid, None).await {
+        Ok(msg) => msg,
+        Err(e) => return Err(format!("is not an archived message. {}", e)),
+    };
+
+    if !signatures.contains_key(&msg.sig.link) {
+        let result = match ipfs.dag_get(msg.sig.link, None).await {
+            Ok(sign_msg) => Ok(sign_msg),
+            Err(e) => Err(format!("has no signed message. {}", e)),
+        };
+
+        signatures.insert(msg.sig.link, result);
+    }
+
+    let sign_msg = match &signatures[&msg.sig.link] {
+        Ok(sign_msg) => sign_msg,
+        Err(e) => return Err(e.clone()),
+    };
+
+    if !sign_msg.verify() {
+        return Err("has an invalid signature".to_owned());
+    }
+
+    if sign_msg.data.peer_id != msg.peer {
+        return Err(format!(
+            "was sent by {} but signed for {}",
+            msg.peer, sign_msg.data.peer_id
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use linked_data::chat::{Message, MessageType};
+    use linked_data::ipfs::MemoryIpfs;
+    use linked_data::signature::SignatureScheme;
+    use linked_data::video::TimecodeNode;
+
+    #[tokio::test]
+    async fn forged_and_legacy_messages() {
+        let ipfs = MemoryIpfs::new();
+
+        let forged = SignedMessage {
+            address: [0u8; 20],
+            data: ChatId {
+                name: "Mallory".to_owned(),
+                peer_id: "12D3KooWMallory".to_owned(),
+            },
+            signature: vec![0u8; 65],
+            scheme: SignatureScheme::PersonalSign,
+        };
+        let sig = ipfs.dag_put(&forged).await.unwrap();
+
+        let message = Message {
+            msg: MessageType::Chat("Hello".to_owned()),
+            sig: sig.into(),
+        };
+        let archived = ArchivedMessage::create("12D3KooWMallory".to_owned(), message);
+        let archived = ipfs.dag_put(&archived).await.unwrap();
+
+        // Before signatures, archives only had the text.
+        let legacy = ipfs.dag_put("Hello").await.unwrap();
+
+        let second = SecondNode {
+            link_to_video: legacy.into(),
+            links_to_chat: vec![archived.into(), legacy.into()],
+        };
+        let second = ipfs.dag_put(&second).await.unwrap();
+        let minute = MinuteNode {
+            links_to_seconds: vec![second.into()],
+        };
+        let minute = ipfs.dag_put(&minute).await.unwrap();
+        let hour = HourNode {
+            links_to_minutes: vec![minute.into()],
+        };
+        let hour = ipfs.dag_put(&hour).await.unwrap();
+        let day = DayNode {
+            links_to_hours: vec![hour.into()],
+        };
+        let day = ipfs.dag_put(&day).await.unwrap();
+        let timecode = TimecodeNode {
+            timecode: day.into(),
+        };
+        let timecode = ipfs.dag_put(&timecode).await.unwrap();
+
+        let report = verify_archive(&ipfs, timecode).await.unwrap();
+
+        assert_eq!(
+            report,
+            ArchiveReport {
+                total: 2,
+                verified: 0
+            }
+        );
+    }
+}
@@ -1,35 +1,43 @@
-use std::convert::TryFrom;
-
+use crate::cli::comments::COMMENT_INDEX_KEY;
 use crate::cli::content::{COMMENTS_KEY, FEED_KEY};
 use crate::cli::friends::FRIENDS_KEY;
 use crate::cli::identity::IDENTITY_KEY;
 use crate::cli::live::LIVE_KEY;
-use crate::cli::moderation::{BANS_KEY, MODS_KEY};
+use crate::cli::moderation::{BANS_KEY, COMMENT_MODERATION_KEY, FILTERS_KEY, MODS_KEY};
 use crate::utils::config::Configuration;
-use crate::utils::dag_nodes::{
-    ipfs_dag_get_node_async, ipfs_dag_put_node_async, search_keypairs, update_ipns,
-};
+use crate::utils::dag_nodes::update_ipns;
+use crate::utils::ipfs::IpfsDaemon;
+use crate::utils::keys::{key_address, load_key, KEY_LOCATION};
 
-use tokio::task::JoinHandle;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
 
-use serde::Serialize;
+use tokio::fs::File;
+use tokio::io::{AsyncWrite, AsyncWriteExt, BufWriter};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
 
-use ipfs_api::response::{Error, PinAddResponse, PinRmResponse};
-use ipfs_api::IpfsClient;
-use ipfs_api::KeyType;
+use serde::{Deserialize, Serialize};
 
 use linked_data::beacon::Beacon;
-use linked_data::comments::Commentary;
-use linked_data::feed::FeedAnchor;
+use linked_data::car::{CarReader, CarWriter};
+use linked_data::comments::{CommentIndex, CommentModeration, Commentary};
+use linked_data::dag_cbor;
+use linked_data::feed::{feed_pages, FeedAnchor};
+use linked_data::filter::Blocklist;
 use linked_data::friends::Friendlies;
 use linked_data::identity::Identity;
+use linked_data::ipfs::{block_links, IpfsBackend, IpfsError, DAG_CBOR};
 use linked_data::keccak256;
 use linked_data::live::Live;
 use linked_data::moderation::{Bans, Moderators};
+use linked_data::{IPLDLink, IPNSAddress};
 
 use structopt::StructOpt;
 
 use cid::multibase::{encode, Base};
+use cid::multihash::{Code, MultihashDigest};
 use cid::Cid;
 
 #[derive(Debug, StructOpt)]
@@ -51,6 +59,13 @@ enum Command {
     /// Unpin a beacon.
     /// Recursively unpin all associated data.
     Unpin(Unpin),
+
+    /// Export a beacon and all associated data to a CAR file.
+    /// Videos are included, the file could be MASSIVE.
+    Export(Export),
+
+    /// Import a beacon from a CAR file then pin it.
+    Import(Import),
 }
 
 pub async fn beacon_cli(cli: BeaconCLI) {
@@ -58,6 +73,8 @@ pub async fn beacon_cli(cli: BeaconCLI) {
         Command::Create(create) => create_beacon(create).await,
         Command::Pin(pin) => pin_beacon(pin).await,
         Command::Unpin(unpin) => unpin_beacon(unpin).await,
+        Command::Export(export) => export_beacon(export).await,
+        Command::Import(import) => import_beacon(import).await,
     };
 
     if let Err(e) = res {
@@ -76,8 +93,8 @@ pub struct Create {
     avatar: Cid,
 }
 
-async fn create_beacon(args: Create) -> Result<(), Error> {
-    let ipfs = IpfsClient::default();
+async fn create_beacon(args: Create) -> Result<(), IpfsError> {
+    let ipfs = IpfsDaemon::default();
 
     let Create {
         display_name,
@@ -103,11 +120,14 @@ async fn create_beacon(args: Create) -> Result<(), Error> {
         Base::Base32Lower,
         &keccak256(&format!("{}_chat", &display_name).into_bytes()),
     );
+    config.comments.topic = encode(
+        Base::Base32Lower,
+        &keccak256(&format!("{}_comments", &display_name).into_bytes()),
+    );
 
-    config.save_to_file().await?;
+    config.save_to_file().await.map_err(IpfsError::new)?;
 
-    let res = ipfs.id(None).await?;
-    let peer_id = res.id;
+    let peer_id = ipfs.peer_id().await?;
 
     #[cfg(debug_assertions)]
     println!("IPFS: peer id => {}", &peer_id);
@@ -116,23 +136,50 @@ async fn create_beacon(args: Create) -> Result<(), Error> {
         video_topic: config.video.pubsub_topic,
         chat_topic: config.chat.topic,
         peer_id,
+        slow_mode: config.chat.slow_mode,
+        address: None,
+    };
+
+    // Comments signed with the local key, if any, can be verified.
+    let eth_address = match load_key(Path::new(KEY_LOCATION)).await {
+        Ok(key) => key_address(&key).ok(),
+        Err(_) => None,
     };
 
     let identity = Identity {
         display_name,
         avatar: avatar.into(),
+        eth_address,
     };
 
     let key_list = ipfs.key_list().await?;
 
-    let (identity, content_feed, comments, live, friends, bans, mods) = tokio::try_join!(
+    let (identity, content_feed, comments, live, friends) = tokio::try_join!(
         create_ipns_link::<Identity>(&ipfs, "Identity", IDENTITY_KEY, &key_list, Some(identity)),
         create_ipns_link::<FeedAnchor>(&ipfs, "Content Feed", FEED_KEY, &key_list, None),
         create_ipns_link::<Commentary>(&ipfs, "Comments", COMMENTS_KEY, &key_list, None),
         create_ipns_link::<Live>(&ipfs, "Live", LIVE_KEY, &key_list, Some(live)),
         create_ipns_link::<Friendlies>(&ipfs, "Friends", FRIENDS_KEY, &key_list, None),
+    )?;
+
+    let (bans, mods, filters, comment_index, comment_moderation) = tokio::try_join!(
         create_ipns_link::<Bans>(&ipfs, "Bans", BANS_KEY, &key_list, None),
         create_ipns_link::<Moderators>(&ipfs, "Mods", MODS_KEY, &key_list, None),
+        create_ipns_link::<Blocklist>(&ipfs, "Filters", FILTERS_KEY, &key_list, None),
+        create_ipns_link::<CommentIndex>(
+            &ipfs,
+            "Comment Index",
+            COMMENT_INDEX_KEY,
+            &key_list,
+            None
+        ),
+        create_ipns_link::<CommentModeration>(
+            &ipfs,
+            "Comment Moderation",
+            COMMENT_MODERATION_KEY,
+            &key_list,
+            None
+        ),
     )?;
 
     let beacon = linked_data::beacon::Beacon {
@@ -143,12 +190,16 @@ async fn create_beacon(args: Create) -> Result<(), Error> {
         live: Some(live),
         bans: Some(bans),
         mods: Some(mods),
+        filters: Some(filters),
+        comments_topic: Some(config.comments.topic),
+        comment_index: Some(comment_index),
+        comment_moderation: Some(comment_moderation),
     };
 
-    let cid = ipfs_dag_put_node_async(&ipfs, &beacon).await?;
+    let cid = ipfs.dag_put(&beacon).await?;
 
-    if let Err(e) = ipfs.pin_add(&cid.to_string(), false).await {
-        eprintln!("❗ IPFS could not pin {}. Error: {}", cid.to_string(), e);
+    if let Err(e) = ipfs.pin_add(cid, false).await {
+        eprintln!("❗ IPFS could not pin {}. Error: {}", cid, e);
     }
 
     println!("✅ Created Beacon {}", &cid);
@@ -163,14 +214,14 @@ pub struct Pin {
     cid: Cid,
 }
 
-async fn pin_beacon(args: Pin) -> Result<(), Error> {
-    let ipfs = IpfsClient::default();
+async fn pin_beacon(args: Pin) -> Result<(), IpfsError> {
+    let ipfs = IpfsDaemon::default();
 
     let Pin { cid } = args;
 
     println!("Getting Beacon...");
 
-    let beacon = ipfs_dag_get_node_async(&ipfs, &cid.to_string()).await?;
+    let beacon = ipfs.dag_get(cid, None).await?;
 
     let Beacon {
         identity,
@@ -180,43 +231,42 @@ async fn pin_beacon(args: Pin) -> Result<(), Error> {
         live,
         bans,
         mods,
+        filters,
+        comment_index,
+        comment_moderation,
+        ..
     } = beacon;
 
     let mut handles = Vec::with_capacity(100);
 
     let handle = tokio::spawn({
         let ipfs = ipfs.clone();
-        let cid = cid.to_string();
 
-        async move { ipfs.pin_add(&cid, false).await }
+        async move { ipfs.pin_add(cid, false).await }
     });
     handles.push(handle);
 
     if let Some(content_feed) = content_feed {
-        if let Ok(res) = ipfs
-            .name_resolve(Some(&content_feed.to_string()), false, false)
-            .await
-        {
+        if let Ok(cid) = ipfs.name_resolve(content_feed).await {
             println!("Getting Content Feed...");
 
-            let handle = tokio::spawn({
-                let ipfs = ipfs.clone();
-                let cid = res.path.clone();
+            if let Ok(pages) = feed_pages(&ipfs, cid).await {
+                for (page, feed) in pages.into_iter() {
+                    let handle = tokio::spawn({
+                        let ipfs = ipfs.clone();
 
-                async move { ipfs.pin_add(&cid, false).await }
-            });
-            handles.push(handle);
+                        async move { ipfs.pin_add(page, false).await }
+                    });
+                    handles.push(handle);
 
-            if let Ok(feed) = ipfs_dag_get_node_async::<FeedAnchor>(&ipfs, &res.path).await {
-                for ipld in feed.content.into_iter() {
-                    let ipfs = ipfs.clone();
+                    for ipld in feed.content.into_iter() {
+                        let ipfs = ipfs.clone();
 
-                    let handle =
-                        tokio::spawn(
-                            async move { ipfs.pin_add(&ipld.link.to_string(), true).await },
-                        );
+                        let handle =
+                            tokio::spawn(async move { ipfs.pin_add(ipld.link, true).await });
 
-                    handles.push(handle);
+                        handles.push(handle);
+                    }
                 }
             }
         } else {
@@ -227,30 +277,28 @@ async fn pin_beacon(args: Pin) -> Result<(), Error> {
     if let Some(comments) = comments {
         println!("Resolving Comments...");
 
-        if let Ok(res) = ipfs
-            .name_resolve(Some(&comments.to_string()), false, false)
-            .await
-        {
+        if let Ok(cid) = ipfs.name_resolve(comments).await {
             let handle = tokio::spawn({
                 let ipfs = ipfs.clone();
-                let cid = res.path.clone();
 
-                async move { ipfs.pin_add(&cid, false).await }
+                async move { ipfs.pin_add(cid, false).await }
             });
             handles.push(handle);
 
             println!("Getting Comments...");
 
-            if let Ok(comments) = ipfs_dag_get_node_async::<Commentary>(&ipfs, &res.path).await {
-                for ipld in comments.comments.into_values().flatten() {
-                    let ipfs = ipfs.clone();
+            if let Ok(comments) = ipfs.dag_get::<Commentary>(cid, None).await {
+                if let Ok((comments, nodes)) = comments.load(&ipfs).await {
+                    let links = comments.comments.into_values().flatten();
+                    let cids = nodes.into_iter().chain(links.map(|ipld| ipld.link));
 
-                    let handle =
-                        tokio::spawn(
-                            async move { ipfs.pin_add(&ipld.link.to_string(), false).await },
-                        );
+                    for cid in cids {
+                        let ipfs = ipfs.clone();
 
-                    handles.push(handle);
+                        let handle = tokio::spawn(async move { ipfs.pin_add(cid, false).await });
+
+                        handles.push(handle);
+                    }
                 }
             }
         } else {
@@ -263,6 +311,9 @@ async fn pin_beacon(args: Pin) -> Result<(), Error> {
     pin(&ipfs, live, &mut handles);
     pin(&ipfs, bans, &mut handles);
     pin(&ipfs, mods, &mut handles);
+    pin(&ipfs, filters, &mut handles);
+    pin(&ipfs, comment_index, &mut handles);
+    pin(&ipfs, comment_moderation, &mut handles);
 
     println!("Pinning...");
 
@@ -294,14 +345,14 @@ pub struct Unpin {
     cid: Cid,
 }
 
-async fn unpin_beacon(args: Unpin) -> Result<(), Error> {
-    let ipfs = IpfsClient::default();
+async fn unpin_beacon(args: Unpin) -> Result<(), IpfsError> {
+    let ipfs = IpfsDaemon::default();
 
     let Unpin { cid } = args;
 
     println!("Getting Beacon...");
 
-    let beacon = ipfs_dag_get_node_async(&ipfs, &cid.to_string()).await?;
+    let beacon = ipfs.dag_get(cid, None).await?;
 
     let Beacon {
         identity,
@@ -311,45 +362,44 @@ async fn unpin_beacon(args: Unpin) -> Result<(), Error> {
         live,
         bans,
         mods,
+        filters,
+        comment_index,
+        comment_moderation,
+        ..
     } = beacon;
 
     let mut handles = Vec::with_capacity(100);
 
     let handle = tokio::spawn({
         let ipfs = ipfs.clone();
-        let cid = cid.to_string();
 
-        async move { ipfs.pin_rm(&cid, false).await }
+        async move { ipfs.pin_rm(cid, false).await }
     });
     handles.push(handle);
 
     println!("Resolving Content Feed...");
 
     if let Some(content_feed) = content_feed {
-        if let Ok(res) = ipfs
-            .name_resolve(Some(&content_feed.to_string()), false, false)
-            .await
-        {
-            let handle = tokio::spawn({
-                let ipfs = ipfs.clone();
-                let cid = res.path.clone();
+        if let Ok(cid) = ipfs.name_resolve(content_feed).await {
+            println!("Getting Content Feed...");
 
-                async move { ipfs.pin_rm(&cid, false).await }
-            });
-            handles.push(handle);
+            if let Ok(pages) = feed_pages(&ipfs, cid).await {
+                for (page, feed) in pages.into_iter() {
+                    let handle = tokio::spawn({
+                        let ipfs = ipfs.clone();
 
-            println!("Getting Content Feed...");
+                        async move { ipfs.pin_rm(page, false).await }
+                    });
+                    handles.push(handle);
 
-            if let Ok(feed) = ipfs_dag_get_node_async::<FeedAnchor>(&ipfs, &res.path).await {
-                for ipld in feed.content.into_iter() {
-                    let ipfs = ipfs.clone();
+                    for ipld in feed.content.into_iter() {
+                        let ipfs = ipfs.clone();
 
-                    let handle =
-                        tokio::spawn(
-                            async move { ipfs.pin_rm(&ipld.link.to_string(), true).await },
-                        );
+                        let handle =
+                            tokio::spawn(async move { ipfs.pin_rm(ipld.link, true).await });
 
-                    handles.push(handle);
+                        handles.push(handle);
+                    }
                 }
             }
         } else {
@@ -360,30 +410,28 @@ async fn unpin_beacon(args: Unpin) -> Result<(), Error> {
     if let Some(comments) = comments {
         println!("Resolving Comments...");
 
-        if let Ok(res) = ipfs
-            .name_resolve(Some(&comments.to_string()), false, false)
-            .await
-        {
+        if let Ok(cid) = ipfs.name_resolve(comments).await {
             let handle = tokio::spawn({
                 let ipfs = ipfs.clone();
-                let cid = res.path.clone();
 
-                async move { ipfs.pin_rm(&cid, false).await }
+                async move { ipfs.pin_rm(cid, false).await }
             });
             handles.push(handle);
 
             println!("Getting Comments...");
 
-            if let Ok(comments) = ipfs_dag_get_node_async::<Commentary>(&ipfs, &res.path).await {
-                for ipld in comments.comments.into_values().flatten() {
-                    let ipfs = ipfs.clone();
+            if let Ok(comments) = ipfs.dag_get::<Commentary>(cid, None).await {
+                if let Ok((comments, nodes)) = comments.load(&ipfs).await {
+                    let links = comments.comments.into_values().flatten();
+                    let cids = nodes.into_iter().chain(links.map(|ipld| ipld.link));
 
-                    let handle =
-                        tokio::spawn(
-                            async move { ipfs.pin_rm(&ipld.link.to_string(), false).await },
-                        );
+                    for cid in cids {
+                        let ipfs = ipfs.clone();
 
-                    handles.push(handle);
+                        let handle = tokio::spawn(async move { ipfs.pin_rm(cid, false).await });
+
+                        handles.push(handle);
+                    }
                 }
             }
         } else {
@@ -396,6 +444,9 @@ async fn unpin_beacon(args: Unpin) -> Result<(), Error> {
     unpin(&ipfs, live, &mut handles);
     unpin(&ipfs, bans, &mut handles);
     unpin(&ipfs, mods, &mut handles);
+    unpin(&ipfs, filters, &mut handles);
+    unpin(&ipfs, comment_index, &mut handles);
+    unpin(&ipfs, comment_moderation, &mut handles);
 
     println!("Unpinning...");
 
@@ -420,20 +471,377 @@ async fn unpin_beacon(args: Unpin) -> Result<(), Error> {
     Ok(())
 }
 
+#[derive(Debug, StructOpt)]
+pub struct Export {
+    /// Beacon CID.
+    #[structopt(short, long)]
+    cid: Cid,
+
+    /// Path of the CAR file to create.
+    #[structopt(long, parse(from_os_str))]
+    car: PathBuf,
+}
+
+async fn export_beacon(args: Export) -> Result<(), IpfsError> {
+    let ipfs = IpfsDaemon::default();
+
+    let Export { cid, car } = args;
+
+    let file = BufWriter::new(File::create(&car).await?);
+
+    let count = write_beacon_car(&ipfs, cid, file).await?;
+
+    println!("✅ Exported {} Blocks to {}", count, car.display());
+
+    Ok(())
+}
+
+#[derive(Debug, StructOpt)]
+pub struct Import {
+    /// Path of the CAR file to load.
+    #[structopt(long, parse(from_os_str))]
+    car: PathBuf,
+}
+
+async fn import_beacon(args: Import) -> Result<(), IpfsError> {
+    let ipfs = IpfsDaemon::default();
+
+    let Import { car } = args;
+
+    let file = BufReader::new(File::open(&car).await?.into_std().await);
+
+    let cid = read_beacon_car(&ipfs, file).await?;
+
+    println!("✅ Imported Beacon {}", cid);
+
+    Ok(())
+}
+
+/// Beacon with IPNS addresses resolved at the time of export. Root of CAR files.
+#[derive(Serialize, Deserialize, Debug)]
+struct BeaconSnapshot {
+    beacon: IPLDLink,
+    identity: Option<IPLDLink>,
+    content_feed: Option<IPLDLink>,
+    comments: Option<IPLDLink>,
+    live: Option<IPLDLink>,
+    friends: Option<IPLDLink>,
+    bans: Option<IPLDLink>,
+    mods: Option<IPLDLink>,
+    #[serde(default)]
+    filters: Option<IPLDLink>,
+    #[serde(default)]
+    comment_index: Option<IPLDLink>,
+    #[serde(default)]
+    comment_moderation: Option<IPLDLink>,
+}
+
+/// Write the beacon graph, the same data beacon pin would pin. Return the number of blocks.
+async fn write_beacon_car<I, W>(ipfs: &I, cid: Cid, mut writer: W) -> Result<usize, IpfsError>
+where
+    I: IpfsBackend,
+    W: AsyncWrite + Unpin,
+{
+    println!("Getting Beacon...");
+
+    let beacon: Beacon = ipfs.dag_get(cid, None).await?;
+
+    println!("Resolving IPNS Links...");
+
+    let snapshot = BeaconSnapshot {
+        beacon: cid.into(),
+        identity: resolve(ipfs, Some(beacon.identity)).await,
+        content_feed: resolve(ipfs, beacon.content_feed).await,
+        comments: resolve(ipfs, beacon.comments).await,
+        live: resolve(ipfs, beacon.live).await,
+        friends: resolve(ipfs, beacon.friends).await,
+        bans: resolve(ipfs, beacon.bans).await,
+        mods: resolve(ipfs, beacon.mods).await,
+        filters: resolve(ipfs, beacon.filters).await,
+        comment_index: resolve(ipfs, beacon.comment_index).await,
+        comment_moderation: resolve(ipfs, beacon.comment_moderation).await,
+    };
+
+    let data = dag_cbor::to_vec(&snapshot)?;
+    let root = Cid::new_v1(DAG_CBOR, Code::Sha2_256.digest(&data));
+
+    // Blocks are encoded in memory then written asynchronously.
+    let mut car = CarWriter::new(Vec::with_capacity(data.len()), &[root])?;
+
+    car.write_block(&root, &data)?;
+    flush_car(&mut car, &mut writer).await?;
+
+    let mut visited = HashSet::with_capacity(100);
+
+    visited.insert(root);
+
+    let direct = [
+        Some(snapshot.beacon),
+        snapshot.identity,
+        snapshot.content_feed,
+        snapshot.comments,
+        snapshot.live,
+        snapshot.friends,
+        snapshot.bans,
+        snapshot.mods,
+        snapshot.filters,
+        snapshot.comment_index,
+        snapshot.comment_moderation,
+    ];
+
+    println!("Exporting Nodes...");
+
+    for ipld in direct.iter().flatten() {
+        write_blocks(ipfs, &mut car, &mut writer, &mut visited, ipld.link, false).await?;
+    }
+
+    if let Some(identity) = snapshot.identity {
+        println!("Exporting Avatar...");
+
+        // Images can span many blocks.
+        let identity: Identity = ipfs.dag_get(identity.link, None).await?;
+
+        write_blocks(
+            ipfs,
+            &mut car,
+            &mut writer,
+            &mut visited,
+            identity.avatar.link,
+            true,
+        )
+        .await?;
+    }
+
+    if let Some(feed) = snapshot.content_feed {
+        println!("Exporting Content...");
+
+        for (page, feed) in feed_pages(ipfs, feed.link).await? {
+            write_blocks(ipfs, &mut car, &mut writer, &mut visited, page, false).await?;
+
+            for ipld in feed.content {
+                write_blocks(ipfs, &mut car, &mut writer, &mut visited, ipld.link, true).await?;
+            }
+        }
+    }
+
+    if let Some(comments) = snapshot.comments {
+        println!("Exporting Comments...");
+
+        let comments: Commentary = ipfs.dag_get(comments.link, None).await?;
+        let (comments, nodes) = comments.load(ipfs).await?;
+
+        for cid in nodes {
+            write_blocks(ipfs, &mut car, &mut writer, &mut visited, cid, false).await?;
+        }
+
+        for ipld in comments.comments.into_values().flatten() {
+            write_blocks(ipfs, &mut car, &mut writer, &mut visited, ipld.link, false).await?;
+        }
+    }
+
+    let buffer = car.finish()?;
+
+    writer.write_all(&buffer).await?;
+    writer.flush().await?;
+
+    Ok(visited.len())
+}
+
+async fn resolve<I>(ipfs: &I, ipns: Option<IPNSAddress>) -> Option<IPLDLink>
+where
+    I: IpfsBackend,
+{
+    let ipns = ipns?;
+
+    match ipfs.name_resolve(ipns).await {
+        Ok(cid) => Some(cid.into()),
+        Err(e) => {
+            eprintln!("❗ IPFS could not resolve {}. Error: {}", ipns, e);
+            None
+        }
+    }
+}
+
+/// Write the block then, if recursive, all the blocks it links to.
+async fn write_blocks<I, W>(
+    ipfs: &I,
+    car: &mut CarWriter<Vec<u8>>,
+    writer: &mut W,
+    visited: &mut HashSet<Cid>,
+    cid: Cid,
+    recursive: bool,
+) -> Result<(), IpfsError>
+where
+    I: IpfsBackend,
+    W: AsyncWrite + Unpin,
+{
+    let mut queue = VecDeque::from(vec![cid]);
+
+    while let Some(cid) = queue.pop_front() {
+        if !visited.insert(cid) {
+            continue;
+        }
+
+        let data = ipfs.block_get(cid).await?;
+
+        if recursive {
+            queue.extend(block_links(&cid, &data)?);
+        }
+
+        car.write_block(&cid, &data)?;
+        flush_car(car, writer).await?;
+    }
+
+    Ok(())
+}
+
+/// Move the encoded blocks to the writer.
+async fn flush_car<W>(car: &mut CarWriter<Vec<u8>>, writer: &mut W) -> Result<(), IpfsError>
+where
+    W: AsyncWrite + Unpin,
+{
+    let buffer = car.get_mut();
+
+    writer.write_all(buffer).await?;
+
+    buffer.clear();
+
+    Ok(())
+}
+
+/// Load all blocks then pin like beacon pin does. Return the beacon CID.
+async fn read_beacon_car<I, R>(ipfs: &I, reader: R) -> Result<Cid, IpfsError>
+where
+    I: IpfsBackend,
+    R: Read + Send + 'static,
+{
+    let (root_tx, root_rx) = oneshot::channel();
+    let (block_tx, mut block_rx) = mpsc::channel(16);
+
+    // The file is read on a blocking thread, blocks are sent as they are decoded.
+    let handle = tokio::task::spawn_blocking(move || -> Result<(), IpfsError> {
+        let mut car = CarReader::new(reader)?;
+
+        let root = match car.roots() {
+            [root] => *root,
+            _ => return Err(IpfsError::new("CAR file must have a single root")),
+        };
+
+        if root_tx.send(root).is_err() {
+            return Ok(());
+        }
+
+        while let Some(block) = car.next_block()? {
+            if block_tx.blocking_send(block).is_err() {
+                break;
+            }
+        }
+
+        Ok(())
+    });
+
+    let root = match root_rx.await {
+        Ok(root) => root,
+        Err(_) => {
+            return match handle.await {
+                Ok(Err(e)) => Err(e),
+                Ok(Ok(())) => Err(IpfsError::new("CAR file has no root")),
+                Err(e) => Err(IpfsError::new(e)),
+            }
+        }
+    };
+
+    println!("Importing Blocks...");
+
+    let mut count = 0;
+
+    while let Some((cid, data)) = block_rx.recv().await {
+        let new_cid = ipfs.block_put(cid.codec(), data).await?;
+
+        if new_cid.hash() != cid.hash() {
+            return Err(IpfsError::new(format!("Block {} is corrupted", cid)));
+        }
+
+        count += 1;
+    }
+
+    handle.await.map_err(IpfsError::new)??;
+
+    println!("Imported {} Blocks", count);
+
+    let snapshot: BeaconSnapshot = ipfs.dag_get(root, None).await?;
+
+    println!("Pinning...");
+
+    let direct = [
+        Some(snapshot.beacon),
+        snapshot.identity,
+        snapshot.content_feed,
+        snapshot.comments,
+        snapshot.live,
+        snapshot.friends,
+        snapshot.bans,
+        snapshot.mods,
+        snapshot.filters,
+        snapshot.comment_index,
+        snapshot.comment_moderation,
+    ];
+
+    let mut pins = Vec::with_capacity(100);
+
+    pins.extend(direct.iter().flatten().map(|ipld| (ipld.link, false)));
+
+    if let Some(identity) = snapshot.identity {
+        let identity: Identity = ipfs.dag_get(identity.link, None).await?;
+
+        pins.push((identity.avatar.link, true));
+    }
+
+    if let Some(feed) = snapshot.content_feed {
+        for (page, feed) in feed_pages(ipfs, feed.link).await? {
+            pins.push((page, false));
+
+            pins.extend(feed.content.into_iter().map(|ipld| (ipld.link, true)));
+        }
+    }
+
+    if let Some(comments) = snapshot.comments {
+        let comments: Commentary = ipfs.dag_get(comments.link, None).await?;
+        let (comments, nodes) = comments.load(ipfs).await?;
+
+        pins.extend(nodes.into_iter().map(|cid| (cid, false)));
+
+        pins.extend(
+            comments
+                .comments
+                .into_values()
+                .flatten()
+                .map(|ipld| (ipld.link, false)),
+        );
+    }
+
+    for (cid, recursive) in pins {
+        if let Err(e) = ipfs.pin_add(cid, recursive).await {
+            eprintln!("❗ IPFS could not pin {}. Error: {}", cid, e);
+        }
+    }
+
+    Ok(snapshot.beacon.link)
+}
+
 fn pin(
-    ipfs: &IpfsClient,
-    ipns: Option<Cid>,
-    handles: &mut Vec<JoinHandle<Result<PinAddResponse, Error>>>,
+    ipfs: &IpfsDaemon,
+    ipns: Option<IPNSAddress>,
+    handles: &mut Vec<JoinHandle<Result<(), IpfsError>>>,
 ) {
     if let Some(ipns) = ipns {
         let handle = tokio::spawn({
             let ipfs = ipfs.clone();
-            let ipns = ipns.to_string();
 
             async move {
-                let res = ipfs.name_resolve(Some(&ipns), false, false).await?;
+                let cid = ipfs.name_resolve(ipns).await?;
 
-                ipfs.pin_add(&res.path, false).await
+                ipfs.pin_add(cid, false).await
             }
         });
 
@@ -442,19 +850,18 @@ fn pin(
 }
 
 fn unpin(
-    ipfs: &IpfsClient,
-    ipns: Option<Cid>,
-    handles: &mut Vec<JoinHandle<Result<PinRmResponse, Error>>>,
+    ipfs: &IpfsDaemon,
+    ipns: Option<IPNSAddress>,
+    handles: &mut Vec<JoinHandle<Result<(), IpfsError>>>,
 ) {
     if let Some(ipns) = ipns {
         let handle = tokio::spawn({
             let ipfs = ipfs.clone();
-            let ipns = ipns.to_string();
 
             async move {
-                let res = ipfs.name_resolve(Some(&ipns), false, false).await?;
+                let cid = ipfs.name_resolve(ipns).await?;
 
-                ipfs.pin_rm(&res.path, false).await
+                ipfs.pin_rm(cid, false).await
             }
         });
 
@@ -463,21 +870,21 @@ fn unpin(
 }
 
 async fn create_ipns_link<T>(
-    ipfs: &IpfsClient,
+    ipfs: &IpfsDaemon,
     name: &str,
     key: &str,
-    key_list: &ipfs_api::response::KeyPairList,
+    key_list: &HashMap<String, IPNSAddress>,
     data: Option<T>,
-) -> Result<Cid, Error>
+) -> Result<IPNSAddress, IpfsError>
 where
-    T: Default + Serialize,
+    T: Default + Serialize + Sync,
 {
-    let link = match search_keypairs(key, key_list) {
-        Some(kp) => kp.id.to_owned(),
+    let cid = match key_list.get(key) {
+        Some(ipns) => *ipns,
         None => {
             println!("Generating {} IPNS Key...", name);
 
-            let ipns_link = generate_key(ipfs, key).await?;
+            let ipns_link = ipfs.key_gen(key).await?;
 
             println!("Updating {} IPNS Link...", name);
 
@@ -485,8 +892,6 @@ where
         }
     };
 
-    let cid = Cid::try_from(link).expect("Cannot Serialize CID");
-
     if let Some(data) = data {
         update_ipns(ipfs, key, &data).await?;
     } else {
@@ -498,14 +903,109 @@ where
     Ok(cid)
 }
 
-async fn generate_key(ipfs: &IpfsClient, key: &str) -> Result<String, Error> {
-    let res = ipfs
-        .key_gen(
-            key,
-            KeyType::Ed25519,
-            64, /* Don't think this does anything... */
-        )
-        .await?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use linked_data::comments::Comment;
+    use linked_data::ipfs::MemoryIpfs;
+    use linked_data::video::{
+        DayNode, HourNode, MinuteNode, SecondNode, TimecodeNode, VideoMetadata,
+    };
+
+    #[tokio::test]
+    async fn car_export_import() {
+        let ipfs = MemoryIpfs::new();
+
+        let avatar = ipfs.add(b"avatar".to_vec()).await.unwrap();
+        let thumbnail = ipfs.add(b"thumbnail".to_vec()).await.unwrap();
+        let segment = ipfs.add(vec![0, 1, 2, 3]).await.unwrap();
+
+        let second = SecondNode {
+            link_to_video: segment.into(),
+            links_to_chat: vec![],
+        };
+        let second = ipfs.dag_put(&second).await.unwrap();
+        let minute = MinuteNode {
+            links_to_seconds: vec![second.into()],
+        };
+        let minute = ipfs.dag_put(&minute).await.unwrap();
+        let hour = HourNode {
+            links_to_minutes: vec![minute.into()],
+        };
+        let hour = ipfs.dag_put(&hour).await.unwrap();
+        let day = DayNode {
+            links_to_hours: vec![hour.into()],
+        };
+        let day = ipfs.dag_put(&day).await.unwrap();
+        let timecode = TimecodeNode {
+            timecode: day.into(),
+        };
+        let timecode = ipfs.dag_put(&timecode).await.unwrap();
+
+        let identity = ipfs.key_gen(IDENTITY_KEY).await.unwrap();
+        let identity_node = Identity {
+            display_name: "Alice".to_owned(),
+            avatar: avatar.into(),
+            eth_address: None,
+        };
+        update_ipns(&ipfs, IDENTITY_KEY, &identity_node)
+            .await
+            .unwrap();
+
+        let beacon = Beacon {
+            identity,
+            content_feed: Some(ipfs.key_gen(FEED_KEY).await.unwrap()),
+            comments: Some(ipfs.key_gen(COMMENTS_KEY).await.unwrap()),
+            ..Default::default()
+        };
+        let beacon_cid = ipfs.dag_put(&beacon).await.unwrap();
+
+        let metadata =
+            VideoMetadata::create("Video".to_owned(), 0.0, thumbnail, timecode, beacon_cid);
+        let video = ipfs.dag_put(&metadata).await.unwrap();
+
+        let feed = FeedAnchor {
+            content: vec![video.into()],
+            previous: None,
+        };
+        update_ipns(&ipfs, FEED_KEY, &feed).await.unwrap();
+
+        // Foreign content must not be exported
+        let foreign = ipfs.dag_put("Someone else's content").await.unwrap();
+
+        let comment = Comment::create(beacon_cid, foreign, "Nice!".to_owned());
+        let comment = ipfs.dag_put(&comment).await.unwrap();
+
+        let mut commentary = Commentary::default();
+        let changes = commentary
+            .add_comment(&ipfs, foreign, comment, None)
+            .await
+            .unwrap();
+        update_ipns(&ipfs, COMMENTS_KEY, &commentary).await.unwrap();
+
+        let mut car = Vec::new();
+        write_beacon_car(&ipfs, beacon_cid, &mut car).await.unwrap();
+
+        let backup = MemoryIpfs::new();
 
-    Ok(res.id)
+        let cid = read_beacon_car(&backup, std::io::Cursor::new(car))
+            .await
+            .unwrap();
+
+        assert_eq!(cid, beacon_cid);
+        assert!(backup.is_pinned(&beacon_cid));
+        assert!(backup.is_pinned(&avatar));
+        assert!(backup.is_pinned(&video));
+        assert!(backup.is_pinned(&comment));
+        assert!(changes.added.iter().all(|cid| backup.is_pinned(cid)));
+
+        let data = backup
+            .cat(timecode, Some("/time/hour/0/minute/0/second/0/video"))
+            .await
+            .unwrap();
+        assert_eq!(data, vec![0, 1, 2, 3]);
+
+        assert!(backup.block_get(foreign).await.is_err());
+    }
 }
@@ -1,15 +1,24 @@
-use crate::utils::dag_nodes::{get_from_ipns, ipfs_dag_put_node_async, update_ipns};
+use crate::actors::CommentIndexer;
+use crate::cli::identity::IDENTITY_KEY;
+use crate::utils::config::Configuration;
+use crate::utils::dag_nodes::{get_from_ipns, pin_nodes, unpin_nodes, update_ipns};
+use crate::utils::ipfs::IpfsDaemon;
+use crate::utils::keys::{key_address, load_key, KEY_LOCATION};
 
-use ipfs_api::response::Error;
-use ipfs_api::IpfsClient;
+use std::path::Path;
 
+use linked_data::beacon::Beacon;
 use linked_data::comments::{Comment, Commentary};
+use linked_data::identity::Identity;
+use linked_data::ipfs::{IpfsBackend, IpfsError};
+use linked_data::signature::SignedMessage;
 
 use cid::Cid;
 
 use structopt::StructOpt;
 
 pub const COMMENTS_KEY: &str = "comments";
+pub const COMMENT_INDEX_KEY: &str = "comment_index";
 
 #[derive(Debug, StructOpt)]
 pub struct Comments {
@@ -24,12 +33,16 @@ enum Command {
 
     /// Remove an old comment.
     Remove(RemoveComment),
+
+    /// Start the daemon indexing comments announced on your content.
+    Index(IndexComments),
 }
 
 pub async fn comments_cli(cli: Comments) {
     let res = match cli.cmd {
         Command::Add(add) => add_comment(add).await,
         Command::Remove(remove) => remove_comment(remove).await,
+        Command::Index(index) => index_comments(index).await,
     };
 
     if let Err(e) = res {
@@ -50,45 +63,77 @@ pub struct AddComment {
     /// Content of your comment.
     #[structopt(short, long)]
     comment: String,
+
+    /// CID of the comment being replied to.
+    #[structopt(short, long)]
+    reply_to: Option<Cid>,
+
+    /// Beacon CID of the content creator, to announce your comment.
+    #[structopt(long)]
+    announce: Option<Cid>,
 }
 
-async fn add_comment(command: AddComment) -> Result<(), Error> {
-    let ipfs = IpfsClient::default();
+async fn add_comment(command: AddComment) -> Result<(), IpfsError> {
+    let ipfs = IpfsDaemon::default();
 
     let AddComment {
         author,
         origin,
         comment,
+        reply_to,
+        announce,
     } = command;
 
-    let comment = Comment::create(author.into(), origin.into(), comment);
-    let comment_cid = ipfs_dag_put_node_async(&ipfs, &comment).await?;
+    let key = load_key(Path::new(KEY_LOCATION))
+        .await
+        .map_err(|e| IpfsError::new(format!("Identity key not found. {}", e)))?;
+
+    let address = key_address(&key).map_err(IpfsError::new)?;
+
+    match get_from_ipns::<_, Identity>(&ipfs, IDENTITY_KEY).await {
+        Ok((_, identity)) if identity.eth_address == Some(address) => {}
+        _ => eprintln!(
+            "❗ Comment will not be verified, identity address is not 0x{}. Command: defluencer-cli identity address",
+            hex::encode(address)
+        ),
+    }
+
+    let comment = match reply_to {
+        Some(parent) => Comment::reply(author, origin, parent, comment),
+        None => Comment::create(author, origin, comment),
+    };
+    let signed = SignedMessage::sign_typed(comment, &key).map_err(IpfsError::new)?;
+
+    let comment_cid = ipfs.dag_put(&signed).await?;
 
     println!("Pinning...");
 
-    let cc = comment_cid.to_string();
-    if let Err(e) = ipfs.pin_add(&cc, false).await {
-        eprintln!("❗ IPFS could not pin {}. Error: {}", cc, e);
+    if let Err(e) = ipfs.pin_add(comment_cid, false).await {
+        eprintln!("❗ IPFS could not pin {}. Error: {}", comment_cid, e);
     }
 
     println!("Updating Comment List...");
 
-    let (old_comments_cid, mut list) = get_from_ipns::<Commentary>(&ipfs, COMMENTS_KEY).await?;
+    let (old_comments_cid, mut list) = get_from_ipns::<_, Commentary>(&ipfs, COMMENTS_KEY).await?;
 
-    match list.comments.get_mut(&origin) {
-        Some(vec) => vec.push(comment_cid.into()),
-        None => {
-            list.comments.insert(origin, vec![comment_cid.into()]);
-        }
-    }
+    let changes = list
+        .add_comment(&ipfs, origin, comment_cid, reply_to)
+        .await?;
+
+    pin_nodes(&ipfs, &changes.added).await;
 
     update_ipns(&ipfs, COMMENTS_KEY, &list).await?;
 
     println!("Unpinning Old List...");
 
-    let occ = old_comments_cid.to_string();
-    if let Err(e) = ipfs.pin_rm(&occ, false).await {
-        eprintln!("❗ IPFS could not unpin {}. Error: {}", occ, e);
+    if let Err(e) = ipfs.pin_rm(old_comments_cid, false).await {
+        eprintln!("❗ IPFS could not unpin {}. Error: {}", old_comments_cid, e);
+    }
+
+    unpin_nodes(&ipfs, &changes.removed).await;
+
+    if let Some(beacon) = announce {
+        announce_comment(&ipfs, beacon, comment_cid).await?;
     }
 
     println!("✅ Added Comment {}", comment_cid);
@@ -96,6 +141,20 @@ async fn add_comment(command: AddComment) -> Result<(), Error> {
     Ok(())
 }
 
+/// Publish the comment CID on the creator's comments topic.
+async fn announce_comment(ipfs: &IpfsDaemon, beacon: Cid, comment: Cid) -> Result<(), IpfsError> {
+    let beacon: Beacon = ipfs.dag_get(beacon, None).await?;
+
+    let topic = match beacon.comments_topic {
+        Some(topic) => topic,
+        None => return Err(IpfsError::new("Beacon has no comments topic")),
+    };
+
+    println!("Announcing Comment...");
+
+    ipfs.pubsub_pub(&topic, &comment.to_string()).await
+}
+
 #[derive(Debug, StructOpt)]
 pub struct RemoveComment {
     /// CID of the content commented on.
@@ -107,41 +166,45 @@ pub struct RemoveComment {
     comment: Cid,
 }
 
-async fn remove_comment(command: RemoveComment) -> Result<(), Error> {
-    let ipfs = IpfsClient::default();
+async fn remove_comment(command: RemoveComment) -> Result<(), IpfsError> {
+    let ipfs = IpfsDaemon::default();
 
     let RemoveComment { origin, comment } = command;
 
-    let (old_comments_cid, mut list) = get_from_ipns::<Commentary>(&ipfs, COMMENTS_KEY).await?;
-
-    let vec = match list.comments.get_mut(&origin) {
-        Some(vec) => vec,
-        None => return Err(Error::Uncategorized("Origin Not Found".into())),
-    };
-
-    let index = match vec.iter().position(|&ipld| ipld == comment.into()) {
-        Some(idx) => idx,
-        None => return Err(Error::Uncategorized("Index Not Found".into())),
-    };
-
-    vec.remove(index);
+    let (old_comments_cid, mut list) = get_from_ipns::<_, Commentary>(&ipfs, COMMENTS_KEY).await?;
 
-    if vec.is_empty() {
-        list.comments.remove(&origin);
-    }
+    let changes = list.remove_comment(&ipfs, origin, comment).await?;
 
     println!("Updating Comment List...");
 
+    pin_nodes(&ipfs, &changes.added).await;
+
     update_ipns(&ipfs, COMMENTS_KEY, &list).await?;
 
     println!("Unpinning Old List...");
 
-    let occ = old_comments_cid.to_string();
-    if let Err(e) = ipfs.pin_rm(&occ, false).await {
-        eprintln!("❗ IPFS could not unpin {}. Error: {}", occ, e);
+    if let Err(e) = ipfs.pin_rm(old_comments_cid, false).await {
+        eprintln!("❗ IPFS could not unpin {}. Error: {}", old_comments_cid, e);
     }
 
+    unpin_nodes(&ipfs, &changes.removed).await;
+
     println!("✅ Removed Comment {}", comment);
 
     Ok(())
 }
+
+#[derive(Debug, StructOpt)]
+pub struct IndexComments {}
+
+async fn index_comments(_: IndexComments) -> Result<(), IpfsError> {
+    let ipfs = IpfsDaemon::default();
+
+    let config = Configuration::from_file().await.map_err(IpfsError::new)?;
+
+    let mut indexer = CommentIndexer::new(ipfs, config.comments.topic).await?;
+
+    indexer.start().await;
+
+    Ok(())
+}
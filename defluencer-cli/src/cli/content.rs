@@ -1,19 +1,15 @@
-use std::convert::TryFrom;
-
-use crate::utils::dag_nodes::{
-    get_from_ipns, ipfs_dag_get_node_async, ipfs_dag_put_node_async, update_ipns,
-};
+use crate::utils::dag_nodes::{get_from_ipns, pin_nodes, unpin_nodes, update_ipns};
+use crate::utils::ipfs::IpfsDaemon;
 
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
-use ipfs_api::response::Error;
-use ipfs_api::IpfsClient;
-
 use linked_data::blog::{FullPost, MicroPost};
 use linked_data::comments::Commentary;
-use linked_data::feed::{FeedAnchor, Media};
-use linked_data::video::{DayNode, HourNode, MinuteNode, VideoMetadata};
+use linked_data::feed::{feed_pages, FeedAnchor, Media, FEED_PAGE_SIZE};
+use linked_data::ipfs::{IpfsBackend, IpfsError};
+use linked_data::video::{DayNode, HourNode, MinuteNode, SecondNode, VideoMetadata, VideoNode};
+use linked_data::IPLDLink;
 
 use cid::Cid;
 
@@ -41,6 +37,9 @@ enum Command {
 
     /// Search for pinned media objects, order them chronologicaly then recreate content feed.
     Repair,
+
+    /// Split a content feed created before pagination into pages.
+    Migrate,
 }
 
 pub async fn content_feed_cli(cli: Content) {
@@ -57,6 +56,7 @@ pub async fn content_feed_cli(cli: Content) {
         },
         Command::Delete(delete) => delete_content(delete).await,
         Command::Repair => repair_content().await,
+        Command::Migrate => migrate_feed().await,
     };
 
     if let Err(e) = res {
@@ -87,8 +87,8 @@ pub struct AddMicroPost {
     content: String,
 }
 
-async fn add_micro_blog(command: AddMicroPost) -> Result<(), Error> {
-    let ipfs = IpfsClient::default();
+async fn add_micro_blog(command: AddMicroPost) -> Result<(), IpfsError> {
+    let ipfs = IpfsDaemon::default();
 
     let AddMicroPost { author, content } = command;
 
@@ -120,8 +120,8 @@ pub struct AddPost {
     content: Cid,
 }
 
-async fn add_blog(command: AddPost) -> Result<(), Error> {
-    let ipfs = IpfsClient::default();
+async fn add_blog(command: AddPost) -> Result<(), IpfsError> {
+    let ipfs = IpfsDaemon::default();
 
     let AddPost {
         author,
@@ -158,8 +158,8 @@ pub struct AddVideo {
     video: Cid,
 }
 
-async fn add_video(command: AddVideo) -> Result<(), Error> {
-    let ipfs = IpfsClient::default();
+async fn add_video(command: AddVideo) -> Result<(), IpfsError> {
+    let ipfs = IpfsDaemon::default();
 
     let AddVideo {
         author,
@@ -201,21 +201,16 @@ pub struct UpdateMicroPost {
     content: String,
 }
 
-async fn update_micro_blog(command: UpdateMicroPost) -> Result<(), Error> {
-    let ipfs = IpfsClient::default();
+async fn update_micro_blog(command: UpdateMicroPost) -> Result<(), IpfsError> {
+    let ipfs = IpfsDaemon::default();
 
     let UpdateMicroPost { cid, content } = command;
 
-    let (old_feed_cid, mut feed, mut metadata) = unload_feed::<MicroPost>(&ipfs, cid).await?;
+    let mut metadata = unload_content::<_, MicroPost>(&ipfs, cid).await?;
 
     metadata.update(content);
 
-    reload_feed(&ipfs, cid, &metadata, &mut feed).await?;
-
-    let ofc = old_feed_cid.to_string();
-    if let Err(e) = ipfs.pin_rm(&ofc, false).await {
-        eprintln!("❗ IPFS could not unpin {}. Error: {}", ofc, e);
-    }
+    reload_feed(&ipfs, cid, &metadata).await?;
 
     println!("✅ Comments Cleared & Updated Weblog");
 
@@ -241,8 +236,8 @@ pub struct UpdatePost {
     content: Option<Cid>,
 }
 
-async fn update_blog(command: UpdatePost) -> Result<(), Error> {
-    let ipfs = IpfsClient::default();
+async fn update_blog(command: UpdatePost) -> Result<(), IpfsError> {
+    let ipfs = IpfsDaemon::default();
 
     let UpdatePost {
         cid,
@@ -251,16 +246,11 @@ async fn update_blog(command: UpdatePost) -> Result<(), Error> {
         content,
     } = command;
 
-    let (old_feed_cid, mut feed, mut metadata) = unload_feed::<FullPost>(&ipfs, cid).await?;
+    let mut metadata = unload_content::<_, FullPost>(&ipfs, cid).await?;
 
     metadata.update(title, image, content);
 
-    reload_feed(&ipfs, cid, &metadata, &mut feed).await?;
-
-    let ofc = old_feed_cid.to_string();
-    if let Err(e) = ipfs.pin_rm(&ofc, false).await {
-        eprintln!("❗ IPFS could not unpin {}. Error: {}", ofc, e);
-    }
+    reload_feed(&ipfs, cid, &metadata).await?;
 
     println!("✅ Comments Cleared & Updated Weblog");
 
@@ -286,8 +276,8 @@ pub struct UpdateVideo {
     video: Option<Cid>,
 }
 
-async fn update_video(command: UpdateVideo) -> Result<(), Error> {
-    let ipfs = IpfsClient::default();
+async fn update_video(command: UpdateVideo) -> Result<(), IpfsError> {
+    let ipfs = IpfsDaemon::default();
 
     let UpdateVideo {
         cid,
@@ -296,7 +286,7 @@ async fn update_video(command: UpdateVideo) -> Result<(), Error> {
         video,
     } = command;
 
-    let (old_feed_cid, mut feed, mut metadata) = unload_feed::<VideoMetadata>(&ipfs, cid).await?;
+    let mut metadata = unload_content::<_, VideoMetadata>(&ipfs, cid).await?;
 
     let duration = match video {
         Some(cid) => Some(get_video_duration(&ipfs, &cid).await?),
@@ -305,12 +295,7 @@ async fn update_video(command: UpdateVideo) -> Result<(), Error> {
 
     metadata.update(title, image, video, duration);
 
-    reload_feed(&ipfs, cid, &metadata, &mut feed).await?;
-
-    let ofc = old_feed_cid.to_string();
-    if let Err(e) = ipfs.pin_rm(&ofc, false).await {
-        eprintln!("❗ IPFS could not unpin {}. Error: {}", ofc, e);
-    }
+    reload_feed(&ipfs, cid, &metadata).await?;
 
     println!("✅ Comments Cleared & Updated Video");
 
@@ -325,53 +310,39 @@ pub struct DeleteContent {
     cid: Cid,
 }
 
-async fn delete_content(command: DeleteContent) -> Result<(), Error> {
+async fn delete_content(command: DeleteContent) -> Result<(), IpfsError> {
     println!("Deleting Content...");
-    let ipfs = IpfsClient::default();
+    let ipfs = IpfsDaemon::default();
 
     let DeleteContent { cid } = command;
 
-    let ((old_feed_cid, mut feed), (old_comments_cid, mut list)) = tokio::try_join!(
-        get_from_ipns::<FeedAnchor>(&ipfs, FEED_KEY),
-        get_from_ipns::<Commentary>(&ipfs, COMMENTS_KEY)
-    )?;
+    let (old_comments_cid, mut list) = get_from_ipns::<_, Commentary>(&ipfs, COMMENTS_KEY).await?;
 
-    let index = match feed.content.iter().position(|&probe| probe.link == cid) {
-        Some(idx) => idx,
-        None => return Err(Error::Uncategorized("Index Not Found".into())),
-    };
-
-    let content = feed.content.remove(index);
+    edit_feed(&ipfs, cid, |content, index| {
+        content.remove(index);
+    })
+    .await?;
 
-    if let Some(comments) = list.comments.remove(&content.link) {
-        //TODO find a way to do that concurently
-        for comment in comments.iter() {
-            let cid = comment.link.to_string();
+    let (comments, changes) = list.remove_content(&ipfs, cid).await?;
 
-            if let Err(e) = ipfs.pin_rm(&cid, false).await {
-                eprintln!("❗ IPFS could not unpin {}. Error: {}", cid, e);
-            }
+    //TODO find a way to do that concurently
+    for comment in comments.iter() {
+        if let Err(e) = ipfs.pin_rm(comment.link, false).await {
+            eprintln!("❗ IPFS could not unpin {}. Error: {}", comment.link, e);
         }
     }
 
-    let content_cid = content.link.to_string();
-    let old_feed_cid = old_feed_cid.to_string();
-    let old_comments_cid = old_comments_cid.to_string();
+    pin_nodes(&ipfs, &changes.added).await;
 
-    tokio::try_join!(
-        update_ipns(&ipfs, FEED_KEY, &feed),
-        update_ipns(&ipfs, COMMENTS_KEY, &list)
-    )?;
+    update_ipns(&ipfs, COMMENTS_KEY, &list).await?;
 
-    if let Err(e) = ipfs.pin_rm(&content_cid, true).await {
-        eprintln!("❗ IPFS could not unpin {}. Error: {}", content_cid, e);
-    }
+    unpin_nodes(&ipfs, &changes.removed).await;
 
-    if let Err(e) = ipfs.pin_rm(&old_feed_cid, false).await {
-        eprintln!("❗ IPFS could not unpin {}. Error: {}", old_feed_cid, e);
+    if let Err(e) = ipfs.pin_rm(cid, true).await {
+        eprintln!("❗ IPFS could not unpin {}. Error: {}", cid, e);
     }
 
-    if let Err(e) = ipfs.pin_rm(&old_comments_cid, false).await {
+    if let Err(e) = ipfs.pin_rm(old_comments_cid, false).await {
         eprintln!("❗ IPFS could not unpin {}. Error: {}", old_comments_cid, e);
     }
 
@@ -380,26 +351,23 @@ async fn delete_content(command: DeleteContent) -> Result<(), Error> {
     Ok(())
 }
 
-async fn repair_content() -> Result<(), Error> {
-    let ipfs = IpfsClient::default();
+async fn repair_content() -> Result<(), IpfsError> {
+    let ipfs = IpfsDaemon::default();
 
-    if let Ok((old_feed_cid, _)) = get_from_ipns::<FeedAnchor>(&ipfs, FEED_KEY).await {
+    if let Ok((head, _)) = get_from_ipns::<_, FeedAnchor>(&ipfs, FEED_KEY).await {
         println!("Unpinnig Old Content Feed...");
 
-        let ofc = old_feed_cid.to_string();
-        if let Err(e) = ipfs.pin_rm(&ofc, false).await {
-            eprintln!("❗ IPFS could not unpin {}. Error: {}", ofc, e);
-        }
+        // Unpin first, unchanged pages are pinned again when the feed is rebuilt.
+        unpin_pages(&ipfs, feed_pages(&ipfs, head).await.unwrap_or_default()).await;
     }
 
     println!("Searching...");
-    let pins = ipfs.pin_ls(None, Some("recursive")).await?;
+    let pins = ipfs.pin_ls().await?;
 
     let mut content = Vec::with_capacity(100);
 
-    for cid in pins.keys.into_keys() {
-        if let Ok(media) = ipfs_dag_get_node_async::<Media>(&ipfs, &cid).await {
-            let cid = Cid::try_from(cid).expect("Invalid Cid");
+    for cid in pins.into_iter() {
+        if let Ok(media) = ipfs.dag_get::<Media>(cid, None).await {
             content.push((cid, media));
         }
     }
@@ -411,127 +379,469 @@ async fn repair_content() -> Result<(), Error> {
 
     let content = content.into_iter().map(|(cid, _)| cid.into()).collect();
 
-    let content_feed = FeedAnchor { content };
-
     println!("Updating Content Feed...");
-    update_ipns(&ipfs, FEED_KEY, &content_feed).await?;
+    let head = build_feed(&ipfs, content).await?;
+
+    ipfs.name_publish(head, FEED_KEY).await?;
 
     println!("✅ Repaired Content Feed");
 
     Ok(())
 }
 
+async fn migrate_feed() -> Result<(), IpfsError> {
+    let ipfs = IpfsDaemon::default();
+
+    let (head, _) = get_from_ipns::<_, FeedAnchor>(&ipfs, FEED_KEY).await?;
+
+    let old_pages = feed_pages(&ipfs, head).await?;
+
+    if old_pages
+        .iter()
+        .all(|(_, page)| page.content.len() <= FEED_PAGE_SIZE)
+    {
+        println!("✅ Content Feed Already Paginated");
+
+        return Ok(());
+    }
+
+    let content = old_pages
+        .iter()
+        .rev()
+        .flat_map(|(_, page)| page.content.iter().copied())
+        .collect();
+
+    unpin_pages(&ipfs, old_pages).await;
+
+    println!("Updating Content Feed...");
+    let head = build_feed(&ipfs, content).await?;
+
+    ipfs.name_publish(head, FEED_KEY).await?;
+
+    println!("✅ Migrated Content Feed {}", head);
+
+    Ok(())
+}
+
 /*** Utils below ****/
 
 /// Serialize and pin content then update IPNS.
-async fn add_content_to_feed<T>(ipfs: &IpfsClient, metadata: &T) -> Result<Cid, Error>
+async fn add_content_to_feed<I, T>(ipfs: &I, metadata: &T) -> Result<Cid, IpfsError>
 where
-    T: Serialize,
+    I: IpfsBackend,
+    T: Serialize + Sync,
 {
     println!("Creating...");
 
-    let content_cid = ipfs_dag_put_node_async(ipfs, metadata).await?;
+    let content_cid = ipfs.dag_put(metadata).await?;
 
     println!("Pinning...");
-    if let Err(e) = ipfs.pin_add(&content_cid.to_string(), true).await {
-        eprintln!(
-            "❗ IPFS could not pin {}. Error: {}",
-            content_cid.to_string(),
-            e
-        );
+    if let Err(e) = ipfs.pin_add(content_cid, true).await {
+        eprintln!("❗ IPFS could not pin {}. Error: {}", content_cid, e);
     }
 
     println!("Updating Content Feed...");
-    let (old_feed_cid, mut feed) = get_from_ipns::<FeedAnchor>(ipfs, FEED_KEY).await?;
+    let (old_feed_cid, mut feed) = get_from_ipns::<_, FeedAnchor>(ipfs, FEED_KEY).await?;
+
+    if feed.is_full() {
+        // The full page stays pinned as is, only the new one is published.
+        let page = FeedAnchor {
+            content: vec![content_cid.into()],
+            previous: Some(old_feed_cid.into()),
+        };
+
+        update_ipns(ipfs, FEED_KEY, &page).await?;
+
+        return Ok(content_cid);
+    }
 
     feed.content.push(content_cid.into());
 
     update_ipns(ipfs, FEED_KEY, &feed).await?;
 
-    let ofc = old_feed_cid.to_string();
-    if let Err(e) = ipfs.pin_rm(&ofc, false).await {
-        eprintln!("❗ IPFS could not unpin {}. Error: {}", ofc, e);
+    if let Err(e) = ipfs.pin_rm(old_feed_cid, false).await {
+        eprintln!("❗ IPFS could not unpin {}. Error: {}", old_feed_cid, e);
     }
 
     Ok(content_cid)
 }
 
-/// Unpin then return feed and cid.
-async fn unload_feed<T>(ipfs: &IpfsClient, cid: Cid) -> Result<(Cid, FeedAnchor, T), Error>
+/// Edit the feed page containing this content then republish it and every newer page.
+async fn edit_feed<I, F>(ipfs: &I, cid: Cid, edit: F) -> Result<(), IpfsError>
 where
-    T: DeserializeOwned,
+    I: IpfsBackend,
+    F: FnOnce(&mut Vec<IPLDLink>, usize),
 {
-    println!("Old Content => {}", cid);
+    let (mut page_cid, mut page) = get_from_ipns::<_, FeedAnchor>(ipfs, FEED_KEY).await?;
+
+    // Pages from the newest to the one containing the content.
+    let mut pages = Vec::new();
+
+    let index = loop {
+        if let Some(idx) = page.content.iter().position(|probe| probe.link == cid) {
+            break idx;
+        }
+
+        let previous = match page.previous {
+            Some(ipld) => ipld.link,
+            None => return Err(IpfsError::new("Index Not Found")),
+        };
+
+        let older = ipfs.dag_get(previous, None).await?;
+
+        pages.push((page_cid, std::mem::replace(&mut page, older)));
+        page_cid = previous;
+    };
+
+    edit(&mut page.content, index);
+
+    let mut previous = page.previous;
+
+    pages.push((page_cid, page));
+
+    let mut old_pages = Vec::with_capacity(pages.len());
+    let mut new_pages = Vec::with_capacity(pages.len());
+    let newest = pages.len() - 1;
+
+    for (i, (old_cid, mut page)) in pages.into_iter().rev().enumerate() {
+        old_pages.push(old_cid);
+
+        // Empty pages are dropped unless the feed would be left without any.
+        if page.content.is_empty() && (previous.is_some() || i != newest) {
+            continue;
+        }
+
+        page.previous = previous;
+
+        let new_cid = ipfs.dag_put(&page).await?;
+
+        if let Err(e) = ipfs.pin_add(new_cid, false).await {
+            eprintln!("❗ IPFS could not pin {}. Error: {}", new_cid, e);
+        }
+
+        new_pages.push(new_cid);
+        previous = Some(new_cid.into());
+    }
+
+    let head = match previous {
+        Some(ipld) => ipld.link,
+        None => return Err(IpfsError::new("Empty Feed")),
+    };
+
+    ipfs.name_publish(head, FEED_KEY).await?;
+
+    for old_cid in old_pages {
+        if new_pages.contains(&old_cid) {
+            continue;
+        }
+
+        if let Err(e) = ipfs.pin_rm(old_cid, false).await {
+            eprintln!("❗ IPFS could not unpin {}. Error: {}", old_cid, e);
+        }
+    }
+
+    Ok(())
+}
 
-    let (old_feed_cid, feed) = get_from_ipns::<FeedAnchor>(ipfs, FEED_KEY).await?;
+/// Split content, ordered from oldest to newest, into linked pages then pin them.
+/// Return the newest page.
+async fn build_feed<I>(ipfs: &I, content: Vec<IPLDLink>) -> Result<Cid, IpfsError>
+where
+    I: IpfsBackend,
+{
+    let mut previous: Option<IPLDLink> = None;
+
+    for chunk in content.chunks(FEED_PAGE_SIZE) {
+        let page = FeedAnchor {
+            content: chunk.to_vec(),
+            previous,
+        };
+
+        let cid = ipfs.dag_put(&page).await?;
+
+        if let Err(e) = ipfs.pin_add(cid, false).await {
+            eprintln!("❗ IPFS could not pin {}. Error: {}", cid, e);
+        }
+
+        previous = Some(cid.into());
+    }
+
+    if let Some(ipld) = previous {
+        return Ok(ipld.link);
+    }
+
+    let cid = ipfs.dag_put(&FeedAnchor::default()).await?;
+
+    if let Err(e) = ipfs.pin_add(cid, false).await {
+        eprintln!("❗ IPFS could not pin {}. Error: {}", cid, e);
+    }
+
+    Ok(cid)
+}
+
+async fn unpin_pages<I>(ipfs: &I, pages: Vec<(Cid, FeedAnchor)>)
+where
+    I: IpfsBackend,
+{
+    for (cid, _) in pages {
+        if let Err(e) = ipfs.pin_rm(cid, false).await {
+            eprintln!("❗ IPFS could not unpin {}. Error: {}", cid, e);
+        }
+    }
+}
+
+/// Unpin then return the content metadata.
+async fn unload_content<I, T>(ipfs: &I, cid: Cid) -> Result<T, IpfsError>
+where
+    I: IpfsBackend,
+    T: DeserializeOwned + Send,
+{
+    println!("Old Content => {}", cid);
 
     println!("Unpinning...");
-    let cid = cid.to_string();
-    if let Err(e) = ipfs.pin_rm(&cid, true).await {
+    if let Err(e) = ipfs.pin_rm(cid, true).await {
         eprintln!("❗ IPFS could not unpin {}. Error: {}", cid, e);
     }
 
-    let metadata: T = ipfs_dag_get_node_async(ipfs, &cid).await?;
+    let metadata: T = ipfs.dag_get(cid, None).await?;
 
-    Ok((old_feed_cid, feed, metadata))
+    Ok(metadata)
 }
 
 /// Serialize and pin metadata then update feed and update IPNS.
-async fn reload_feed<T>(
-    ipfs: &IpfsClient,
-    cid: Cid,
-    metadata: &T,
-    feed: &mut FeedAnchor,
-) -> Result<(), Error>
+async fn reload_feed<I, T>(ipfs: &I, cid: Cid, metadata: &T) -> Result<(), IpfsError>
 where
-    T: Serialize,
+    I: IpfsBackend,
+    T: Serialize + Sync,
 {
-    let new_cid = ipfs_dag_put_node_async(ipfs, metadata).await?;
+    let new_cid = ipfs.dag_put(metadata).await?;
     println!("New Content => {}", new_cid);
 
     println!("Pinning...");
-    if let Err(e) = ipfs.pin_add(&new_cid.to_string(), true).await {
-        eprintln!(
-            "❗ IPFS could not pin {}. Error: {}",
-            new_cid.to_string(),
-            e
-        );
+    if let Err(e) = ipfs.pin_add(new_cid, true).await {
+        eprintln!("❗ IPFS could not pin {}. Error: {}", new_cid, e);
     }
 
     println!("Updating Content Feed...");
 
-    let idx = match feed.content.iter().position(|&probe| probe.link == cid) {
-        Some(idx) => idx,
-        None => return Err(Error::Uncategorized("Index Not Found".into())),
-    };
+    edit_feed(ipfs, cid, |content, idx| content[idx] = new_cid.into()).await
+}
 
-    feed.content[idx] = new_cid.into();
+async fn get_video_duration<I>(ipfs: &I, video: &Cid) -> Result<f64, IpfsError>
+where
+    I: IpfsBackend,
+{
+    let days: DayNode = ipfs.dag_get(*video, Some("/time")).await?;
 
-    update_ipns(ipfs, FEED_KEY, feed).await?;
+    let mut duration = 0.0;
 
-    Ok(())
+    for (i, ipld) in days.links_to_hours.iter().enumerate().rev().take(1) {
+        let hours: HourNode = ipfs.dag_get(ipld.link, None).await?;
+
+        for (j, ipld) in hours.links_to_minutes.iter().enumerate().rev().take(1) {
+            let minutes: MinuteNode = ipfs.dag_get(ipld.link, None).await?;
+
+            let last = match minutes.links_to_seconds.last() {
+                Some(ipld) => ipld.link,
+                None => continue,
+            };
+
+            // 3600 second in 1 hour, 60 second in 1 minute
+            let seconds = i * 3600 + j * 60 + minutes.links_to_seconds.len();
+
+            let second: SecondNode = ipfs.dag_get(last, None).await?;
+
+            // Older video nodes have no duration, their last second is not counted.
+            let segment = match ipfs
+                .dag_get::<VideoNode>(second.link_to_video.link, None)
+                .await
+                .ok()
+                .and_then(|node| node.duration)
+            {
+                Some(segment) => segment,
+                None => {
+                    duration = (seconds - 1) as f64;
+                    continue;
+                }
+            };
+
+            // The archive links the last segment to the seconds its duration rounds to.
+            let trailing = (segment.round() as usize).clamp(1, seconds);
+
+            duration = (seconds - trailing) as f64 + segment;
+        }
+    }
+
+    Ok(duration)
 }
 
-async fn get_video_duration(ipfs: &IpfsClient, video: &Cid) -> Result<f64, Error> {
-    let path = format!("{}/time", video.to_string());
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let days: DayNode = ipfs_dag_get_node_async(ipfs, &path).await?;
+    use std::collections::HashMap;
 
-    let mut duration = 0.0;
+    use linked_data::ipfs::MemoryIpfs;
+    use linked_data::video::TimecodeNode;
 
-    for (i, ipld) in days.links_to_hours.iter().enumerate().rev().take(1) {
-        duration += (i * 3600) as f64; // 3600 second in 1 hour
+    #[tokio::test]
+    async fn add_micro_blog_to_feed() {
+        let ipfs = MemoryIpfs::new();
+
+        ipfs.key_gen(FEED_KEY).await.unwrap();
+        update_ipns(&ipfs, FEED_KEY, &FeedAnchor::default())
+            .await
+            .unwrap();
+
+        let author = ipfs.dag_put("Beacon").await.unwrap();
+        let post = MicroPost::create(author, "Hello World!".into());
+
+        let cid = add_content_to_feed(&ipfs, &post).await.unwrap();
+
+        let (_, feed) = get_from_ipns::<_, FeedAnchor>(&ipfs, FEED_KEY)
+            .await
+            .unwrap();
 
-        let hours: HourNode = ipfs_dag_get_node_async(ipfs, &ipld.link.to_string()).await?;
+        assert_eq!(feed.content, vec![IPLDLink::from(cid)]);
+        assert!(ipfs.is_pinned(&cid));
+    }
+
+    #[tokio::test]
+    async fn paginated_feed() {
+        let ipfs = MemoryIpfs::new();
+
+        ipfs.key_gen(FEED_KEY).await.unwrap();
+        update_ipns(&ipfs, FEED_KEY, &FeedAnchor::default())
+            .await
+            .unwrap();
 
-        for (i, ipld) in hours.links_to_minutes.iter().enumerate().rev().take(1) {
-            duration += (i * 60) as f64; // 60 second in 1 minute
+        let author = ipfs.dag_put("Beacon").await.unwrap();
 
-            let minutes: MinuteNode = ipfs_dag_get_node_async(ipfs, &ipld.link.to_string()).await?;
+        let mut posts = Vec::with_capacity(FEED_PAGE_SIZE + 1);
 
-            duration += (minutes.links_to_seconds.len() - 1) as f64;
+        for i in 0..=FEED_PAGE_SIZE {
+            let post = MicroPost::create(author, format!("Post #{}", i));
+
+            posts.push(add_content_to_feed(&ipfs, &post).await.unwrap());
         }
+
+        let (head, _) = get_from_ipns::<_, FeedAnchor>(&ipfs, FEED_KEY)
+            .await
+            .unwrap();
+        let pages = feed_pages(&ipfs, head).await.unwrap();
+
+        assert_eq!(pages.len(), 2);
+        assert_eq!(
+            pages[0].1.content,
+            vec![IPLDLink::from(posts[FEED_PAGE_SIZE])]
+        );
+        assert_eq!(pages[1].1.content.len(), FEED_PAGE_SIZE);
+        assert!(pages.iter().all(|(cid, _)| ipfs.is_pinned(cid)));
+
+        // Editing an older page rewrites it and every newer page.
+        edit_feed(&ipfs, posts[0], |content, idx| {
+            content.remove(idx);
+        })
+        .await
+        .unwrap();
+
+        let (new_head, _) = get_from_ipns::<_, FeedAnchor>(&ipfs, FEED_KEY)
+            .await
+            .unwrap();
+        let new_pages = feed_pages(&ipfs, new_head).await.unwrap();
+
+        assert_eq!(new_pages.len(), 2);
+        assert_eq!(new_pages[0].1.content, pages[0].1.content);
+        assert_eq!(new_pages[1].1.content[..], pages[1].1.content[1..]);
+        assert!(new_pages.iter().all(|(cid, _)| ipfs.is_pinned(cid)));
+        assert!(pages.iter().all(|(cid, _)| !ipfs.is_pinned(cid)));
+
+        // Emptied pages are dropped.
+        edit_feed(&ipfs, posts[FEED_PAGE_SIZE], |content, idx| {
+            content.remove(idx);
+        })
+        .await
+        .unwrap();
+
+        let (head, _) = get_from_ipns::<_, FeedAnchor>(&ipfs, FEED_KEY)
+            .await
+            .unwrap();
+        let pages = feed_pages(&ipfs, head).await.unwrap();
+
+        assert_eq!(pages.len(), 1);
+        assert_eq!(pages[0].1.content.len(), FEED_PAGE_SIZE - 1);
     }
 
-    Ok(duration)
+    #[tokio::test]
+    async fn build_paginated_feed() {
+        let ipfs = MemoryIpfs::new();
+
+        let mut content = Vec::with_capacity(FEED_PAGE_SIZE * 2 + 1);
+
+        for i in 0..(FEED_PAGE_SIZE * 2 + 1) {
+            content.push(ipfs.dag_put(&i).await.unwrap().into());
+        }
+
+        let head = build_feed(&ipfs, content.clone()).await.unwrap();
+        let pages = feed_pages(&ipfs, head).await.unwrap();
+
+        assert_eq!(pages.len(), 3);
+        assert_eq!(pages[0].1.content.len(), 1);
+        assert!(pages.iter().all(|(cid, _)| ipfs.is_pinned(cid)));
+
+        let links: Vec<IPLDLink> = pages
+            .into_iter()
+            .rev()
+            .flat_map(|(_, page)| page.content)
+            .collect();
+
+        assert_eq!(links, content);
+    }
+
+    #[tokio::test]
+    async fn video_duration_from_segments() {
+        let ipfs = MemoryIpfs::new();
+
+        let mut links_to_seconds = Vec::with_capacity(8);
+
+        let mut previous = None;
+
+        // 2 segments of 4 seconds.
+        for _ in 0..2 {
+            let video = VideoNode {
+                tracks: HashMap::new(),
+                setup: None,
+                previous,
+                duration: Some(4.0),
+            };
+            let video = ipfs.dag_put(&video).await.unwrap();
+
+            previous = Some(video.into());
+
+            let second = SecondNode {
+                link_to_video: video.into(),
+                links_to_chat: vec![],
+            };
+            let second = ipfs.dag_put(&second).await.unwrap();
+
+            links_to_seconds.extend(std::iter::repeat(IPLDLink::from(second)).take(4));
+        }
+
+        let minute = ipfs
+            .dag_put(&MinuteNode { links_to_seconds })
+            .await
+            .unwrap();
+        let hour = HourNode {
+            links_to_minutes: vec![minute.into()],
+        };
+        let day = DayNode {
+            links_to_hours: vec![ipfs.dag_put(&hour).await.unwrap().into()],
+        };
+        let timecode = TimecodeNode {
+            timecode: ipfs.dag_put(&day).await.unwrap().into(),
+        };
+        let timecode = ipfs.dag_put(&timecode).await.unwrap();
+
+        assert_eq!(get_video_duration(&ipfs, &timecode).await.unwrap(), 8.0);
+    }
 }
@@ -1,10 +1,15 @@
-use crate::actors::{Archivist, SetupAggregator, VideoAggregator};
-use crate::server::start_server;
+use crate::actors::{ArchiveState, Archivist, SetupAggregator, VideoAggregator, VideoState};
+use crate::server::{start_server, Control};
 use crate::utils::config::Configuration;
+use crate::utils::ipfs::IpfsDaemon;
+use crate::utils::metrics::Metrics;
+use crate::utils::queues::QueueMetrics;
+use crate::utils::shutdown::Shutdown;
+use crate::utils::status::DaemonStatus;
 
-use tokio::sync::mpsc::unbounded_channel;
+use tokio::sync::mpsc::channel;
 
-use ipfs_api::IpfsClient;
+use linked_data::ipfs::IpfsBackend;
 
 use structopt::StructOpt;
 
@@ -12,9 +17,9 @@ use structopt::StructOpt;
 pub struct File {}
 
 pub async fn file_cli(_file: File) {
-    let ipfs = IpfsClient::default();
+    let ipfs = IpfsDaemon::default();
 
-    if let Err(e) = ipfs.id(None).await {
+    if let Err(e) = ipfs.peer_id().await {
         eprintln!("❗ IPFS must be started beforehand. {}", e);
         return;
     }
@@ -33,16 +38,28 @@ pub async fn file_cli(_file: File) {
         input_socket_addr,
         mut archive,
         mut video,
-        chat,
+        queues,
+        ..
     } = config;
 
+    let queues = QueueMetrics::new(queues);
+    let metrics = Metrics::default();
+    let status = DaemonStatus::default();
+
     let mut handles = Vec::with_capacity(4);
 
-    let (archive_tx, archive_rx) = unbounded_channel();
+    let (archive_tx, archive_rx) = channel(queues.config.archive);
 
     archive.archive_live_chat = false;
 
-    let mut archivist = Archivist::new(ipfs.clone(), archive_rx);
+    let mut archivist = Archivist::new(
+        ipfs.clone(),
+        archive_rx,
+        ArchiveState::default(),
+        None,
+        metrics.clone(),
+        status.clone(),
+    );
 
     let archive_handle = tokio::spawn(async move {
         archivist.start().await;
@@ -50,11 +67,21 @@ pub async fn file_cli(_file: File) {
 
     handles.push(archive_handle);
 
-    let (video_tx, video_rx) = unbounded_channel();
+    let (video_tx, video_rx) = channel(queues.config.video);
 
     video.pubsub_enable = false;
 
-    let mut video = VideoAggregator::new(ipfs.clone(), video_rx, Some(archive_tx.clone()), video);
+    let mut video = VideoAggregator::new(
+        ipfs.clone(),
+        video_rx,
+        Some(archive_tx.clone()),
+        video,
+        queues.clone(),
+        metrics.clone(),
+        status.clone(),
+        VideoState::default(),
+        None,
+    );
 
     let video_handle = tokio::spawn(async move {
         video.start().await;
@@ -62,7 +89,7 @@ pub async fn file_cli(_file: File) {
 
     handles.push(video_handle);
 
-    let (setup_tx, setup_rx) = unbounded_channel();
+    let (setup_tx, setup_rx) = channel(queues.config.setup);
 
     let mut setup = SetupAggregator::new(ipfs.clone(), setup_rx, video_tx.clone());
 
@@ -72,14 +99,23 @@ pub async fn file_cli(_file: File) {
 
     handles.push(setup_handle);
 
+    let control = Control {
+        status,
+        shutdown: Shutdown::new(),
+        chat_tx: None,
+        local: false,
+    };
+
     let server_handle = tokio::spawn(async move {
         start_server(
             input_socket_addr,
             video_tx,
             setup_tx,
             Some(archive_tx),
+            queues,
+            metrics,
+            control,
             ipfs,
-            chat.topic,
         )
         .await;
     });
@@ -1,10 +1,23 @@
-use crate::actors::{Archivist, ChatAggregator, SetupAggregator, VideoAggregator};
-use crate::server::start_server;
+use crate::actors::{
+    ArchiveState, Archivist, ChatAggregator, SetupAggregator, VideoAggregator, VideoState,
+};
+use crate::server::{start_server, Control};
 use crate::utils::config::Configuration;
+use crate::utils::ipfs::IpfsDaemon;
+use crate::utils::journal::{Journal, ARCHIVE_JOURNAL, VIDEO_JOURNAL};
+use crate::utils::keys::{load_key, KEY_LOCATION};
+use crate::utils::metrics::Metrics;
+use crate::utils::queues::QueueMetrics;
+use crate::utils::shutdown::Shutdown;
+use crate::utils::status::DaemonStatus;
 
-use tokio::sync::mpsc::unbounded_channel;
+use std::path::{Path, PathBuf};
 
-use ipfs_api::IpfsClient;
+use tokio::sync::mpsc::channel;
+
+use linked_data::ipfs::IpfsBackend;
+
+use serde::de::DeserializeOwned;
 
 use structopt::StructOpt;
 
@@ -17,23 +30,57 @@ pub struct Stream {
     /// Disable all archiving.
     #[structopt(long)]
     no_archive: bool,
+
+    /// Path to the hex encoded private key of the Live address.
+    /// Used to sign video announcements. Defaults to the identity key, if any.
+    #[structopt(long, parse(from_os_str))]
+    signing_key: Option<PathBuf>,
+
+    /// Continue the video and archive of a session that did not shutdown.
+    #[structopt(long)]
+    resume: bool,
 }
 
 pub async fn stream_cli(stream: Stream) {
     let Stream {
         no_chat,
         no_archive,
+        signing_key,
+        resume,
     } = stream;
 
-    let ipfs = IpfsClient::default();
+    let ipfs = IpfsDaemon::default();
 
-    if ipfs.id(None).await.is_err() {
+    if ipfs.peer_id().await.is_err() {
         eprintln!("❗ IPFS must be started beforehand. Aborting...");
         return;
     }
 
     println!("Initialization...");
 
+    let video_journal = Journal::new(VIDEO_JOURNAL);
+    let archive_journal = Journal::new(ARCHIVE_JOURNAL);
+
+    if !resume && (video_journal.exists() || archive_journal.exists()) {
+        eprintln!("❗ A previous session did not shutdown. Use --resume or finalize-archive.");
+        return;
+    }
+
+    if resume && !video_journal.exists() && !archive_journal.exists() {
+        eprintln!("❗ No session to resume.");
+        return;
+    }
+
+    let video_state = match load_state::<VideoState>(&video_journal, resume).await {
+        Some(state) => state,
+        None => return,
+    };
+
+    let archive_state = match load_state::<ArchiveState>(&archive_journal, resume).await {
+        Some(state) => state,
+        None => return,
+    };
+
     let config = match Configuration::from_file().await {
         Ok(conf) => conf,
         Err(e) => {
@@ -42,30 +89,68 @@ pub async fn stream_cli(stream: Stream) {
         }
     };
 
+    let key = match signing_key {
+        Some(path) => match load_key(&path).await {
+            Ok(key) => Some(key),
+            Err(e) => {
+                eprintln!("❗ Invalid signing key {:?}. {}", path, e);
+                return;
+            }
+        },
+        None => match load_key(Path::new(KEY_LOCATION)).await {
+            Ok(key) => Some(key),
+            Err(_) => {
+                println!("No signing key, video announcements will not be signed.");
+                None
+            }
+        },
+    };
+
     let Configuration {
         input_socket_addr,
         mut archive,
         mut video,
         chat,
+        queues,
+        ..
     } = config;
 
+    let queues = QueueMetrics::new(queues);
+    let metrics = Metrics::default();
+    let status = DaemonStatus::default();
+    let shutdown = Shutdown::new();
+
     let mut handles = Vec::with_capacity(4);
 
-    let topic = chat.topic.clone();
+    let mut chat_tx = None;
 
     let archive_tx = {
         if !no_archive {
-            let (archive_tx, archive_rx) = unbounded_channel();
+            let (archive_tx, archive_rx) = channel(queues.config.archive);
 
             if !no_chat {
-                let mut chat =
-                    match ChatAggregator::new(ipfs.clone(), archive_tx.clone(), chat).await {
-                        Ok(chat) => chat,
-                        Err(e) => {
-                            eprintln!("❗ IPFS: {:#?}", e);
-                            return;
-                        }
-                    };
+                let (control_tx, control_rx) = channel(1);
+
+                chat_tx = Some(control_tx);
+
+                let mut chat = match ChatAggregator::new(
+                    ipfs.clone(),
+                    archive_tx.clone(),
+                    control_rx,
+                    shutdown.clone(),
+                    queues.clone(),
+                    metrics.clone(),
+                    status.clone(),
+                    chat,
+                )
+                .await
+                {
+                    Ok(chat) => chat,
+                    Err(e) => {
+                        eprintln!("❗ IPFS: {:#?}", e);
+                        return;
+                    }
+                };
 
                 let chat_handle = tokio::spawn(async move {
                     chat.start().await;
@@ -76,7 +161,14 @@ pub async fn stream_cli(stream: Stream) {
 
             archive.archive_live_chat = !no_chat;
 
-            let mut archivist = Archivist::new(ipfs.clone(), archive_rx);
+            let mut archivist = Archivist::new(
+                ipfs.clone(),
+                archive_rx,
+                archive_state,
+                Some(archive_journal),
+                metrics.clone(),
+                status.clone(),
+            );
 
             let archive_handle = tokio::spawn(async move {
                 archivist.start().await;
@@ -90,11 +182,22 @@ pub async fn stream_cli(stream: Stream) {
         }
     };
 
-    let (video_tx, video_rx) = unbounded_channel();
+    let (video_tx, video_rx) = channel(queues.config.video);
 
     video.pubsub_enable = true;
-
-    let mut video = VideoAggregator::new(ipfs.clone(), video_rx, archive_tx.clone(), video);
+    video.signing_key = key;
+
+    let mut video = VideoAggregator::new(
+        ipfs.clone(),
+        video_rx,
+        archive_tx.clone(),
+        video,
+        queues.clone(),
+        metrics.clone(),
+        status.clone(),
+        video_state,
+        Some(video_journal),
+    );
 
     let video_handle = tokio::spawn(async move {
         video.start().await;
@@ -102,7 +205,7 @@ pub async fn stream_cli(stream: Stream) {
 
     handles.push(video_handle);
 
-    let (setup_tx, setup_rx) = unbounded_channel();
+    let (setup_tx, setup_rx) = channel(queues.config.setup);
 
     let mut setup = SetupAggregator::new(ipfs.clone(), setup_rx, video_tx.clone());
 
@@ -112,14 +215,23 @@ pub async fn stream_cli(stream: Stream) {
 
     handles.push(setup_handle);
 
+    let control = Control {
+        status,
+        shutdown,
+        chat_tx,
+        local: false,
+    };
+
     let server_handle = tokio::spawn(async move {
         start_server(
             input_socket_addr,
             video_tx,
             setup_tx,
             archive_tx,
+            queues,
+            metrics,
+            control,
             ipfs,
-            topic,
         )
         .await;
     });
@@ -132,3 +244,21 @@ pub async fn stream_cli(stream: Stream) {
         }
     }
 }
+
+/// Load the journal when resuming, a fresh state otherwise.
+async fn load_state<T>(journal: &Journal, resume: bool) -> Option<T>
+where
+    T: DeserializeOwned + Default,
+{
+    if !resume || !journal.exists() {
+        return Some(T::default());
+    }
+
+    match journal.load().await {
+        Ok(state) => Some(state),
+        Err(e) => {
+            eprintln!("❗ Journal corrupted. {}", e);
+            None
+        }
+    }
+}
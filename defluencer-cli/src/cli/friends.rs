@@ -1,9 +1,8 @@
 use crate::utils::dag_nodes::{get_from_ipns, update_ipns};
-
-use ipfs_api::response::Error;
-use ipfs_api::IpfsClient;
+use crate::utils::ipfs::IpfsDaemon;
 
 use linked_data::friends::{Friend, Friendlies};
+use linked_data::ipfs::{IpfsBackend, IpfsError};
 
 use cid::Cid;
 
@@ -52,8 +51,8 @@ pub struct AddFriend {
     ens: Option<String>,
 }
 
-async fn add_friend(command: AddFriend) -> Result<(), Error> {
-    let ipfs = IpfsClient::default();
+async fn add_friend(command: AddFriend) -> Result<(), IpfsError> {
+    let ipfs = IpfsDaemon::default();
 
     let AddFriend { beacon, ens } = command;
 
@@ -64,16 +63,12 @@ async fn add_friend(command: AddFriend) -> Result<(), Error> {
         (None, Some(name)) => Friend {
             friend: Either::Left(name),
         },
-        (_, _) => {
-            return Err(Error::Uncategorized(
-                "Use either beacon Cid Or ENS domain name".into(),
-            ))
-        }
+        (_, _) => return Err(IpfsError::new("Use either beacon Cid Or ENS domain name")),
     };
 
     println!("Adding Friend {:?}", &new_friend.friend);
 
-    let (old_friends_cid, mut list) = get_from_ipns::<Friendlies>(&ipfs, FRIENDS_KEY).await?;
+    let (old_friends_cid, mut list) = get_from_ipns::<_, Friendlies>(&ipfs, FRIENDS_KEY).await?;
 
     list.friends.insert(new_friend);
 
@@ -83,9 +78,8 @@ async fn add_friend(command: AddFriend) -> Result<(), Error> {
 
     println!("Unpinning Old List...");
 
-    let ofc = old_friends_cid.to_string();
-    if let Err(e) = ipfs.pin_rm(&ofc, false).await {
-        eprintln!("❗ IPFS could not unpin {}. Error: {}", ofc, e);
+    if let Err(e) = ipfs.pin_rm(old_friends_cid, false).await {
+        eprintln!("❗ IPFS could not unpin {}. Error: {}", old_friends_cid, e);
     }
 
     println!("✅ Friend Added");
@@ -104,8 +98,8 @@ pub struct RemoveFriend {
     ens: Option<String>,
 }
 
-async fn remove_friend(command: RemoveFriend) -> Result<(), Error> {
-    let ipfs = IpfsClient::default();
+async fn remove_friend(command: RemoveFriend) -> Result<(), IpfsError> {
+    let ipfs = IpfsDaemon::default();
 
     let RemoveFriend { beacon, ens } = command;
 
@@ -116,16 +110,12 @@ async fn remove_friend(command: RemoveFriend) -> Result<(), Error> {
         (None, Some(name)) => Friend {
             friend: Either::Left(name),
         },
-        (_, _) => {
-            return Err(Error::Uncategorized(
-                "Use either beacon Cid Or ENS domain name".into(),
-            ))
-        }
+        (_, _) => return Err(IpfsError::new("Use either beacon Cid Or ENS domain name")),
     };
 
     println!("Removing Friend {:?}", &old_friend.friend);
 
-    let (old_friends_cid, mut list) = get_from_ipns::<Friendlies>(&ipfs, FRIENDS_KEY).await?;
+    let (old_friends_cid, mut list) = get_from_ipns::<_, Friendlies>(&ipfs, FRIENDS_KEY).await?;
 
     list.friends.remove(&old_friend);
 
@@ -135,9 +125,8 @@ async fn remove_friend(command: RemoveFriend) -> Result<(), Error> {
 
     println!("Unpinning Old List...");
 
-    let ofc = old_friends_cid.to_string();
-    if let Err(e) = ipfs.pin_rm(&ofc, false).await {
-        eprintln!("❗ IPFS could not unpin {}. Error: {}", ofc, e);
+    if let Err(e) = ipfs.pin_rm(old_friends_cid, false).await {
+        eprintln!("❗ IPFS could not unpin {}. Error: {}", old_friends_cid, e);
     }
 
     println!("✅ Friend Removed");
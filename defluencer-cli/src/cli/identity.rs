@@ -1,11 +1,15 @@
+use crate::cli::moderation::parse_address;
 use crate::utils::dag_nodes::{get_from_ipns, update_ipns};
+use crate::utils::ipfs::IpfsDaemon;
+use crate::utils::keys::{
+    decrypt_keystore, key_address, load_key, parse_key, random_key, save_key, KEY_LOCATION,
+};
 
-//use std::path::PathBuf;
-
-use ipfs_api::response::Error;
-use ipfs_api::IpfsClient;
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
 
 use linked_data::identity::Identity;
+use linked_data::ipfs::{IpfsBackend, IpfsError};
 
 use cid::Cid;
 
@@ -26,12 +30,20 @@ enum Command {
 
     /// Choose a new image avatar.
     Avatar(UpdateAvatar),
+
+    /// Choose the address signing your comments.
+    Address(UpdateAddress),
+
+    /// Manage the local Ethereum key used for signing.
+    Key(KeyCommands),
 }
 
 pub async fn identity_cli(cli: IdentityCLI) {
     let res = match cli.cmd {
         Command::Name(name) => update_name(name).await,
         Command::Avatar(avatar) => update_avatar(avatar).await,
+        Command::Address(address) => update_address(address).await,
+        Command::Key(key) => return key_command(key).await,
     };
 
     if let Err(e) = res {
@@ -46,20 +58,19 @@ pub struct UpdateName {
     name: String,
 }
 
-async fn update_name(command: UpdateName) -> Result<(), Error> {
-    let ipfs = IpfsClient::default();
+async fn update_name(command: UpdateName) -> Result<(), IpfsError> {
+    let ipfs = IpfsDaemon::default();
 
     let UpdateName { name } = command;
 
-    let (old_id_cid, mut id) = get_from_ipns::<Identity>(&ipfs, IDENTITY_KEY).await?;
+    let (old_id_cid, mut id) = get_from_ipns::<_, Identity>(&ipfs, IDENTITY_KEY).await?;
 
     id.display_name = name;
 
     update_ipns(&ipfs, IDENTITY_KEY, &id).await?;
 
-    let oidc = old_id_cid.to_string();
-    if let Err(e) = ipfs.pin_rm(&oidc, false).await {
-        eprintln!("❗ IPFS could not unpin {}. Error: {}", oidc, e);
+    if let Err(e) = ipfs.pin_rm(old_id_cid, false).await {
+        eprintln!("❗ IPFS could not unpin {}. Error: {}", old_id_cid, e);
     }
 
     println!("✅ Display Name Updated");
@@ -77,23 +88,173 @@ pub struct UpdateAvatar {
     //path: Option<PathBuf>,
 }
 
-async fn update_avatar(command: UpdateAvatar) -> Result<(), Error> {
-    let ipfs = IpfsClient::default();
+async fn update_avatar(command: UpdateAvatar) -> Result<(), IpfsError> {
+    let ipfs = IpfsDaemon::default();
 
     let UpdateAvatar { image } = command;
 
-    let (old_id_cid, mut id) = get_from_ipns::<Identity>(&ipfs, IDENTITY_KEY).await?;
+    let (old_id_cid, mut id) = get_from_ipns::<_, Identity>(&ipfs, IDENTITY_KEY).await?;
 
     id.avatar = image.into();
 
     update_ipns(&ipfs, IDENTITY_KEY, &id).await?;
 
-    let ofc = old_id_cid.to_string();
-    if let Err(e) = ipfs.pin_rm(&ofc, false).await {
-        eprintln!("❗ IPFS could not unpin {}. Error: {}", ofc, e);
+    if let Err(e) = ipfs.pin_rm(old_id_cid, false).await {
+        eprintln!("❗ IPFS could not unpin {}. Error: {}", old_id_cid, e);
     }
 
     println!("✅ Avatar Updated");
 
     Ok(())
 }
+
+#[derive(Debug, StructOpt)]
+pub struct UpdateAddress {
+    /// Ethereum address. Defaults to the identity key address.
+    #[structopt(short, long)]
+    address: Option<String>,
+}
+
+async fn update_address(command: UpdateAddress) -> Result<(), IpfsError> {
+    let ipfs = IpfsDaemon::default();
+
+    let address = match command.address {
+        Some(address) => parse_address(&address),
+        None => {
+            let key = load_key(Path::new(KEY_LOCATION))
+                .await
+                .map_err(IpfsError::new)?;
+
+            key_address(&key).map_err(IpfsError::new)?
+        }
+    };
+
+    let (old_id_cid, mut id) = get_from_ipns::<_, Identity>(&ipfs, IDENTITY_KEY).await?;
+
+    id.eth_address = Some(address);
+
+    update_ipns(&ipfs, IDENTITY_KEY, &id).await?;
+
+    if let Err(e) = ipfs.pin_rm(old_id_cid, false).await {
+        eprintln!("❗ IPFS could not unpin {}. Error: {}", old_id_cid, e);
+    }
+
+    println!("✅ Comments Address Set To 0x{}", hex::encode(address));
+
+    Ok(())
+}
+
+#[derive(Debug, StructOpt)]
+pub struct KeyCommands {
+    #[structopt(subcommand)]
+    cmd: KeyCommand,
+}
+
+#[derive(Debug, StructOpt)]
+enum KeyCommand {
+    /// Create a new random key.
+    Create(CreateKey),
+
+    /// Import an existing key.
+    Import(ImportKey),
+
+    /// Show the address of the local key.
+    Show,
+}
+
+async fn key_command(cli: KeyCommands) {
+    let res = match cli.cmd {
+        KeyCommand::Create(args) => create_key(args).await,
+        KeyCommand::Import(args) => import_key(args).await,
+        KeyCommand::Show => show_key().await,
+    };
+
+    if let Err(e) = res {
+        eprintln!("❗ Key: {}", e);
+    }
+}
+
+#[derive(Debug, StructOpt)]
+pub struct CreateKey {
+    /// Replace the existing key.
+    #[structopt(long)]
+    force: bool,
+}
+
+async fn create_key(args: CreateKey) -> Result<(), Error> {
+    if !args.force && Path::new(KEY_LOCATION).exists() {
+        eprintln!("❗ A key already exists. Use --force to replace it.");
+        return Ok(());
+    }
+
+    let key = random_key();
+
+    save_key(&key, args.force).await?;
+
+    println!(
+        "✅ Key Created. Address 0x{}",
+        hex::encode(key_address(&key)?)
+    );
+
+    Ok(())
+}
+
+#[derive(Debug, StructOpt)]
+pub struct ImportKey {
+    /// Hex encoded private key.
+    #[structopt(long, conflicts_with = "keystore", required_unless = "keystore")]
+    hex: Option<String>,
+
+    /// Path to keystore JSON file. Password will be asked.
+    #[structopt(long, parse(from_os_str))]
+    keystore: Option<PathBuf>,
+
+    /// Replace the existing key.
+    #[structopt(long)]
+    force: bool,
+}
+
+async fn import_key(args: ImportKey) -> Result<(), Error> {
+    let ImportKey {
+        hex: hex_key,
+        keystore,
+        force,
+    } = args;
+
+    if !force && Path::new(KEY_LOCATION).exists() {
+        eprintln!("❗ A key already exists. Use --force to replace it.");
+        return Ok(());
+    }
+
+    let key = match (hex_key, keystore) {
+        (Some(hex_key), _) => parse_key(&hex_key)?,
+        (None, Some(path)) => {
+            let password = rpassword::read_password_from_tty(Some("Keystore Password: "))?;
+
+            decrypt_keystore(&path, &password)?
+        }
+        (None, None) => {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "either --hex or --keystore is required",
+            ))
+        }
+    };
+
+    save_key(&key, force).await?;
+
+    println!(
+        "✅ Key Imported. Address 0x{}",
+        hex::encode(key_address(&key)?)
+    );
+
+    Ok(())
+}
+
+async fn show_key() -> Result<(), Error> {
+    let key = load_key(Path::new(KEY_LOCATION)).await?;
+
+    println!("✅ Address 0x{}", hex::encode(key_address(&key)?));
+
+    Ok(())
+}
@@ -1,8 +1,12 @@
+use crate::cli::moderation::parse_address;
+use crate::utils::config::Configuration;
 use crate::utils::dag_nodes::{get_from_ipns, update_ipns};
+use crate::utils::ipfs::IpfsDaemon;
+use crate::utils::keys::{key_address, load_key, KEY_LOCATION};
 
-use ipfs_api::response::Error;
-use ipfs_api::IpfsClient;
+use std::path::Path;
 
+use linked_data::ipfs::{IpfsBackend, IpfsError};
 use linked_data::live::Live;
 
 use structopt::StructOpt;
@@ -22,12 +26,20 @@ enum Command {
 
     /// Choose the IPFS node that will be streaming.
     PeerID(UpdatePeerId),
+
+    /// Choose live chat settings.
+    Chat(UpdateChat),
+
+    /// Choose the address signing video announcements.
+    Address(UpdateAddress),
 }
 
 pub async fn live_cli(cli: LiveCLI) {
     let res = match cli.cmd {
         Command::Topics(topics) => update_topics(topics).await,
         Command::PeerID(peer) => update_peer_id(peer).await,
+        Command::Chat(chat) => update_chat(chat).await,
+        Command::Address(address) => update_address(address).await,
     };
 
     if let Err(e) = res {
@@ -46,12 +58,12 @@ pub struct UpdateTopics {
     video: Option<String>,
 }
 
-async fn update_topics(command: UpdateTopics) -> Result<(), Error> {
-    let ipfs = IpfsClient::default();
+async fn update_topics(command: UpdateTopics) -> Result<(), IpfsError> {
+    let ipfs = IpfsDaemon::default();
 
     let UpdateTopics { chat, video } = command;
 
-    let (old_live_cid, mut live) = get_from_ipns::<Live>(&ipfs, LIVE_KEY).await?;
+    let (old_live_cid, mut live) = get_from_ipns::<_, Live>(&ipfs, LIVE_KEY).await?;
 
     if let Some(chat_topic) = chat {
         live.chat_topic = chat_topic;
@@ -63,9 +75,8 @@ async fn update_topics(command: UpdateTopics) -> Result<(), Error> {
 
     update_ipns(&ipfs, LIVE_KEY, &live).await?;
 
-    let ofc = old_live_cid.to_string();
-    if let Err(e) = ipfs.pin_rm(&ofc, false).await {
-        eprintln!("❗ IPFS could not unpin {}. Error: {}", ofc, e);
+    if let Err(e) = ipfs.pin_rm(old_live_cid, false).await {
+        eprintln!("❗ IPFS could not unpin {}. Error: {}", old_live_cid, e);
     }
 
     println!("✅ Display Name Updated");
@@ -80,23 +91,101 @@ pub struct UpdatePeerId {
     peer_id: String,
 }
 
-async fn update_peer_id(command: UpdatePeerId) -> Result<(), Error> {
-    let ipfs = IpfsClient::default();
+async fn update_peer_id(command: UpdatePeerId) -> Result<(), IpfsError> {
+    let ipfs = IpfsDaemon::default();
 
     let UpdatePeerId { peer_id } = command;
 
-    let (old_live_cid, mut live) = get_from_ipns::<Live>(&ipfs, LIVE_KEY).await?;
+    let (old_live_cid, mut live) = get_from_ipns::<_, Live>(&ipfs, LIVE_KEY).await?;
 
     live.peer_id = peer_id;
 
     update_ipns(&ipfs, LIVE_KEY, &live).await?;
 
-    let ofc = old_live_cid.to_string();
-    if let Err(e) = ipfs.pin_rm(&ofc, false).await {
-        eprintln!("❗ IPFS could not unpin {}. Error: {}", ofc, e);
+    if let Err(e) = ipfs.pin_rm(old_live_cid, false).await {
+        eprintln!("❗ IPFS could not unpin {}. Error: {}", old_live_cid, e);
     }
 
     println!("✅ Avatar Updated");
 
     Ok(())
 }
+
+#[derive(Debug, StructOpt)]
+pub struct UpdateChat {
+    /// Minimum number of seconds between messages of a user. 0 to disable.
+    #[structopt(short, long)]
+    slow_mode: u64,
+}
+
+async fn update_chat(command: UpdateChat) -> Result<(), IpfsError> {
+    let ipfs = IpfsDaemon::default();
+
+    let UpdateChat { slow_mode } = command;
+
+    let mut config = match Configuration::from_file().await {
+        Ok(conf) => conf,
+        Err(e) => {
+            eprintln!("❗ Cannot get configuration file. Error: {:#?}", e);
+            eprintln!("Using Default...");
+            Configuration::default()
+        }
+    };
+
+    config.chat.slow_mode = slow_mode;
+
+    config.save_to_file().await.map_err(IpfsError::new)?;
+
+    let (old_live_cid, mut live) = get_from_ipns::<_, Live>(&ipfs, LIVE_KEY).await?;
+
+    live.slow_mode = slow_mode;
+
+    update_ipns(&ipfs, LIVE_KEY, &live).await?;
+
+    if let Err(e) = ipfs.pin_rm(old_live_cid, false).await {
+        eprintln!("❗ IPFS could not unpin {}. Error: {}", old_live_cid, e);
+    }
+
+    println!("✅ Chat Settings Updated");
+
+    Ok(())
+}
+
+#[derive(Debug, StructOpt)]
+pub struct UpdateAddress {
+    /// Ethereum address. Defaults to the identity key address.
+    #[structopt(short, long)]
+    address: Option<String>,
+}
+
+async fn update_address(command: UpdateAddress) -> Result<(), IpfsError> {
+    let ipfs = IpfsDaemon::default();
+
+    let address = match command.address {
+        Some(address) => parse_address(&address),
+        None => {
+            let key = load_key(Path::new(KEY_LOCATION))
+                .await
+                .map_err(IpfsError::new)?;
+
+            key_address(&key).map_err(IpfsError::new)?
+        }
+    };
+
+    let (old_live_cid, mut live) = get_from_ipns::<_, Live>(&ipfs, LIVE_KEY).await?;
+
+    live.address = Some(address);
+
+    update_ipns(&ipfs, LIVE_KEY, &live).await?;
+
+    if let Err(e) = ipfs.pin_rm(old_live_cid, false).await {
+        eprintln!("❗ IPFS could not unpin {}. Error: {}", old_live_cid, e);
+    }
+
+    println!(
+        "✅ Video Announcements Address Set To 0x{}",
+        hex::encode(address)
+    );
+
+    Ok(())
+}
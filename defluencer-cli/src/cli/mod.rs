@@ -1,3 +1,4 @@
+pub mod archive;
 pub mod beacon;
 pub mod comments;
 pub mod content;
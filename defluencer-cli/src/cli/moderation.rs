@@ -1,9 +1,11 @@
 use crate::utils::dag_nodes::{get_from_ipns, update_ipns};
+use crate::utils::ipfs::IpfsDaemon;
 
 use hex::FromHex;
 
-use ipfs_api::response::Error;
-use ipfs_api::IpfsClient;
+use linked_data::comments::CommentModeration;
+use linked_data::filter::{Blocklist, ChatFilter};
+use linked_data::ipfs::{IpfsBackend, IpfsError};
 
 use cid::Cid;
 
@@ -11,6 +13,8 @@ use structopt::StructOpt;
 
 pub const BANS_KEY: &str = "bans";
 pub const MODS_KEY: &str = "mods";
+pub const FILTERS_KEY: &str = "filters";
+pub const COMMENT_MODERATION_KEY: &str = "comment_moderation";
 
 #[derive(Debug, StructOpt)]
 pub struct Moderation {
@@ -25,12 +29,20 @@ enum Command {
 
     /// Manage list of moderators.
     Mods(ModCommands),
+
+    /// Manage chat content filters.
+    Filters(FilterCommands),
+
+    /// Hide comments on your content.
+    Comments(CommentCommands),
 }
 
 pub async fn moderation_cli(cli: Moderation) {
     let res = match cli.cmd {
         Command::Ban(update) => ban_command(update).await,
         Command::Mods(update) => mod_command(update).await,
+        Command::Filters(update) => filter_command(update).await,
+        Command::Comments(update) => comment_command(update).await,
     };
 
     if let Err(e) = res {
@@ -56,7 +68,7 @@ enum BanCommand {
     ReplaceList(ReplaceBanList),
 }
 
-async fn ban_command(cli: BanCommands) -> Result<(), Error> {
+async fn ban_command(cli: BanCommands) -> Result<(), IpfsError> {
     match cli.cmd {
         BanCommand::Add(args) => ban_user(args).await,
         BanCommand::Remove(args) => unban_user(args).await,
@@ -71,23 +83,22 @@ pub struct Ban {
     address: String,
 }
 
-async fn ban_user(args: Ban) -> Result<(), Error> {
+async fn ban_user(args: Ban) -> Result<(), IpfsError> {
     let address = parse_address(&args.address);
 
     println!("Banning User...");
 
-    let ipfs = IpfsClient::default();
+    let ipfs = IpfsDaemon::default();
 
     let (old_ban_cid, mut ban_list) =
-        get_from_ipns::<linked_data::moderation::Bans>(&ipfs, BANS_KEY).await?;
+        get_from_ipns::<_, linked_data::moderation::Bans>(&ipfs, BANS_KEY).await?;
 
     ban_list.banned.insert(address);
 
     update_ipns(&ipfs, BANS_KEY, &ban_list).await?;
 
-    let rm_cid = old_ban_cid.to_string();
-    if let Err(e) = ipfs.pin_rm(&rm_cid, false).await {
-        eprintln!("❗ IPFS could not unpin {}. Error: {}", rm_cid, e);
+    if let Err(e) = ipfs.pin_rm(old_ban_cid, false).await {
+        eprintln!("❗ IPFS could not unpin {}. Error: {}", old_ban_cid, e);
     }
 
     println!("✅ User {} Banned", args.address);
@@ -102,22 +113,21 @@ pub struct UnBan {
     address: String,
 }
 
-async fn unban_user(args: UnBan) -> Result<(), Error> {
+async fn unban_user(args: UnBan) -> Result<(), IpfsError> {
     let address = parse_address(&args.address);
 
     println!("Unbanning User...");
 
-    let ipfs = IpfsClient::default();
+    let ipfs = IpfsDaemon::default();
 
     let (old_ban_cid, mut ban_list) =
-        get_from_ipns::<linked_data::moderation::Bans>(&ipfs, BANS_KEY).await?;
+        get_from_ipns::<_, linked_data::moderation::Bans>(&ipfs, BANS_KEY).await?;
 
     if ban_list.banned.remove(&address) {
         update_ipns(&ipfs, BANS_KEY, &ban_list).await?;
 
-        let rm_cid = old_ban_cid.to_string();
-        if let Err(e) = ipfs.pin_rm(&rm_cid, false).await {
-            eprintln!("❗ IPFS could not unpin {}. Error: {}", rm_cid, e);
+        if let Err(e) = ipfs.pin_rm(old_ban_cid, false).await {
+            eprintln!("❗ IPFS could not unpin {}. Error: {}", old_ban_cid, e);
         }
 
         println!("✅ User {} Unbanned", args.address);
@@ -137,27 +147,20 @@ pub struct ReplaceBanList {
     cid: Cid,
 }
 
-async fn replace_ban_list(args: ReplaceBanList) -> Result<(), Error> {
+async fn replace_ban_list(args: ReplaceBanList) -> Result<(), IpfsError> {
     println!("Replacing Ban List...");
 
-    let ipfs = IpfsClient::default();
+    let ipfs = IpfsDaemon::default();
 
-    let (old_ban_cid, _) = get_from_ipns::<linked_data::moderation::Bans>(&ipfs, BANS_KEY).await?;
+    let (old_ban_cid, _) =
+        get_from_ipns::<_, linked_data::moderation::Bans>(&ipfs, BANS_KEY).await?;
 
-    ipfs.pin_add(&args.cid.to_string(), false).await?;
+    ipfs.pin_add(args.cid, false).await?;
 
-    ipfs.name_publish(
-        &args.cid.to_string(),
-        true,
-        Some("4320h"), // 6 months
-        None,
-        Some(BANS_KEY),
-    )
-    .await?;
+    ipfs.name_publish(args.cid, BANS_KEY).await?;
 
-    let rm_cid = old_ban_cid.to_string();
-    if let Err(e) = ipfs.pin_rm(&rm_cid, false).await {
-        eprintln!("❗ IPFS could not unpin {}. Error: {}", rm_cid, e);
+    if let Err(e) = ipfs.pin_rm(old_ban_cid, false).await {
+        eprintln!("❗ IPFS could not unpin {}. Error: {}", old_ban_cid, e);
     }
 
     println!("✅ Previous Ban List Replaced with {:?}", &args.cid);
@@ -181,13 +184,17 @@ enum ModCommand {
 
     /// Replace the current moderator list with another.
     ReplaceModList(ReplaceModList),
+
+    /// Set the beacon owner, who can always moderate.
+    Owner(Owner),
 }
 
-async fn mod_command(cli: ModCommands) -> Result<(), Error> {
+async fn mod_command(cli: ModCommands) -> Result<(), IpfsError> {
     match cli.cmd {
         ModCommand::Add(args) => mod_user(args).await,
         ModCommand::Remove(args) => unmod_user(args).await,
         ModCommand::ReplaceModList(args) => replace_mod_list(args).await,
+        ModCommand::Owner(args) => set_owner(args).await,
     }
 }
 
@@ -198,23 +205,22 @@ pub struct Mod {
     address: String,
 }
 
-async fn mod_user(args: Mod) -> Result<(), Error> {
+async fn mod_user(args: Mod) -> Result<(), IpfsError> {
     let address = parse_address(&args.address);
 
     println!("Promoting User...");
 
-    let ipfs = IpfsClient::default();
+    let ipfs = IpfsDaemon::default();
 
     let (old_mods_cid, mut mods_list) =
-        get_from_ipns::<linked_data::moderation::Moderators>(&ipfs, MODS_KEY).await?;
+        get_from_ipns::<_, linked_data::moderation::Moderators>(&ipfs, MODS_KEY).await?;
 
     mods_list.mods.insert(address);
 
     update_ipns(&ipfs, MODS_KEY, &mods_list).await?;
 
-    let rm_cid = old_mods_cid.to_string();
-    if let Err(e) = ipfs.pin_rm(&rm_cid, false).await {
-        eprintln!("❗ IPFS could not unpin {}. Error: {}", rm_cid, e);
+    if let Err(e) = ipfs.pin_rm(old_mods_cid, false).await {
+        eprintln!("❗ IPFS could not unpin {}. Error: {}", old_mods_cid, e);
     }
 
     println!("✅ User {} Promoted To Moderator Position", args.address);
@@ -229,21 +235,20 @@ pub struct UnMod {
     address: String,
 }
 
-async fn unmod_user(args: UnMod) -> Result<(), Error> {
+async fn unmod_user(args: UnMod) -> Result<(), IpfsError> {
     let address = parse_address(&args.address);
     println!("Demoting Moderator...");
 
-    let ipfs = IpfsClient::default();
+    let ipfs = IpfsDaemon::default();
 
     let (old_mods_cid, mut mods_list) =
-        get_from_ipns::<linked_data::moderation::Moderators>(&ipfs, MODS_KEY).await?;
+        get_from_ipns::<_, linked_data::moderation::Moderators>(&ipfs, MODS_KEY).await?;
 
     if mods_list.mods.remove(&address) {
         update_ipns(&ipfs, MODS_KEY, &mods_list).await?;
 
-        let rm_cid = old_mods_cid.to_string();
-        if let Err(e) = ipfs.pin_rm(&rm_cid, false).await {
-            eprintln!("❗ IPFS could not unpin {}. Error: {}", rm_cid, e);
+        if let Err(e) = ipfs.pin_rm(old_mods_cid, false).await {
+            eprintln!("❗ IPFS could not unpin {}. Error: {}", old_mods_cid, e);
         }
 
         println!("✅ Moderator {} Demoted", args.address);
@@ -263,28 +268,20 @@ pub struct ReplaceModList {
     cid: Cid,
 }
 
-async fn replace_mod_list(args: ReplaceModList) -> Result<(), Error> {
+async fn replace_mod_list(args: ReplaceModList) -> Result<(), IpfsError> {
     println!("Replacing Moderator List...");
 
-    let ipfs = IpfsClient::default();
+    let ipfs = IpfsDaemon::default();
 
     let (old_mods_cid, _) =
-        get_from_ipns::<linked_data::moderation::Moderators>(&ipfs, MODS_KEY).await?;
+        get_from_ipns::<_, linked_data::moderation::Moderators>(&ipfs, MODS_KEY).await?;
 
-    ipfs.pin_add(&args.cid.to_string(), false).await?;
+    ipfs.pin_add(args.cid, false).await?;
 
-    ipfs.name_publish(
-        &args.cid.to_string(),
-        true,
-        Some("4320h"), // 6 months
-        None,
-        Some(MODS_KEY),
-    )
-    .await?;
+    ipfs.name_publish(args.cid, MODS_KEY).await?;
 
-    let rm_cid = old_mods_cid.to_string();
-    if let Err(e) = ipfs.pin_rm(&rm_cid, false).await {
-        eprintln!("❗ IPFS could not unpin {}. Error: {}", rm_cid, e);
+    if let Err(e) = ipfs.pin_rm(old_mods_cid, false).await {
+        eprintln!("❗ IPFS could not unpin {}. Error: {}", old_mods_cid, e);
     }
 
     println!("✅ Previous Moderator List Replaced with {:?}", &args.cid);
@@ -292,7 +289,277 @@ async fn replace_mod_list(args: ReplaceModList) -> Result<(), Error> {
     Ok(())
 }
 
-fn parse_address(addrs: &str) -> [u8; 20] {
+#[derive(Debug, StructOpt)]
+pub struct Owner {
+    /// Ethereum address.
+    #[structopt(long)]
+    address: String,
+}
+
+async fn set_owner(args: Owner) -> Result<(), IpfsError> {
+    let address = parse_address(&args.address);
+
+    println!("Updating Owner...");
+
+    let ipfs = IpfsDaemon::default();
+
+    let (old_mods_cid, mut mods_list) =
+        get_from_ipns::<_, linked_data::moderation::Moderators>(&ipfs, MODS_KEY).await?;
+
+    mods_list.owner = Some(address);
+
+    update_ipns(&ipfs, MODS_KEY, &mods_list).await?;
+
+    if let Err(e) = ipfs.pin_rm(old_mods_cid, false).await {
+        eprintln!("❗ IPFS could not unpin {}. Error: {}", old_mods_cid, e);
+    }
+
+    println!("✅ Owner Set To {}", args.address);
+
+    Ok(())
+}
+
+#[derive(Debug, StructOpt)]
+struct FilterCommands {
+    #[structopt(subcommand)]
+    cmd: FilterCommand,
+}
+
+#[derive(Debug, StructOpt)]
+enum FilterCommand {
+    /// Block words or regular expressions.
+    Block(Block),
+
+    /// Unblock words or regular expressions.
+    Unblock(Unblock),
+
+    /// Update message length, link and duplicate settings.
+    Settings(FilterSettings),
+}
+
+async fn filter_command(cli: FilterCommands) -> Result<(), IpfsError> {
+    match cli.cmd {
+        FilterCommand::Block(args) => block(args).await,
+        FilterCommand::Unblock(args) => unblock(args).await,
+        FilterCommand::Settings(args) => update_filter_settings(args).await,
+    }
+}
+
+#[derive(Debug, StructOpt)]
+pub struct Block {
+    /// Case insensitive whole word.
+    #[structopt(short, long)]
+    word: Vec<String>,
+
+    /// Regular expression.
+    #[structopt(short, long)]
+    regex: Vec<String>,
+}
+
+async fn block(args: Block) -> Result<(), IpfsError> {
+    let Block { word, regex } = args;
+
+    println!("Updating Filters...");
+
+    update_filters(|blocklist| {
+        for word in word {
+            if !blocklist.words.contains(&word) {
+                blocklist.words.push(word);
+            }
+        }
+
+        for regex in regex {
+            if !blocklist.regexes.contains(&regex) {
+                blocklist.regexes.push(regex);
+            }
+        }
+    })
+    .await?;
+
+    println!("✅ Blocklist Updated");
+
+    Ok(())
+}
+
+#[derive(Debug, StructOpt)]
+pub struct Unblock {
+    /// Case insensitive whole word.
+    #[structopt(short, long)]
+    word: Vec<String>,
+
+    /// Regular expression.
+    #[structopt(short, long)]
+    regex: Vec<String>,
+}
+
+async fn unblock(args: Unblock) -> Result<(), IpfsError> {
+    let Unblock { word, regex } = args;
+
+    println!("Updating Filters...");
+
+    update_filters(|blocklist| {
+        blocklist.words.retain(|item| !word.contains(item));
+        blocklist.regexes.retain(|item| !regex.contains(item));
+    })
+    .await?;
+
+    println!("✅ Blocklist Updated");
+
+    Ok(())
+}
+
+#[derive(Debug, StructOpt)]
+pub struct FilterSettings {
+    /// Maximum number of characters per message. 0 for unlimited.
+    #[structopt(short, long)]
+    max_length: Option<usize>,
+
+    /// Remove links from messages.
+    #[structopt(short, long)]
+    strip_links: Option<bool>,
+
+    /// Drop messages identical to the previous one of the same user.
+    #[structopt(short, long)]
+    duplicates: Option<bool>,
+}
+
+async fn update_filter_settings(args: FilterSettings) -> Result<(), IpfsError> {
+    let FilterSettings {
+        max_length,
+        strip_links,
+        duplicates,
+    } = args;
+
+    println!("Updating Filters...");
+
+    update_filters(|blocklist| {
+        if let Some(max_length) = max_length {
+            blocklist.max_length = max_length;
+        }
+
+        if let Some(strip_links) = strip_links {
+            blocklist.strip_links = strip_links;
+        }
+
+        if let Some(duplicates) = duplicates {
+            blocklist.suppress_duplicates = duplicates;
+        }
+    })
+    .await?;
+
+    println!("✅ Filter Settings Updated");
+
+    Ok(())
+}
+
+/// Get the blocklist, apply the update then publish if still valid.
+async fn update_filters<F>(update: F) -> Result<(), IpfsError>
+where
+    F: FnOnce(&mut Blocklist),
+{
+    let ipfs = IpfsDaemon::default();
+
+    let (old_filters_cid, mut blocklist) =
+        get_from_ipns::<_, Blocklist>(&ipfs, FILTERS_KEY).await?;
+
+    update(&mut blocklist);
+
+    ChatFilter::new(&blocklist).map_err(IpfsError::new)?;
+
+    update_ipns(&ipfs, FILTERS_KEY, &blocklist).await?;
+
+    if let Err(e) = ipfs.pin_rm(old_filters_cid, false).await {
+        eprintln!("❗ IPFS could not unpin {}. Error: {}", old_filters_cid, e);
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, StructOpt)]
+struct CommentCommands {
+    #[structopt(subcommand)]
+    cmd: CommentCommand,
+}
+
+#[derive(Debug, StructOpt)]
+enum CommentCommand {
+    /// Hide comments.
+    Hide(HideComment),
+
+    /// Show hidden comments again.
+    Unhide(HideComment),
+
+    /// Hide all comments of users.
+    Block(BlockCommenter),
+
+    /// Show comments of blocked users again.
+    Unblock(BlockCommenter),
+}
+
+async fn comment_command(cli: CommentCommands) -> Result<(), IpfsError> {
+    println!("Updating Comment Moderation...");
+
+    match cli.cmd {
+        CommentCommand::Hide(args) => {
+            update_comment_moderation(|moderation| moderation.hidden.extend(args.comment)).await?
+        }
+        CommentCommand::Unhide(args) => {
+            update_comment_moderation(|moderation| {
+                moderation.hidden.retain(|cid| !args.comment.contains(cid))
+            })
+            .await?
+        }
+        CommentCommand::Block(args) => {
+            update_comment_moderation(|moderation| moderation.blocked.extend(args.beacon)).await?
+        }
+        CommentCommand::Unblock(args) => {
+            update_comment_moderation(|moderation| {
+                moderation.blocked.retain(|cid| !args.beacon.contains(cid))
+            })
+            .await?
+        }
+    }
+
+    println!("✅ Comment Moderation Updated");
+
+    Ok(())
+}
+
+#[derive(Debug, StructOpt)]
+pub struct HideComment {
+    /// Comment CID.
+    #[structopt(short, long, required = true)]
+    comment: Vec<Cid>,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct BlockCommenter {
+    /// Beacon CID of the commenter.
+    #[structopt(short, long, required = true)]
+    beacon: Vec<Cid>,
+}
+
+async fn update_comment_moderation<F>(update: F) -> Result<(), IpfsError>
+where
+    F: FnOnce(&mut CommentModeration),
+{
+    let ipfs = IpfsDaemon::default();
+
+    let (old_cid, mut moderation) =
+        get_from_ipns::<_, CommentModeration>(&ipfs, COMMENT_MODERATION_KEY).await?;
+
+    update(&mut moderation);
+
+    update_ipns(&ipfs, COMMENT_MODERATION_KEY, &moderation).await?;
+
+    if let Err(e) = ipfs.pin_rm(old_cid, false).await {
+        eprintln!("❗ IPFS could not unpin {}. Error: {}", old_cid, e);
+    }
+
+    Ok(())
+}
+
+pub fn parse_address(addrs: &str) -> [u8; 20] {
     if let Some(end) = addrs.strip_prefix("0x") {
         return <[u8; 20]>::from_hex(end).expect("Invalid Ethereum Address");
     }
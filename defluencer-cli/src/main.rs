@@ -3,6 +3,9 @@ mod cli;
 mod server;
 mod utils;
 
+use crate::cli::archive::{
+    finalize_archive_cli, verify_archive_cli, FinalizeArchive, VerifyArchive,
+};
 use crate::cli::beacon::{beacon_cli, BeaconCLI};
 use crate::cli::comments::{comments_cli, Comments};
 use crate::cli::content::{content_feed_cli, Content};
@@ -46,6 +49,12 @@ enum CommandLineInterface {
 
     /// Manage streaming metadata
     Live(LiveCLI),
+
+    /// Verify the signatures of an archived chat.
+    VerifyArchive(VerifyArchive),
+
+    /// Build the archive of a stream that did not shutdown.
+    FinalizeArchive(FinalizeArchive),
 }
 
 #[tokio::main]
@@ -60,5 +69,7 @@ async fn main() {
         CommandLineInterface::Friends(friends) => friends_cli(friends).await,
         CommandLineInterface::Identity(id) => identity_cli(id).await,
         CommandLineInterface::Live(live) => live_cli(live).await,
+        CommandLineInterface::VerifyArchive(args) => verify_archive_cli(args).await,
+        CommandLineInterface::FinalizeArchive(args) => finalize_archive_cli(args).await,
     }
 }
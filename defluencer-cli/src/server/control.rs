@@ -0,0 +1,173 @@
+//! Local control API of the ingest daemon. eg. curl -X POST 127.0.0.1:2526/control/finalize
+//!
+//! - GET /control/status
+//! - POST /control/finalize
+//! - POST /control/chat?archive=false
+//! - POST /control/topics?video=new_video_topic&chat=new_chat_topic
+
+use crate::actors::{Archive, ChatCommand, VideoData};
+use crate::cli::live::LIVE_KEY;
+use crate::server::services::{
+    bad_request_response, internal_error_response, json_response, not_found_response, query_value,
+};
+use crate::utils::dag_nodes::{get_from_ipns, update_ipns};
+use crate::utils::queues::{channel_depth, QueueMetrics};
+use crate::utils::shutdown::Shutdown;
+use crate::utils::status::DaemonStatus;
+
+use tokio::sync::mpsc::Sender;
+
+use hyper::{Body, Error, Method, Request, Response, StatusCode};
+
+use linked_data::ipfs::IpfsBackend;
+use linked_data::live::Live;
+
+pub const CONTROL_PATH: &str = "/control/";
+
+#[derive(Debug, Clone)]
+pub struct Control {
+    pub status: DaemonStatus,
+    pub shutdown: Shutdown,
+
+    /// None if chat is not archived.
+    pub chat_tx: Option<Sender<ChatCommand>>,
+
+    /// Request from the loopback interface, others are forbidden.
+    pub local: bool,
+}
+
+pub async fn control_requests<I>(
+    req: Request<Body>,
+    control: Control,
+    video_tx: &Sender<VideoData>,
+    archive_tx: Option<&Sender<Archive>>,
+    queues: &QueueMetrics,
+    ipfs: &I,
+) -> Result<Response<Body>, Error>
+where
+    I: IpfsBackend,
+{
+    let res = Response::new(Body::empty());
+
+    if !control.local {
+        return forbidden_response(res);
+    }
+
+    let path = req
+        .uri()
+        .path()
+        .strip_prefix(CONTROL_PATH)
+        .unwrap_or_default();
+    let query = req.uri().query().unwrap_or_default();
+
+    match (req.method(), path) {
+        (&Method::GET, "status") => {
+            let mut status = control.status.report();
+
+            status.archive_depth =
+                archive_tx.map(|tx| channel_depth(tx, queues.config.archive).depth);
+
+            json_response(res, &status)
+        }
+        (&Method::POST, "finalize") => {
+            println!("Finalize requested");
+
+            control.shutdown.trigger();
+
+            no_content_response(res)
+        }
+        (&Method::POST, "chat") => {
+            let chat_tx = match control.chat_tx.as_ref() {
+                Some(tx) => tx,
+                None => return not_found_response(res),
+            };
+
+            let archive = match query_value(query, "archive").map(|value| value.parse()) {
+                Some(Ok(archive)) => archive,
+                _ => return bad_request_response(res, &"Query must have archive=true|false"),
+            };
+
+            if let Err(error) = chat_tx.send(ChatCommand::Archive(archive)).await {
+                return internal_error_response(res, &error);
+            }
+
+            no_content_response(res)
+        }
+        (&Method::POST, "topics") => {
+            let video = query_value(query, "video").filter(|topic| !topic.is_empty());
+            let chat = query_value(query, "chat").filter(|topic| !topic.is_empty());
+
+            if video.is_none() && chat.is_none() {
+                return bad_request_response(res, &"Query must have video or chat topic");
+            }
+
+            if chat.is_some() && control.chat_tx.is_none() {
+                return not_found_response(res);
+            }
+
+            let (old_live_cid, mut live) = match get_from_ipns::<_, Live>(ipfs, LIVE_KEY).await {
+                Ok(result) => result,
+                Err(error) => return internal_error_response(res, &error),
+            };
+
+            // Both actors must have a slot, a failed request changes nothing.
+            let video_permit = match video.as_ref() {
+                Some(_) => match video_tx.reserve().await {
+                    Ok(permit) => Some(permit),
+                    Err(error) => return internal_error_response(res, &error),
+                },
+                None => None,
+            };
+
+            let chat_permit = match (chat.as_ref(), control.chat_tx.as_ref()) {
+                (Some(_), Some(chat_tx)) => match chat_tx.reserve().await {
+                    Ok(permit) => Some(permit),
+                    Err(error) => return internal_error_response(res, &error),
+                },
+                _ => None,
+            };
+
+            if let (Some(topic), Some(permit)) = (video, video_permit) {
+                live.video_topic = topic.clone();
+
+                permit.send(VideoData::Topic(topic));
+            }
+
+            if let (Some(topic), Some(permit)) = (chat, chat_permit) {
+                live.chat_topic = topic.clone();
+
+                permit.send(ChatCommand::Topic(topic));
+            }
+
+            // Viewers follow the topics of the Live record.
+            if let Err(error) = update_ipns(ipfs, LIVE_KEY, &live).await {
+                return internal_error_response(res, &error);
+            }
+
+            if let Err(e) = ipfs.pin_rm(old_live_cid, false).await {
+                eprintln!("❗ IPFS could not unpin {}. Error: {}", old_live_cid, e);
+            }
+
+            no_content_response(res)
+        }
+        _ => not_found_response(res),
+    }
+}
+
+fn no_content_response(mut res: Response<Body>) -> Result<Response<Body>, Error> {
+    *res.status_mut() = StatusCode::NO_CONTENT;
+
+    #[cfg(debug_assertions)]
+    println!("Service: {:#?}", res);
+
+    Ok(res)
+}
+
+fn forbidden_response(mut res: Response<Body>) -> Result<Response<Body>, Error> {
+    *res.status_mut() = StatusCode::FORBIDDEN;
+
+    #[cfg(debug_assertions)]
+    println!("Service: {:#?}", res);
+
+    Ok(res)
+}
@@ -0,0 +1,461 @@
+//! Split a fragmented MP4 stream into an initialization segment and media segments.
+//!
+//! Everything before the first moof box is the initialization segment (ftyp, moov).
+//! Each media segment starts at a styp or moof box and ends before the next one.
+//!
+//! Segment durations are read from the sample durations of the trun boxes,
+//! in units of the timescale found in the initialization segment.
+
+use std::convert::TryInto;
+use std::fmt;
+
+/// Reject boxes bigger than this, segments are a few seconds at most.
+const MAX_BOX_LEN: u64 = 32 * 1024 * 1024;
+
+const MOOF: &[u8; 4] = b"moof";
+const STYP: &[u8; 4] = b"styp";
+
+const MOOV: &[u8; 4] = b"moov";
+const TRAK: &[u8; 4] = b"trak";
+const MDIA: &[u8; 4] = b"mdia";
+const MDHD: &[u8; 4] = b"mdhd";
+const MVEX: &[u8; 4] = b"mvex";
+const TREX: &[u8; 4] = b"trex";
+const TRAF: &[u8; 4] = b"traf";
+const TFHD: &[u8; 4] = b"tfhd";
+const TRUN: &[u8; 4] = b"trun";
+
+/// tfhd flag, default sample duration present.
+const TFHD_DEFAULT_DURATION: u32 = 0x08;
+
+/// trun flags.
+const TRUN_DATA_OFFSET: u32 = 0x01;
+const TRUN_FIRST_SAMPLE_FLAGS: u32 = 0x04;
+const TRUN_SAMPLE_DURATION: u32 = 0x100;
+const TRUN_SAMPLE_SIZE: u32 = 0x200;
+const TRUN_SAMPLE_FLAGS: u32 = 0x400;
+const TRUN_SAMPLE_COMPOSITION: u32 = 0x800;
+
+#[derive(Debug, PartialEq)]
+pub enum Fragment {
+    Init(Vec<u8>),
+    Media(Vec<u8>),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SplitError {
+    BoxTooBig(u64),
+    InvalidBoxSize(u64),
+    MissingMedia,
+    TrailingBytes(usize),
+}
+
+impl fmt::Display for SplitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SplitError::BoxTooBig(size) => write!(f, "fMP4: box of {} bytes is too big", size),
+            SplitError::InvalidBoxSize(size) => write!(f, "fMP4: invalid box size {}", size),
+            SplitError::MissingMedia => write!(f, "fMP4: stream has no media segment"),
+            SplitError::TrailingBytes(len) => write!(f, "fMP4: {} bytes of incomplete box", len),
+        }
+    }
+}
+
+impl std::error::Error for SplitError {}
+
+#[derive(Default)]
+pub struct FragmentSplitter {
+    /// Bytes not yet part of a complete box.
+    pending: Vec<u8>,
+
+    /// Complete boxes of the current segment.
+    segment: Vec<u8>,
+
+    init_done: bool,
+    has_moof: bool,
+}
+
+impl FragmentSplitter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add stream data and return every segment that is now complete.
+    pub fn push(&mut self, data: &[u8]) -> Result<Vec<Fragment>, SplitError> {
+        self.pending.extend_from_slice(data);
+
+        let mut fragments = Vec::new();
+        let mut pos = 0;
+
+        while let Some((box_type, len)) = box_header(&self.pending[pos..])? {
+            if self.pending.len() - pos < len {
+                break;
+            }
+
+            if &box_type == MOOF || &box_type == STYP {
+                if !self.init_done {
+                    fragments.push(Fragment::Init(std::mem::take(&mut self.segment)));
+                    self.init_done = true;
+                } else if self.has_moof {
+                    fragments.push(Fragment::Media(std::mem::take(&mut self.segment)));
+                    self.has_moof = false;
+                }
+            }
+
+            if &box_type == MOOF {
+                self.has_moof = true;
+            }
+
+            self.segment
+                .extend_from_slice(&self.pending[pos..pos + len]);
+
+            pos += len;
+        }
+
+        self.pending.drain(..pos);
+
+        Ok(fragments)
+    }
+
+    /// Return the last segment at the end of the stream.
+    pub fn finish(self) -> Result<Option<Fragment>, SplitError> {
+        if !self.pending.is_empty() {
+            return Err(SplitError::TrailingBytes(self.pending.len()));
+        }
+
+        if !self.init_done {
+            return Err(SplitError::MissingMedia);
+        }
+
+        if !self.has_moof {
+            return Ok(None);
+        }
+
+        Ok(Some(Fragment::Media(self.segment)))
+    }
+}
+
+/// Timing of a track, from its initialization segment.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrackTiming {
+    /// Units per second.
+    pub timescale: u32,
+
+    /// Sample duration used when fragments do not specify one.
+    pub default_duration: Option<u32>,
+}
+
+impl TrackTiming {
+    /// Read the timescale (moov/trak/mdia/mdhd) and default sample duration (moov/mvex/trex).
+    pub fn from_init(init: &[u8]) -> Option<Self> {
+        let moov = child(init, MOOV)?;
+
+        let mdhd = child(child(child(moov, TRAK)?, MDIA)?, MDHD)?;
+
+        // version & flags then creation and modification times
+        let timescale = match *mdhd.get(0)? {
+            0 => read_u32(mdhd, 12)?,
+            _ => read_u32(mdhd, 20)?,
+        };
+
+        if timescale == 0 {
+            return None;
+        }
+
+        let default_duration = child(moov, MVEX)
+            .and_then(|mvex| child(mvex, TREX))
+            .and_then(|trex| read_u32(trex, 12));
+
+        Some(Self {
+            timescale,
+            default_duration,
+        })
+    }
+
+    /// Sum of the sample durations of every fragment of the segment, in seconds.
+    pub fn segment_duration(&self, segment: &[u8]) -> Option<f64> {
+        let mut ticks = 0u64;
+        let mut found = false;
+
+        for (box_type, moof) in boxes(segment) {
+            if &box_type != MOOF {
+                continue;
+            }
+
+            for (box_type, traf) in boxes(moof) {
+                if &box_type != TRAF {
+                    continue;
+                }
+
+                ticks += self.fragment_ticks(traf)?;
+                found = true;
+            }
+        }
+
+        if !found {
+            return None;
+        }
+
+        Some(ticks as f64 / self.timescale as f64)
+    }
+
+    fn fragment_ticks(&self, traf: &[u8]) -> Option<u64> {
+        let tfhd = child(traf, TFHD)?;
+        let tfhd_flags = read_u32(tfhd, 0)? & 0x00FF_FFFF;
+
+        let mut default_duration = self.default_duration;
+
+        if tfhd_flags & TFHD_DEFAULT_DURATION != 0 {
+            // track id then optional base data offset and sample description index
+            let mut offset = 8;
+
+            if tfhd_flags & 0x01 != 0 {
+                offset += 8;
+            }
+
+            if tfhd_flags & 0x02 != 0 {
+                offset += 4;
+            }
+
+            default_duration = Some(read_u32(tfhd, offset)?);
+        }
+
+        let mut ticks = 0;
+
+        for (box_type, trun) in boxes(traf) {
+            if &box_type != TRUN {
+                continue;
+            }
+
+            let flags = read_u32(trun, 0)? & 0x00FF_FFFF;
+            let sample_count = read_u32(trun, 4)? as usize;
+
+            if flags & TRUN_SAMPLE_DURATION == 0 {
+                ticks += default_duration? as u64 * sample_count as u64;
+                continue;
+            }
+
+            let mut offset = 8;
+
+            if flags & TRUN_DATA_OFFSET != 0 {
+                offset += 4;
+            }
+
+            if flags & TRUN_FIRST_SAMPLE_FLAGS != 0 {
+                offset += 4;
+            }
+
+            let sample_len = [
+                TRUN_SAMPLE_DURATION,
+                TRUN_SAMPLE_SIZE,
+                TRUN_SAMPLE_FLAGS,
+                TRUN_SAMPLE_COMPOSITION,
+            ]
+            .iter()
+            .filter(|flag| flags & **flag != 0)
+            .count()
+                * 4;
+
+            for i in 0..sample_count {
+                ticks += read_u32(trun, offset + i * sample_len)? as u64;
+            }
+        }
+
+        Some(ticks)
+    }
+}
+
+/// Payload of the first child box of this type.
+fn child<'a>(data: &'a [u8], box_type: &[u8; 4]) -> Option<&'a [u8]> {
+    boxes(data).find_map(|(probe, payload)| {
+        if &probe == box_type {
+            Some(payload)
+        } else {
+            None
+        }
+    })
+}
+
+/// Type and payload of every complete box, stopping at the first invalid one.
+fn boxes(data: &[u8]) -> impl Iterator<Item = ([u8; 4], &[u8])> {
+    let mut pos = 0;
+
+    std::iter::from_fn(move || {
+        let (box_type, len) = box_header(&data[pos..]).ok()??;
+
+        let header_len = if read_u32(&data[pos..], 0)? == 1 {
+            16
+        } else {
+            8
+        };
+
+        let payload = data.get(pos + header_len..pos + len)?;
+
+        pos += len;
+
+        Some((box_type, payload))
+    })
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+
+    Some(u32::from_be_bytes(bytes.try_into().ok()?))
+}
+
+/// Return box type and total length or None if the header is incomplete.
+fn box_header(data: &[u8]) -> Result<Option<([u8; 4], usize)>, SplitError> {
+    if data.len() < 8 {
+        return Ok(None);
+    }
+
+    let size = u32::from_be_bytes(data[0..4].try_into().unwrap()) as u64;
+    let box_type: [u8; 4] = data[4..8].try_into().unwrap();
+
+    let (size, header_len) = match size {
+        // Box extends to the end of the stream, impossible to split.
+        0 => return Err(SplitError::InvalidBoxSize(0)),
+        1 => {
+            if data.len() < 16 {
+                return Ok(None);
+            }
+
+            (u64::from_be_bytes(data[8..16].try_into().unwrap()), 16)
+        }
+        size => (size, 8),
+    };
+
+    if size < header_len {
+        return Err(SplitError::InvalidBoxSize(size));
+    }
+
+    if size > MAX_BOX_LEN {
+        return Err(SplitError::BoxTooBig(size));
+    }
+
+    Ok(Some((box_type, size as usize)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mp4_box(box_type: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut data = ((payload.len() + 8) as u32).to_be_bytes().to_vec();
+        data.extend_from_slice(box_type);
+        data.extend_from_slice(payload);
+        data
+    }
+
+    #[test]
+    fn split_stream() {
+        let init = [mp4_box(b"ftyp", b"iso6"), mp4_box(b"moov", &[1; 20])].concat();
+        let first = [mp4_box(b"moof", &[2; 10]), mp4_box(b"mdat", &[3; 30])].concat();
+        let second = [
+            mp4_box(b"styp", b"msdh"),
+            mp4_box(b"moof", &[4; 10]),
+            mp4_box(b"mdat", &[5; 30]),
+        ]
+        .concat();
+
+        let stream = [init.clone(), first.clone(), second.clone()].concat();
+
+        let mut splitter = FragmentSplitter::new();
+        let mut fragments = Vec::new();
+
+        // Chunks that cut through box headers
+        for chunk in stream.chunks(7) {
+            fragments.extend(splitter.push(chunk).unwrap());
+        }
+
+        fragments.extend(splitter.finish().unwrap());
+
+        assert_eq!(
+            fragments,
+            vec![
+                Fragment::Init(init),
+                Fragment::Media(first),
+                Fragment::Media(second)
+            ]
+        );
+    }
+
+    fn full_box(box_type: &[u8; 4], version: u8, flags: u32, fields: &[u32]) -> Vec<u8> {
+        let mut payload = (flags | ((version as u32) << 24)).to_be_bytes().to_vec();
+
+        for field in fields {
+            payload.extend_from_slice(&field.to_be_bytes());
+        }
+
+        mp4_box(box_type, &payload)
+    }
+
+    #[test]
+    fn segment_durations() {
+        let mdhd = full_box(b"mdhd", 0, 0, &[0, 0, 90_000, 0, 0]);
+        let trak = mp4_box(b"trak", &mp4_box(b"mdia", &mdhd));
+        let trex = full_box(b"trex", 0, 0, &[1, 1, 3000, 0, 0]);
+        let moov = mp4_box(b"moov", &[trak, mp4_box(b"mvex", &trex)].concat());
+        let init = [mp4_box(b"ftyp", b"iso6"), moov].concat();
+
+        let timing = TrackTiming::from_init(&init).unwrap();
+
+        assert_eq!(
+            timing,
+            TrackTiming {
+                timescale: 90_000,
+                default_duration: Some(3000),
+            }
+        );
+
+        // Per sample durations with sizes, 2 seconds.
+        let tfhd = full_box(b"tfhd", 0, 0, &[1]);
+        let trun = full_box(b"trun", 0, 0x301, &[2, 0, 90_000, 10, 90_000, 10]);
+        let traf = mp4_box(b"traf", &[tfhd, trun].concat());
+        let first = [mp4_box(b"moof", &traf), mp4_box(b"mdat", &[0; 20])].concat();
+
+        assert_eq!(timing.segment_duration(&first), Some(2.0));
+
+        // Default duration of the fragment, 4 seconds.
+        let tfhd = full_box(b"tfhd", 0, 0x08, &[1, 9000]);
+        let trun = full_box(b"trun", 0, 0x01, &[40, 0]);
+        let traf = mp4_box(b"traf", &[tfhd, trun].concat());
+        let second = [
+            mp4_box(b"styp", b"msdh"),
+            mp4_box(b"moof", &traf),
+            mp4_box(b"mdat", &[0; 20]),
+        ]
+        .concat();
+
+        assert_eq!(timing.segment_duration(&second), Some(4.0));
+
+        // Default duration of the track, 6 seconds over 2 fragments.
+        let tfhd = full_box(b"tfhd", 0, 0, &[1]);
+        let trun = full_box(b"trun", 0, 0, &[90]);
+        let moof = mp4_box(b"moof", &mp4_box(b"traf", &[tfhd, trun].concat()));
+        let third = [moof.clone(), moof].concat();
+
+        assert_eq!(timing.segment_duration(&third), Some(6.0));
+
+        assert_eq!(timing.segment_duration(&mp4_box(b"mdat", &[0; 8])), None);
+        assert_eq!(TrackTiming::from_init(&mp4_box(b"ftyp", b"iso6")), None);
+    }
+
+    #[test]
+    fn reject_invalid() {
+        let mut splitter = FragmentSplitter::new();
+        assert_eq!(
+            splitter.push(&[0, 0, 0, 4, b'f', b't', b'y', b'p']),
+            Err(SplitError::InvalidBoxSize(4))
+        );
+
+        let mut splitter = FragmentSplitter::new();
+        splitter.push(&mp4_box(b"ftyp", b"iso6")).unwrap();
+        splitter.push(&[0, 0]).unwrap();
+        assert_eq!(splitter.finish(), Err(SplitError::TrailingBytes(2)));
+
+        let mut splitter = FragmentSplitter::new();
+        splitter.push(&mp4_box(b"ftyp", b"iso6")).unwrap();
+        assert_eq!(splitter.finish(), Err(SplitError::MissingMedia));
+    }
+}
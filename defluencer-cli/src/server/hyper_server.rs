@@ -1,58 +1,84 @@
 use crate::actors::{Archive, SetupData, VideoData};
-use crate::server::services::put_requests;
+use crate::server::control::Control;
+use crate::server::services::{ingest_requests, TrackTimings};
+use crate::utils::metrics::Metrics;
+use crate::utils::queues::QueueMetrics;
+use crate::utils::shutdown::Shutdown;
 
 use std::convert::Infallible;
 use std::net::SocketAddr;
 
 use tokio::signal::ctrl_c;
-use tokio::sync::mpsc::UnboundedSender;
+use tokio::sync::mpsc::Sender;
 
+use hyper::server::conn::AddrStream;
 use hyper::service::{make_service_fn, service_fn};
 use hyper::Server;
 
-use ipfs_api::IpfsClient;
+use linked_data::ipfs::IpfsBackend;
 
-async fn shutdown_signal(
-    ipfs: IpfsClient,
-    topic: String,
-    archive_tx: Option<UnboundedSender<Archive>>,
-) {
-    ctrl_c()
-        .await
-        .expect("Failed to install CTRL+C signal handler");
+/// On CTRL+C or finalize request, stop the chat then finalize the archive.
+async fn shutdown_signal(shutdown: Shutdown, archive_tx: Option<Sender<Archive>>) {
+    tokio::select! {
+        result = ctrl_c() => result.expect("Failed to install CTRL+C signal handler"),
+        _ = shutdown.wait() => {}
+    }
+
+    shutdown.trigger();
 
     if let Some(archive_tx) = archive_tx {
         let msg = Archive::Finalize;
 
-        if let Err(error) = archive_tx.send(msg) {
+        if let Err(error) = archive_tx.send(msg).await {
             eprintln!("Archive receiver hung up {}", error);
         }
-
-        //Hacky way to shutdown chat actor. Send some msg to trigger a check
-        ipfs.pubsub_pub(&topic, "Stopping")
-            .await
-            .expect("PubSub Pub Failed!");
     }
 }
 
-pub async fn start_server(
+#[allow(clippy::too_many_arguments)]
+pub async fn start_server<I>(
     server_addr: SocketAddr,
-    video_tx: UnboundedSender<VideoData>,
-    setup_tx: UnboundedSender<SetupData>,
-    archive_tx: Option<UnboundedSender<Archive>>,
-    ipfs: IpfsClient,
-    topic: String,
-) {
-    let ipfs_clone = ipfs.clone();
-
-    let service = make_service_fn(move |_| {
+    video_tx: Sender<VideoData>,
+    setup_tx: Sender<SetupData>,
+    archive_tx: Option<Sender<Archive>>,
+    queues: QueueMetrics,
+    metrics: Metrics,
+    control: Control,
+    ipfs: I,
+) where
+    I: IpfsBackend + Clone + Send + Sync + 'static,
+{
+    let shutdown = control.shutdown.clone();
+
+    let timings = TrackTimings::default();
+
+    let service_archive_tx = archive_tx.clone();
+
+    let service = make_service_fn(move |conn: &AddrStream| {
         let ipfs = ipfs.clone();
         let video_tx = video_tx.clone();
         let setup_tx = setup_tx.clone();
+        let archive_tx = service_archive_tx.clone();
+        let timings = timings.clone();
+        let queues = queues.clone();
+        let metrics = metrics.clone();
+
+        let mut control = control.clone();
+        control.local = conn.remote_addr().ip().is_loopback();
 
         async move {
             Ok::<_, Infallible>(service_fn(move |req| {
-                put_requests(req, video_tx.clone(), setup_tx.clone(), ipfs.clone())
+                ingest_requests(
+                    req,
+                    video_tx.clone(),
+                    setup_tx.clone(),
+                    archive_tx.clone(),
+                    timings.clone(),
+                    queues.clone(),
+                    metrics.clone(),
+                    control.clone(),
+                    ipfs.clone(),
+                )
             }))
         }
     });
@@ -63,7 +89,7 @@ pub async fn start_server(
 
     println!("✅ Ingess Server Online");
 
-    let graceful = server.with_graceful_shutdown(shutdown_signal(ipfs_clone, topic, archive_tx));
+    let graceful = server.with_graceful_shutdown(shutdown_signal(shutdown, archive_tx));
 
     if let Err(e) = graceful.await {
         eprintln!("Server: {}", e);
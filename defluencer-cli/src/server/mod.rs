@@ -1,5 +1,8 @@
+mod control;
+mod fmp4;
 mod hyper_server;
 mod services;
 
+pub use control::Control;
 pub use hyper_server::start_server;
 pub use services::{M4S, MP4};
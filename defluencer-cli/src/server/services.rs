@@ -1,95 +1,191 @@
-use crate::actors::{SetupData, VideoData};
+use crate::actors::{Archive, SetupData, VideoData};
+use crate::server::control::{control_requests, Control, CONTROL_PATH};
+use crate::server::fmp4::{Fragment, FragmentSplitter, TrackTiming};
+use crate::utils::metrics::Metrics;
+use crate::utils::queues::QueueMetrics;
 
-use std::convert::TryFrom;
+use std::collections::HashMap;
 use std::fmt::Debug;
 use std::path::Path;
+use std::sync::{Arc, Mutex};
 
-use futures_util::stream::TryStreamExt;
+use futures_util::StreamExt;
 
-use tokio::sync::mpsc::UnboundedSender;
-use tokio_util::io::StreamReader;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::mpsc::{Permit, Sender};
 
-use hyper::header::{HeaderValue, LOCATION};
+use hyper::header::{HeaderValue, CONTENT_TYPE, LOCATION, RETRY_AFTER};
 use hyper::{Body, Error, Method, Request, Response, StatusCode};
 
-use ipfs_api::IpfsClient;
-
-use cid::Cid;
+use linked_data::ipfs::IpfsBackend;
 
 use m3u8_rs::playlist::Playlist;
 
+use serde::Serialize;
+
 const M3U8: &str = "m3u8";
 pub const MP4: &str = "mp4";
 pub const M4S: &str = "m4s";
 
-const OPTIONS: ipfs_api::request::Add = ipfs_api::request::Add {
-    trickle: None,
-    only_hash: None,
-    wrap_with_directory: None,
-    chunker: None,
-    pin: Some(false),
-    raw_leaves: None,
-    cid_version: Some(1),
-    hash: None,
-    inline: None,
-    inline_limit: None,
-};
-
-pub async fn put_requests(
+/// Direct fMP4 ingest, one POST per track. eg. /ingest/1080p60?codecs=avc1.42c02a&bandwidth=6000000&tracks=2
+const INGEST_PATH: &str = "/ingest/";
+
+/// Depth of the actor queues, as JSON.
+const QUEUES_PATH: &str = "/queues";
+
+/// Prometheus metrics.
+const METRICS_PATH: &str = "/metrics";
+
+/// Timing of each track, read from its initialization segment.
+pub type TrackTimings = Arc<Mutex<HashMap<String, TrackTiming>>>;
+
+/// Queue slot reserved before a segment is read.
+enum Slot<'a> {
+    Video(Permit<'a, VideoData>, usize),
+    Setup(Permit<'a, SetupData>),
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn ingest_requests<I>(
     req: Request<Body>,
-    video_tx: UnboundedSender<VideoData>,
-    setup_tx: UnboundedSender<SetupData>,
-    ipfs: IpfsClient,
-) -> Result<Response<Body>, Error> {
+    video_tx: Sender<VideoData>,
+    setup_tx: Sender<SetupData>,
+    archive_tx: Option<Sender<Archive>>,
+    timings: TrackTimings,
+    queues: QueueMetrics,
+    metrics: Metrics,
+    control: Control,
+    ipfs: I,
+) -> Result<Response<Body>, Error>
+where
+    I: IpfsBackend,
+{
     #[cfg(debug_assertions)]
     println!("Service: {:#?}", req);
 
+    if req.uri().path().starts_with(CONTROL_PATH) {
+        return control_requests(req, control, &video_tx, archive_tx.as_ref(), &queues, &ipfs)
+            .await;
+    }
+
+    match *req.method() {
+        Method::PUT => put_requests(req, video_tx, setup_tx, timings, queues, metrics, ipfs).await,
+        Method::POST => post_requests(req, video_tx, setup_tx, metrics, ipfs).await,
+        Method::GET if req.uri().path() == QUEUES_PATH => {
+            let report = queues.report(&setup_tx, &video_tx, archive_tx.as_ref());
+
+            json_response(Response::new(Body::empty()), &report)
+        }
+        Method::GET if req.uri().path() == METRICS_PATH => {
+            let report = queues.report(&setup_tx, &video_tx, archive_tx.as_ref());
+
+            metrics_response(Response::new(Body::empty()), metrics.render(&report))
+        }
+        _ => not_found_response(Response::new(Body::empty())),
+    }
+}
+
+/// HLS files as written by FFMPEG. eg. /1080p60/42.m4s
+///
+/// Answer 503 when the actors are overloaded, FFMPEG will retry later.
+async fn put_requests<I>(
+    req: Request<Body>,
+    video_tx: Sender<VideoData>,
+    setup_tx: Sender<SetupData>,
+    timings: TrackTimings,
+    queues: QueueMetrics,
+    metrics: Metrics,
+    ipfs: I,
+) -> Result<Response<Body>, Error>
+where
+    I: IpfsBackend,
+{
     let mut res = Response::new(Body::empty());
 
     let (parts, body) = req.into_parts();
 
     let path = Path::new(parts.uri.path());
 
-    if parts.method != Method::PUT
-        || path.extension() == None
-        || (path.extension().unwrap() != M3U8
-            && path.extension().unwrap() != M4S
-            && path.extension().unwrap() != MP4)
-    {
-        return not_found_response(res);
-    }
+    let extension = match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) if ext == M3U8 || ext == M4S || ext == MP4 => ext,
+        _ => return not_found_response(res),
+    };
 
-    if path.extension().unwrap() == M3U8 {
-        return manifest_response(res, body, path, setup_tx).await;
+    if extension == M3U8 {
+        return manifest_response(res, body, path, setup_tx, queues).await;
     }
 
-    //Change error type
-    let stream =
-        body.map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err.to_string()));
+    let track = match path
+        .parent()
+        .and_then(|dir| dir.file_name())
+        .and_then(|name| name.to_str())
+    {
+        Some(track) => track.to_owned(),
+        None => return bad_request_response(res, &"Segment is not in a track directory"),
+    };
+
+    let index = if extension == M4S {
+        match path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.parse::<usize>().ok())
+        {
+            Some(index) => Some(index),
+            None => return bad_request_response(res, &"Segment file name is not an index"),
+        }
+    } else {
+        None
+    };
 
-    //Stream to AsyncRead
-    let reader = StreamReader::new(stream);
+    // Segments rejected with 503 are neither read nor added.
+    let reserved = match index {
+        Some(index) => video_tx
+            .try_reserve()
+            .map(|permit| Slot::Video(permit, index)),
+        None => setup_tx.try_reserve().map(Slot::Setup),
+    };
 
-    let cid = match ipfs.add_with_options(reader, OPTIONS).await {
-        Ok(res) => Cid::try_from(res.hash).expect("Invalid Cid"),
+    let slot = match reserved {
+        Ok(slot) => slot,
+        Err(TrySendError::Full(())) => return unavailable_response(res, &queues),
         Err(error) => return internal_error_response(res, &error),
     };
 
-    #[cfg(debug_assertions)]
-    println!("IPFS: add => {}", &cid.to_string());
+    let bytes = hyper::body::to_bytes(body).await?;
+
+    let duration = {
+        let mut timings = timings.lock().expect("Poisoned Lock");
 
-    if path.extension().unwrap() == M4S {
-        let msg = VideoData::Segment((path.to_path_buf(), cid));
+        if index.is_some() {
+            timings
+                .get(&track)
+                .and_then(|timing| timing.segment_duration(&bytes))
+        } else {
+            if let Some(timing) = TrackTiming::from_init(&bytes) {
+                timings.insert(track.clone(), timing);
+            }
 
-        if let Err(error) = video_tx.send(msg) {
-            return internal_error_response(res, &error);
+            None
         }
-    } else if path.extension().unwrap() == MP4 {
-        let msg = SetupData::Segment((path.to_path_buf(), cid));
+    };
 
-        if let Err(error) = setup_tx.send(msg) {
-            return internal_error_response(res, &error);
+    let cid = match metrics.time_add(ipfs.add(bytes.to_vec())).await {
+        Ok(cid) => cid,
+        Err(error) => return internal_error_response(res, &error),
+    };
+
+    match slot {
+        Slot::Video(permit, index) => {
+            metrics.segment_received(&track);
+
+            permit.send(VideoData::Segment {
+                track,
+                index,
+                cid,
+                duration,
+            });
         }
+        Slot::Setup(permit) => permit.send(SetupData::Segment { track, cid }),
     }
 
     *res.status_mut() = StatusCode::CREATED;
@@ -104,7 +200,203 @@ pub async fn put_requests(
     Ok(res)
 }
 
-fn not_found_response(mut res: Response<Body>) -> Result<Response<Body>, Error> {
+/// One chunked fragmented MP4 stream per track, split at moof boxes.
+///
+/// The body is not read while the actors are overloaded.
+async fn post_requests<I>(
+    req: Request<Body>,
+    video_tx: Sender<VideoData>,
+    setup_tx: Sender<SetupData>,
+    metrics: Metrics,
+    ipfs: I,
+) -> Result<Response<Body>, Error>
+where
+    I: IpfsBackend,
+{
+    let mut res = Response::new(Body::empty());
+
+    let (parts, mut body) = req.into_parts();
+
+    let track = match parts.uri.path().strip_prefix(INGEST_PATH) {
+        Some(track) if !track.is_empty() && !track.contains('/') => track.to_owned(),
+        _ => return not_found_response(res),
+    };
+
+    let query = parts.uri.query().unwrap_or_default();
+
+    let (codecs, bandwidth, track_count) = match (
+        query_value(query, "codecs"),
+        query_value(query, "bandwidth").and_then(|value| value.parse().ok()),
+        query_value(query, "tracks").and_then(|value| value.parse().ok()),
+    ) {
+        (Some(codecs), Some(bandwidth), Some(track_count)) if track_count > 0 => {
+            (codecs, bandwidth, track_count)
+        }
+        _ => return bad_request_response(res, &"Query must have codecs, bandwidth & tracks"),
+    };
+
+    let msg = SetupData::Track {
+        name: track.clone(),
+        codecs,
+        bandwidth,
+        track_count,
+    };
+
+    if let Err(error) = setup_tx.send(msg).await {
+        return internal_error_response(res, &error);
+    }
+
+    let mut splitter = FragmentSplitter::new();
+    let mut timing = None;
+    let mut index = 0;
+
+    while let Some(chunk) = body.next().await {
+        let fragments = match splitter.push(&chunk?) {
+            Ok(fragments) => fragments,
+            Err(error) => return bad_request_response(res, &error),
+        };
+
+        for fragment in fragments {
+            if let Err(error) = send_fragment(
+                &ipfs,
+                &video_tx,
+                &setup_tx,
+                &metrics,
+                &track,
+                &mut timing,
+                &mut index,
+                fragment,
+            )
+            .await
+            {
+                return internal_error_response(res, &error);
+            }
+        }
+    }
+
+    match splitter.finish() {
+        Ok(Some(fragment)) => {
+            if let Err(error) = send_fragment(
+                &ipfs,
+                &video_tx,
+                &setup_tx,
+                &metrics,
+                &track,
+                &mut timing,
+                &mut index,
+                fragment,
+            )
+            .await
+            {
+                return internal_error_response(res, &error);
+            }
+        }
+        Ok(None) => {}
+        Err(error) => return bad_request_response(res, &error),
+    }
+
+    println!("Track {} ended after {} segments", track, index);
+
+    *res.status_mut() = StatusCode::NO_CONTENT;
+
+    #[cfg(debug_assertions)]
+    println!("Service: {:#?}", res);
+
+    Ok(res)
+}
+
+/// Wait for a queue slot, add the segment to IPFS then send it to the setup or video actor.
+#[allow(clippy::too_many_arguments)]
+async fn send_fragment<I>(
+    ipfs: &I,
+    video_tx: &Sender<VideoData>,
+    setup_tx: &Sender<SetupData>,
+    metrics: &Metrics,
+    track: &str,
+    timing: &mut Option<TrackTiming>,
+    index: &mut usize,
+    fragment: Fragment,
+) -> Result<(), String>
+where
+    I: IpfsBackend,
+{
+    let (data, is_init) = match fragment {
+        Fragment::Init(data) => (data, true),
+        Fragment::Media(data) => (data, false),
+    };
+
+    let track = track.to_owned();
+
+    if is_init {
+        *timing = TrackTiming::from_init(&data);
+
+        let permit = setup_tx.reserve().await.map_err(|e| e.to_string())?;
+
+        let cid = metrics
+            .time_add(ipfs.add(data))
+            .await
+            .map_err(|e| e.to_string())?;
+
+        permit.send(SetupData::Segment { track, cid });
+
+        return Ok(());
+    }
+
+    let duration = timing.and_then(|timing| timing.segment_duration(&data));
+
+    let permit = video_tx.reserve().await.map_err(|e| e.to_string())?;
+
+    let cid = metrics
+        .time_add(ipfs.add(data))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    metrics.segment_received(&track);
+
+    permit.send(VideoData::Segment {
+        track,
+        index: *index,
+        cid,
+        duration,
+    });
+
+    *index += 1;
+
+    Ok(())
+}
+
+/// Return the percent decoded value of this query key.
+pub fn query_value(query: &str, key: &str) -> Option<String> {
+    let value = query.split('&').find_map(|pair| {
+        let mut pair = pair.splitn(2, '=');
+
+        if pair.next()? == key {
+            pair.next()
+        } else {
+            None
+        }
+    })?;
+
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut iter = value.bytes();
+
+    while let Some(byte) = iter.next() {
+        match byte {
+            b'%' => {
+                let hex = [iter.next()?, iter.next()?];
+                let hex = std::str::from_utf8(&hex).ok()?;
+
+                bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            }
+            b'+' => bytes.push(b' '),
+            byte => bytes.push(byte),
+        }
+    }
+
+    String::from_utf8(bytes).ok()
+}
+
+pub fn not_found_response(mut res: Response<Body>) -> Result<Response<Body>, Error> {
     *res.status_mut() = StatusCode::NOT_FOUND;
 
     #[cfg(debug_assertions)]
@@ -117,26 +409,30 @@ async fn manifest_response(
     mut res: Response<Body>,
     body: Body,
     path: &Path,
-    setup_tx: UnboundedSender<SetupData>,
+    setup_tx: Sender<SetupData>,
+    queues: QueueMetrics,
 ) -> Result<Response<Body>, Error> {
     let bytes = hyper::body::to_bytes(body).await?;
 
     let playlist = match m3u8_rs::parse_playlist(&bytes) {
         Ok((_, playlist)) => playlist,
-        Err(e) => return internal_error_response(res, &e),
+        Err(e) => return bad_request_response(res, &e),
     };
 
     if let Playlist::MasterPlaylist(playlist) = playlist {
         let msg = SetupData::Playlist(playlist);
 
-        if let Err(error) = setup_tx.send(msg) {
-            return internal_error_response(res, &error);
+        match setup_tx.try_send(msg) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => return unavailable_response(res, &queues),
+            Err(error) => return internal_error_response(res, &error),
         }
     }
 
     *res.status_mut() = StatusCode::NO_CONTENT;
 
-    let header_value = HeaderValue::from_str(path.to_str().unwrap()).unwrap();
+    let header_value =
+        HeaderValue::from_str(path.to_str().unwrap_or_default()).expect("Invalid Header Value");
 
     res.headers_mut().insert(LOCATION, header_value);
 
@@ -146,7 +442,75 @@ async fn manifest_response(
     Ok(res)
 }
 
-fn internal_error_response(
+pub fn json_response(
+    mut res: Response<Body>,
+    data: &impl Serialize,
+) -> Result<Response<Body>, Error> {
+    let body = match serde_json::to_vec(data) {
+        Ok(body) => body,
+        Err(error) => return internal_error_response(res, &error),
+    };
+
+    *res.body_mut() = Body::from(body);
+
+    res.headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+
+    #[cfg(debug_assertions)]
+    println!("Service: {:#?}", res);
+
+    Ok(res)
+}
+
+fn metrics_response(mut res: Response<Body>, text: String) -> Result<Response<Body>, Error> {
+    *res.body_mut() = Body::from(text);
+
+    res.headers_mut().insert(
+        CONTENT_TYPE,
+        HeaderValue::from_static("text/plain; version=0.0.4"),
+    );
+
+    #[cfg(debug_assertions)]
+    println!("Service: {:#?}", res);
+
+    Ok(res)
+}
+
+/// The actor queue is full, the request should be retried.
+fn unavailable_response(
+    mut res: Response<Body>,
+    queues: &QueueMetrics,
+) -> Result<Response<Body>, Error> {
+    queues.segment_rejected();
+
+    eprintln!("❗ Ingest queue full, request rejected");
+
+    *res.status_mut() = StatusCode::SERVICE_UNAVAILABLE;
+
+    res.headers_mut()
+        .insert(RETRY_AFTER, HeaderValue::from_static("1"));
+
+    #[cfg(debug_assertions)]
+    println!("Service: {:#?}", res);
+
+    Ok(res)
+}
+
+pub fn bad_request_response(
+    mut res: Response<Body>,
+    error: &dyn Debug,
+) -> Result<Response<Body>, Error> {
+    eprintln!("Service: {:#?}", error);
+
+    *res.status_mut() = StatusCode::BAD_REQUEST;
+
+    #[cfg(debug_assertions)]
+    println!("Service: {:#?}", res);
+
+    Ok(res)
+}
+
+pub fn internal_error_response(
     mut res: Response<Body>,
     error: &dyn Debug,
 ) -> Result<Response<Body>, Error> {
@@ -159,3 +523,54 @@ fn internal_error_response(
 
     Ok(res)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::utils::config::QueueConfig;
+
+    use linked_data::ipfs::MemoryIpfs;
+
+    use tokio::sync::mpsc::channel;
+
+    #[tokio::test]
+    async fn reject_before_adding() {
+        let ipfs = MemoryIpfs::new();
+        let queues = QueueMetrics::new(QueueConfig::default());
+        let metrics = Metrics::default();
+
+        let (video_tx, _video_rx) = channel(1);
+        let (setup_tx, _setup_rx) = channel(1);
+
+        for status in [StatusCode::CREATED, StatusCode::SERVICE_UNAVAILABLE].iter() {
+            let req = Request::put("/audio/0.m4s")
+                .body(Body::from(vec![0u8; 8]))
+                .unwrap();
+
+            let res = put_requests(
+                req,
+                video_tx.clone(),
+                setup_tx.clone(),
+                TrackTimings::default(),
+                queues.clone(),
+                metrics.clone(),
+                ipfs.clone(),
+            )
+            .await
+            .unwrap();
+
+            assert_eq!(res.status(), *status);
+        }
+
+        // The rejected segment was never added nor counted.
+        assert_eq!(ipfs.block_count(), 1);
+
+        let report = queues.report::<_, _, Archive>(&setup_tx, &video_tx, None);
+
+        assert_eq!(report.rejected_segments, 1);
+        assert!(metrics
+            .render(&report)
+            .contains("defluencer_segments_received_total{track=\"audio\"} 1\n"));
+    }
+}
@@ -20,11 +20,59 @@ pub struct VideoConfig {
     pub pubsub_enable: bool, // get from argument not file
 
     pub pubsub_topic: String,
+
+    #[serde(skip)]
+    pub signing_key: Option<[u8; 32]>, // get from argument not file
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ChatConfig {
     pub topic: String,
+
+    /// Minimum number of seconds between messages of a user. 0 to disable.
+    #[serde(default)]
+    pub slow_mode: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CommentsConfig {
+    /// Topic where new comments are announced.
+    pub topic: String,
+}
+
+impl Default for CommentsConfig {
+    fn default() -> Self {
+        Self {
+            topic: "defluencer_comments".into(),
+        }
+    }
+}
+
+/// Capacity of the channels between ingest actors.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct QueueConfig {
+    /// Initialization segments & playlists waiting for the setup actor.
+    pub setup: usize,
+
+    /// Media segments waiting for the video actor. The ingest server answers 503 when full.
+    pub video: usize,
+
+    /// Video & chat nodes waiting for the archivist. Chat messages are dropped when full.
+    pub archive: usize,
+
+    /// Incomplete video nodes kept in memory. The oldest is dropped when full.
+    pub video_nodes: usize,
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        Self {
+            setup: 32,
+            video: 64,
+            archive: 256,
+            video_nodes: 30,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -33,6 +81,12 @@ pub struct Configuration {
     pub archive: ArchiveConfig,
     pub video: VideoConfig,
     pub chat: ChatConfig,
+
+    #[serde(default)]
+    pub comments: CommentsConfig,
+
+    #[serde(default)]
+    pub queues: QueueConfig,
 }
 
 impl Configuration {
@@ -62,11 +116,17 @@ impl Default for Configuration {
             video: VideoConfig {
                 pubsub_enable: true,
                 pubsub_topic: "defluencer_live_video".into(),
+                signing_key: None,
             },
 
             chat: ChatConfig {
                 topic: "defluencer_live_chat".into(),
+                slow_mode: 0,
             },
+
+            comments: CommentsConfig::default(),
+
+            queues: QueueConfig::default(),
         }
     }
 }
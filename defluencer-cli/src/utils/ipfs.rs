@@ -0,0 +1,417 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::io::Cursor;
+
+use futures_util::{StreamExt, TryStreamExt};
+
+use async_trait::async_trait;
+
+use hyper::body::Bytes;
+use hyper::client::HttpConnector;
+use hyper::header::CONTENT_TYPE;
+use hyper::{Body, Client, Request};
+
+use ipfs_api::IpfsClient;
+use ipfs_api::KeyType;
+use ipfs_api::TryFromUri;
+
+use linked_data::dag_cbor;
+use linked_data::ipfs::{
+    codec_name, decode_block, select, IpfsBackend, IpfsError, PubSubMessage, PubSubStream, Result,
+};
+use linked_data::{IPLDLink, IPNSAddress, PeerId};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use cid::Cid;
+
+/// Environment variable overriding the IPFS API address. eg. http://127.0.0.1:5001
+pub const IPFS_API_ENV: &str = "DEFLUENCER_IPFS_API";
+
+const DEFAULT_API: &str = "http://127.0.0.1:5001";
+
+const MULTIPART_BOUNDARY: &str = "defluencer-boundary";
+
+const ADD_OPTIONS: ipfs_api::request::Add = ipfs_api::request::Add {
+    trickle: None,
+    only_hash: None,
+    wrap_with_directory: None,
+    chunker: None,
+    pin: Some(false),
+    raw_leaves: None,
+    cid_version: Some(1),
+    hash: None,
+    inline: None,
+    inline_limit: None,
+};
+
+/// IPFS backend using the go-ipfs HTTP API.
+///
+/// DAG nodes are requested directly since the client can't choose codecs.
+#[derive(Clone)]
+pub struct IpfsDaemon {
+    client: IpfsClient,
+
+    http: Client<HttpConnector>,
+    api: String,
+}
+
+impl IpfsDaemon {
+    /// Connect to the API at this address. eg. http://127.0.0.1:5001
+    pub fn new(api: &str) -> Result<Self> {
+        let client = IpfsClient::from_str(api).map_err(IpfsError::new)?;
+
+        Ok(Self {
+            client,
+
+            http: Client::new(),
+            api: api.trim_end_matches('/').to_owned(),
+        })
+    }
+
+    /// POST to an API command, data is sent as multipart. Return the response body.
+    async fn api_post(&self, command: &str, query: &str, data: Option<Vec<u8>>) -> Result<Bytes> {
+        let uri = format!("{}/api/v0/{}?{}", self.api, command, query);
+
+        let request = Request::post(uri);
+
+        let request = match data {
+            Some(data) => {
+                let content_type = format!("multipart/form-data; boundary={}", MULTIPART_BOUNDARY);
+
+                request
+                    .header(CONTENT_TYPE, content_type)
+                    .body(Body::from(multipart_body(data)))
+            }
+            None => request.body(Body::empty()),
+        };
+
+        let request = request.map_err(IpfsError::new)?;
+
+        let response = self.http.request(request).await.map_err(IpfsError::new)?;
+
+        let status = response.status();
+
+        let bytes = hyper::body::to_bytes(response.into_body())
+            .await
+            .map_err(IpfsError::new)?;
+
+        if !status.is_success() {
+            let message = match serde_json::from_slice::<ApiError>(&bytes) {
+                Ok(error) => error.message,
+                Err(_) => String::from_utf8_lossy(&bytes).into_owned(),
+            };
+
+            return Err(IpfsError::new(format!("{} {}", status, message)));
+        }
+
+        Ok(bytes)
+    }
+}
+
+impl Default for IpfsDaemon {
+    /// Use the API address from the environment if any, otherwise the default local node.
+    fn default() -> Self {
+        if let Ok(api) = std::env::var(IPFS_API_ENV) {
+            match Self::new(&api) {
+                Ok(ipfs) => return ipfs,
+                Err(e) => eprintln!("❗ Invalid {} {}. Error: {}", IPFS_API_ENV, api, e),
+            }
+        }
+
+        Self::new(DEFAULT_API).expect("Invalid Default Address")
+    }
+}
+
+#[derive(Deserialize)]
+struct DagPutResponse {
+    #[serde(rename = "Cid")]
+    cid: IPLDLink,
+}
+
+#[derive(Deserialize)]
+struct DagResolveResponse {
+    #[serde(rename = "Cid")]
+    cid: IPLDLink,
+
+    #[serde(rename = "RemPath")]
+    rem_path: String,
+}
+
+#[derive(Deserialize)]
+struct BlockPutResponse {
+    #[serde(rename = "Key")]
+    key: String,
+}
+
+#[derive(Deserialize)]
+struct ApiError {
+    #[serde(rename = "Message")]
+    message: String,
+}
+
+fn multipart_body(data: Vec<u8>) -> Vec<u8> {
+    let mut body = Vec::with_capacity(data.len() + 256);
+
+    body.extend_from_slice(
+        format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"file\"\r\nContent-Type: application/octet-stream\r\n\r\n",
+            MULTIPART_BOUNDARY
+        )
+        .as_bytes(),
+    );
+    body.extend_from_slice(&data);
+    body.extend_from_slice(format!("\r\n--{}--\r\n", MULTIPART_BOUNDARY).as_bytes());
+
+    body
+}
+
+fn path_to(cid: Cid, path: Option<&str>) -> String {
+    match path {
+        Some(path) => format!("{}/{}", cid, path.trim_start_matches('/')),
+        None => cid.to_string(),
+    }
+}
+
+#[async_trait]
+impl IpfsBackend for IpfsDaemon {
+    async fn dag_put<T>(&self, node: &T) -> Result<Cid>
+    where
+        T: ?Sized + Serialize + Sync,
+    {
+        #[cfg(debug_assertions)]
+        println!(
+            "Serde: Serialize => {}",
+            serde_json::to_string_pretty(node).unwrap()
+        );
+
+        let data = dag_cbor::to_vec(node)?;
+
+        let bytes = self
+            .api_post(
+                "dag/put",
+                "store-codec=dag-cbor&input-codec=dag-cbor",
+                Some(data),
+            )
+            .await?;
+
+        let response = serde_json::from_slice::<DagPutResponse>(&bytes)?;
+
+        let cid = response.cid.link;
+
+        #[cfg(debug_assertions)]
+        println!("IPFS: dag put => {}", &cid);
+
+        Ok(cid)
+    }
+
+    async fn dag_get<T>(&self, cid: Cid, path: Option<&str>) -> Result<T>
+    where
+        T: DeserializeOwned + Send,
+    {
+        #[cfg(debug_assertions)]
+        println!("IPFS: dag get => {}", path_to(cid, path));
+
+        // Let the daemon follow links then decode the last block here
+        let (cid, rem_path) = match path {
+            Some(path) => {
+                let query = format!("arg={}", path_to(cid, Some(path)));
+
+                let bytes = self.api_post("dag/resolve", &query, None).await?;
+
+                let response = serde_json::from_slice::<DagResolveResponse>(&bytes)?;
+
+                (response.cid.link, response.rem_path)
+            }
+            None => (cid, String::new()),
+        };
+
+        let data = self.block_get(cid).await?;
+
+        let value = decode_block(&cid, &data)?;
+        let value = select(value, &rem_path)?;
+
+        #[cfg(debug_assertions)]
+        println!("Serde: Deserialize => {}", value);
+
+        let node = serde_json::from_value::<T>(value)?;
+
+        Ok(node)
+    }
+
+    async fn add(&self, data: Vec<u8>) -> Result<Cid> {
+        let response = self
+            .client
+            .add_with_options(Cursor::new(data), ADD_OPTIONS)
+            .await
+            .map_err(IpfsError::new)?;
+
+        let cid = Cid::try_from(response.hash)?;
+
+        #[cfg(debug_assertions)]
+        println!("IPFS: add => {}", &cid);
+
+        Ok(cid)
+    }
+
+    async fn cat(&self, cid: Cid, path: Option<&str>) -> Result<Vec<u8>> {
+        let path = path_to(cid, path);
+
+        self.client
+            .cat(&path)
+            .map_ok(|chunk| chunk.to_vec())
+            .try_concat()
+            .await
+            .map_err(IpfsError::new)
+    }
+
+    async fn block_get(&self, cid: Cid) -> Result<Vec<u8>> {
+        let bytes = self
+            .api_post("block/get", &format!("arg={}", cid), None)
+            .await?;
+
+        Ok(bytes.to_vec())
+    }
+
+    async fn block_put(&self, codec: u64, data: Vec<u8>) -> Result<Cid> {
+        let codec = match codec_name(codec) {
+            Some(name) => name,
+            None => return Err(IpfsError::new(format!("unknown codec {:#x}", codec))),
+        };
+
+        let query = format!("cid-codec={}&mhtype=sha2-256", codec);
+
+        let bytes = self.api_post("block/put", &query, Some(data)).await?;
+
+        let response = serde_json::from_slice::<BlockPutResponse>(&bytes)?;
+
+        let cid = Cid::try_from(response.key)?;
+
+        Ok(cid)
+    }
+
+    async fn pin_add(&self, cid: Cid, recursive: bool) -> Result<()> {
+        self.client
+            .pin_add(&cid.to_string(), recursive)
+            .await
+            .map_err(IpfsError::new)?;
+
+        Ok(())
+    }
+
+    async fn pin_rm(&self, cid: Cid, recursive: bool) -> Result<()> {
+        self.client
+            .pin_rm(&cid.to_string(), recursive)
+            .await
+            .map_err(IpfsError::new)?;
+
+        Ok(())
+    }
+
+    async fn pin_ls(&self) -> Result<Vec<Cid>> {
+        let response = self
+            .client
+            .pin_ls(None, Some("recursive"))
+            .await
+            .map_err(IpfsError::new)?;
+
+        let mut pins = Vec::with_capacity(response.keys.len());
+
+        for key in response.keys.into_keys() {
+            pins.push(Cid::try_from(key)?);
+        }
+
+        Ok(pins)
+    }
+
+    async fn key_list(&self) -> Result<HashMap<String, IPNSAddress>> {
+        let response = self.client.key_list().await.map_err(IpfsError::new)?;
+
+        let mut keys = HashMap::with_capacity(response.keys.len());
+
+        for keypair in response.keys {
+            let address = Cid::try_from(keypair.id)?;
+
+            keys.insert(keypair.name, address);
+        }
+
+        Ok(keys)
+    }
+
+    async fn key_gen(&self, name: &str) -> Result<IPNSAddress> {
+        let response = self
+            .client
+            .key_gen(name, KeyType::Ed25519, 64)
+            .await
+            .map_err(IpfsError::new)?;
+
+        let address = Cid::try_from(response.id)?;
+
+        Ok(address)
+    }
+
+    async fn name_publish(&self, cid: Cid, key: &str) -> Result<()> {
+        let cid = cid.to_string();
+
+        let lifetime = if cfg!(debug_assertions) {
+            None
+        } else {
+            Some("4320h") // 6 months
+        };
+
+        self.client
+            .name_publish(&cid, true, lifetime, None, Some(key))
+            .await
+            .map_err(IpfsError::new)?;
+
+        Ok(())
+    }
+
+    async fn name_resolve(&self, ipns: IPNSAddress) -> Result<Cid> {
+        let response = self
+            .client
+            .name_resolve(Some(&ipns.to_string()), false, false)
+            .await
+            .map_err(IpfsError::new)?;
+
+        let cid = Cid::try_from(response.path)?;
+
+        Ok(cid)
+    }
+
+    async fn peer_id(&self) -> Result<PeerId> {
+        let response = self.client.id(None).await.map_err(IpfsError::new)?;
+
+        Ok(response.id)
+    }
+
+    async fn pubsub_pub(&self, topic: &str, msg: &str) -> Result<()> {
+        self.client
+            .pubsub_pub(topic, msg)
+            .await
+            .map_err(IpfsError::new)?;
+
+        Ok(())
+    }
+
+    fn pubsub_sub(&self, topic: &str) -> PubSubStream<'_> {
+        let stream = self.client.pubsub_sub(topic, true).map(|result| {
+            let response = result.map_err(IpfsError::new)?;
+
+            let from = match response.from {
+                Some(from) => from,
+                None => return Err(IpfsError::new("PubSub message without sender")),
+            };
+
+            let data = match response.data {
+                Some(data) => data,
+                None => return Err(IpfsError::new("PubSub message without data")),
+            };
+
+            Ok(PubSubMessage { from, data })
+        });
+
+        Box::pin(stream)
+    }
+}
@@ -0,0 +1,58 @@
+use tokio::fs;
+
+use std::io::{Error, ErrorKind};
+use std::path::PathBuf;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+pub const ARCHIVE_JOURNAL: &str = "archive_journal.json";
+pub const VIDEO_JOURNAL: &str = "video_journal.json";
+
+/// Actor state saved to a local file after every change.
+#[derive(Debug, Clone)]
+pub struct Journal {
+    path: PathBuf,
+}
+
+impl Journal {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn exists(&self) -> bool {
+        self.path.exists()
+    }
+
+    pub async fn load<T>(&self) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        let data = fs::read(&self.path).await?;
+        let state = serde_json::from_slice::<T>(&data)?;
+
+        Ok(state)
+    }
+
+    /// Write to a temporary file then rename it, a crash never leaves a partial journal.
+    pub async fn save<T>(&self, state: &T) -> Result<(), Error>
+    where
+        T: Serialize,
+    {
+        let data = serde_json::to_vec(state)?;
+
+        let temp = self.path.with_extension("tmp");
+
+        fs::write(&temp, data).await?;
+
+        fs::rename(&temp, &self.path).await
+    }
+
+    /// Remove the journal, if any.
+    pub async fn remove(&self) -> Result<(), Error> {
+        match fs::remove_file(&self.path).await {
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use std::convert::TryFrom;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+
+use linked_data::signature::eth_address;
+use linked_data::Address;
+
+use hex::FromHex;
+
+use rand::RngCore;
+
+/// Local Ethereum private key, hex encoded.
+pub const KEY_LOCATION: &str = "identity.key";
+
+/// Parse a hex encoded secp256k1 private key.
+pub fn parse_key(hex: &str) -> Result<[u8; 32], Error> {
+    let hex = hex.trim();
+    let hex = hex.strip_prefix("0x").unwrap_or(hex);
+
+    let key = <[u8; 32]>::from_hex(hex).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+
+    key_address(&key)?;
+
+    Ok(key)
+}
+
+pub fn key_address(key: &[u8; 32]) -> Result<Address, Error> {
+    eth_address(key).map_err(|e| Error::new(ErrorKind::InvalidData, e.to_string()))
+}
+
+/// Generate a new valid private key.
+pub fn random_key() -> [u8; 32] {
+    let mut key = [0u8; 32];
+
+    loop {
+        rand::thread_rng().fill_bytes(&mut key);
+
+        if key_address(&key).is_ok() {
+            return key;
+        }
+    }
+}
+
+/// Decrypt a Web3 Secret Storage (keystore) file.
+pub fn decrypt_keystore(path: &Path, password: &str) -> Result<[u8; 32], Error> {
+    let key = eth_keystore::decrypt_key(path, password)
+        .map_err(|e| Error::new(ErrorKind::InvalidData, e.to_string()))?;
+
+    let key =
+        <[u8; 32]>::try_from(key.as_slice()).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+
+    key_address(&key)?;
+
+    Ok(key)
+}
+
+pub async fn load_key(path: &Path) -> Result<[u8; 32], Error> {
+    let data = fs::read_to_string(path).await?;
+
+    parse_key(&data)
+}
+
+/// Save the key in the working directory, readable only by the owner.
+///
+/// An existing key is only removed when replace is true.
+pub async fn save_key(key: &[u8; 32], replace: bool) -> Result<(), Error> {
+    if replace {
+        match fs::remove_file(KEY_LOCATION).await {
+            Err(e) if e.kind() != ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+    }
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+
+    #[cfg(unix)]
+    options.mode(0o600);
+
+    let mut file = options.open(KEY_LOCATION).await?;
+
+    file.write_all(hex::encode(key).as_bytes()).await?;
+    file.flush().await?;
+
+    Ok(())
+}
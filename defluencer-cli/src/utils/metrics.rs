@@ -0,0 +1,301 @@
+use crate::utils::queues::QueueReport;
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Upper bounds of the latency buckets in seconds.
+const BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Counters of the stream daemon, served in Prometheus text format.
+#[derive(Debug, Clone, Default)]
+pub struct Metrics {
+    inner: Arc<Counters>,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    segments: Mutex<BTreeMap<String, u64>>,
+
+    ipfs_add: Histogram,
+    dag_put: Histogram,
+
+    video_nodes_minted: AtomicU64,
+    pubsub_failures: AtomicU64,
+
+    chat_messages_archived: AtomicU64,
+    bans_issued: AtomicU64,
+}
+
+#[derive(Debug, Default)]
+struct Histogram {
+    /// Observations per bucket, the last one is +Inf.
+    buckets: [AtomicU64; BUCKETS.len() + 1],
+
+    sum_micros: AtomicU64,
+}
+
+impl Histogram {
+    fn observe(&self, seconds: f64) {
+        let i = BUCKETS
+            .iter()
+            .position(|bound| seconds <= *bound)
+            .unwrap_or(BUCKETS.len());
+
+        self.buckets[i].fetch_add(1, Ordering::Relaxed);
+
+        self.sum_micros
+            .fetch_add((seconds * 1_000_000.0) as u64, Ordering::Relaxed);
+    }
+
+    fn render(&self, out: &mut String, name: &str, help: &str) {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} histogram", name);
+
+        let mut count = 0;
+
+        for (i, bucket) in self.buckets.iter().enumerate() {
+            count += bucket.load(Ordering::Relaxed);
+
+            match BUCKETS.get(i) {
+                Some(bound) => {
+                    let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, bound, count);
+                }
+                None => {
+                    let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, count);
+                }
+            }
+        }
+
+        let sum = self.sum_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0;
+
+        let _ = writeln!(out, "{}_sum {}", name, sum);
+        let _ = writeln!(out, "{}_count {}", name, count);
+    }
+}
+
+impl Metrics {
+    pub fn segment_received(&self, track: &str) {
+        let mut segments = self.inner.segments.lock().expect("Poisoned Lock");
+
+        match segments.get_mut(track) {
+            Some(count) => *count += 1,
+            None => {
+                segments.insert(track.to_owned(), 1);
+            }
+        }
+    }
+
+    /// Await the ipfs add and record its latency.
+    pub async fn time_add<F, T>(&self, add: F) -> T
+    where
+        F: Future<Output = T>,
+    {
+        let start = Instant::now();
+
+        let result = add.await;
+
+        self.inner.ipfs_add.observe(start.elapsed().as_secs_f64());
+
+        result
+    }
+
+    /// Await the dag put and record its latency.
+    pub async fn time_dag_put<F, T>(&self, dag_put: F) -> T
+    where
+        F: Future<Output = T>,
+    {
+        let start = Instant::now();
+
+        let result = dag_put.await;
+
+        self.inner.dag_put.observe(start.elapsed().as_secs_f64());
+
+        result
+    }
+
+    pub fn video_node_minted(&self) {
+        self.inner
+            .video_nodes_minted
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn pubsub_failed(&self) {
+        self.inner.pubsub_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn chat_message_archived(&self) {
+        self.inner
+            .chat_messages_archived
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn ban_issued(&self) {
+        self.inner.bans_issued.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Prometheus text format, queue depths included.
+    pub fn render(&self, queues: &QueueReport) -> String {
+        let mut out = String::with_capacity(4096);
+
+        out.push_str("# HELP defluencer_segments_received_total Media segments received.\n");
+        out.push_str("# TYPE defluencer_segments_received_total counter\n");
+
+        for (track, count) in self.inner.segments.lock().expect("Poisoned Lock").iter() {
+            let _ = writeln!(
+                out,
+                "defluencer_segments_received_total{{track=\"{}\"}} {}",
+                escape_label(track),
+                count
+            );
+        }
+
+        self.inner.ipfs_add.render(
+            &mut out,
+            "defluencer_ipfs_add_seconds",
+            "Latency of IPFS add.",
+        );
+
+        self.inner.dag_put.render(
+            &mut out,
+            "defluencer_dag_put_seconds",
+            "Latency of IPFS dag put.",
+        );
+
+        let counters = [
+            (
+                "defluencer_video_nodes_minted_total",
+                "Video nodes minted.",
+                &self.inner.video_nodes_minted,
+            ),
+            (
+                "defluencer_pubsub_publish_failures_total",
+                "Failed video announcements.",
+                &self.inner.pubsub_failures,
+            ),
+            (
+                "defluencer_chat_messages_archived_total",
+                "Chat messages linked in the archive.",
+                &self.inner.chat_messages_archived,
+            ),
+            (
+                "defluencer_bans_issued_total",
+                "Chat bans issued by moderators.",
+                &self.inner.bans_issued,
+            ),
+        ];
+
+        for (name, help, counter) in counters.iter() {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} counter", name);
+            let _ = writeln!(out, "{} {}", name, counter.load(Ordering::Relaxed));
+        }
+
+        out.push_str("# HELP defluencer_buffered_video_nodes Incomplete video nodes in memory.\n");
+        out.push_str("# TYPE defluencer_buffered_video_nodes gauge\n");
+        let _ = writeln!(
+            out,
+            "defluencer_buffered_video_nodes {}",
+            queues.video_nodes.depth
+        );
+
+        out.push_str("# HELP defluencer_queue_depth Messages waiting for each actor.\n");
+        out.push_str("# TYPE defluencer_queue_depth gauge\n");
+
+        let depths = [
+            ("setup", Some(&queues.setup)),
+            ("video", Some(&queues.video)),
+            ("archive", queues.archive.as_ref()),
+        ];
+
+        for (queue, depth) in depths.iter() {
+            if let Some(depth) = depth {
+                let _ = writeln!(
+                    out,
+                    "defluencer_queue_depth{{queue=\"{}\"}} {}",
+                    queue, depth.depth
+                );
+            }
+        }
+
+        out
+    }
+}
+
+/// Escape backslashes, quotes and line feeds in label values.
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::utils::queues::QueueDepth;
+
+    #[test]
+    fn render_text_format() {
+        let metrics = Metrics::default();
+
+        metrics.segment_received("audio");
+        metrics.segment_received("audio");
+        metrics.segment_received("1080\"p");
+
+        metrics.inner.ipfs_add.observe(0.003);
+        metrics.inner.ipfs_add.observe(0.2);
+        metrics.inner.ipfs_add.observe(20.0);
+
+        metrics.video_node_minted();
+        metrics.ban_issued();
+
+        let queues = QueueReport {
+            setup: QueueDepth {
+                depth: 0,
+                capacity: 2,
+            },
+            video: QueueDepth {
+                depth: 1,
+                capacity: 4,
+            },
+            archive: None,
+            video_nodes: QueueDepth {
+                depth: 3,
+                capacity: 30,
+            },
+            dropped_video_nodes: 0,
+            dropped_chat_messages: 0,
+            rejected_segments: 0,
+        };
+
+        let text = metrics.render(&queues);
+
+        for line in [
+            "defluencer_segments_received_total{track=\"audio\"} 2",
+            "defluencer_segments_received_total{track=\"1080\\\"p\"} 1",
+            "defluencer_ipfs_add_seconds_bucket{le=\"0.005\"} 1",
+            "defluencer_ipfs_add_seconds_bucket{le=\"0.25\"} 2",
+            "defluencer_ipfs_add_seconds_bucket{le=\"10\"} 2",
+            "defluencer_ipfs_add_seconds_bucket{le=\"+Inf\"} 3",
+            "defluencer_ipfs_add_seconds_count 3",
+            "defluencer_dag_put_seconds_count 0",
+            "defluencer_video_nodes_minted_total 1",
+            "defluencer_bans_issued_total 1",
+            "defluencer_buffered_video_nodes 3",
+            "defluencer_queue_depth{queue=\"video\"} 1",
+        ]
+        .iter()
+        {
+            assert!(text.lines().any(|l| l == *line), "{}", line);
+        }
+
+        assert!(!text.contains("queue=\"archive\""));
+    }
+}
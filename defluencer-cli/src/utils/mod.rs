@@ -1,2 +1,9 @@
 pub mod config;
 pub mod dag_nodes;
+pub mod ipfs;
+pub mod journal;
+pub mod keys;
+pub mod metrics;
+pub mod queues;
+pub mod shutdown;
+pub mod status;
@@ -0,0 +1,149 @@
+use crate::utils::config::QueueConfig;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use tokio::sync::mpsc::Sender;
+
+use serde::Serialize;
+
+/// Queue depths and overload counters shared between the ingest actors and server.
+#[derive(Debug, Clone)]
+pub struct QueueMetrics {
+    pub config: QueueConfig,
+
+    video_nodes: Arc<AtomicUsize>,
+
+    dropped_video_nodes: Arc<AtomicUsize>,
+    dropped_chat_messages: Arc<AtomicUsize>,
+    rejected_segments: Arc<AtomicUsize>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct QueueDepth {
+    pub depth: usize,
+    pub capacity: usize,
+}
+
+/// Snapshot of every queue, as served by the ingest server.
+#[derive(Serialize, Debug)]
+pub struct QueueReport {
+    pub setup: QueueDepth,
+    pub video: QueueDepth,
+    pub archive: Option<QueueDepth>,
+    pub video_nodes: QueueDepth,
+
+    pub dropped_video_nodes: usize,
+    pub dropped_chat_messages: usize,
+    pub rejected_segments: usize,
+}
+
+impl QueueMetrics {
+    pub fn new(config: QueueConfig) -> Self {
+        Self {
+            config,
+
+            video_nodes: Default::default(),
+
+            dropped_video_nodes: Default::default(),
+            dropped_chat_messages: Default::default(),
+            rejected_segments: Default::default(),
+        }
+    }
+
+    pub fn set_video_nodes(&self, len: usize) {
+        self.video_nodes.store(len, Ordering::Relaxed);
+    }
+
+    pub fn video_node_dropped(&self) {
+        self.dropped_video_nodes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn chat_message_dropped(&self) {
+        self.dropped_chat_messages.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn segment_rejected(&self) {
+        self.rejected_segments.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Channel depths are read from the remaining capacity of the senders.
+    pub fn report<S, V, A>(
+        &self,
+        setup_tx: &Sender<S>,
+        video_tx: &Sender<V>,
+        archive_tx: Option<&Sender<A>>,
+    ) -> QueueReport {
+        QueueReport {
+            setup: channel_depth(setup_tx, self.config.setup),
+            video: channel_depth(video_tx, self.config.video),
+            archive: archive_tx.map(|tx| channel_depth(tx, self.config.archive)),
+            video_nodes: QueueDepth {
+                depth: self.video_nodes.load(Ordering::Relaxed),
+                capacity: self.config.video_nodes,
+            },
+
+            dropped_video_nodes: self.dropped_video_nodes.load(Ordering::Relaxed),
+            dropped_chat_messages: self.dropped_chat_messages.load(Ordering::Relaxed),
+            rejected_segments: self.rejected_segments.load(Ordering::Relaxed),
+        }
+    }
+}
+
+pub fn channel_depth<T>(tx: &Sender<T>, capacity: usize) -> QueueDepth {
+    QueueDepth {
+        depth: capacity.saturating_sub(tx.capacity()),
+        capacity,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tokio::sync::mpsc::channel;
+
+    #[tokio::test]
+    async fn report_depths() {
+        let config = QueueConfig {
+            setup: 2,
+            video: 4,
+            archive: 8,
+            video_nodes: 30,
+        };
+
+        let queues = QueueMetrics::new(config);
+
+        let (setup_tx, _setup_rx) = channel::<()>(config.setup);
+        let (video_tx, mut video_rx) = channel::<()>(config.video);
+
+        for _ in 0..3 {
+            video_tx.try_send(()).unwrap();
+        }
+
+        video_rx.recv().await.unwrap();
+
+        queues.set_video_nodes(5);
+        queues.segment_rejected();
+
+        let report = queues.report::<_, _, ()>(&setup_tx, &video_tx, None);
+
+        assert_eq!(
+            report.setup,
+            QueueDepth {
+                depth: 0,
+                capacity: 2
+            }
+        );
+        assert_eq!(
+            report.video,
+            QueueDepth {
+                depth: 2,
+                capacity: 4
+            }
+        );
+        assert_eq!(report.archive, None);
+        assert_eq!(report.video_nodes.depth, 5);
+        assert_eq!(report.rejected_segments, 1);
+    }
+}
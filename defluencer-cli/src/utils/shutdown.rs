@@ -0,0 +1,43 @@
+use std::sync::Arc;
+
+use tokio::sync::watch::{channel, Receiver, Sender};
+
+/// Stop signal shared by the control API, the CTRL+C handler and the actors.
+#[derive(Debug, Clone)]
+pub struct Shutdown {
+    tx: Arc<Sender<bool>>,
+    rx: Receiver<bool>,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        let (tx, rx) = channel(false);
+
+        Self {
+            tx: Arc::new(tx),
+            rx,
+        }
+    }
+
+    pub fn trigger(&self) {
+        // Cannot fail, a receiver is always kept.
+        let _ = self.tx.send(true);
+    }
+
+    /// Resolve once the shutdown is triggered.
+    pub async fn wait(&self) {
+        let mut rx = self.rx.clone();
+
+        while !*rx.borrow() {
+            if rx.changed().await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,50 @@
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+
+/// State of the stream daemon, updated by the actors and served by the control API.
+#[derive(Debug, Clone, Default)]
+pub struct DaemonStatus {
+    inner: Arc<Mutex<StatusReport>>,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct StatusReport {
+    /// Tracks of the last minted video node.
+    pub tracks: Vec<String>,
+
+    pub video_topic: String,
+    pub last_video_node: Option<String>,
+    pub video_node_count: usize,
+
+    /// Seconds linked in the timecode tree, none if not archiving.
+    pub archived_seconds: Option<usize>,
+
+    /// Messages waiting for the archivist.
+    pub archive_depth: Option<usize>,
+
+    pub chat: Option<ChatStatus>,
+}
+
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct ChatStatus {
+    pub topic: String,
+    pub archiving: bool,
+
+    /// Peers with a verified signature.
+    pub peers: usize,
+    pub banned_peers: usize,
+}
+
+impl DaemonStatus {
+    pub fn update<F>(&self, f: F)
+    where
+        F: FnOnce(&mut StatusReport),
+    {
+        f(&mut self.inner.lock().expect("Poisoned Lock"))
+    }
+
+    pub fn report(&self) -> StatusReport {
+        self.inner.lock().expect("Poisoned Lock").clone()
+    }
+}
@@ -0,0 +1,611 @@
+mod common;
+
+use common::{find_cid, work_dir, MockIpfs};
+
+use std::path::Path;
+
+use linked_data::beacon::Beacon;
+use linked_data::feed::{feed_pages, FeedAnchor, FEED_PAGE_SIZE};
+use linked_data::ipfs::IpfsBackend;
+use linked_data::video::{DayNode, HourNode, MinuteNode, SecondNode, TimecodeNode, VideoMetadata};
+
+use cid::Cid;
+
+/// Create a beacon for Alice in the work directory and return its CID.
+fn create_beacon(mock: &MockIpfs, dir: &Path) -> Cid {
+    let avatar = mock.block_on(mock.ipfs.add(b"avatar".to_vec())).unwrap();
+
+    let output = mock
+        .cli(dir)
+        .args(&["beacon", "create", "-d", "Alice", "-a", &avatar.to_string()])
+        .output()
+        .expect("Run CLI");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{}", stdout);
+
+    find_cid(&stdout, "✅ Created Beacon ").expect("Beacon CID")
+}
+
+#[test]
+fn beacon_create() {
+    let mock = MockIpfs::start();
+    let dir = work_dir("beacon_create");
+
+    let cid = create_beacon(&mock, &dir);
+
+    assert!(mock.ipfs.is_pinned(&cid));
+
+    let beacon: Beacon = mock.block_on(mock.ipfs.dag_get(cid, None)).unwrap();
+    let keys = mock.block_on(mock.ipfs.key_list()).unwrap();
+
+    assert_eq!(keys.len(), 10);
+    assert_eq!(Some(&beacon.identity), keys.get("identity"));
+    assert_eq!(beacon.content_feed.as_ref(), keys.get("feed"));
+
+    let feed = mock.block_on(mock.ipfs.name_resolve(keys["feed"])).unwrap();
+    let feed: FeedAnchor = mock.block_on(mock.ipfs.dag_get(feed, None)).unwrap();
+
+    assert!(feed.content.is_empty());
+    assert!(dir.join("config.json").exists());
+}
+
+#[test]
+fn content_add_video() {
+    let mock = MockIpfs::start();
+    let dir = work_dir("content_add_video");
+
+    let beacon = create_beacon(&mock, &dir);
+
+    let timecode = mock.block_on(async {
+        let ipfs = &mock.ipfs;
+
+        let mut links_to_seconds = Vec::with_capacity(3);
+
+        for i in 0..3u8 {
+            let video = ipfs.add(vec![i]).await.unwrap();
+
+            let second = SecondNode {
+                link_to_video: video.into(),
+                links_to_chat: vec![],
+            };
+
+            links_to_seconds.push(ipfs.dag_put(&second).await.unwrap().into());
+        }
+
+        let minute = ipfs
+            .dag_put(&MinuteNode { links_to_seconds })
+            .await
+            .unwrap();
+
+        let hour = HourNode {
+            links_to_minutes: vec![minute.into()],
+        };
+        let hour = ipfs.dag_put(&hour).await.unwrap();
+
+        let day = DayNode {
+            links_to_hours: vec![hour.into()],
+        };
+        let day = ipfs.dag_put(&day).await.unwrap();
+
+        let timecode = TimecodeNode {
+            timecode: day.into(),
+        };
+
+        ipfs.dag_put(&timecode).await.unwrap()
+    });
+
+    let image = mock.block_on(mock.ipfs.add(b"thumbnail".to_vec())).unwrap();
+
+    let output = mock
+        .cli(&dir)
+        .args(&["content", "add", "video"])
+        .args(&["-a", &beacon.to_string()])
+        .args(&["-t", "My Video"])
+        .args(&["-i", &image.to_string()])
+        .args(&["-v", &timecode.to_string()])
+        .output()
+        .expect("Run CLI");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{}", stdout);
+
+    let cid = find_cid(&stdout, "✅ Added Video ").expect("Video CID");
+
+    let metadata: VideoMetadata = mock.block_on(mock.ipfs.dag_get(cid, None)).unwrap();
+
+    assert_eq!(metadata.title, "My Video");
+    assert_eq!(metadata.duration, 2.0);
+    assert_eq!(metadata.video.link, timecode);
+
+    let keys = mock.block_on(mock.ipfs.key_list()).unwrap();
+    let feed = mock.block_on(mock.ipfs.name_resolve(keys["feed"])).unwrap();
+    let feed: FeedAnchor = mock.block_on(mock.ipfs.dag_get(feed, None)).unwrap();
+
+    assert_eq!(feed.content.len(), 1);
+    assert_eq!(feed.content[0].link, cid);
+    assert!(feed.previous.is_none());
+}
+
+#[test]
+fn content_migrate() {
+    let mock = MockIpfs::start();
+    let dir = work_dir("content_migrate");
+
+    create_beacon(&mock, &dir);
+
+    // Feed created before pagination, one oversized page.
+    let legacy = mock.block_on(async {
+        let ipfs = &mock.ipfs;
+
+        let mut content = Vec::with_capacity(FEED_PAGE_SIZE * 2);
+
+        for i in 0..FEED_PAGE_SIZE * 2 {
+            content.push(ipfs.dag_put(&i).await.unwrap().into());
+        }
+
+        let legacy = FeedAnchor {
+            content,
+            previous: None,
+        };
+        let cid = ipfs.dag_put(&legacy).await.unwrap();
+
+        ipfs.pin_add(cid, false).await.unwrap();
+        ipfs.name_publish(cid, "feed").await.unwrap();
+
+        legacy
+    });
+
+    let output = mock
+        .cli(&dir)
+        .args(&["content", "migrate"])
+        .output()
+        .expect("Run CLI");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{}", stdout);
+
+    let head = find_cid(&stdout, "✅ Migrated Content Feed ").expect("Feed CID");
+    let pages = mock.block_on(feed_pages(&mock.ipfs, head)).unwrap();
+
+    assert_eq!(pages.len(), 2);
+    assert!(pages.iter().all(|(cid, _)| mock.ipfs.is_pinned(cid)));
+
+    let content: Vec<_> = pages
+        .into_iter()
+        .rev()
+        .flat_map(|(_, page)| page.content)
+        .collect();
+
+    assert_eq!(content, legacy.content);
+}
+
+#[test]
+fn identity_key() {
+    use linked_data::live::Live;
+
+    let mock = MockIpfs::start();
+    let dir = work_dir("identity_key");
+
+    create_beacon(&mock, &dir);
+
+    let key = "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+    let address = "2c7536e3605d9c16a7a3d7b1898e529396a65c23";
+
+    let output = mock
+        .cli(&dir)
+        .args(&["identity", "key", "import", "--hex", key])
+        .output()
+        .expect("Run CLI");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(
+        stdout.contains(&format!("✅ Key Imported. Address 0x{}", address)),
+        "{}",
+        stdout
+    );
+
+    // Existing keys are never replaced by accident.
+    let output = mock
+        .cli(&dir)
+        .args(&["identity", "key", "create"])
+        .output()
+        .expect("Run CLI");
+
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("✅"));
+
+    let output = mock
+        .cli(&dir)
+        .args(&["live", "address"])
+        .output()
+        .expect("Run CLI");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{}", stdout);
+
+    let keys = mock.block_on(mock.ipfs.key_list()).unwrap();
+    let live = mock.block_on(mock.ipfs.name_resolve(keys["live"])).unwrap();
+    let live: Live = mock.block_on(mock.ipfs.dag_get(live, None)).unwrap();
+
+    assert_eq!(live.address.map(hex::encode), Some(address.to_owned()));
+}
+
+#[test]
+fn signed_comment() {
+    use linked_data::comments::Comment;
+    use linked_data::identity::Identity;
+    use linked_data::signature::SignedMessage;
+
+    let mock = MockIpfs::start();
+    let dir = work_dir("signed_comment");
+
+    let beacon = create_beacon(&mock, &dir);
+
+    let key = "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+    let address = "2c7536e3605d9c16a7a3d7b1898e529396a65c23";
+
+    for args in [
+        &["identity", "key", "import", "--hex", key][..],
+        &["identity", "address"][..],
+    ]
+    .iter()
+    {
+        let output = mock.cli(&dir).args(*args).output().expect("Run CLI");
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(stdout.contains("✅"), "{}", stdout);
+    }
+
+    let origin = mock.block_on(mock.ipfs.add(b"content".to_vec())).unwrap();
+
+    let output = mock
+        .cli(&dir)
+        .args(&["comments", "add"])
+        .args(&["-a", &beacon.to_string()])
+        .args(&["-o", &origin.to_string()])
+        .args(&["-c", "Nice!"])
+        .output()
+        .expect("Run CLI");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    let cid = find_cid(&stdout, "✅ Added Comment ").expect("Comment CID");
+
+    let signed: SignedMessage<Comment> = mock.block_on(mock.ipfs.dag_get(cid, None)).unwrap();
+
+    assert!(signed.verify());
+    assert_eq!(hex::encode(signed.address), address);
+    assert_eq!(signed.data.author.link, beacon);
+    assert_eq!(signed.data.comment, "Nice!");
+
+    let keys = mock.block_on(mock.ipfs.key_list()).unwrap();
+    let identity = mock
+        .block_on(mock.ipfs.name_resolve(keys["identity"]))
+        .unwrap();
+    let identity: Identity = mock.block_on(mock.ipfs.dag_get(identity, None)).unwrap();
+
+    assert_eq!(identity.eth_address, Some(signed.address));
+}
+
+/// Send a PUT like FFMPEG does and return the response status code.
+#[cfg(unix)]
+fn put(addr: std::net::SocketAddr, path: &str, body: &[u8]) -> u16 {
+    request(addr, "PUT", path, body).0
+}
+
+/// Send a request and return the response status code and body.
+#[cfg(unix)]
+fn request(addr: std::net::SocketAddr, method: &str, path: &str, body: &[u8]) -> (u16, String) {
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    let mut stream = TcpStream::connect(addr).expect("Connect Ingess Server");
+
+    write!(
+        stream,
+        "{} {} HTTP/1.1\r\nHost: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        method,
+        path,
+        addr,
+        body.len()
+    )
+    .unwrap();
+    stream.write_all(body).unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+
+    let status = response
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse().ok())
+        .expect("Invalid HTTP Response");
+
+    let body = match response.split_once("\r\n\r\n") {
+        Some((_, body)) => body.to_owned(),
+        None => String::new(),
+    };
+
+    (status, body)
+}
+
+/// Start the stream daemon on a free port, send the setup then segments first to last.
+/// Output goes to stream.log, a full pipe would block the daemon.
+#[cfg(unix)]
+fn stream_segments(
+    mock: &MockIpfs,
+    dir: &Path,
+    args: &[&str],
+    segments: std::ops::Range<usize>,
+) -> std::process::Child {
+    use std::net::{TcpListener, TcpStream};
+    use std::process::Stdio;
+    use std::thread::sleep;
+    use std::time::{Duration, Instant};
+
+    use serde_json::Value;
+
+    let addr = {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+
+        listener.local_addr().unwrap()
+    };
+
+    let config_path = dir.join("config.json");
+    let mut config: Value = serde_json::from_slice(&std::fs::read(&config_path).unwrap()).unwrap();
+    config["input_socket_addr"] = Value::String(addr.to_string());
+    std::fs::write(&config_path, config.to_string()).unwrap();
+
+    let log = std::fs::File::create(dir.join("stream.log")).unwrap();
+
+    let child = mock
+        .cli(dir)
+        .arg("stream")
+        .args(args)
+        .stdout(Stdio::from(log))
+        .spawn()
+        .expect("Run CLI");
+
+    let deadline = Instant::now() + Duration::from_secs(30);
+
+    while TcpStream::connect(addr).is_err() {
+        assert!(Instant::now() < deadline, "Ingess Server never came online");
+
+        sleep(Duration::from_millis(100));
+    }
+
+    let playlist = "#EXTM3U\n#EXT-X-VERSION:7\n#EXT-X-STREAM-INF:BANDWIDTH=128000,CODECS=\"mp4a.40.2\"\naudio/index.m3u8\n";
+
+    assert_eq!(put(addr, "/master.m3u8", playlist.as_bytes()), 204);
+    assert_eq!(put(addr, "/audio/init.mp4", b"init"), 201);
+
+    // Give the setup node time to reach the video system.
+    sleep(Duration::from_millis(500));
+
+    for i in segments {
+        let path = format!("/audio/{}.m4s", i);
+
+        assert_eq!(put(addr, &path, &[i as u8; 16]), 201);
+
+        sleep(Duration::from_millis(200));
+    }
+
+    // Segments outside a track directory used to crash the server.
+    assert_eq!(put(addr, "/3.m4s", b"orphan"), 400);
+    assert_eq!(put(addr, "/audio/last.m4s", b"orphan"), 400);
+
+    child
+}
+
+/// Wait for the daemon to exit, killing it after 30 seconds.
+#[cfg(unix)]
+fn wait_exit(child: &mut std::process::Child) {
+    use std::thread::sleep;
+    use std::time::{Duration, Instant};
+
+    let deadline = Instant::now() + Duration::from_secs(30);
+
+    loop {
+        if child.try_wait().unwrap().is_some() {
+            break;
+        }
+
+        if Instant::now() > deadline {
+            child.kill().unwrap();
+            panic!("Stream daemon did not shutdown");
+        }
+
+        sleep(Duration::from_millis(100));
+    }
+}
+
+/// Send a signal to the daemon.
+#[cfg(unix)]
+fn kill(child: &std::process::Child, signal: &str) {
+    let status = std::process::Command::new("kill")
+        .args(&[signal, &child.id().to_string()])
+        .status()
+        .unwrap();
+
+    assert!(status.success());
+}
+
+#[cfg(unix)]
+#[test]
+fn stream_archive() {
+    let mock = MockIpfs::start();
+    let dir = work_dir("stream_archive");
+
+    create_beacon(&mock, &dir);
+
+    let mut child = stream_segments(&mock, &dir, &["--no-chat"], 0..3);
+
+    kill(&child, "-INT");
+    wait_exit(&mut child);
+
+    let stdout = std::fs::read_to_string(dir.join("stream.log")).unwrap();
+
+    let cid = find_cid(&stdout, "Final Timecode-addressable Node => ").expect("Timecode CID");
+
+    assert!(mock.ipfs.is_pinned(&cid));
+
+    let minute: MinuteNode = mock
+        .block_on(mock.ipfs.dag_get(cid, Some("/time/hour/0/minute/0")))
+        .unwrap();
+
+    assert_eq!(minute.links_to_seconds.len(), 3);
+
+    // Clean shutdown, nothing to resume.
+    assert!(!dir.join("archive_journal.json").exists());
+    assert!(!dir.join("video_journal.json").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn stream_control_api() {
+    use serde_json::Value;
+
+    let mock = MockIpfs::start();
+    let dir = work_dir("stream_control_api");
+
+    create_beacon(&mock, &dir);
+
+    let mut child = stream_segments(&mock, &dir, &["--no-chat"], 0..3);
+
+    let config: Value =
+        serde_json::from_slice(&std::fs::read(dir.join("config.json")).unwrap()).unwrap();
+    let addr = config["input_socket_addr"]
+        .as_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+
+    let (code, body) = request(addr, "GET", "/control/status", b"");
+
+    assert_eq!(code, 200);
+
+    let status: Value = serde_json::from_str(&body).unwrap();
+
+    assert_eq!(status["tracks"], serde_json::json!(["audio"]));
+    assert_eq!(status["video_node_count"], 3);
+    assert_eq!(status["archived_seconds"], 2);
+    assert_eq!(status["archive_depth"], 0);
+    assert_eq!(status["chat"], Value::Null);
+
+    let (code, body) = request(addr, "GET", "/metrics", b"");
+
+    assert_eq!(code, 200);
+    assert!(body.contains("defluencer_segments_received_total{track=\"audio\"} 3"));
+    assert!(body.contains("defluencer_video_nodes_minted_total 3"));
+
+    // Chat is not running.
+    assert_eq!(
+        request(addr, "POST", "/control/chat?archive=false", b"").0,
+        404
+    );
+    assert_eq!(request(addr, "POST", "/control/topics", b"").0, 400);
+    assert_eq!(
+        request(addr, "POST", "/control/topics?video=rotated", b"").0,
+        204
+    );
+
+    std::thread::sleep(std::time::Duration::from_millis(200));
+
+    let (_, body) = request(addr, "GET", "/control/status", b"");
+    let status: Value = serde_json::from_str(&body).unwrap();
+
+    assert_eq!(status["video_topic"], "rotated");
+
+    // Viewers find the new topic in the Live record.
+    let keys = mock.block_on(mock.ipfs.key_list()).unwrap();
+    let live = mock.block_on(mock.ipfs.name_resolve(keys["live"])).unwrap();
+    let live: linked_data::live::Live = mock.block_on(mock.ipfs.dag_get(live, None)).unwrap();
+
+    assert_eq!(live.video_topic, "rotated");
+
+    assert_eq!(request(addr, "POST", "/control/finalize", b"").0, 204);
+
+    wait_exit(&mut child);
+
+    let stdout = std::fs::read_to_string(dir.join("stream.log")).unwrap();
+
+    let cid = find_cid(&stdout, "Final Timecode-addressable Node => ").expect("Timecode CID");
+
+    assert!(mock.ipfs.is_pinned(&cid));
+    assert!(!dir.join("archive_journal.json").exists());
+    assert!(!dir.join("video_journal.json").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn stream_resume_and_finalize() {
+    use linked_data::video::VideoNode;
+
+    let mock = MockIpfs::start();
+    let dir = work_dir("stream_resume_and_finalize");
+
+    create_beacon(&mock, &dir);
+
+    let mut child = stream_segments(&mock, &dir, &["--no-chat"], 0..2);
+
+    kill(&child, "-KILL");
+    wait_exit(&mut child);
+
+    assert!(dir.join("archive_journal.json").exists());
+    assert!(dir.join("video_journal.json").exists());
+
+    // A new session must not overwrite the journals.
+    let output = mock.cli(&dir).args(&["stream"]).output().expect("Run CLI");
+
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--resume"));
+    assert!(dir.join("archive_journal.json").exists());
+
+    // Segment indices restart at zero in the resumed session.
+    let mut child = stream_segments(&mock, &dir, &["--no-chat", "--resume"], 0..2);
+
+    kill(&child, "-KILL");
+    wait_exit(&mut child);
+
+    let output = mock
+        .cli(&dir)
+        .args(&["finalize-archive"])
+        .output()
+        .expect("Run CLI");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    let cid = find_cid(&stdout, "Final Timecode-addressable Node => ").expect("Timecode CID");
+
+    assert!(mock.ipfs.is_pinned(&cid));
+    assert!(!dir.join("archive_journal.json").exists());
+    assert!(!dir.join("video_journal.json").exists());
+
+    let minute: MinuteNode = mock
+        .block_on(mock.ipfs.dag_get(cid, Some("/time/hour/0/minute/0")))
+        .unwrap();
+
+    assert_eq!(minute.links_to_seconds.len(), 4);
+
+    // The chain of video nodes continues across sessions.
+    let last: VideoNode = mock
+        .block_on(
+            mock.ipfs
+                .dag_get(cid, Some("/time/hour/0/minute/0/second/3/video")),
+        )
+        .unwrap();
+    let previous: VideoNode = mock
+        .block_on(
+            mock.ipfs
+                .dag_get(last.previous.unwrap().link, Some("/previous")),
+        )
+        .unwrap();
+
+    assert!(previous.previous.is_some());
+    assert!(previous.tracks.contains_key("audio"));
+}
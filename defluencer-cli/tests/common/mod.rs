@@ -0,0 +1,452 @@
+//! Stand-in for the go-ipfs HTTP API backed by memory.
+
+#![allow(dead_code)]
+
+use std::convert::{Infallible, TryFrom};
+use std::future::Future;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use tokio::runtime::Runtime;
+
+use futures_util::StreamExt;
+
+use hyper::header::CONTENT_TYPE;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server, StatusCode};
+
+use linked_data::dag_cbor;
+use linked_data::ipfs::{codec_from_name, IpfsBackend, IpfsError, MemoryIpfs};
+
+use serde_json::{json, Value};
+
+use cid::multibase::Base;
+use cid::Cid;
+
+const API_PATH: &str = "/api/v0/";
+
+/// Mock IPFS node serving the HTTP API on a random local port.
+pub struct MockIpfs {
+    pub ipfs: MemoryIpfs,
+
+    addr: SocketAddr,
+
+    runtime: Runtime,
+}
+
+impl MockIpfs {
+    pub fn start() -> Self {
+        let runtime = Runtime::new().expect("Tokio Runtime");
+
+        let ipfs = MemoryIpfs::new();
+
+        let service_ipfs = ipfs.clone();
+
+        let service = make_service_fn(move |_| {
+            let ipfs = service_ipfs.clone();
+
+            async move { Ok::<_, Infallible>(service_fn(move |req| api_request(req, ipfs.clone()))) }
+        });
+
+        let server = {
+            let _guard = runtime.enter();
+
+            Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(service)
+        };
+
+        let addr = server.local_addr();
+
+        runtime.spawn(server);
+
+        Self {
+            ipfs,
+            addr,
+            runtime,
+        }
+    }
+
+    /// URL to use as DEFLUENCER_IPFS_API.
+    pub fn api_url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Run a future on the mock runtime, used to inspect the node from tests.
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+
+    /// Prepare a CLI command using this node and running in the work directory.
+    pub fn cli(&self, work_dir: &Path) -> Command {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_defluencer-cli"));
+
+        command
+            .env("DEFLUENCER_IPFS_API", self.api_url())
+            .current_dir(work_dir);
+
+        command
+    }
+}
+
+/// Return an empty directory unique to this test, config.json is written there.
+pub fn work_dir(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("defluencer-{}-{}", test, std::process::id()));
+
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir).expect("Remove Work Dir");
+    }
+
+    std::fs::create_dir_all(&dir).expect("Create Work Dir");
+
+    dir
+}
+
+/// Return the CID following the prefix in the CLI output.
+pub fn find_cid(output: &str, prefix: &str) -> Option<Cid> {
+    output.lines().find_map(|line| {
+        let cid = line.strip_prefix(prefix)?;
+
+        Cid::try_from(cid.trim()).ok()
+    })
+}
+
+async fn api_request(req: Request<Body>, ipfs: MemoryIpfs) -> Result<Response<Body>, Infallible> {
+    let (parts, body) = req.into_parts();
+
+    let command = match parts.uri.path().strip_prefix(API_PATH) {
+        Some(command) => command.to_owned(),
+        None => return Ok(status_response(StatusCode::NOT_FOUND)),
+    };
+
+    let args = query_args(parts.uri.query().unwrap_or_default());
+
+    let content_type = parts
+        .headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_owned();
+
+    let body = match hyper::body::to_bytes(body).await {
+        Ok(bytes) => bytes,
+        Err(e) => return Ok(error_response(IpfsError::new(e))),
+    };
+
+    let data = multipart_data(&content_type, &body);
+
+    let result = match command.as_str() {
+        "pubsub/sub" => return Ok(pubsub_sub(ipfs, &args)),
+        "cat" => cat(&ipfs, &args).await.map(Body::from),
+        "block/get" => block_get(&ipfs, &args).await.map(Body::from),
+        _ => json_command(&ipfs, &command, &args, data)
+            .await
+            .map(|value| Body::from(value.to_string())),
+    };
+
+    match result {
+        Ok(body) => Ok(Response::new(body)),
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+async fn json_command(
+    ipfs: &MemoryIpfs,
+    command: &str,
+    args: &[(String, String)],
+    data: Option<Vec<u8>>,
+) -> Result<Value, IpfsError> {
+    match command {
+        "dag/put" => {
+            let data = data.ok_or_else(|| IpfsError::new("missing data"))?;
+
+            let node: Value = match arg(args, "input-codec") {
+                Ok("dag-cbor") => dag_cbor::decode(&data)?,
+                _ => serde_json::from_slice(&data)?,
+            };
+
+            let cid = ipfs.dag_put(&node).await?;
+
+            Ok(json!({ "Cid": { "/": cid.to_string() } }))
+        }
+        "dag/get" => {
+            let (cid, path) = split_path(arg(args, "arg")?)?;
+
+            ipfs.dag_get::<Value>(cid, path).await
+        }
+        "dag/resolve" => {
+            let (cid, path) = split_path(arg(args, "arg")?)?;
+
+            let (cid, rem_path) = ipfs.dag_resolve(cid, path)?;
+
+            Ok(json!({ "Cid": { "/": cid.to_string() }, "RemPath": rem_path }))
+        }
+        "block/put" => {
+            let data = data.ok_or_else(|| IpfsError::new("missing data"))?;
+            let size = data.len();
+
+            let codec = arg(args, "cid-codec").unwrap_or("raw");
+            let codec = codec_from_name(codec)
+                .ok_or_else(|| IpfsError::new(format!("unknown codec {}", codec)))?;
+
+            let cid = ipfs.block_put(codec, data).await?;
+
+            Ok(json!({ "Key": cid.to_string(), "Size": size }))
+        }
+        "add" => {
+            let data = data.ok_or_else(|| IpfsError::new("missing data"))?;
+            let size = data.len();
+
+            let cid = ipfs.add(data).await?;
+
+            Ok(
+                json!({ "Name": cid.to_string(), "Hash": cid.to_string(), "Size": size.to_string() }),
+            )
+        }
+        "pin/add" => {
+            let cid = Cid::try_from(arg(args, "arg")?)?;
+
+            ipfs.pin_add(cid, flag(args, "recursive", true)).await?;
+
+            Ok(json!({ "Pins": [cid.to_string()] }))
+        }
+        "pin/rm" => {
+            let cid = Cid::try_from(arg(args, "arg")?)?;
+
+            ipfs.pin_rm(cid, flag(args, "recursive", true)).await?;
+
+            Ok(json!({ "Pins": [cid.to_string()] }))
+        }
+        "pin/ls" => {
+            let mut keys = serde_json::Map::new();
+
+            for cid in ipfs.pin_ls().await? {
+                keys.insert(cid.to_string(), json!({ "Type": "recursive" }));
+            }
+
+            Ok(json!({ "Keys": keys }))
+        }
+        "key/list" => {
+            let keys: Vec<Value> = ipfs
+                .key_list()
+                .await?
+                .into_iter()
+                .map(|(name, address)| json!({ "Name": name, "Id": address.to_string() }))
+                .collect();
+
+            Ok(json!({ "Keys": keys }))
+        }
+        "key/gen" => {
+            let name = arg(args, "arg")?;
+
+            let address = ipfs.key_gen(name).await?;
+
+            Ok(json!({ "Name": name, "Id": address.to_string() }))
+        }
+        "name/publish" => {
+            let (cid, _) = split_path(arg(args, "arg")?)?;
+            let key = arg(args, "key").unwrap_or("self");
+
+            ipfs.name_publish(cid, key).await?;
+
+            let address = ipfs.key_list().await?[key];
+
+            Ok(json!({ "Name": address.to_string(), "Value": format!("/ipfs/{}", cid) }))
+        }
+        "name/resolve" => {
+            let name = arg(args, "arg")?;
+            let name = name.trim_start_matches("/ipns/");
+
+            let cid = ipfs.name_resolve(Cid::try_from(name)?).await?;
+
+            Ok(json!({ "Path": format!("/ipfs/{}", cid) }))
+        }
+        "id" => {
+            let peer_id = ipfs.peer_id().await?;
+
+            Ok(json!({
+                "ID": peer_id,
+                "PublicKey": "",
+                "Addresses": [],
+                "AgentVersion": "mock-ipfs",
+                "ProtocolVersion": "ipfs/0.1.0",
+            }))
+        }
+        "pubsub/pub" => {
+            let topic = arg(args, "arg")?;
+
+            // Payload is either the second argument or the multipart body.
+            let msg = match args.iter().filter(|(key, _)| key == "arg").nth(1) {
+                Some((_, msg)) => msg.clone(),
+                None => {
+                    let data = data.ok_or_else(|| IpfsError::new("missing data"))?;
+
+                    String::from_utf8(data).map_err(IpfsError::new)?
+                }
+            };
+
+            ipfs.pubsub_pub(topic, &msg).await?;
+
+            Ok(Value::Null)
+        }
+        _ => Err(IpfsError::new(format!("unknown command {}", command))),
+    }
+}
+
+async fn cat(ipfs: &MemoryIpfs, args: &[(String, String)]) -> Result<Vec<u8>, IpfsError> {
+    let (cid, path) = split_path(arg(args, "arg")?)?;
+
+    ipfs.cat(cid, path).await
+}
+
+async fn block_get(ipfs: &MemoryIpfs, args: &[(String, String)]) -> Result<Vec<u8>, IpfsError> {
+    let cid = Cid::try_from(arg(args, "arg")?)?;
+
+    ipfs.block_get(cid).await
+}
+
+/// Stream messages as newline delimited JSON, encoded like go-ipfs does.
+fn pubsub_sub(ipfs: MemoryIpfs, args: &[(String, String)]) -> Response<Body> {
+    let topic = match arg(args, "arg") {
+        Ok(topic) => topic.to_owned(),
+        Err(e) => return error_response(e),
+    };
+
+    let (mut sender, body) = Body::channel();
+
+    tokio::spawn(async move {
+        let mut stream = ipfs.pubsub_sub(&topic);
+
+        let mut seqno: u64 = 0;
+
+        while let Some(Ok(msg)) = stream.next().await {
+            seqno += 1;
+
+            let from = match Base::Base58Btc.decode(&msg.from) {
+                Ok(from) => from,
+                Err(_) => continue,
+            };
+
+            let line = json!({
+                "from": Base::Base64Pad.encode(from),
+                "data": Base::Base64Pad.encode(&msg.data),
+                "seqno": Base::Base64Pad.encode(seqno.to_be_bytes()),
+                "topicIDs": [topic],
+            });
+
+            if sender
+                .send_data(format!("{}\n", line).into())
+                .await
+                .is_err()
+            {
+                break;
+            }
+        }
+    });
+
+    Response::new(body)
+}
+
+fn status_response(status: StatusCode) -> Response<Body> {
+    let mut res = Response::new(Body::empty());
+
+    *res.status_mut() = status;
+
+    res
+}
+
+fn error_response(error: IpfsError) -> Response<Body> {
+    let body = json!({ "Message": error.to_string(), "Code": 0, "Type": "error" });
+
+    let mut res = Response::new(Body::from(body.to_string()));
+
+    *res.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+
+    res
+}
+
+fn arg<'a>(args: &'a [(String, String)], key: &str) -> Result<&'a str, IpfsError> {
+    match args.iter().find(|(k, _)| k == key) {
+        Some((_, value)) => Ok(value),
+        None => Err(IpfsError::new(format!("argument {} is required", key))),
+    }
+}
+
+fn flag(args: &[(String, String)], key: &str, default: bool) -> bool {
+    match arg(args, key) {
+        Ok(value) => value == "true",
+        Err(_) => default,
+    }
+}
+
+/// Split "/ipfs/CID/some/path" into CID and path.
+fn split_path(path: &str) -> Result<(Cid, Option<&str>), IpfsError> {
+    let path = path.trim_start_matches("/ipfs/");
+
+    let (cid, path) = match path.find('/') {
+        Some(idx) => (&path[..idx], Some(&path[idx..])),
+        None => (path, None),
+    };
+
+    Ok((Cid::try_from(cid)?, path))
+}
+
+fn query_args(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.find('=') {
+            Some(idx) => (
+                percent_decode(&pair[..idx]),
+                percent_decode(&pair[idx + 1..]),
+            ),
+            None => (percent_decode(pair), String::new()),
+        })
+        .collect()
+}
+
+fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+
+    let mut output = Vec::with_capacity(bytes.len());
+
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = |idx: usize| bytes.get(idx).and_then(|byte| (*byte as char).to_digit(16));
+
+        match (bytes[i], hex(i + 1), hex(i + 2)) {
+            (b'%', Some(high), Some(low)) => {
+                output.push((high * 16 + low) as u8);
+                i += 2;
+            }
+            (b'+', _, _) => output.push(b' '),
+            (byte, _, _) => output.push(byte),
+        }
+
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&output).into_owned()
+}
+
+/// Return the content of the first part of a multipart body.
+fn multipart_data(content_type: &str, body: &[u8]) -> Option<Vec<u8>> {
+    let boundary = content_type.split("boundary=").nth(1)?.trim_matches('"');
+
+    let delimiter = format!("--{}", boundary);
+
+    let start = find(body, delimiter.as_bytes())? + delimiter.len();
+    let part = &body[start..];
+
+    let start = find(part, b"\r\n\r\n")? + 4;
+    let part = &part[start..];
+
+    let end = find(part, format!("\r\n{}", delimiter).as_bytes())?;
+
+    Some(part[..end].to_vec())
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
@@ -0,0 +1,139 @@
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+
+use linked_data::comments::Commentary;
+use linked_data::feed::{ContentCache, FeedAnchor, FEED_PAGE_SIZE};
+use linked_data::identity::Identity;
+
+use cid::multihash::{Code, MultihashDigest};
+use cid::Cid;
+
+/// Number of followed beacons, each with a full feed page.
+const BEACONS: usize = 200;
+
+/// Number of comments published by each beacon.
+const COMMENTS: usize = 25;
+
+fn cid(kind: &str, i: usize, j: usize) -> Cid {
+    Cid::new_v1(
+        0x71,
+        Code::Sha2_256.digest(format!("{}-{}-{}", kind, i, j).as_bytes()),
+    )
+}
+
+fn feed(i: usize) -> FeedAnchor {
+    FeedAnchor {
+        content: (0..FEED_PAGE_SIZE)
+            .map(|j| cid("media", i, j).into())
+            .collect(),
+        previous: None,
+    }
+}
+
+fn commentary(i: usize) -> Commentary {
+    let mut commentary = Commentary::default();
+
+    for j in 0..COMMENTS {
+        // Comment on the content of the next beacon.
+        let media = cid("media", (i + 1) % BEACONS, j);
+
+        commentary
+            .comments
+            .entry(media)
+            .or_default()
+            .push(cid("comment", i, j).into());
+    }
+
+    commentary
+}
+
+/// 10 000 media & 5 000 comments.
+fn filled_cache() -> ContentCache {
+    let mut cache = ContentCache::create();
+
+    for i in 0..BEACONS {
+        let beacon = cid("beacon", i, 0);
+
+        cache.insert_identity(
+            beacon,
+            Identity {
+                display_name: format!("Beacon {}", i),
+                ..Default::default()
+            },
+        );
+        cache.insert_media_content(beacon, cid("feed", i, 0), feed(i));
+        cache.insert_comments(beacon, commentary(i));
+    }
+
+    cache
+}
+
+fn insert(c: &mut Criterion) {
+    c.bench_function("insert feeds & comments", |b| {
+        b.iter(|| black_box(filled_cache()))
+    });
+}
+
+fn lookups(c: &mut Criterion) {
+    let cache = filled_cache();
+
+    let media: Vec<Cid> = cache.iter_media_content().copied().collect();
+
+    c.bench_function("media author & comments count", |b| {
+        b.iter(|| {
+            for cid in media.iter() {
+                black_box(cache.media_content_author(cid));
+                black_box(cache.comments_count(cid));
+            }
+        })
+    });
+
+    c.bench_function("comment authors", |b| {
+        b.iter(|| {
+            for cid in media.iter() {
+                if let Some(comments) = cache.iter_comments(cid) {
+                    for comment in comments {
+                        black_box(cache.comment_author(comment));
+                    }
+                }
+            }
+        })
+    });
+}
+
+fn updates(c: &mut Criterion) {
+    let cache = filled_cache();
+
+    c.bench_function("feed update removing content", |b| {
+        b.iter_batched(
+            || cache.clone(),
+            |mut cache| {
+                for i in 0..BEACONS {
+                    let mut feed = feed(i);
+                    feed.content.pop();
+
+                    cache.insert_media_content(cid("beacon", i, 0), cid("feed", i, 1), feed);
+                }
+
+                cache
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    c.bench_function("comments update removing comments", |b| {
+        b.iter_batched(
+            || cache.clone(),
+            |mut cache| {
+                for i in 0..BEACONS {
+                    cache.insert_comments(cid("beacon", i, 0), Commentary::default());
+                }
+
+                cache
+            },
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+criterion_group!(benches, insert, lookups, updates);
+criterion_main!(benches);
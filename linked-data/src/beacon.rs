@@ -35,6 +35,25 @@ pub struct Beacon {
     /// Link to all chat moderator addresses.
     #[serde_as(as = "Option<DisplayFromStr>")]
     pub mods: Option<IPNSAddress>,
+
+    /// Link to chat content filters.
+    #[serde(default)]
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub filters: Option<IPNSAddress>,
+
+    /// Topic where new comments are announced.
+    #[serde(default)]
+    pub comments_topic: Option<String>,
+
+    /// Link to comments from everyone, verified by the beacon owner.
+    #[serde(default)]
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub comment_index: Option<IPNSAddress>,
+
+    /// Link to comments hidden from your content.
+    #[serde(default)]
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub comment_moderation: Option<IPNSAddress>,
 }
 
 #[cfg(test)]
@@ -56,6 +75,10 @@ mod tests {
             live: None,
             bans: None,
             mods: None,
+            filters: None,
+            comments_topic: Some("topic".to_owned()),
+            comment_index: None,
+            comment_moderation: None,
         };
 
         let json = serde_json::to_string_pretty(&old_beacon).expect("Cannot serialize");
@@ -0,0 +1,194 @@
+//! Content addressable archives, CARv1.
+//!
+//! Header is a DAG-CBOR map of roots and version, then each block is a varint length,
+//! the CID bytes and the block data.
+
+use crate::dag_cbor;
+use crate::ipfs::{IpfsError, Result};
+use crate::IPLDLink;
+
+use std::io::{Read, Write};
+
+use serde::{Deserialize, Serialize};
+
+use cid::Cid;
+
+const VERSION: u64 = 1;
+
+/// Reject sections bigger than this, IPFS blocks are at most a few MiB.
+const MAX_SECTION_LEN: u64 = 8 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize)]
+struct CarHeader {
+    roots: Vec<IPLDLink>,
+    version: u64,
+}
+
+pub struct CarWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> CarWriter<W> {
+    /// Write the header with these roots.
+    pub fn new(mut writer: W, roots: &[Cid]) -> Result<Self> {
+        let header = CarHeader {
+            roots: roots.iter().map(|cid| IPLDLink::from(*cid)).collect(),
+            version: VERSION,
+        };
+
+        let header = dag_cbor::to_vec(&header)?;
+
+        write_varint(&mut writer, header.len() as u64)?;
+        writer.write_all(&header)?;
+
+        Ok(Self { writer })
+    }
+
+    pub fn write_block(&mut self, cid: &Cid, data: &[u8]) -> Result<()> {
+        let cid = cid.to_bytes();
+
+        write_varint(&mut self.writer, (cid.len() + data.len()) as u64)?;
+        self.writer.write_all(&cid)?;
+        self.writer.write_all(data)?;
+
+        Ok(())
+    }
+
+    /// The inner writer, to drain blocks buffered in memory.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Flush and return the inner writer.
+    pub fn finish(mut self) -> Result<W> {
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+}
+
+pub struct CarReader<R: Read> {
+    reader: R,
+    roots: Vec<Cid>,
+}
+
+impl<R: Read> CarReader<R> {
+    /// Read the header.
+    pub fn new(mut reader: R) -> Result<Self> {
+        let len = match read_varint(&mut reader)? {
+            Some(len) => len,
+            None => return Err(IpfsError::new("CAR: missing header")),
+        };
+
+        let header = read_section(&mut reader, len)?;
+        let header: CarHeader = dag_cbor::from_slice(&header)?;
+
+        if header.version != VERSION {
+            return Err(IpfsError::new(format!(
+                "CAR: unsupported version {}",
+                header.version
+            )));
+        }
+
+        let roots = header.roots.into_iter().map(|ipld| ipld.link).collect();
+
+        Ok(Self { reader, roots })
+    }
+
+    pub fn roots(&self) -> &[Cid] {
+        &self.roots
+    }
+
+    /// Return the next block or None at the end of the file.
+    pub fn next_block(&mut self) -> Result<Option<(Cid, Vec<u8>)>> {
+        let len = match read_varint(&mut self.reader)? {
+            Some(len) => len,
+            None => return Ok(None),
+        };
+
+        let section = read_section(&mut self.reader, len)?;
+
+        let mut data = section.as_slice();
+
+        let cid = Cid::read_bytes(&mut data)?;
+
+        Ok(Some((cid, data.to_vec())))
+    }
+}
+
+fn read_section<R: Read>(reader: &mut R, len: u64) -> Result<Vec<u8>> {
+    if len > MAX_SECTION_LEN {
+        return Err(IpfsError::new("CAR: section too big"));
+    }
+
+    let mut section = vec![0; len as usize];
+
+    reader.read_exact(&mut section)?;
+
+    Ok(section)
+}
+
+fn write_varint<W: Write>(writer: &mut W, mut int: u64) -> Result<()> {
+    let mut buf = Vec::with_capacity(10);
+
+    while int >= 0x80 {
+        buf.push(int as u8 | 0x80);
+        int >>= 7;
+    }
+
+    buf.push(int as u8);
+
+    writer.write_all(&buf)?;
+
+    Ok(())
+}
+
+/// Return None if the reader is at the end.
+fn read_varint<R: Read>(reader: &mut R) -> Result<Option<u64>> {
+    let mut int = 0;
+
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0];
+
+        if reader.read(&mut byte)? == 0 {
+            if shift == 0 {
+                return Ok(None);
+            }
+
+            return Err(IpfsError::new("CAR: unexpected end of file"));
+        }
+
+        int |= ((byte[0] & 0x7f) as u64) << shift;
+
+        if byte[0] & 0x80 == 0 {
+            return Ok(Some(int));
+        }
+    }
+
+    Err(IpfsError::new("CAR: varint too long"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cid::multihash::{Code, MultihashDigest};
+
+    #[test]
+    fn write_read() {
+        let data = vec![0u8; 300];
+        let cid = Cid::new_v1(0x55, Code::Sha2_256.digest(&data));
+
+        let mut car = CarWriter::new(Vec::new(), &[cid]).unwrap();
+        car.write_block(&cid, &data).unwrap();
+        let car = car.finish().unwrap();
+
+        let mut reader = CarReader::new(car.as_slice()).unwrap();
+
+        assert_eq!(reader.roots(), &[cid]);
+        assert_eq!(reader.next_block().unwrap(), Some((cid, data)));
+        assert_eq!(reader.next_block().unwrap(), None);
+
+        assert!(CarReader::new(&car[..10]).is_err());
+    }
+}
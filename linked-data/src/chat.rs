@@ -1,6 +1,9 @@
+use crate::eip712::{Member, TypedData};
 use crate::{IPLDLink, PeerId};
 
-use crate::moderation::{Ban, Moderator};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::moderation::{Ban, Moderator, Timeout};
 use serde::{Deserialize, Serialize};
 
 /// GossipSub Live Chat Message.
@@ -12,11 +15,14 @@ pub struct Message {
     pub sig: IPLDLink,
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, Debug)]
 pub enum MessageType {
     Chat(String),
     Ban(Ban),
+    Unban(Ban),
     Mod(Moderator),
+    Unmod(Moderator),
+    Timeout(Timeout),
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -25,3 +31,51 @@ pub struct ChatId {
 
     pub peer_id: PeerId,
 }
+
+impl TypedData for ChatId {
+    const TYPE_NAME: &'static str = "ChatId";
+
+    const MEMBERS: &'static [(&'static str, &'static str)] =
+        &[("name", "string"), ("peerId", "string")];
+
+    fn members(&self) -> Vec<Member<'_>> {
+        vec![
+            Member::String(self.name.as_str().into()),
+            Member::String(self.peer_id.as_str().into()),
+        ]
+    }
+}
+
+/// Chat message as archived with the video.
+/// Can be attributed and verified using the signed message.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ArchivedMessage {
+    /// Timestamp at the time of reception in Unix time.
+    pub timestamp: u64,
+
+    /// GossipSub peer that sent the message.
+    pub peer: PeerId,
+
+    pub msg: MessageType,
+
+    /// Link to signed message.
+    pub sig: IPLDLink,
+}
+
+impl ArchivedMessage {
+    pub fn create(peer: PeerId, message: Message) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("SystemTime before UNIX EPOCH!")
+            .as_secs();
+
+        let Message { msg, sig } = message;
+
+        Self {
+            timestamp,
+            peer,
+            msg,
+            sig,
+        }
+    }
+}
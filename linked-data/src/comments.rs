@@ -1,6 +1,9 @@
+use crate::eip712::{Member, TypedData};
+use crate::hamt::{self, HamtChanges};
+use crate::ipfs::{IpfsBackend, IpfsError};
 use crate::IPLDLink;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use serde::{Deserialize, Serialize};
@@ -14,9 +17,368 @@ use cid::Cid;
 #[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq, Clone)]
 pub struct Commentary {
     /// Content cids mapped to lists of links to comments ordered from oldest to newest.
+    /// Comments in memory or published before the trees, moved into them on the next update.
     #[serde_as(as = "HashMap<DisplayFromStr, Vec<_>>")]
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     pub comments: HashMap<Cid, Vec<IPLDLink>>,
     //Could use different indexing method. chrono, keywords, etc...
+    /// Reply cids mapped to links to the comments they reply to.
+    #[serde_as(as = "HashMap<DisplayFromStr, _>")]
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub replies: HashMap<Cid, IPLDLink>,
+
+    /// Link to the HAMT of content cids mapped to lists of links to comments.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment_tree: Option<IPLDLink>,
+
+    /// Link to the HAMT of reply cids mapped to links to the comments they reply to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reply_tree: Option<IPLDLink>,
+}
+
+impl Commentary {
+    /// Add a comment, only the tree nodes on its path are rewritten.
+    pub async fn add_comment<I>(
+        &mut self,
+        ipfs: &I,
+        origin: Cid,
+        comment: Cid,
+        reply_to: Option<Cid>,
+    ) -> Result<HamtChanges, IpfsError>
+    where
+        I: IpfsBackend,
+    {
+        let mut changes = self.migrate(ipfs).await?;
+
+        let root = hamt::update(
+            ipfs,
+            self.comment_tree.map(|ipld| ipld.link),
+            origin,
+            |comments: Option<Vec<IPLDLink>>| {
+                let mut comments = comments.unwrap_or_default();
+                comments.push(comment.into());
+                Some(comments)
+            },
+            &mut changes,
+        )
+        .await?;
+        self.comment_tree = root.map(IPLDLink::from);
+
+        if let Some(parent) = reply_to {
+            let root = hamt::insert(
+                ipfs,
+                self.reply_tree.map(|ipld| ipld.link),
+                comment,
+                IPLDLink::from(parent),
+                &mut changes,
+            )
+            .await?;
+            self.reply_tree = root.map(IPLDLink::from);
+        }
+
+        Ok(changes)
+    }
+
+    /// Remove a comment, only the tree nodes on its path are rewritten.
+    pub async fn remove_comment<I>(
+        &mut self,
+        ipfs: &I,
+        origin: Cid,
+        comment: Cid,
+    ) -> Result<HamtChanges, IpfsError>
+    where
+        I: IpfsBackend,
+    {
+        let comments = self.comments_on(ipfs, &origin).await?;
+
+        if !comments.iter().any(|ipld| ipld.link == comment) {
+            return Err(IpfsError::new("Comment Not Found"));
+        }
+
+        let mut changes = self.migrate(ipfs).await?;
+
+        let root = hamt::update(
+            ipfs,
+            self.comment_tree.map(|ipld| ipld.link),
+            origin,
+            |comments: Option<Vec<IPLDLink>>| {
+                let mut comments = comments?;
+
+                if let Some(idx) = comments.iter().position(|ipld| ipld.link == comment) {
+                    comments.remove(idx);
+                }
+
+                if comments.is_empty() {
+                    None
+                } else {
+                    Some(comments)
+                }
+            },
+            &mut changes,
+        )
+        .await?;
+
+        self.comment_tree = root.map(IPLDLink::from);
+
+        self.remove_replies(ipfs, &[comment], &mut changes).await?;
+
+        Ok(changes)
+    }
+
+    /// Remove all comments on this content and return them.
+    pub async fn remove_content<I>(
+        &mut self,
+        ipfs: &I,
+        origin: Cid,
+    ) -> Result<(Vec<IPLDLink>, HamtChanges), IpfsError>
+    where
+        I: IpfsBackend,
+    {
+        let mut changes = self.migrate(ipfs).await?;
+
+        let (root, comments) = hamt::remove::<_, Vec<IPLDLink>>(
+            ipfs,
+            self.comment_tree.map(|ipld| ipld.link),
+            origin,
+            &mut changes,
+        )
+        .await?;
+        self.comment_tree = root.map(IPLDLink::from);
+
+        let comments = comments.unwrap_or_default();
+
+        let cids: Vec<Cid> = comments.iter().map(|ipld| ipld.link).collect();
+        self.remove_replies(ipfs, &cids, &mut changes).await?;
+
+        Ok((comments, changes))
+    }
+
+    /// Links to the comments on this content, only the tree nodes on its path are fetched.
+    pub async fn comments_on<I>(&self, ipfs: &I, origin: &Cid) -> Result<Vec<IPLDLink>, IpfsError>
+    where
+        I: IpfsBackend,
+    {
+        let mut comments = self.comments.get(origin).cloned().unwrap_or_default();
+
+        if let Some(root) = self.comment_tree {
+            if let Some(mut tree) = hamt::get(ipfs, root.link, origin).await? {
+                comments.append(&mut tree);
+            }
+        }
+
+        Ok(comments)
+    }
+
+    /// Comments on this content and the comments they reply to.
+    /// Only the tree nodes on their paths are fetched.
+    pub async fn load_content<I>(&self, ipfs: &I, origin: &Cid) -> Result<Self, IpfsError>
+    where
+        I: IpfsBackend,
+    {
+        let comments = self.comments_on(ipfs, origin).await?;
+
+        let mut replies = HashMap::with_capacity(comments.len());
+
+        for comment in comments.iter() {
+            let parent = match (self.replies.get(&comment.link), self.reply_tree) {
+                (Some(parent), _) => Some(*parent),
+                (None, Some(root)) => hamt::get(ipfs, root.link, &comment.link).await?,
+                (None, None) => None,
+            };
+
+            if let Some(parent) = parent {
+                replies.insert(comment.link, parent);
+            }
+        }
+
+        let mut commentary = Self {
+            replies,
+            ..Default::default()
+        };
+
+        if !comments.is_empty() {
+            commentary.comments.insert(*origin, comments);
+        }
+
+        Ok(commentary)
+    }
+
+    /// Fetch the trees. Return all the comments in memory and the tree nodes CIDs.
+    pub async fn load<I>(mut self, ipfs: &I) -> Result<(Self, Vec<Cid>), IpfsError>
+    where
+        I: IpfsBackend,
+    {
+        let mut nodes = Vec::new();
+
+        if let Some(root) = self.comment_tree.take() {
+            let (mut tree_nodes, entries) = hamt::walk(ipfs, root.link).await?;
+            nodes.append(&mut tree_nodes);
+
+            for entry in entries.into_iter() {
+                let list: Vec<IPLDLink> = entry.value;
+
+                self.comments.entry(entry.key).or_default().extend(list);
+            }
+        }
+
+        if let Some(root) = self.reply_tree.take() {
+            let (mut tree_nodes, entries) = hamt::walk(ipfs, root.link).await?;
+            nodes.append(&mut tree_nodes);
+
+            self.replies
+                .extend(entries.into_iter().map(|entry| (entry.key, entry.value)));
+        }
+
+        Ok((self, nodes))
+    }
+
+    /// Move the comments published before the trees into them.
+    async fn migrate<I>(&mut self, ipfs: &I) -> Result<HamtChanges, IpfsError>
+    where
+        I: IpfsBackend,
+    {
+        let mut changes = HamtChanges::default();
+
+        for (origin, mut list) in std::mem::take(&mut self.comments).into_iter() {
+            let root = hamt::update(
+                ipfs,
+                self.comment_tree.map(|ipld| ipld.link),
+                origin,
+                |comments: Option<Vec<IPLDLink>>| {
+                    let mut comments = comments.unwrap_or_default();
+                    comments.append(&mut list);
+                    Some(comments)
+                },
+                &mut changes,
+            )
+            .await?;
+            self.comment_tree = root.map(IPLDLink::from);
+        }
+
+        for (reply, parent) in std::mem::take(&mut self.replies).into_iter() {
+            let root = hamt::insert(
+                ipfs,
+                self.reply_tree.map(|ipld| ipld.link),
+                reply,
+                parent,
+                &mut changes,
+            )
+            .await?;
+            self.reply_tree = root.map(IPLDLink::from);
+        }
+
+        Ok(changes)
+    }
+
+    async fn remove_replies<I>(
+        &mut self,
+        ipfs: &I,
+        comments: &[Cid],
+        changes: &mut HamtChanges,
+    ) -> Result<(), IpfsError>
+    where
+        I: IpfsBackend,
+    {
+        for comment in comments {
+            let (root, _) = hamt::remove::<_, IPLDLink>(
+                ipfs,
+                self.reply_tree.map(|ipld| ipld.link),
+                *comment,
+                changes,
+            )
+            .await?;
+            self.reply_tree = root.map(IPLDLink::from);
+        }
+
+        Ok(())
+    }
+}
+
+/// Comments on your content, from any beacon.
+/// Direct Pin.
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq, Clone)]
+pub struct CommentIndex {
+    /// Content cids mapped to lists of links to comments ordered from oldest to newest.
+    #[serde_as(as = "HashMap<DisplayFromStr, Vec<_>>")]
+    pub comments: HashMap<Cid, Vec<IPLDLink>>,
+
+    /// Comment cids mapped to links to their author's beacon.
+    #[serde_as(as = "HashMap<DisplayFromStr, _>")]
+    pub authors: HashMap<Cid, IPLDLink>,
+
+    /// Reply cids mapped to links to the comments they reply to.
+    #[serde_as(as = "HashMap<DisplayFromStr, _>")]
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub replies: HashMap<Cid, IPLDLink>,
+}
+
+impl CommentIndex {
+    /// Add a comment. Return false if already indexed.
+    pub fn insert(&mut self, cid: Cid, comment: &Comment) -> bool {
+        if self.authors.contains_key(&cid) {
+            return false;
+        }
+
+        self.authors.insert(cid, comment.author);
+
+        self.comments
+            .entry(comment.origin.link)
+            .or_default()
+            .push(cid.into());
+
+        if let Some(parent) = comment.reply_to {
+            self.replies.insert(cid, parent);
+        }
+
+        true
+    }
+
+    /// Split the index into each author's comments.
+    pub fn into_commentaries(self) -> HashMap<Cid, Commentary> {
+        let mut commentaries: HashMap<Cid, Commentary> = HashMap::new();
+
+        for (origin, comments) in self.comments.into_iter() {
+            for comment in comments.into_iter() {
+                let author = match self.authors.get(&comment.link) {
+                    Some(author) => author.link,
+                    None => continue,
+                };
+
+                let commentary = commentaries.entry(author).or_default();
+
+                commentary.comments.entry(origin).or_default().push(comment);
+
+                if let Some(parent) = self.replies.get(&comment.link) {
+                    commentary.replies.insert(comment.link, *parent);
+                }
+            }
+        }
+
+        commentaries
+    }
+}
+
+/// Comments hidden from your content.
+/// Direct Pin.
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq, Clone)]
+pub struct CommentModeration {
+    /// Comments not shown.
+    #[serde_as(as = "HashSet<DisplayFromStr>")]
+    #[serde(default)]
+    pub hidden: HashSet<Cid>,
+
+    /// Beacons whose comments are not shown.
+    #[serde_as(as = "HashSet<DisplayFromStr>")]
+    #[serde(default)]
+    pub blocked: HashSet<Cid>,
+}
+
+impl CommentModeration {
+    pub fn is_hidden(&self, comment: &Cid, author: &Cid) -> bool {
+        self.hidden.contains(comment) || self.blocked.contains(author)
+    }
 }
 
 /// Comment metadata and text.
@@ -35,6 +397,10 @@ pub struct Comment {
 
     /// Text as content of the comment.
     pub comment: String,
+
+    /// Link to the comment being replied to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reply_to: Option<IPLDLink>,
 }
 
 impl Comment {
@@ -49,19 +415,58 @@ impl Comment {
             author: author.into(),
             origin: origin.into(),
             comment,
+            reply_to: None,
         }
     }
+
+    /// Reply to another comment on the same content.
+    pub fn reply(author: Cid, origin: Cid, parent: Cid, comment: String) -> Self {
+        let mut reply = Self::create(author, origin, comment);
+
+        reply.reply_to = Some(parent.into());
+
+        reply
+    }
+}
+
+impl TypedData for Comment {
+    const TYPE_NAME: &'static str = "Comment";
+
+    const MEMBERS: &'static [(&'static str, &'static str)] = &[
+        ("timestamp", "uint64"),
+        ("author", "string"),
+        ("origin", "string"),
+        ("comment", "string"),
+        ("replyTo", "string"),
+    ];
+
+    fn members(&self) -> Vec<Member<'_>> {
+        vec![
+            Member::Uint(self.timestamp),
+            Member::String(self.author.link.to_string().into()),
+            Member::String(self.origin.link.to_string().into()),
+            Member::String(self.comment.as_str().into()),
+            // Empty for top level comments.
+            Member::String(match self.reply_to.as_ref() {
+                Some(parent) => parent.link.to_string().into(),
+                None => "".into(),
+            }),
+        ]
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use cid::multihash::{Code, MultihashDigest};
     use std::str::FromStr;
 
     #[test]
     fn serde_test() {
         let mut old_comments = Commentary {
             comments: HashMap::with_capacity(2),
+            replies: HashMap::with_capacity(1),
+            ..Default::default()
         };
 
         let cid =
@@ -73,6 +478,7 @@ mod tests {
         old_comments
             .comments
             .insert(cid, vec![Cid::default().into()]);
+        old_comments.replies.insert(Cid::default(), cid.into());
 
         let json = serde_json::to_string_pretty(&old_comments).expect("Cannot Serialize");
         println!("{}", json);
@@ -82,4 +488,106 @@ mod tests {
 
         assert_eq!(old_comments, new_comments);
     }
+
+    #[test]
+    fn index_by_author() {
+        let alice =
+            Cid::from_str("bafyreibjo4xmgaevkgud7mbifn3dzp4v4lyaui4yvqp3f2bqwtxcjrdqg4").unwrap();
+        let bob = Cid::default();
+        let first = Cid::new_v1(0x71, Code::Sha2_256.digest(b"first"));
+        let second = Cid::new_v1(0x71, Code::Sha2_256.digest(b"second"));
+
+        let comment = Comment::create(alice, bob, "First".to_owned());
+        let reply = Comment::reply(bob, bob, first, "Second".to_owned());
+
+        let mut index = CommentIndex::default();
+
+        assert!(index.insert(first, &comment));
+        assert!(index.insert(second, &reply));
+        assert!(!index.insert(first, &comment));
+
+        let commentaries = index.into_commentaries();
+
+        assert_eq!(commentaries[&alice].comments[&bob], vec![first.into()]);
+        assert_eq!(commentaries[&bob].comments[&bob], vec![second.into()]);
+        assert_eq!(commentaries[&bob].replies[&second], first.into());
+    }
+
+    #[test]
+    fn commentary_tree() {
+        use crate::ipfs::MemoryIpfs;
+        use futures::executor::block_on;
+
+        let ipfs = MemoryIpfs::new();
+
+        let cid = |data: &[u8]| Cid::new_v1(0x71, Code::Sha2_256.digest(data));
+        let (media, other) = (cid(b"media"), cid(b"other"));
+        let (legacy, first, reply) = (cid(b"legacy"), cid(b"first"), cid(b"reply"));
+
+        block_on(async {
+            // Published before the trees.
+            let mut commentary = Commentary::default();
+            commentary.comments.insert(media, vec![legacy.into()]);
+
+            // Nothing is migrated when the comment is missing.
+            assert!(commentary
+                .remove_comment(&ipfs, media, first)
+                .await
+                .is_err());
+            assert_eq!(commentary.comments[&media], vec![legacy.into()]);
+            assert!(commentary.comment_tree.is_none());
+
+            let changes = commentary
+                .add_comment(&ipfs, media, first, None)
+                .await
+                .unwrap();
+
+            assert!(commentary.comments.is_empty());
+            assert_eq!(changes.added.len(), 1);
+
+            commentary
+                .add_comment(&ipfs, other, reply, Some(first))
+                .await
+                .unwrap();
+
+            assert_eq!(
+                commentary.comments_on(&ipfs, &media).await.unwrap(),
+                vec![legacy.into(), first.into()]
+            );
+
+            let loaded = commentary.load_content(&ipfs, &other).await.unwrap();
+
+            assert_eq!(loaded.comments.len(), 1);
+            assert_eq!(loaded.comments[&other], vec![reply.into()]);
+            assert_eq!(loaded.replies[&reply], first.into());
+
+            let (loaded, nodes) = commentary.clone().load(&ipfs).await.unwrap();
+
+            assert_eq!(nodes.len(), 2);
+            assert_eq!(loaded.comments[&other], vec![reply.into()]);
+            assert_eq!(loaded.replies[&reply], first.into());
+
+            commentary
+                .remove_comment(&ipfs, other, reply)
+                .await
+                .unwrap();
+
+            assert!(commentary.reply_tree.is_none());
+            assert!(commentary
+                .comments_on(&ipfs, &other)
+                .await
+                .unwrap()
+                .is_empty());
+
+            assert!(commentary
+                .remove_comment(&ipfs, other, reply)
+                .await
+                .is_err());
+
+            let (removed, _) = commentary.remove_content(&ipfs, media).await.unwrap();
+
+            assert_eq!(removed, vec![legacy.into(), first.into()]);
+            assert!(commentary.comment_tree.is_none());
+        });
+    }
 }
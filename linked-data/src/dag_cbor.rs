@@ -0,0 +1,386 @@
+//! DAG-CBOR codec.
+//!
+//! Nodes go through serde_json values, IPLD links {"/": CID} are encoded as CBOR tag 42.
+//! Map keys are sorted canonically and floats are always 64 bits.
+//! CBOR byte strings are decoded as arrays of numbers, like serde serializes Vec<u8>.
+
+use crate::ipfs::{as_link, IpfsError, Result};
+
+use std::convert::TryFrom;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Number, Value};
+
+use cid::Cid;
+
+const CID_TAG: u64 = 42;
+
+/// Limit recursion when decoding untrusted data.
+const MAX_DEPTH: usize = 128;
+
+const UNSIGNED: u8 = 0;
+const NEGATIVE: u8 = 1;
+const BYTES: u8 = 2;
+const TEXT: u8 = 3;
+const ARRAY: u8 = 4;
+const MAP: u8 = 5;
+const TAG: u8 = 6;
+
+const FALSE: u8 = 0xf4;
+const TRUE: u8 = 0xf5;
+const NULL: u8 = 0xf6;
+const FLOAT32: u8 = 0xfa;
+const FLOAT64: u8 = 0xfb;
+
+/// Serialize a node to DAG-CBOR bytes.
+pub fn to_vec<T>(node: &T) -> Result<Vec<u8>>
+where
+    T: ?Sized + Serialize,
+{
+    let value = serde_json::to_value(node)?;
+
+    Ok(encode(&value))
+}
+
+/// Deserialize a node from DAG-CBOR bytes.
+pub fn from_slice<T>(data: &[u8]) -> Result<T>
+where
+    T: DeserializeOwned,
+{
+    let value = decode(data)?;
+
+    let node = serde_json::from_value(value)?;
+
+    Ok(node)
+}
+
+pub fn encode(value: &Value) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(128);
+
+    write_value(&mut buf, value);
+
+    buf
+}
+
+pub fn decode(data: &[u8]) -> Result<Value> {
+    let mut decoder = Decoder { data, pos: 0 };
+
+    let value = decoder.value(0)?;
+
+    if decoder.pos != data.len() {
+        return Err(IpfsError::new("DAG-CBOR: trailing bytes"));
+    }
+
+    Ok(value)
+}
+
+fn write_head(buf: &mut Vec<u8>, major: u8, arg: u64) {
+    let major = major << 5;
+
+    if arg < 24 {
+        buf.push(major | arg as u8);
+    } else if arg <= u8::MAX as u64 {
+        buf.push(major | 24);
+        buf.push(arg as u8);
+    } else if arg <= u16::MAX as u64 {
+        buf.push(major | 25);
+        buf.extend_from_slice(&(arg as u16).to_be_bytes());
+    } else if arg <= u32::MAX as u64 {
+        buf.push(major | 26);
+        buf.extend_from_slice(&(arg as u32).to_be_bytes());
+    } else {
+        buf.push(major | 27);
+        buf.extend_from_slice(&arg.to_be_bytes());
+    }
+}
+
+fn write_value(buf: &mut Vec<u8>, value: &Value) {
+    match value {
+        Value::Null => buf.push(NULL),
+        Value::Bool(false) => buf.push(FALSE),
+        Value::Bool(true) => buf.push(TRUE),
+        Value::Number(number) => write_number(buf, number),
+        Value::String(text) => write_text(buf, text),
+        Value::Array(array) => {
+            write_head(buf, ARRAY, array.len() as u64);
+
+            for value in array {
+                write_value(buf, value);
+            }
+        }
+        Value::Object(map) => match as_link(value) {
+            Some(cid) => write_link(buf, &cid),
+            None => write_map(buf, map),
+        },
+    }
+}
+
+fn write_number(buf: &mut Vec<u8>, number: &Number) {
+    if let Some(int) = number.as_u64() {
+        write_head(buf, UNSIGNED, int);
+    } else if let Some(int) = number.as_i64() {
+        write_head(buf, NEGATIVE, (-1 - int) as u64);
+    } else if let Some(float) = number.as_f64() {
+        buf.push(FLOAT64);
+        buf.extend_from_slice(&float.to_be_bytes());
+    }
+}
+
+fn write_text(buf: &mut Vec<u8>, text: &str) {
+    write_head(buf, TEXT, text.len() as u64);
+    buf.extend_from_slice(text.as_bytes());
+}
+
+fn write_link(buf: &mut Vec<u8>, cid: &Cid) {
+    let bytes = cid.to_bytes();
+
+    write_head(buf, TAG, CID_TAG);
+    write_head(buf, BYTES, bytes.len() as u64 + 1);
+    buf.push(0); // Identity multibase prefix
+    buf.extend_from_slice(&bytes);
+}
+
+/// Keys are sorted by length first then bytewise.
+fn write_map(buf: &mut Vec<u8>, map: &Map<String, Value>) {
+    let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+
+    entries.sort_unstable_by(|(a, _), (b, _)| a.len().cmp(&b.len()).then_with(|| a.cmp(b)));
+
+    write_head(buf, MAP, entries.len() as u64);
+
+    for (key, value) in entries {
+        write_text(buf, key);
+        write_value(buf, value);
+    }
+}
+
+struct Decoder<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let remaining = self.data.len() - self.pos;
+
+        if len > remaining {
+            return Err(IpfsError::new("DAG-CBOR: unexpected end of data"));
+        }
+
+        let bytes = &self.data[self.pos..self.pos + len];
+
+        self.pos += len;
+
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn uint(&mut self, len: usize) -> Result<u64> {
+        let bytes = self.take(len)?;
+
+        Ok(bytes.iter().fold(0, |int, byte| int << 8 | *byte as u64))
+    }
+
+    fn argument(&mut self, info: u8) -> Result<u64> {
+        match info {
+            0..=23 => Ok(info as u64),
+            24 => self.uint(1),
+            25 => self.uint(2),
+            26 => self.uint(4),
+            27 => self.uint(8),
+            _ => Err(IpfsError::new(
+                "DAG-CBOR: indefinite length items are not allowed",
+            )),
+        }
+    }
+
+    /// Return a length, sanity checked against the remaining data.
+    fn length(&mut self, info: u8) -> Result<usize> {
+        let len = self.argument(info)?;
+
+        match usize::try_from(len) {
+            Ok(len) if len <= self.data.len() - self.pos => Ok(len),
+            _ => Err(IpfsError::new("DAG-CBOR: length exceeds data")),
+        }
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Value> {
+        if depth > MAX_DEPTH {
+            return Err(IpfsError::new("DAG-CBOR: nesting too deep"));
+        }
+
+        let initial = self.byte()?;
+
+        let major = initial >> 5;
+        let info = initial & 0x1f;
+
+        match major {
+            UNSIGNED => Ok(Value::from(self.argument(info)?)),
+            NEGATIVE => {
+                let int = self.argument(info)?;
+
+                if int > i64::MAX as u64 {
+                    return Err(IpfsError::new("DAG-CBOR: integer out of range"));
+                }
+
+                Ok(Value::from(-1 - int as i64))
+            }
+            BYTES => {
+                let len = self.length(info)?;
+                let bytes = self.take(len)?;
+
+                Ok(bytes.iter().map(|byte| Value::from(*byte)).collect())
+            }
+            TEXT => {
+                let len = self.length(info)?;
+                let bytes = self.take(len)?;
+
+                let text = std::str::from_utf8(bytes).map_err(IpfsError::new)?;
+
+                Ok(Value::from(text))
+            }
+            ARRAY => {
+                let len = self.length(info)?;
+
+                let mut array = Vec::with_capacity(len);
+
+                for _ in 0..len {
+                    array.push(self.value(depth + 1)?);
+                }
+
+                Ok(Value::Array(array))
+            }
+            MAP => {
+                let len = self.length(info)?;
+
+                let mut map = Map::new();
+
+                for _ in 0..len {
+                    let key = match self.value(depth + 1)? {
+                        Value::String(key) => key,
+                        _ => return Err(IpfsError::new("DAG-CBOR: map keys must be strings")),
+                    };
+
+                    let value = self.value(depth + 1)?;
+
+                    map.insert(key, value);
+                }
+
+                Ok(Value::Object(map))
+            }
+            TAG => {
+                let tag = self.argument(info)?;
+
+                if tag != CID_TAG {
+                    return Err(IpfsError::new(format!("DAG-CBOR: unknown tag {}", tag)));
+                }
+
+                self.link()
+            }
+            _ => self.simple(initial),
+        }
+    }
+
+    /// Decode the byte string following tag 42.
+    fn link(&mut self) -> Result<Value> {
+        let initial = self.byte()?;
+
+        if initial >> 5 != BYTES {
+            return Err(IpfsError::new("DAG-CBOR: CID must be a byte string"));
+        }
+
+        let len = self.length(initial & 0x1f)?;
+        let bytes = self.take(len)?;
+
+        let cid = match bytes.split_first() {
+            Some((0, bytes)) => Cid::try_from(bytes)?,
+            _ => return Err(IpfsError::new("DAG-CBOR: CID without identity prefix")),
+        };
+
+        Ok(serde_json::json!({ "/": cid.to_string() }))
+    }
+
+    fn simple(&mut self, initial: u8) -> Result<Value> {
+        let float = match initial {
+            FALSE => return Ok(Value::Bool(false)),
+            TRUE => return Ok(Value::Bool(true)),
+            NULL => return Ok(Value::Null),
+            FLOAT32 => f32::from_bits(self.uint(4)? as u32) as f64,
+            FLOAT64 => f64::from_bits(self.uint(8)?),
+            _ => {
+                return Err(IpfsError::new(format!(
+                    "DAG-CBOR: unsupported simple value {:#x}",
+                    initial
+                )))
+            }
+        };
+
+        match Number::from_f64(float) {
+            Some(number) => Ok(Value::Number(number)),
+            None => Err(IpfsError::new("DAG-CBOR: floats must be finite")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::video::SecondNode;
+
+    use cid::multihash::{Code, MultihashDigest};
+
+    use serde_json::json;
+
+    #[test]
+    fn link_round_trip() {
+        let video = Cid::new_v1(0x55, Code::Sha2_256.digest(b"video"));
+        let chat = Cid::new_v1(0x71, Code::Sha2_256.digest(b"chat"));
+
+        let node = SecondNode {
+            link_to_video: video.into(),
+            links_to_chat: vec![chat.into()],
+        };
+
+        let data = to_vec(&node).unwrap();
+
+        // Tag 42 then byte string with identity prefix
+        assert!(data.windows(3).any(|window| window == [0xd8, 0x2a, 0x58]));
+
+        let node: SecondNode = from_slice(&data).unwrap();
+
+        assert_eq!(node.link_to_video.link, video);
+        assert_eq!(node.links_to_chat[0].link, chat);
+    }
+
+    #[test]
+    fn canonical_encoding() {
+        let value = json!({ "bb": -2, "c": 2.0, "a": [true, null, "x"] });
+
+        let data = encode(&value);
+
+        assert_eq!(
+            data,
+            vec![
+                0xa3, // map of 3
+                0x61, b'a', 0x83, 0xf5, 0xf6, 0x61, b'x', // "a": [true, null, "x"]
+                0x61, b'c', 0xfb, 0x40, 0, 0, 0, 0, 0, 0, 0, // "c": 2.0
+                0x62, b'b', b'b', 0x21, // "bb": -2
+            ]
+        );
+
+        assert_eq!(decode(&data).unwrap(), value);
+    }
+
+    #[test]
+    fn reject_malformed() {
+        assert!(decode(&[0x9f, 0xff]).is_err()); // indefinite array
+        assert!(decode(&[0x82, 0x01]).is_err()); // truncated
+        assert!(decode(&[0x01, 0x01]).is_err()); // trailing
+        assert!(decode(&[0xc1, 0x01]).is_err()); // unknown tag
+    }
+}
@@ -0,0 +1,156 @@
+//! Minimal DAG-PB parsing, enough to walk chunked files.
+
+use crate::ipfs::{IpfsError, Result};
+
+use std::convert::TryFrom;
+
+use cid::Cid;
+
+/// PBNode field 2, repeated PBLink.
+const NODE_LINKS: u64 = 2;
+
+/// PBLink field 1, CID bytes.
+const LINK_HASH: u64 = 1;
+
+const VARINT: u64 = 0;
+const LENGTH_DELIMITED: u64 = 2;
+
+/// Return the CIDs of all the links of a DAG-PB node.
+pub fn links(data: &[u8]) -> Result<Vec<Cid>> {
+    let mut links = Vec::new();
+
+    for field in Fields::new(data) {
+        let (number, bytes) = field?;
+
+        if number != NODE_LINKS {
+            continue;
+        }
+
+        let hash = Fields::new(bytes).find_map(|field| match field {
+            Ok((LINK_HASH, hash)) => Some(Ok(hash)),
+            Ok(_) => None,
+            Err(e) => Some(Err(e)),
+        });
+
+        match hash {
+            Some(hash) => links.push(Cid::try_from(hash?)?),
+            None => return Err(IpfsError::new("DAG-PB: link without hash")),
+        }
+    }
+
+    Ok(links)
+}
+
+/// Iterate protobuf fields, returning field numbers and length delimited bytes.
+/// Varint fields are skipped over and returned empty.
+struct Fields<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Fields<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn varint(&mut self) -> Result<u64> {
+        let mut int = 0;
+
+        for shift in (0..64).step_by(7) {
+            let byte = match self.data.get(self.pos) {
+                Some(byte) => *byte,
+                None => return Err(IpfsError::new("DAG-PB: unexpected end of data")),
+            };
+
+            self.pos += 1;
+
+            int |= ((byte & 0x7f) as u64) << shift;
+
+            if byte & 0x80 == 0 {
+                return Ok(int);
+            }
+        }
+
+        Err(IpfsError::new("DAG-PB: varint too long"))
+    }
+
+    fn field(&mut self) -> Result<(u64, &'a [u8])> {
+        let key = self.varint()?;
+
+        match key & 0x07 {
+            VARINT => {
+                self.varint()?;
+
+                Ok((key >> 3, &[][..]))
+            }
+            LENGTH_DELIMITED => {
+                let len = self.varint()?;
+
+                let end = match usize::try_from(len) {
+                    Ok(len) if len <= self.data.len() - self.pos => self.pos + len,
+                    _ => return Err(IpfsError::new("DAG-PB: length exceeds data")),
+                };
+
+                let bytes = &self.data[self.pos..end];
+
+                self.pos = end;
+
+                Ok((key >> 3, bytes))
+            }
+            wire => Err(IpfsError::new(format!(
+                "DAG-PB: unexpected wire type {}",
+                wire
+            ))),
+        }
+    }
+}
+
+impl<'a> Iterator for Fields<'a> {
+    type Item = Result<(u64, &'a [u8])>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos >= self.data.len() {
+            return None;
+        }
+
+        let field = self.field();
+
+        if field.is_err() {
+            // Stop at the first error
+            self.pos = self.data.len();
+        }
+
+        Some(field)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cid::multihash::{Code, MultihashDigest};
+
+    #[test]
+    fn file_links() {
+        let chunk = Cid::new_v1(0x55, Code::Sha2_256.digest(b"chunk"));
+        let hash = chunk.to_bytes();
+
+        // PBLink { Hash, Name: "", Tsize: 5 }
+        let mut link = vec![0x0a, hash.len() as u8];
+        link.extend_from_slice(&hash);
+        link.extend_from_slice(&[0x12, 0x00, 0x18, 0x05]);
+
+        // PBNode { Links: [link, link], Data: [0x08, 0x02] }
+        let mut node = Vec::new();
+        for _ in 0..2 {
+            node.push(0x12);
+            node.push(link.len() as u8);
+            node.extend_from_slice(&link);
+        }
+        node.extend_from_slice(&[0x0a, 0x02, 0x08, 0x02]);
+
+        assert_eq!(links(&node).unwrap(), vec![chunk, chunk]);
+
+        assert!(links(&node[..10]).is_err());
+    }
+}
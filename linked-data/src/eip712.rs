@@ -0,0 +1,235 @@
+//! EIP-712 typed structured data hashing.
+//!
+//! https://eips.ethereum.org/EIPS/eip-712
+
+use crate::{keccak256, Address};
+
+use std::borrow::Cow;
+
+use serde_json::{json, Map, Value};
+
+const DOMAIN_NAME: &str = "Defluencer";
+const DOMAIN_VERSION: &str = "1";
+
+const DOMAIN_MEMBERS: &[(&str, &str)] = &[("name", "string"), ("version", "string")];
+
+/// Value of a struct member.
+pub enum Member<'a> {
+    String(Cow<'a, str>),
+    Uint(u64),
+    Address(&'a Address),
+}
+
+impl Member<'_> {
+    /// Encoded as one 32 bytes word.
+    fn encode(&self) -> [u8; 32] {
+        let mut word = [0u8; 32];
+
+        match self {
+            Member::String(string) => word = keccak256(string.as_bytes()),
+            Member::Uint(uint) => word[24..].copy_from_slice(&uint.to_be_bytes()),
+            Member::Address(address) => word[12..].copy_from_slice(&address[..]),
+        }
+
+        word
+    }
+
+    fn to_json(&self) -> Value {
+        match self {
+            Member::String(string) => Value::String(string.to_string()),
+            Member::Uint(uint) => Value::from(*uint),
+            Member::Address(address) => Value::String(hex_address(address)),
+        }
+    }
+}
+
+/// Struct that can be signed as EIP-712 typed data.
+/// Only atomic member types are supported.
+pub trait TypedData {
+    /// Struct name.
+    const TYPE_NAME: &'static str;
+
+    /// Member names and solidity types, in order.
+    const MEMBERS: &'static [(&'static str, &'static str)];
+
+    /// Member values, same order as MEMBERS.
+    fn members(&self) -> Vec<Member<'_>>;
+
+    /// The hash to sign.
+    fn signing_hash(&self) -> [u8; 32] {
+        let domain = [
+            Member::String(DOMAIN_NAME.into()),
+            Member::String(DOMAIN_VERSION.into()),
+        ];
+
+        let mut data = vec![0x19, 0x01];
+        data.extend_from_slice(&hash_struct("EIP712Domain", DOMAIN_MEMBERS, &domain));
+        data.extend_from_slice(&hash_struct(
+            Self::TYPE_NAME,
+            Self::MEMBERS,
+            &self.members(),
+        ));
+
+        keccak256(&data)
+    }
+
+    /// JSON request for eth_signTypedData_v4.
+    fn typed_data_json(&self) -> Value {
+        let message: Map<String, Value> = Self::MEMBERS
+            .iter()
+            .zip(self.members().iter())
+            .map(|((name, _), member)| (name.to_string(), member.to_json()))
+            .collect();
+
+        let mut types = Map::with_capacity(2);
+        types.insert("EIP712Domain".to_owned(), types_json(DOMAIN_MEMBERS));
+        types.insert(Self::TYPE_NAME.to_owned(), types_json(Self::MEMBERS));
+
+        json!({
+            "types": types,
+            "primaryType": Self::TYPE_NAME,
+            "domain": {
+                "name": DOMAIN_NAME,
+                "version": DOMAIN_VERSION,
+            },
+            "message": message,
+        })
+    }
+}
+
+fn hash_struct(name: &str, types: &[(&str, &str)], members: &[Member]) -> [u8; 32] {
+    let members_type: Vec<String> = types
+        .iter()
+        .map(|(name, solidity_type)| format!("{} {}", solidity_type, name))
+        .collect();
+
+    let encoded_type = format!("{}({})", name, members_type.join(","));
+
+    let mut data = Vec::with_capacity(32 * (members.len() + 1));
+    data.extend_from_slice(&keccak256(encoded_type.as_bytes()));
+
+    for member in members {
+        data.extend_from_slice(&member.encode());
+    }
+
+    keccak256(&data)
+}
+
+fn types_json(types: &[(&str, &str)]) -> Value {
+    types
+        .iter()
+        .map(|(name, solidity_type)| json!({ "name": name, "type": solidity_type }))
+        .collect()
+}
+
+fn hex_address(address: &Address) -> String {
+    let mut hex = String::with_capacity(42);
+    hex.push_str("0x");
+
+    for byte in address {
+        hex.push_str(&format!("{:02x}", byte));
+    }
+
+    hex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::moderation::Ban;
+
+    #[test]
+    fn typed_data_request() {
+        let ban = Ban {
+            address: [0xab; 20],
+            peer_id: "12D3KooWBob".to_owned(),
+        };
+
+        let request = ban.typed_data_json();
+
+        assert_eq!(request["primaryType"], "Ban");
+        assert_eq!(
+            request["types"]["Ban"],
+            json!([
+                { "name": "address", "type": "address" },
+                { "name": "peerId", "type": "string" },
+            ])
+        );
+        assert_eq!(
+            request["message"]["address"],
+            "0xabababababababababababababababababababab"
+        );
+        assert_eq!(request["message"]["peerId"], "12D3KooWBob");
+
+        let other = Ban {
+            address: [0xab; 20],
+            peer_id: "12D3KooWMallory".to_owned(),
+        };
+
+        assert_ne!(ban.signing_hash(), other.signing_hash());
+    }
+
+    fn from_hex<const N: usize>(hex: &str) -> [u8; N] {
+        let mut bytes = [0u8; N];
+
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).unwrap();
+        }
+
+        bytes
+    }
+
+    /// Example from the EIP-712 specification, as hashed by wallets.
+    #[test]
+    fn specification_example() {
+        let verifying_contract = [0xcc; 20];
+
+        let domain = hash_struct(
+            "EIP712Domain",
+            &[
+                ("name", "string"),
+                ("version", "string"),
+                ("chainId", "uint256"),
+                ("verifyingContract", "address"),
+            ],
+            &[
+                Member::String("Ether Mail".into()),
+                Member::String("1".into()),
+                Member::Uint(1),
+                Member::Address(&verifying_contract),
+            ],
+        );
+
+        assert_eq!(
+            domain,
+            from_hex("f2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f")
+        );
+
+        let wallet = from_hex("cd2a3d9f938e13cd947ec05abc7fe734df8dd826");
+
+        let person = hash_struct(
+            "Person",
+            &[("name", "string"), ("wallet", "address")],
+            &[Member::String("Cow".into()), Member::Address(&wallet)],
+        );
+
+        assert_eq!(
+            person,
+            from_hex("fc71e5fa27ff56c350aa531bc129ebdf613b772b6604664f5d8dbe21b85eb0c8")
+        );
+
+        // Mail has nested structs, its hash is taken from the specification.
+        let mail: [u8; 32] =
+            from_hex("c52c0ee5d84264471806290a3f2c4cecfc5490626bf912d01f240d7a274b371e");
+
+        let mut data = vec![0x19, 0x01];
+        data.extend_from_slice(&domain);
+        data.extend_from_slice(&mail);
+
+        assert_eq!(
+            keccak256(&data),
+            from_hex("be609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2")
+        );
+    }
+}
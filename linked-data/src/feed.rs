@@ -1,21 +1,56 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use crate::blog::{FullPost, MicroPost};
-use crate::comments::Commentary;
+use crate::comments::{CommentIndex, CommentModeration, Commentary};
 use crate::identity::Identity;
+use crate::ipfs::{IpfsBackend, IpfsError};
 use crate::video::VideoMetadata;
-use crate::IPLDLink;
+use crate::{Address, IPLDLink};
 
 use serde::{Deserialize, Serialize};
 
 use cid::Cid;
 
-/// Content feed in chronological order.
+/// Number of links after which a new feed page is started.
+pub const FEED_PAGE_SIZE: usize = 50;
+
+/// Page of the content feed in chronological order.
 /// Direct pin.
-#[derive(Serialize, Deserialize, Default, Clone, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Default, Clone, PartialEq, Eq, Debug)]
 pub struct FeedAnchor {
     /// List of links to content ordered from oldest to newest.
     pub content: Vec<IPLDLink>,
+
+    /// Link to the previous page, older content.
+    /// Feeds created before pagination are a single page without one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous: Option<IPLDLink>,
+}
+
+impl FeedAnchor {
+    /// New content should go on a new page.
+    pub fn is_full(&self) -> bool {
+        self.content.len() >= FEED_PAGE_SIZE
+    }
+}
+
+/// Fetch every page of a feed, from the newest to the oldest.
+pub async fn feed_pages<I>(ipfs: &I, head: Cid) -> Result<Vec<(Cid, FeedAnchor)>, IpfsError>
+where
+    I: IpfsBackend,
+{
+    let mut pages = Vec::new();
+    let mut next = Some(head);
+
+    while let Some(cid) = next {
+        let page: FeedAnchor = ipfs.dag_get(cid, None).await?;
+
+        next = page.previous.map(|ipld| ipld.link);
+
+        pages.push((cid, page));
+    }
+
+    Ok(pages)
 }
 
 #[derive(Deserialize, PartialEq, Clone)]
@@ -36,202 +71,735 @@ impl Media {
     }
 }
 
+/// Pages of a beacon's content feed fetched so far.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+struct Feed {
+    /// Newest page.
+    head: Option<Cid>,
+
+    /// Page CIDs mapped to pages.
+    pages: HashMap<Cid, FeedAnchor>,
+
+    /// Next older page to fetch, if any.
+    older: Option<Cid>,
+}
+
+/// Where a comment was published and what it replies to.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+struct CommentEntry {
+    /// Beacon of the author.
+    beacon: Cid,
+
+    /// Content commented on.
+    media: Cid,
+
+    /// Comment replied to.
+    parent: Option<Cid>,
+}
+
 /// Identity, Media & Comments Cache
 #[derive(Debug, PartialEq, Clone)]
 pub struct ContentCache {
-    /// Comments CIDs
-    comments: Vec<Cid>,
+    /// Beacons mapped to identities.
+    identities: HashMap<Cid, Identity>,
+
+    /// Beacons mapped to their content feed.
+    feeds: HashMap<Cid, Feed>,
 
-    /// Comment index mapped to beacon index.
-    comment_to_beacon: HashMap<usize, usize>,
+    /// Media mapped to the beacon that published it.
+    media: HashMap<Cid, Cid>,
 
-    /// Beacons CIDs.
-    beacons: Vec<Cid>,
+    /// Beacons mapped to the media they published.
+    beacon_media: HashMap<Cid, HashSet<Cid>>,
 
-    /// Beacon index mapped to name & avatar index.
-    beacon_to_identity: HashMap<usize, usize>,
+    /// Comments mapped to author, media and parent.
+    comments: HashMap<Cid, CommentEntry>,
 
-    /// Display names.
-    names: Vec<String>,
+    /// Media mapped to the comments on it.
+    media_comments: HashMap<Cid, HashSet<Cid>>,
 
-    /// Links to avatars.
-    avatars: Vec<Cid>,
+    /// Comments mapped to their direct replies.
+    replies: HashMap<Cid, HashSet<Cid>>,
 
-    /// Comment index mapped to media index.
-    comment_to_media: HashMap<usize, usize>,
+    /// Beacons mapped to the comments of their comment list.
+    beacon_comments: HashMap<Cid, HashSet<Cid>>,
 
-    /// Media CIDs.
-    media_content: Vec<Cid>,
+    /// Comments from the comment index.
+    indexed_comments: HashSet<Cid>,
 
-    /// Media index mapped to beacon index.
-    media_to_beacon: HashMap<usize, usize>,
+    /// Beacons mapped to comments hidden from their content.
+    moderation: HashMap<Cid, CommentModeration>,
 }
 
 impl ContentCache {
     pub fn create() -> Self {
         Self {
-            comments: Vec::with_capacity(100),
-            comment_to_beacon: HashMap::with_capacity(100),
-            beacons: Vec::with_capacity(100),
-            beacon_to_identity: HashMap::with_capacity(100),
-            names: Vec::with_capacity(100),
-            avatars: Vec::with_capacity(100),
-            comment_to_media: HashMap::with_capacity(100),
-            media_content: Vec::with_capacity(100),
-            media_to_beacon: HashMap::with_capacity(100),
+            identities: HashMap::with_capacity(100),
+            feeds: HashMap::with_capacity(100),
+            media: HashMap::with_capacity(100),
+            beacon_media: HashMap::with_capacity(100),
+            comments: HashMap::with_capacity(100),
+            media_comments: HashMap::with_capacity(100),
+            replies: HashMap::with_capacity(100),
+            beacon_comments: HashMap::with_capacity(100),
+            indexed_comments: HashSet::with_capacity(100),
+            moderation: HashMap::with_capacity(10),
         }
     }
 
-    /// Idempotent way to add a user's identity.
+    /// Idempotent way to add or update a user's identity.
     pub fn insert_identity(&mut self, beacon: Cid, identity: Identity) {
-        let beacon_idx = match self.beacons.iter().position(|item| *item == beacon) {
-            Some(idx) => idx,
-            None => {
-                let idx = self.beacons.len();
-                self.beacons.push(beacon);
+        self.identities.insert(beacon, identity);
+    }
+
+    /// Forget everything published by this beacon.
+    pub fn remove_beacon(&mut self, beacon: &Cid) {
+        self.identities.remove(beacon);
+        self.moderation.remove(beacon);
+        self.feeds.remove(beacon);
 
-                idx
+        if let Some(media) = self.beacon_media.remove(beacon) {
+            for cid in media.iter() {
+                self.media.remove(cid);
             }
-        };
+        }
 
-        match self.beacon_to_identity.get(&beacon_idx) {
-            Some(name_idx) => {
-                self.names[*name_idx] = identity.display_name;
-                self.avatars[*name_idx] = identity.avatar.link;
+        if let Some(comments) = self.beacon_comments.remove(beacon) {
+            for cid in comments.iter() {
+                if !self.indexed_comments.contains(cid) {
+                    self.remove_comment(cid);
+                }
             }
-            None => {
-                let name_idx = self.names.len();
+        }
+    }
 
-                self.names.push(identity.display_name);
-                self.avatars.push(identity.avatar.link);
+    /// Idempotent way to update user media content with the newest feed page.
+    /// Content no longer in the feed is removed.
+    pub fn insert_media_content(&mut self, beacon: Cid, feed_cid: Cid, feed: FeedAnchor) {
+        let entry = self.feeds.entry(beacon).or_default();
 
-                self.beacon_to_identity.insert(beacon_idx, name_idx);
-            }
+        if entry.pages.contains_key(&feed_cid) && entry.head != Some(feed_cid) {
+            // Older page of the current feed, a stale head.
+            return;
         }
+
+        entry.head = Some(feed_cid);
+        entry.pages.insert(feed_cid, feed);
+
+        self.update_feed(beacon);
     }
 
-    /// Idempotent way to add user media content.
-    pub fn insert_media_content(&mut self, beacon: Cid, feed: FeedAnchor) {
-        let beacon_idx = match self.beacons.iter().position(|item| *item == beacon) {
-            Some(idx) => idx,
-            None => {
-                let idx = self.beacons.len();
-                self.beacons.push(beacon);
+    /// Idempotent way to add user media content from an older feed page.
+    pub fn insert_feed_page(&mut self, beacon: Cid, page_cid: Cid, page: FeedAnchor) {
+        let entry = match self.feeds.get_mut(&beacon) {
+            Some(entry) => entry,
+            None => return,
+        };
 
-                idx
-            }
+        entry.pages.insert(page_cid, page);
+
+        self.update_feed(beacon);
+    }
+
+    /// Walk the known pages from the head, forget unreachable ones and sync media.
+    fn update_feed(&mut self, beacon: Cid) {
+        let feed = match self.feeds.get_mut(&beacon) {
+            Some(feed) => feed,
+            None => return,
         };
 
-        for ipld in feed.content.into_iter() {
-            if !self.media_content.contains(&ipld.link) {
-                let idx = self.media_content.len();
+        let mut reachable = HashSet::with_capacity(feed.pages.len());
+        let mut media = HashSet::new();
 
-                self.media_content.push(ipld.link);
+        feed.older = None;
+
+        let mut next = feed.head;
+
+        while let Some(cid) = next {
+            let page = match feed.pages.get(&cid) {
+                Some(page) => page,
+                None => {
+                    feed.older = Some(cid);
+                    break;
+                }
+            };
 
-                self.media_to_beacon.insert(idx, beacon_idx);
+            if !reachable.insert(cid) {
+                break;
             }
+
+            media.extend(page.content.iter().map(|ipld| ipld.link));
+
+            next = page.previous.map(|ipld| ipld.link);
+        }
+
+        feed.pages.retain(|cid, _| reachable.contains(cid));
+
+        let old_media = self.beacon_media.remove(&beacon).unwrap_or_default();
+
+        for cid in old_media.difference(&media) {
+            self.media.remove(cid);
         }
+
+        for cid in media.iter() {
+            self.media.insert(*cid, beacon);
+        }
+
+        self.beacon_media.insert(beacon, media);
+    }
+
+    /// Beacons and the next older feed page of each, if any.
+    pub fn iter_older_pages(&self) -> impl Iterator<Item = (&Cid, &Cid)> {
+        self.feeds
+            .iter()
+            .filter_map(|(beacon, feed)| Some((beacon, feed.older.as_ref()?)))
     }
 
     pub fn iter_media_content(&self) -> impl Iterator<Item = &Cid> {
-        self.media_content.iter()
+        self.media.keys()
     }
 
     pub fn media_content_author(&self, media: &Cid) -> Option<&str> {
-        let media_idx = self.media_content.iter().position(|item| *item == *media)?;
+        let beacon = self.media.get(media)?;
+
+        let identity = self.identities.get(beacon)?;
 
-        let beacon_idx = self.media_to_beacon.get(&media_idx)?;
+        Some(&identity.display_name)
+    }
+
+    /// Idempotent way to update user comments.
+    /// Comments no longer in the list are removed.
+    pub fn insert_comments(&mut self, beacon: Cid, commentary: Commentary) {
+        let new_comments = self.insert_commentary(beacon, &commentary);
 
-        let name_idx = self.beacon_to_identity.get(&beacon_idx)?;
+        let old_comments = self.beacon_comments.remove(&beacon).unwrap_or_default();
 
-        let name = self.names.get(*name_idx)?;
+        for cid in old_comments.difference(&new_comments) {
+            if !self.indexed_comments.contains(cid) {
+                self.remove_comment(cid);
+            }
+        }
 
-        Some(name)
+        self.beacon_comments.insert(beacon, new_comments);
     }
 
-    /// Idempotent way to add user comments.
-    pub fn insert_comments(&mut self, beacon: Cid, commentary: Commentary) {
-        let beacon_idx = match self.beacons.iter().position(|item| *item == beacon) {
-            Some(idx) => idx,
-            None => {
-                let idx = self.beacons.len();
-                self.beacons.push(beacon);
+    /// Idempotent way to update user comments on one media.
+    /// Comments on it no longer in the list are removed.
+    pub fn insert_media_comments(&mut self, beacon: Cid, media: Cid, commentary: Commentary) {
+        let new_comments = self.insert_commentary(beacon, &commentary);
 
-                idx
+        let comments = &self.comments;
+        let listed = self.beacon_comments.entry(beacon).or_default();
+
+        let old_comments: Vec<Cid> = listed
+            .iter()
+            .filter(|cid| !new_comments.contains(cid))
+            .filter(|cid| matches!(comments.get(cid), Some(entry) if entry.media == media))
+            .copied()
+            .collect();
+
+        for cid in old_comments.iter() {
+            listed.remove(cid);
+        }
+
+        listed.extend(new_comments);
+
+        for cid in old_comments.iter() {
+            if !self.indexed_comments.contains(cid) {
+                self.remove_comment(cid);
             }
-        };
+        }
+    }
 
-        for (media_cid, comments) in commentary.comments.into_iter() {
-            let media_idx = match self
-                .media_content
-                .iter()
-                .position(|item| *item == media_cid)
-            {
-                Some(idx) => idx,
-                None => {
-                    let idx = self.media_content.len();
+    /// Idempotent way to update comments indexed by a beacon owner.
+    /// Comments no longer in the index are removed.
+    pub fn insert_comment_index(&mut self, index: CommentIndex) {
+        let mut new_comments = HashSet::new();
 
-                    self.media_content.push(media_cid);
+        for (beacon, commentary) in index.into_commentaries() {
+            new_comments.extend(self.insert_commentary(beacon, &commentary));
+        }
 
-                    idx
-                }
-            };
+        let old_comments = std::mem::replace(&mut self.indexed_comments, new_comments);
+
+        for cid in old_comments.iter() {
+            if self.indexed_comments.contains(cid) {
+                continue;
+            }
+
+            let listed = self
+                .comments
+                .get(cid)
+                .and_then(|entry| self.beacon_comments.get(&entry.beacon))
+                .filter(|comments| comments.contains(cid))
+                .is_some();
+
+            if !listed {
+                self.remove_comment(cid);
+            }
+        }
+    }
 
-            for comment in comments.into_iter() {
-                if !self.comments.contains(&comment.link) {
-                    let comment_idx = self.comments.len();
+    /// Index every comment of a list. Return their CIDs.
+    fn insert_commentary(&mut self, beacon: Cid, commentary: &Commentary) -> HashSet<Cid> {
+        let mut cids = HashSet::new();
 
-                    self.comments.push(comment.link);
+        for (media, comments) in commentary.comments.iter() {
+            for comment in comments.iter() {
+                let entry = CommentEntry {
+                    beacon,
+                    media: *media,
+                    parent: commentary.replies.get(&comment.link).map(|ipld| ipld.link),
+                };
 
-                    self.comment_to_beacon.insert(comment_idx, beacon_idx);
+                self.insert_comment(comment.link, entry);
 
-                    self.comment_to_media.insert(comment_idx, media_idx);
-                }
+                cids.insert(comment.link);
             }
         }
+
+        cids
+    }
+
+    fn insert_comment(&mut self, cid: Cid, entry: CommentEntry) {
+        match self.comments.get(&cid) {
+            Some(old) if *old == entry => return,
+            Some(_) => self.remove_comment(&cid),
+            None => {}
+        }
+
+        self.media_comments
+            .entry(entry.media)
+            .or_default()
+            .insert(cid);
+
+        if let Some(parent) = entry.parent {
+            self.replies.entry(parent).or_default().insert(cid);
+        }
+
+        self.comments.insert(cid, entry);
+    }
+
+    fn remove_comment(&mut self, cid: &Cid) {
+        let entry = match self.comments.remove(cid) {
+            Some(entry) => entry,
+            None => return,
+        };
+
+        remove_from(&mut self.media_comments, &entry.media, cid);
+
+        if let Some(parent) = entry.parent {
+            remove_from(&mut self.replies, &parent, cid);
+        }
+    }
+
+    /// Idempotent way to add the comment moderation of a beacon owner.
+    pub fn insert_comment_moderation(&mut self, beacon: Cid, moderation: CommentModeration) {
+        self.moderation.insert(beacon, moderation);
+    }
+
+    /// True if the creator of the content commented on hid this comment or blocked its author.
+    pub fn is_comment_hidden(&self, comment: &Cid) -> bool {
+        let entry = match self.comments.get(comment) {
+            Some(entry) => entry,
+            None => return false,
+        };
+
+        let moderation = match self
+            .media
+            .get(&entry.media)
+            .and_then(|creator| self.moderation.get(creator))
+        {
+            Some(moderation) => moderation,
+            None => return false,
+        };
+
+        moderation.is_hidden(comment, &entry.beacon)
+    }
+
+    /// Beacon of the user that published this content.
+    pub fn media_content_beacon(&self, media: &Cid) -> Option<&Cid> {
+        self.media.get(media)
     }
 
     pub fn iter_comments(&self, media: &Cid) -> Option<impl Iterator<Item = &Cid>> {
-        let media_idx = self.media_content.iter().position(|item| *item == *media)?;
+        let comments = self.media_comments.get(media)?;
 
-        let iterator = self
-            .comment_to_media
+        let iterator = comments
             .iter()
-            .filter_map(move |(comment_idx, idx)| {
-                if *idx == media_idx {
-                    self.comments.get(*comment_idx)
-                } else {
-                    None
-                }
-            });
+            .filter(move |comment| !self.is_comment_hidden(comment));
 
         Some(iterator)
     }
 
+    /// Comment this one replies to.
+    pub fn comment_parent(&self, comment: &Cid) -> Option<&Cid> {
+        self.comments.get(comment)?.parent.as_ref()
+    }
+
+    /// Direct replies to a comment.
+    pub fn iter_replies<'a>(&'a self, comment: &'a Cid) -> impl Iterator<Item = &'a Cid> {
+        self.replies.get(comment).into_iter().flatten()
+    }
+
     pub fn comment_author(&self, comment: &Cid) -> Option<&str> {
-        let comment_idx = self.comments.iter().position(|item| *item == *comment)?;
+        let entry = self.comments.get(comment)?;
 
-        let beacon_idx = self.comment_to_beacon.get(&comment_idx)?;
+        let identity = self.identities.get(&entry.beacon)?;
 
-        let name_idx = self.beacon_to_identity.get(&beacon_idx)?;
+        Some(&identity.display_name)
+    }
+
+    /// Beacon of the user that published this comment.
+    pub fn comment_beacon(&self, comment: &Cid) -> Option<&Cid> {
+        Some(&self.comments.get(comment)?.beacon)
+    }
 
-        let name = self.names.get(*name_idx)?;
+    /// Address of the user that published this comment.
+    pub fn comment_author_address(&self, comment: &Cid) -> Option<&Address> {
+        let entry = self.comments.get(comment)?;
 
-        Some(name)
+        self.identities.get(&entry.beacon)?.eth_address.as_ref()
     }
 
     pub fn comments_count(&self, media: &Cid) -> usize {
-        let media_idx = match self.media_content.iter().position(|item| *item == *media) {
-            Some(idx) => idx,
-            None => return 0,
-        };
+        match self.iter_comments(media) {
+            Some(iterator) => iterator.count(),
+            None => 0,
+        }
+    }
+}
 
-        self.comment_to_media.values().fold(0, |count, idx| {
-            if *idx == media_idx {
-                count + 1
-            } else {
-                count + 0
-            }
-        })
+/// Remove a value from a set index, dropping the set once empty.
+fn remove_from(index: &mut HashMap<Cid, HashSet<Cid>>, key: &Cid, value: &Cid) {
+    if let Some(set) = index.get_mut(key) {
+        set.remove(value);
+
+        if set.is_empty() {
+            index.remove(key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cid::multihash::{Code, MultihashDigest};
+
+    fn cid(data: &[u8]) -> Cid {
+        Cid::new_v1(0x71, Code::Sha2_256.digest(data))
+    }
+
+    #[test]
+    fn reply_tree() {
+        let (media, comment, reply, nested) = (cid(b"media"), cid(b"a"), cid(b"b"), cid(b"c"));
+
+        let mut alice = Commentary::default();
+        alice
+            .comments
+            .insert(media, vec![comment.into(), nested.into()]);
+        alice.replies.insert(nested, reply.into());
+
+        let mut bob = Commentary::default();
+        bob.comments.insert(media, vec![reply.into()]);
+        bob.replies.insert(reply, comment.into());
+
+        let mut cache = ContentCache::create();
+        cache.insert_comments(cid(b"alice"), alice);
+        cache.insert_comments(cid(b"bob"), bob);
+
+        assert_eq!(cache.comments_count(&media), 3);
+        assert_eq!(cache.comment_parent(&comment), None);
+        assert_eq!(cache.comment_parent(&nested), Some(&reply));
+        assert_eq!(
+            cache.iter_replies(&comment).collect::<Vec<_>>(),
+            vec![&reply]
+        );
+        assert_eq!(
+            cache.iter_replies(&reply).collect::<Vec<_>>(),
+            vec![&nested]
+        );
+        assert_eq!(cache.iter_replies(&nested).count(), 0);
+    }
+
+    #[test]
+    fn comments_per_media() {
+        let alice = cid(b"alice");
+        let (media, other) = (cid(b"media"), cid(b"other"));
+        let (first, second, third) = (cid(b"1"), cid(b"2"), cid(b"3"));
+
+        let mut cache = ContentCache::create();
+
+        let mut commentary = Commentary::default();
+        commentary.comments.insert(media, vec![first.into()]);
+        cache.insert_media_comments(alice, media, commentary);
+
+        let mut commentary = Commentary::default();
+        commentary.comments.insert(other, vec![second.into()]);
+        cache.insert_media_comments(alice, other, commentary);
+
+        let mut commentary = Commentary::default();
+        commentary.comments.insert(media, vec![third.into()]);
+        cache.insert_media_comments(alice, media, commentary);
+
+        assert_eq!(
+            cache.iter_comments(&media).unwrap().collect::<Vec<_>>(),
+            vec![&third]
+        );
+        assert_eq!(cache.comments_count(&other), 1);
+        assert_eq!(cache.comment_beacon(&first), None);
+    }
+
+    #[test]
+    fn hidden_comments() {
+        let (creator, alice, bob) = (cid(b"creator"), cid(b"alice"), cid(b"bob"));
+        let (media, first, second, third) = (cid(b"media"), cid(b"1"), cid(b"2"), cid(b"3"));
+
+        let mut cache = ContentCache::create();
+        cache.insert_media_content(
+            creator,
+            cid(b"feed"),
+            FeedAnchor {
+                content: vec![media.into()],
+                previous: None,
+            },
+        );
+
+        let mut commentary = Commentary::default();
+        commentary
+            .comments
+            .insert(media, vec![first.into(), second.into()]);
+        cache.insert_comments(alice, commentary);
+
+        let mut commentary = Commentary::default();
+        commentary.comments.insert(media, vec![third.into()]);
+        cache.insert_comments(bob, commentary);
+
+        assert_eq!(cache.comments_count(&media), 3);
+
+        let mut moderation = CommentModeration::default();
+        moderation.hidden.insert(first);
+        moderation.blocked.insert(bob);
+        cache.insert_comment_moderation(creator, moderation);
+
+        assert!(cache.is_comment_hidden(&first));
+        assert!(!cache.is_comment_hidden(&second));
+        assert!(cache.is_comment_hidden(&third));
+        assert_eq!(cache.comments_count(&media), 1);
+        assert_eq!(
+            cache.iter_comments(&media).unwrap().collect::<Vec<_>>(),
+            vec![&second]
+        );
+    }
+
+    #[test]
+    fn walk_feed_pages() {
+        use crate::ipfs::MemoryIpfs;
+        use futures::executor::block_on;
+
+        let ipfs = MemoryIpfs::new();
+
+        block_on(async {
+            let old = FeedAnchor {
+                content: vec![cid(b"1").into(), cid(b"2").into()],
+                previous: None,
+            };
+            let old_cid = ipfs.dag_put(&old).await.unwrap();
+
+            let head = FeedAnchor {
+                content: vec![cid(b"3").into()],
+                previous: Some(old_cid.into()),
+            };
+            let head_cid = ipfs.dag_put(&head).await.unwrap();
+
+            let pages = feed_pages(&ipfs, head_cid).await.unwrap();
+
+            assert_eq!(pages, vec![(head_cid, head), (old_cid, old)]);
+        });
+    }
+
+    #[test]
+    fn older_pages() {
+        let (alice, first, second, third) =
+            (cid(b"alice"), cid(b"first"), cid(b"second"), cid(b"third"));
+
+        let mut cache = ContentCache::create();
+        cache.insert_media_content(
+            alice,
+            third,
+            FeedAnchor {
+                content: vec![cid(b"3").into()],
+                previous: Some(second.into()),
+            },
+        );
+
+        assert_eq!(
+            cache.iter_older_pages().collect::<Vec<_>>(),
+            vec![(&alice, &second)]
+        );
+
+        cache.insert_feed_page(
+            alice,
+            second,
+            FeedAnchor {
+                content: vec![cid(b"2").into()],
+                previous: Some(first.into()),
+            },
+        );
+
+        assert_eq!(
+            cache.iter_older_pages().collect::<Vec<_>>(),
+            vec![(&alice, &first)]
+        );
+
+        cache.insert_feed_page(
+            alice,
+            first,
+            FeedAnchor {
+                content: vec![cid(b"1").into()],
+                previous: None,
+            },
+        );
+
+        assert_eq!(cache.iter_older_pages().count(), 0);
+        assert_eq!(cache.iter_media_content().count(), 3);
+
+        // A new head page must not restart the walk.
+        cache.insert_media_content(
+            alice,
+            cid(b"fourth"),
+            FeedAnchor {
+                content: vec![cid(b"4").into()],
+                previous: Some(third.into()),
+            },
+        );
+
+        assert_eq!(cache.iter_older_pages().count(), 0);
+        assert_eq!(cache.iter_media_content().count(), 4);
+
+        // Neither must the previous head arriving late.
+        cache.insert_media_content(
+            alice,
+            third,
+            FeedAnchor {
+                content: vec![cid(b"3").into()],
+                previous: Some(second.into()),
+            },
+        );
+
+        assert_eq!(cache.media_content_beacon(&cid(b"4")), Some(&alice));
+    }
+
+    #[test]
+    fn feed_update_removes_content() {
+        let (alice, first, second) = (cid(b"alice"), cid(b"first"), cid(b"second"));
+
+        let mut cache = ContentCache::create();
+        cache.insert_media_content(
+            alice,
+            second,
+            FeedAnchor {
+                content: vec![cid(b"3").into()],
+                previous: Some(first.into()),
+            },
+        );
+        cache.insert_feed_page(
+            alice,
+            first,
+            FeedAnchor {
+                content: vec![cid(b"1").into(), cid(b"2").into()],
+                previous: None,
+            },
+        );
+
+        assert_eq!(cache.iter_media_content().count(), 3);
+
+        // Content deleted from the head page.
+        cache.insert_media_content(
+            alice,
+            cid(b"edited head"),
+            FeedAnchor {
+                content: vec![],
+                previous: Some(first.into()),
+            },
+        );
+
+        assert_eq!(cache.media_content_beacon(&cid(b"3")), None);
+        assert_eq!(cache.iter_media_content().count(), 2);
+        assert_eq!(cache.iter_older_pages().count(), 0);
+
+        // Older page rewritten, it must be fetched again.
+        let rewritten = cid(b"rewritten");
+        cache.insert_media_content(
+            alice,
+            cid(b"new head"),
+            FeedAnchor {
+                content: vec![],
+                previous: Some(rewritten.into()),
+            },
+        );
+
+        assert_eq!(cache.iter_media_content().count(), 0);
+        assert_eq!(
+            cache.iter_older_pages().collect::<Vec<_>>(),
+            vec![(&alice, &rewritten)]
+        );
+
+        cache.remove_beacon(&alice);
+
+        assert_eq!(cache.iter_older_pages().count(), 0);
+    }
+
+    #[test]
+    fn comments_update_removes_comments() {
+        let (alice, bob) = (cid(b"alice"), cid(b"bob"));
+        let (media, first, reply) = (cid(b"media"), cid(b"1"), cid(b"2"));
+
+        let mut cache = ContentCache::create();
+
+        let mut commentary = Commentary::default();
+        commentary.comments.insert(media, vec![first.into()]);
+        cache.insert_comments(alice, commentary);
+
+        let mut commentary = Commentary::default();
+        commentary.comments.insert(media, vec![reply.into()]);
+        commentary.replies.insert(reply, first.into());
+        cache.insert_comments(bob, commentary);
+
+        let mut index = CommentIndex::default();
+        index.comments.insert(media, vec![reply.into()]);
+        index.authors.insert(reply, bob.into());
+        index.replies.insert(reply, first.into());
+        cache.insert_comment_index(index);
+
+        assert_eq!(cache.comments_count(&media), 2);
+
+        // Bob deleted his reply but the creator still indexes it.
+        cache.insert_comments(bob, Commentary::default());
+
+        assert_eq!(cache.comment_beacon(&reply), Some(&bob));
+
+        cache.insert_comment_index(CommentIndex::default());
+
+        assert_eq!(cache.comment_beacon(&reply), None);
+        assert_eq!(cache.iter_replies(&first).count(), 0);
+        assert_eq!(cache.comments_count(&media), 1);
+
+        cache.insert_identity(
+            alice,
+            Identity {
+                display_name: "Alice".to_owned(),
+                ..Default::default()
+            },
+        );
+        cache.insert_comment_moderation(alice, CommentModeration::default());
+
+        assert_eq!(cache.comment_author(&first), Some("Alice"));
+
+        cache.remove_beacon(&alice);
+
+        assert_eq!(cache.comment_author(&first), None);
+        assert_eq!(cache.comments_count(&media), 0);
+        assert!(cache.iter_comments(&media).is_none());
     }
 }
@@ -0,0 +1,154 @@
+use crate::PeerId;
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use regex::{Regex, RegexBuilder};
+
+/// Chat content filters.
+/// Direct pin.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Clone)]
+pub struct Blocklist {
+    /// Case insensitive whole words.
+    #[serde(default)]
+    pub words: Vec<String>,
+
+    /// Regular expressions.
+    #[serde(default)]
+    pub regexes: Vec<String>,
+
+    /// Maximum number of characters. 0 for unlimited.
+    #[serde(default)]
+    pub max_length: usize,
+
+    /// Remove links from messages.
+    #[serde(default)]
+    pub strip_links: bool,
+
+    /// Drop messages identical to the previous one of the same user.
+    #[serde(default)]
+    pub suppress_duplicates: bool,
+}
+
+/// Blocklist compiled and ready to apply.
+pub struct ChatFilter {
+    blocked: Vec<Regex>,
+
+    max_length: usize,
+
+    links: Option<Regex>,
+
+    suppress_duplicates: bool,
+    last_messages: HashMap<PeerId, String>,
+}
+
+impl ChatFilter {
+    pub fn new(blocklist: &Blocklist) -> Result<Self, regex::Error> {
+        let mut blocked = Vec::with_capacity(blocklist.words.len() + blocklist.regexes.len());
+
+        for word in blocklist.words.iter() {
+            let regex = RegexBuilder::new(&format!(r"\b{}\b", regex::escape(word)))
+                .case_insensitive(true)
+                .build()?;
+
+            blocked.push(regex);
+        }
+
+        for regex in blocklist.regexes.iter() {
+            blocked.push(Regex::new(regex)?);
+        }
+
+        let links = if blocklist.strip_links {
+            Some(Regex::new(r"(?i)\b(?:[a-z][a-z0-9+.-]*://|www\.)\S+")?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            blocked,
+
+            max_length: blocklist.max_length,
+
+            links,
+
+            suppress_duplicates: blocklist.suppress_duplicates,
+            last_messages: HashMap::new(),
+        })
+    }
+
+    /// Return the message to display or None if it should be dropped.
+    pub fn apply(&mut self, peer: &str, message: &str) -> Option<String> {
+        let message = match self.links.as_ref() {
+            Some(links) => links.replace_all(message, "").trim().to_owned(),
+            None => message.trim().to_owned(),
+        };
+
+        if message.is_empty() {
+            return None;
+        }
+
+        if self.max_length > 0 && message.chars().count() > self.max_length {
+            return None;
+        }
+
+        if self.blocked.iter().any(|regex| regex.is_match(&message)) {
+            return None;
+        }
+
+        if self.suppress_duplicates {
+            if self.last_messages.get(peer) == Some(&message) {
+                return None;
+            }
+
+            self.last_messages.insert(peer.to_owned(), message.clone());
+        }
+
+        Some(message)
+    }
+}
+
+impl Default for ChatFilter {
+    fn default() -> Self {
+        Self::new(&Blocklist::default()).expect("Empty Blocklist")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_messages() {
+        let blocklist = Blocklist {
+            words: vec!["spam".to_owned()],
+            regexes: vec![r"^!\w+".to_owned()],
+            max_length: 20,
+            strip_links: true,
+            suppress_duplicates: true,
+        };
+
+        let mut filter = ChatFilter::new(&blocklist).unwrap();
+
+        assert_eq!(filter.apply("A", "Hello"), Some("Hello".to_owned()));
+        assert_eq!(filter.apply("A", "Hello"), None);
+        assert_eq!(filter.apply("B", "Hello"), Some("Hello".to_owned()));
+
+        assert_eq!(filter.apply("A", "Buy SPAM now"), None);
+        assert_eq!(filter.apply("A", "spamalot"), Some("spamalot".to_owned()));
+        assert_eq!(filter.apply("A", "!command"), None);
+        assert_eq!(filter.apply("A", "This is way too long to pass"), None);
+
+        assert_eq!(
+            filter.apply("A", "see https://example.com/x"),
+            Some("see".to_owned())
+        );
+        assert_eq!(filter.apply("A", "www.example.com"), None);
+
+        assert!(ChatFilter::new(&Blocklist {
+            regexes: vec!["(".to_owned()],
+            ..Default::default()
+        })
+        .is_err());
+    }
+}
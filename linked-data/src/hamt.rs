@@ -0,0 +1,526 @@
+//! Hash array mapped trie of IPLD nodes, keyed by CIDs.
+//!
+//! Lookups only fetch and updates only rewrite the nodes on the path to the key.
+
+use crate::ipfs::{IpfsBackend, IpfsError};
+use crate::{keccak256, IPLDLink};
+
+use std::collections::BTreeMap;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+
+use cid::Cid;
+
+/// Number of entries a bucket holds before being pushed down into a new node.
+pub const BUCKET_SIZE: usize = 3;
+
+/// Number of levels the 256 bits key hash allows, 16 buckets per node.
+const MAX_DEPTH: usize = 64;
+
+/// Trie node.
+/// Direct pin.
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(bound(serialize = "V: Serialize", deserialize = "V: DeserializeOwned"))]
+pub struct HamtNode<V> {
+    /// Non-empty buckets by index.
+    #[serde_as(as = "BTreeMap<DisplayFromStr, _>")]
+    pub buckets: BTreeMap<u8, Bucket<V>>,
+}
+
+impl<V> Default for HamtNode<V> {
+    fn default() -> Self {
+        Self {
+            buckets: BTreeMap::new(),
+        }
+    }
+}
+
+impl<V> HamtNode<V> {
+    /// Entries of this node if it could be folded into its parent's bucket.
+    fn collapse(&mut self) -> Option<Vec<Entry<V>>> {
+        let mut count = 0;
+
+        for bucket in self.buckets.values() {
+            match bucket {
+                Bucket::Link(_) => return None,
+                Bucket::Entries(entries) => count += entries.len(),
+            }
+        }
+
+        if count > BUCKET_SIZE {
+            return None;
+        }
+
+        let mut entries = Vec::with_capacity(count);
+
+        for bucket in std::mem::take(&mut self.buckets).into_values() {
+            if let Bucket::Entries(mut bucket) = bucket {
+                entries.append(&mut bucket);
+            }
+        }
+
+        entries.sort_unstable_by_key(|entry| entry.key);
+
+        Some(entries)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum Bucket<V> {
+    /// Link to a child node.
+    Link(IPLDLink),
+
+    /// Entries ordered by key.
+    Entries(Vec<Entry<V>>),
+}
+
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Entry<V> {
+    #[serde_as(as = "DisplayFromStr")]
+    pub key: Cid,
+
+    pub value: V,
+}
+
+/// Nodes written and nodes replaced by updates.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct HamtChanges {
+    /// New nodes, to pin.
+    pub added: Vec<Cid>,
+
+    /// Replaced nodes, to unpin.
+    pub removed: Vec<Cid>,
+}
+
+impl HamtChanges {
+    fn add(&mut self, cid: Cid) {
+        match self.removed.iter().position(|item| *item == cid) {
+            Some(idx) => {
+                self.removed.swap_remove(idx);
+            }
+            None => self.added.push(cid),
+        }
+    }
+
+    fn remove(&mut self, cid: Cid) {
+        match self.added.iter().position(|item| *item == cid) {
+            Some(idx) => {
+                self.added.swap_remove(idx);
+            }
+            None => self.removed.push(cid),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+fn key_hash(key: &Cid) -> [u8; 32] {
+    keccak256(&key.to_bytes())
+}
+
+fn bucket_index(hash: &[u8; 32], depth: usize) -> Result<u8, IpfsError> {
+    if depth >= MAX_DEPTH {
+        return Err(IpfsError::new("HAMT Max Depth Reached"));
+    }
+
+    let byte = hash[depth / 2];
+
+    if depth.is_multiple_of(2) {
+        Ok(byte >> 4)
+    } else {
+        Ok(byte & 0x0f)
+    }
+}
+
+/// Return the value of this key.
+pub async fn get<I, V>(ipfs: &I, root: Cid, key: &Cid) -> Result<Option<V>, IpfsError>
+where
+    I: IpfsBackend,
+    V: DeserializeOwned + Send,
+{
+    let hash = key_hash(key);
+
+    let mut node: HamtNode<V> = ipfs.dag_get(root, None).await?;
+    let mut depth = 0;
+
+    loop {
+        let idx = bucket_index(&hash, depth)?;
+
+        match node.buckets.remove(&idx) {
+            Some(Bucket::Link(ipld)) => {
+                node = ipfs.dag_get(ipld.link, None).await?;
+                depth += 1;
+            }
+            Some(Bucket::Entries(entries)) => {
+                let value = entries
+                    .into_iter()
+                    .find(|entry| entry.key == *key)
+                    .map(|entry| entry.value);
+
+                return Ok(value);
+            }
+            None => return Ok(None),
+        }
+    }
+}
+
+/// Fetch every node. Return their CIDs and all the entries.
+pub async fn walk<I, V>(ipfs: &I, root: Cid) -> Result<(Vec<Cid>, Vec<Entry<V>>), IpfsError>
+where
+    I: IpfsBackend,
+    V: DeserializeOwned + Send,
+{
+    let mut nodes = Vec::new();
+    let mut entries = Vec::new();
+
+    let mut stack = vec![root];
+
+    while let Some(cid) = stack.pop() {
+        let node: HamtNode<V> = ipfs.dag_get(cid, None).await?;
+
+        nodes.push(cid);
+
+        for bucket in node.buckets.into_values() {
+            match bucket {
+                Bucket::Link(ipld) => stack.push(ipld.link),
+                Bucket::Entries(mut bucket) => entries.append(&mut bucket),
+            }
+        }
+    }
+
+    Ok((nodes, entries))
+}
+
+/// Set the value of this key.
+pub async fn insert<I, V>(
+    ipfs: &I,
+    root: Option<Cid>,
+    key: Cid,
+    value: V,
+    changes: &mut HamtChanges,
+) -> Result<Option<Cid>, IpfsError>
+where
+    I: IpfsBackend,
+    V: Serialize + DeserializeOwned + Send + Sync,
+{
+    update(ipfs, root, key, |_| Some(value), changes).await
+}
+
+/// Remove this key.
+pub async fn remove<I, V>(
+    ipfs: &I,
+    root: Option<Cid>,
+    key: Cid,
+    changes: &mut HamtChanges,
+) -> Result<(Option<Cid>, Option<V>), IpfsError>
+where
+    I: IpfsBackend,
+    V: Serialize + DeserializeOwned + Send + Sync,
+{
+    let mut removed = None;
+
+    let root = update(
+        ipfs,
+        root,
+        key,
+        |value| {
+            removed = value;
+            None
+        },
+        changes,
+    )
+    .await?;
+
+    Ok((root, removed))
+}
+
+/// Replace the value of this key, None being no value.
+/// Return the new root or None if the trie is empty.
+pub async fn update<I, V, F>(
+    ipfs: &I,
+    root: Option<Cid>,
+    key: Cid,
+    change: F,
+    changes: &mut HamtChanges,
+) -> Result<Option<Cid>, IpfsError>
+where
+    I: IpfsBackend,
+    V: Serialize + DeserializeOwned + Send + Sync,
+    F: FnOnce(Option<V>) -> Option<V>,
+{
+    let hash = key_hash(&key);
+
+    let mut node_cid = root;
+    let mut node: HamtNode<V> = match root {
+        Some(cid) => ipfs.dag_get(cid, None).await?,
+        None => HamtNode::default(),
+    };
+    let mut depth = 0;
+
+    // Parent nodes and the bucket linking to the child.
+    let mut path = Vec::new();
+
+    let idx = loop {
+        let idx = bucket_index(&hash, depth)?;
+
+        let child = match node.buckets.get(&idx) {
+            Some(Bucket::Link(ipld)) => ipld.link,
+            _ => break idx,
+        };
+
+        let child_node = ipfs.dag_get(child, None).await?;
+
+        path.push((node_cid, std::mem::replace(&mut node, child_node), idx));
+        node_cid = Some(child);
+        depth += 1;
+    };
+
+    let mut entries = match node.buckets.remove(&idx) {
+        Some(Bucket::Entries(entries)) => entries,
+        _ => Vec::new(),
+    };
+
+    let old_value = match entries.binary_search_by(|entry| entry.key.cmp(&key)) {
+        Ok(pos) => Some(entries.remove(pos).value),
+        Err(_) => None,
+    };
+
+    if let Some(value) = change(old_value) {
+        let pos = entries
+            .binary_search_by(|entry| entry.key.cmp(&key))
+            .unwrap_or_else(|pos| pos);
+
+        entries.insert(pos, Entry { key, value });
+    }
+
+    if entries.len() > BUCKET_SIZE {
+        let child = split(ipfs, entries, depth + 1, changes).await?;
+
+        node.buckets.insert(idx, Bucket::Link(child.into()));
+    } else if !entries.is_empty() {
+        node.buckets.insert(idx, Bucket::Entries(entries));
+    }
+
+    while let Some((parent_cid, mut parent, idx)) = path.pop() {
+        if let Some(cid) = node_cid {
+            changes.remove(cid);
+        }
+
+        if node.buckets.is_empty() {
+            parent.buckets.remove(&idx);
+        } else if let Some(entries) = node.collapse() {
+            parent.buckets.insert(idx, Bucket::Entries(entries));
+        } else {
+            let cid = ipfs.dag_put(&node).await?;
+            changes.add(cid);
+
+            parent.buckets.insert(idx, Bucket::Link(cid.into()));
+        }
+
+        node_cid = parent_cid;
+        node = parent;
+    }
+
+    if let Some(cid) = node_cid {
+        changes.remove(cid);
+    }
+
+    if node.buckets.is_empty() {
+        return Ok(None);
+    }
+
+    let cid = ipfs.dag_put(&node).await?;
+    changes.add(cid);
+
+    Ok(Some(cid))
+}
+
+/// Push the entries of an overflowing bucket down into a new node.
+/// Only one entry over the bucket size so at most one bucket of the new node
+/// can overflow, when all the keys share it.
+async fn split<I, V>(
+    ipfs: &I,
+    entries: Vec<Entry<V>>,
+    mut depth: usize,
+    changes: &mut HamtChanges,
+) -> Result<Cid, IpfsError>
+where
+    I: IpfsBackend,
+    V: Serialize + Sync,
+{
+    let hashes: Vec<[u8; 32]> = entries.iter().map(|entry| key_hash(&entry.key)).collect();
+
+    // Buckets of the nodes where all the keys are still together.
+    let mut shared = Vec::new();
+
+    loop {
+        let first = bucket_index(&hashes[0], depth)?;
+
+        if hashes
+            .iter()
+            .any(|hash| bucket_index(hash, depth).ok() != Some(first))
+        {
+            break;
+        }
+
+        shared.push(first);
+        depth += 1;
+    }
+
+    let mut node = HamtNode::default();
+
+    for (hash, entry) in hashes.iter().zip(entries.into_iter()) {
+        let idx = bucket_index(hash, depth)?;
+
+        if let Bucket::Entries(bucket) = node
+            .buckets
+            .entry(idx)
+            .or_insert_with(|| Bucket::Entries(Vec::new()))
+        {
+            bucket.push(entry);
+        }
+    }
+
+    let mut cid = ipfs.dag_put(&node).await?;
+    changes.add(cid);
+
+    for idx in shared.into_iter().rev() {
+        let mut node: HamtNode<V> = HamtNode::default();
+        node.buckets.insert(idx, Bucket::Link(cid.into()));
+
+        cid = ipfs.dag_put(&node).await?;
+        changes.add(cid);
+    }
+
+    Ok(cid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::ipfs::MemoryIpfs;
+
+    use cid::multihash::{Code, MultihashDigest};
+    use futures::executor::block_on;
+
+    fn cid(i: usize) -> Cid {
+        Cid::new_v1(0x71, Code::Sha2_256.digest(&i.to_be_bytes()))
+    }
+
+    #[test]
+    fn insert_get_remove() {
+        let ipfs = MemoryIpfs::new();
+
+        block_on(async {
+            let mut changes = HamtChanges::default();
+            let mut root = None;
+
+            for i in 0..100 {
+                root = insert(&ipfs, root, cid(i), i, &mut changes).await.unwrap();
+            }
+
+            let root_cid = root.unwrap();
+
+            assert_eq!(get(&ipfs, root_cid, &cid(42)).await.unwrap(), Some(42));
+            assert_eq!(
+                get::<_, usize>(&ipfs, root_cid, &cid(100)).await.unwrap(),
+                None
+            );
+
+            let (mut nodes, entries) = walk::<_, usize>(&ipfs, root_cid).await.unwrap();
+
+            assert_eq!(entries.len(), 100);
+            assert!(nodes.len() > 1);
+
+            // Only the nodes still in the trie are left to pin.
+            changes.added.sort();
+            nodes.sort();
+            assert_eq!(changes.added, nodes);
+            assert!(changes.removed.is_empty());
+
+            let mut changes = HamtChanges::default();
+
+            for i in 0..100 {
+                let (new_root, value) = remove::<_, usize>(&ipfs, root, cid(i), &mut changes)
+                    .await
+                    .unwrap();
+
+                assert_eq!(value, Some(i));
+
+                root = new_root;
+            }
+
+            assert_eq!(root, None);
+            assert!(changes.added.is_empty());
+            assert_eq!(changes.removed.len(), nodes.len());
+        });
+    }
+
+    #[test]
+    fn only_path_rewritten() {
+        let ipfs = MemoryIpfs::new();
+
+        block_on(async {
+            let mut changes = HamtChanges::default();
+            let mut root = None;
+
+            for i in 0..200 {
+                root = insert(&ipfs, root, cid(i), i, &mut changes).await.unwrap();
+            }
+
+            let (nodes, _) = walk::<_, usize>(&ipfs, root.unwrap()).await.unwrap();
+
+            let mut changes = HamtChanges::default();
+            let new_root = insert(&ipfs, root, cid(7), 700, &mut changes)
+                .await
+                .unwrap();
+
+            assert!(changes.added.len() < nodes.len());
+            assert_eq!(changes.added.len(), changes.removed.len());
+            assert!(changes.removed.contains(&root.unwrap()));
+            assert!(changes.added.contains(&new_root.unwrap()));
+
+            let value = get(&ipfs, new_root.unwrap(), &cid(7)).await.unwrap();
+            assert_eq!(value, Some(700));
+        });
+    }
+
+    #[test]
+    fn canonical_shape() {
+        let ipfs = MemoryIpfs::new();
+
+        block_on(async {
+            let mut changes = HamtChanges::default();
+
+            let mut forward = None;
+            for i in 0..50 {
+                forward = insert(&ipfs, forward, cid(i), i, &mut changes)
+                    .await
+                    .unwrap();
+            }
+
+            let mut backward = None;
+            for i in (0..60).rev() {
+                backward = insert(&ipfs, backward, cid(i), i, &mut changes)
+                    .await
+                    .unwrap();
+            }
+            for i in 50..60 {
+                backward = remove::<_, usize>(&ipfs, backward, cid(i), &mut changes)
+                    .await
+                    .unwrap()
+                    .0;
+            }
+
+            assert_eq!(forward, backward);
+        });
+    }
+}
@@ -1,4 +1,4 @@
-use crate::IPLDLink;
+use crate::{Address, IPLDLink};
 
 use serde::{Deserialize, Serialize};
 
@@ -9,4 +9,8 @@ pub struct Identity {
 
     /// Link to your avatar. egg an image.
     pub avatar: IPLDLink,
+
+    /// Address signing your comments.
+    #[serde(default)]
+    pub eth_address: Option<Address>,
 }
@@ -0,0 +1,403 @@
+use crate::dag_cbor;
+use crate::ipfs::{
+    as_link, decode_block, select, step, IpfsBackend, IpfsError, PubSubMessage, PubSubStream,
+    Result, DAG_CBOR, RAW,
+};
+use crate::{IPNSAddress, PeerId};
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use async_trait::async_trait;
+
+use futures::channel::mpsc::{unbounded, UnboundedSender};
+use futures::StreamExt;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+use cid::multihash::{Code, MultihashDigest};
+use cid::Cid;
+
+const LIBP2P_KEY: u64 = 0x72;
+
+const PEER_ID: &str = "12D3KooWMemoryNode";
+
+#[derive(Default)]
+struct Store {
+    blocks: HashMap<Cid, Vec<u8>>,
+
+    /// CID and whether it is pinned recursively.
+    pins: HashMap<Cid, bool>,
+
+    keys: HashMap<String, IPNSAddress>,
+    names: HashMap<IPNSAddress, Cid>,
+
+    topics: HashMap<String, Vec<UnboundedSender<PubSubMessage>>>,
+}
+
+/// IPFS node living in memory. Clones share the same blocks, pins, keys and topics.
+///
+/// No networking, no garbage collection. Only useful for testing.
+#[derive(Clone, Default)]
+pub struct MemoryIpfs {
+    store: Arc<Mutex<Store>>,
+}
+
+impl MemoryIpfs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return true if this CID is pinned, directly or recursively.
+    pub fn is_pinned(&self, cid: &Cid) -> bool {
+        self.store().pins.contains_key(cid)
+    }
+
+    /// Return the number of blocks stored.
+    pub fn block_count(&self) -> usize {
+        self.store().blocks.len()
+    }
+
+    fn store(&self) -> MutexGuard<'_, Store> {
+        self.store.lock().expect("Lock Poisoned")
+    }
+
+    /// Add a block encoded with this codec. Useful to test old nodes.
+    pub fn put_block(&self, codec: u64, data: Vec<u8>) -> Cid {
+        let cid = Cid::new_v1(codec, Code::Sha2_256.digest(&data));
+
+        self.store().blocks.insert(cid, data);
+
+        cid
+    }
+
+    /// Follow links along the path from the root CID.
+    ///
+    /// Return the last CID and the path remaining inside that block.
+    pub fn dag_resolve(&self, cid: Cid, path: Option<&str>) -> Result<(Cid, String)> {
+        let store = self.store();
+
+        let mut cid = cid;
+        let mut value = None;
+        let mut remainder = Vec::new();
+
+        let segments = path.unwrap_or_default().split('/');
+
+        for segment in segments.filter(|segment| !segment.is_empty()) {
+            let current = match value.take() {
+                Some(value) => value,
+                None => decode(&store, cid)?,
+            };
+
+            let next = match step(current, segment) {
+                Some(next) => next,
+                None => {
+                    return Err(IpfsError::new(format!(
+                        "no link named {} under {}",
+                        segment, cid
+                    )))
+                }
+            };
+
+            match as_link(&next) {
+                Some(link) => {
+                    cid = link;
+                    remainder.clear();
+                }
+                None => {
+                    remainder.push(segment);
+                    value = Some(next);
+                }
+            }
+        }
+
+        Ok((cid, remainder.join("/")))
+    }
+}
+
+fn decode(store: &Store, cid: Cid) -> Result<Value> {
+    match store.blocks.get(&cid) {
+        Some(block) => decode_block(&cid, block),
+        None => Err(IpfsError::new(format!("block not found {}", cid))),
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl IpfsBackend for MemoryIpfs {
+    async fn dag_put<T>(&self, node: &T) -> Result<Cid>
+    where
+        T: ?Sized + Serialize + Sync,
+    {
+        let data = dag_cbor::to_vec(node)?;
+
+        Ok(self.put_block(DAG_CBOR, data))
+    }
+
+    async fn dag_get<T>(&self, cid: Cid, path: Option<&str>) -> Result<T>
+    where
+        T: DeserializeOwned + Send,
+    {
+        let (cid, remainder) = self.dag_resolve(cid, path)?;
+
+        let value = decode(&self.store(), cid)?;
+        let value = select(value, &remainder)?;
+
+        let node = serde_json::from_value(value)?;
+
+        Ok(node)
+    }
+
+    async fn add(&self, data: Vec<u8>) -> Result<Cid> {
+        Ok(self.put_block(RAW, data))
+    }
+
+    async fn cat(&self, cid: Cid, path: Option<&str>) -> Result<Vec<u8>> {
+        let (cid, remainder) = self.dag_resolve(cid, path)?;
+
+        if !remainder.is_empty() {
+            return Err(IpfsError::new("path does not end on a link"));
+        }
+
+        match self.store().blocks.get(&cid) {
+            Some(data) => Ok(data.clone()),
+            None => Err(IpfsError::new(format!("block not found {}", cid))),
+        }
+    }
+
+    async fn block_get(&self, cid: Cid) -> Result<Vec<u8>> {
+        match self.store().blocks.get(&cid) {
+            Some(data) => Ok(data.clone()),
+            None => Err(IpfsError::new(format!("block not found {}", cid))),
+        }
+    }
+
+    async fn block_put(&self, codec: u64, data: Vec<u8>) -> Result<Cid> {
+        Ok(self.put_block(codec, data))
+    }
+
+    async fn pin_add(&self, cid: Cid, recursive: bool) -> Result<()> {
+        let mut store = self.store();
+
+        if !store.blocks.contains_key(&cid) {
+            return Err(IpfsError::new(format!("block not found {}", cid)));
+        }
+
+        store.pins.insert(cid, recursive);
+
+        Ok(())
+    }
+
+    async fn pin_rm(&self, cid: Cid, recursive: bool) -> Result<()> {
+        let mut store = self.store();
+
+        match store.pins.get(&cid) {
+            Some(pin) if *pin == recursive => {}
+            _ => return Err(IpfsError::new(format!("{} is not pinned", cid))),
+        }
+
+        store.pins.remove(&cid);
+
+        Ok(())
+    }
+
+    async fn pin_ls(&self) -> Result<Vec<Cid>> {
+        let store = self.store();
+
+        let pins = store
+            .pins
+            .iter()
+            .filter_map(|(cid, recursive)| if *recursive { Some(*cid) } else { None })
+            .collect();
+
+        Ok(pins)
+    }
+
+    async fn key_list(&self) -> Result<HashMap<String, IPNSAddress>> {
+        Ok(self.store().keys.clone())
+    }
+
+    async fn key_gen(&self, name: &str) -> Result<IPNSAddress> {
+        let mut store = self.store();
+
+        if store.keys.contains_key(name) {
+            return Err(IpfsError::new(format!(
+                "key with name {} already exists",
+                name
+            )));
+        }
+
+        let address = Cid::new_v1(LIBP2P_KEY, Code::Sha2_256.digest(name.as_bytes()));
+
+        store.keys.insert(name.to_owned(), address);
+
+        Ok(address)
+    }
+
+    async fn name_publish(&self, cid: Cid, key: &str) -> Result<()> {
+        let mut store = self.store();
+
+        let address = match store.keys.get(key) {
+            Some(address) => *address,
+            None => return Err(IpfsError::new(format!("no key named {}", key))),
+        };
+
+        store.names.insert(address, cid);
+
+        Ok(())
+    }
+
+    async fn name_resolve(&self, ipns: IPNSAddress) -> Result<Cid> {
+        match self.store().names.get(&ipns) {
+            Some(cid) => Ok(*cid),
+            None => Err(IpfsError::new(format!("could not resolve name {}", ipns))),
+        }
+    }
+
+    async fn peer_id(&self) -> Result<PeerId> {
+        Ok(PEER_ID.to_owned())
+    }
+
+    async fn pubsub_pub(&self, topic: &str, msg: &str) -> Result<()> {
+        let msg = PubSubMessage {
+            from: PEER_ID.to_owned(),
+            data: msg.as_bytes().to_vec(),
+        };
+
+        if let Some(senders) = self.store().topics.get_mut(topic) {
+            senders.retain(|sender| sender.unbounded_send(msg.clone()).is_ok());
+        }
+
+        Ok(())
+    }
+
+    fn pubsub_sub(&self, topic: &str) -> PubSubStream<'_> {
+        let (sender, receiver) = unbounded();
+
+        self.store()
+            .topics
+            .entry(topic.to_owned())
+            .or_default()
+            .push(sender);
+
+        Box::pin(receiver.map(Ok))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::video::{DayNode, HourNode, MinuteNode, SecondNode, TimecodeNode};
+
+    use futures::executor::block_on;
+
+    #[test]
+    fn dag_path_resolve() {
+        let ipfs = MemoryIpfs::new();
+
+        block_on(async {
+            let video = ipfs.add(vec![0, 1, 2, 3]).await.unwrap();
+
+            let second = SecondNode {
+                link_to_video: video.into(),
+                links_to_chat: vec![],
+            };
+            let second = ipfs.dag_put(&second).await.unwrap();
+
+            let minute = MinuteNode {
+                links_to_seconds: vec![second.into()],
+            };
+            let minute = ipfs.dag_put(&minute).await.unwrap();
+
+            let hour = HourNode {
+                links_to_minutes: vec![minute.into()],
+            };
+            let hour = ipfs.dag_put(&hour).await.unwrap();
+
+            let day = DayNode {
+                links_to_hours: vec![hour.into()],
+            };
+            let day = ipfs.dag_put(&day).await.unwrap();
+
+            let root = ipfs
+                .dag_put(&TimecodeNode {
+                    timecode: day.into(),
+                })
+                .await
+                .unwrap();
+
+            let node: SecondNode = ipfs
+                .dag_get(root, Some("/time/hour/0/minute/0/second/0"))
+                .await
+                .unwrap();
+
+            assert_eq!(node.link_to_video.link, video);
+
+            let data = ipfs
+                .cat(root, Some("/time/hour/0/minute/0/second/0/video"))
+                .await
+                .unwrap();
+
+            assert_eq!(data, vec![0, 1, 2, 3]);
+
+            assert!(ipfs
+                .dag_get::<SecondNode>(root, Some("/time/hour/1"))
+                .await
+                .is_err());
+        });
+    }
+
+    #[test]
+    fn ipns_publish_resolve() {
+        let ipfs = MemoryIpfs::new();
+
+        block_on(async {
+            let address = ipfs.key_gen("feed").await.unwrap();
+            let cid = ipfs.dag_put("Hello World!").await.unwrap();
+
+            ipfs.name_publish(cid, "feed").await.unwrap();
+
+            assert_eq!(cid.codec(), DAG_CBOR);
+            assert_eq!(ipfs.name_resolve(address).await.unwrap(), cid);
+            assert_eq!(ipfs.key_list().await.unwrap().get("feed"), Some(&address));
+        });
+    }
+
+    #[test]
+    fn json_block_decode() {
+        let ipfs = MemoryIpfs::new();
+
+        block_on(async {
+            let video = ipfs.add(vec![0, 1, 2, 3]).await.unwrap();
+
+            let json = format!(r#"{{"video":{{"/":"{}"}},"chat":[]}}"#, video);
+            let cid = ipfs.put_block(crate::ipfs::DAG_JSON, json.into_bytes());
+
+            let node: SecondNode = ipfs.dag_get(cid, None).await.unwrap();
+
+            assert_eq!(node.link_to_video.link, video);
+            assert_eq!(
+                ipfs.cat(cid, Some("video")).await.unwrap(),
+                vec![0, 1, 2, 3]
+            );
+        });
+    }
+
+    #[test]
+    fn pubsub_round_trip() {
+        let ipfs = MemoryIpfs::new();
+
+        block_on(async {
+            let mut stream = ipfs.pubsub_sub("topic");
+
+            ipfs.pubsub_pub("topic", "Hello").await.unwrap();
+
+            let msg = stream.next().await.unwrap().unwrap();
+
+            assert_eq!(msg.data, b"Hello".to_vec());
+        });
+    }
+}
@@ -0,0 +1,248 @@
+mod memory;
+
+pub use memory::MemoryIpfs;
+
+use crate::{IPNSAddress, PeerId};
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+
+use async_trait::async_trait;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+use cid::Cid;
+
+/// Multicodec of raw data blocks.
+pub const RAW: u64 = 0x55;
+
+/// Multicodec of JSON blocks.
+pub const JSON: u64 = 0x0200;
+
+/// Multicodec of DAG-JSON blocks.
+pub const DAG_JSON: u64 = 0x0129;
+
+/// Multicodec of DAG-CBOR blocks. All new nodes use this codec.
+pub const DAG_CBOR: u64 = 0x71;
+
+/// Multicodec of DAG-PB blocks. Files are chunked in those.
+pub const DAG_PB: u64 = 0x70;
+
+const CODEC_NAMES: [(u64, &str); 5] = [
+    (RAW, "raw"),
+    (JSON, "json"),
+    (DAG_JSON, "dag-json"),
+    (DAG_CBOR, "dag-cbor"),
+    (DAG_PB, "dag-pb"),
+];
+
+pub type Result<T> = std::result::Result<T, IpfsError>;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub type PubSubStream<'a> = futures::stream::BoxStream<'a, Result<PubSubMessage>>;
+
+#[cfg(target_arch = "wasm32")]
+pub type PubSubStream<'a> = futures::stream::LocalBoxStream<'a, Result<PubSubMessage>>;
+
+/// Message received from a GossipSub topic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PubSubMessage {
+    pub from: PeerId,
+    pub data: Vec<u8>,
+}
+
+/// Error returned by any IPFS backend.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IpfsError {
+    message: String,
+}
+
+impl IpfsError {
+    pub fn new<E>(error: E) -> Self
+    where
+        E: ToString,
+    {
+        Self {
+            message: error.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for IpfsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for IpfsError {}
+
+impl From<serde_json::Error> for IpfsError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::new(error)
+    }
+}
+
+impl From<cid::Error> for IpfsError {
+    fn from(error: cid::Error) -> Self {
+        Self::new(error)
+    }
+}
+
+impl From<std::io::Error> for IpfsError {
+    fn from(error: std::io::Error) -> Self {
+        Self::new(error)
+    }
+}
+
+/// Common interface to IPFS.
+///
+/// Implemented by the go-ipfs HTTP clients of the CLI and the web app and by an in memory store for testing.
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait IpfsBackend {
+    /// Serialize then add dag node to IPFS and return CID.
+    async fn dag_put<T>(&self, node: &T) -> Result<Cid>
+    where
+        T: ?Sized + Serialize + Sync;
+
+    /// Deserialize dag node at CID and optional path.
+    async fn dag_get<T>(&self, cid: Cid, path: Option<&str>) -> Result<T>
+    where
+        T: DeserializeOwned + Send;
+
+    /// Add raw data to IPFS and return CID. Data is not pinned.
+    async fn add(&self, data: Vec<u8>) -> Result<Cid>;
+
+    /// Return all the data of the file at CID and optional path.
+    async fn cat(&self, cid: Cid, path: Option<&str>) -> Result<Vec<u8>>;
+
+    /// Return the raw bytes of a block.
+    async fn block_get(&self, cid: Cid) -> Result<Vec<u8>>;
+
+    /// Add a block already encoded with this codec and return CID.
+    async fn block_put(&self, codec: u64, data: Vec<u8>) -> Result<Cid>;
+
+    async fn pin_add(&self, cid: Cid, recursive: bool) -> Result<()>;
+
+    async fn pin_rm(&self, cid: Cid, recursive: bool) -> Result<()>;
+
+    /// Return all recursively pinned CIDs.
+    async fn pin_ls(&self) -> Result<Vec<Cid>>;
+
+    /// Return all key names and their IPNS addresses.
+    async fn key_list(&self) -> Result<HashMap<String, IPNSAddress>>;
+
+    /// Generate a new key and return its IPNS address.
+    async fn key_gen(&self, name: &str) -> Result<IPNSAddress>;
+
+    /// Publish CID under the IPNS key with this name.
+    async fn name_publish(&self, cid: Cid, key: &str) -> Result<()>;
+
+    async fn name_resolve(&self, ipns: IPNSAddress) -> Result<Cid>;
+
+    /// Return the peer ID of this IPFS node.
+    async fn peer_id(&self) -> Result<PeerId>;
+
+    async fn pubsub_pub(&self, topic: &str, msg: &str) -> Result<()>;
+
+    /// Subscribe to a topic, the subscription ends when the stream is dropped.
+    fn pubsub_sub(&self, topic: &str) -> PubSubStream<'_>;
+}
+
+/// Decode a block according to the codec of its CID.
+pub fn decode_block(cid: &Cid, data: &[u8]) -> Result<Value> {
+    match cid.codec() {
+        DAG_CBOR => crate::dag_cbor::decode(data),
+        DAG_JSON | JSON => Ok(serde_json::from_slice(data)?),
+        codec => Err(IpfsError::new(format!(
+            "cannot decode {}, unsupported codec {:#x}",
+            cid, codec
+        ))),
+    }
+}
+
+/// Return the name of a multicodec as used by the HTTP API.
+pub fn codec_name(codec: u64) -> Option<&'static str> {
+    CODEC_NAMES
+        .iter()
+        .find(|(code, _)| *code == codec)
+        .map(|(_, name)| *name)
+}
+
+/// Return the multicodec with this name.
+pub fn codec_from_name(name: &str) -> Option<u64> {
+    CODEC_NAMES
+        .iter()
+        .find(|(_, codec_name)| *codec_name == name)
+        .map(|(code, _)| *code)
+}
+
+/// Return the CIDs of all the blocks linked from this one.
+pub fn block_links(cid: &Cid, data: &[u8]) -> Result<Vec<Cid>> {
+    match cid.codec() {
+        RAW => Ok(Vec::new()),
+        DAG_PB => crate::dag_pb::links(data),
+        _ => {
+            let value = decode_block(cid, data)?;
+
+            let mut links = Vec::new();
+
+            collect_links(&value, &mut links);
+
+            Ok(links)
+        }
+    }
+}
+
+fn collect_links(value: &Value, links: &mut Vec<Cid>) {
+    if let Some(cid) = as_link(value) {
+        links.push(cid);
+        return;
+    }
+
+    match value {
+        Value::Array(array) => array.iter().for_each(|value| collect_links(value, links)),
+        Value::Object(map) => map.values().for_each(|value| collect_links(value, links)),
+        _ => {}
+    }
+}
+
+/// Return the value at this path inside a block. Links are not followed.
+pub fn select(mut value: Value, path: &str) -> Result<Value> {
+    for segment in path.split('/').filter(|segment| !segment.is_empty()) {
+        value = match step(value, segment) {
+            Some(value) => value,
+            None => return Err(IpfsError::new(format!("no link named {}", segment))),
+        };
+    }
+
+    Ok(value)
+}
+
+/// Return the value under this map key or array index.
+pub(crate) fn step(value: Value, segment: &str) -> Option<Value> {
+    match value {
+        Value::Object(mut map) => map.remove(segment),
+        Value::Array(mut vec) => match segment.parse::<usize>() {
+            Ok(idx) if idx < vec.len() => Some(vec.swap_remove(idx)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Return the CID if this value is a link.
+pub(crate) fn as_link(value: &Value) -> Option<Cid> {
+    let map = value.as_object()?;
+
+    if map.len() != 1 {
+        return None;
+    }
+
+    let link = map.get("/")?.as_str()?;
+
+    Cid::try_from(link).ok()
+}
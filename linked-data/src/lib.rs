@@ -1,10 +1,17 @@
 pub mod beacon;
 pub mod blog;
+pub mod car;
 pub mod chat;
 pub mod comments;
+pub mod dag_cbor;
+pub mod dag_pb;
+pub mod eip712;
 pub mod feed;
+pub mod filter;
 pub mod friends;
+pub mod hamt;
 pub mod identity;
+pub mod ipfs;
 pub mod live;
 pub mod mime_type;
 pub mod moderation;
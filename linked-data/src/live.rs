@@ -1,4 +1,5 @@
-use crate::PeerId;
+use crate::eip712::{Member, TypedData};
+use crate::{Address, IPLDLink, PeerId};
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Debug, Default, PartialEq, Clone)]
@@ -11,4 +12,78 @@ pub struct Live {
 
     /// IPFS Peer ID. Base58btc.
     pub peer_id: PeerId,
+
+    /// Minimum number of seconds between chat messages of a user. 0 to disable.
+    #[serde(default)]
+    pub slow_mode: u64,
+
+    /// Ethereum address signing video announcements.
+    /// Any node can publish the stream if set, otherwise only peer_id can.
+    #[serde(default)]
+    pub address: Option<Address>,
+}
+
+/// New video node of a live stream.
+/// Signed and sent on the video topic.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub struct VideoAnnouncement {
+    pub video: IPLDLink,
+
+    /// Number of video nodes minted before this one.
+    pub sequence: u64,
+
+    /// Unix time in seconds.
+    pub timestamp: u64,
+}
+
+impl TypedData for VideoAnnouncement {
+    const TYPE_NAME: &'static str = "VideoAnnouncement";
+
+    const MEMBERS: &'static [(&'static str, &'static str)] = &[
+        ("video", "string"),
+        ("sequence", "uint64"),
+        ("timestamp", "uint64"),
+    ];
+
+    fn members(&self) -> Vec<Member<'_>> {
+        vec![
+            Member::String(self.video.link.to_string().into()),
+            Member::Uint(self.sequence),
+            Member::Uint(self.timestamp),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::signature::SignedMessage;
+
+    use cid::multihash::{Code, MultihashDigest};
+    use cid::Cid;
+
+    #[test]
+    fn signed_announcement() {
+        let cid = Cid::new_v1(0x71, Code::Sha2_256.digest(b"video"));
+
+        let announcement = VideoAnnouncement {
+            video: cid.into(),
+            sequence: 42,
+            timestamp: 1_600_000_000,
+        };
+
+        let signed = SignedMessage::sign(announcement, &[7u8; 32]).unwrap();
+
+        assert!(signed.verify());
+
+        let json = serde_json::to_vec(&signed).unwrap();
+        let mut decoded: SignedMessage<VideoAnnouncement> = serde_json::from_slice(&json).unwrap();
+
+        assert_eq!(decoded, signed);
+
+        decoded.data.sequence = 43;
+
+        assert!(!decoded.verify());
+    }
 }
@@ -1,3 +1,4 @@
+use crate::ipfs::{IpfsBackend, IpfsError};
 use crate::IPLDLink;
 
 use std::borrow::Cow;
@@ -32,11 +33,13 @@ impl MimeTyped {
         )
     }
 
-    //TODO once you have a unified IPFS API you could do this
-    /* pub async fn data_url(self, ipfs: &IpfsClient ) -> String {
-        let data = ipfs.cat(self.data.link).await;
-        let data = Base::Base64.encode(data);
+    /// Fetch the data from IPFS then return it as a data URL.
+    pub async fn fetch_data_url<I>(&self, ipfs: &I) -> Result<String, IpfsError>
+    where
+        I: IpfsBackend,
+    {
+        let data = ipfs.cat(self.data.link, None).await?;
 
-        format!("data:{};base64,{}", self.mime_type, data)
-    } */
+        Ok(self.data_url(&data))
+    }
 }
@@ -1,3 +1,4 @@
+use crate::eip712::{Member, TypedData};
 use crate::{Address, PeerId};
 
 use std::collections::HashMap;
@@ -14,6 +15,20 @@ pub struct Ban {
     pub peer_id: PeerId,
 }
 
+impl TypedData for Ban {
+    const TYPE_NAME: &'static str = "Ban";
+
+    const MEMBERS: &'static [(&'static str, &'static str)] =
+        &[("address", "address"), ("peerId", "string")];
+
+    fn members(&self) -> Vec<Member<'_>> {
+        vec![
+            Member::Address(&self.address),
+            Member::String(self.peer_id.as_str().into()),
+        ]
+    }
+}
+
 /// Message to mod/unmod a user.
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Moderator {
@@ -21,20 +36,69 @@ pub struct Moderator {
     pub moderator: Address,
 }
 
+impl TypedData for Moderator {
+    const TYPE_NAME: &'static str = "Moderator";
+
+    const MEMBERS: &'static [(&'static str, &'static str)] = &[("mod", "address")];
+
+    fn members(&self) -> Vec<Member<'_>> {
+        vec![Member::Address(&self.moderator)]
+    }
+}
+
+/// Message to temporarily mute a user.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Timeout {
+    pub address: Address,
+    pub peer_id: PeerId,
+
+    /// Duration in seconds.
+    pub duration: u64,
+}
+
+impl TypedData for Timeout {
+    const TYPE_NAME: &'static str = "Timeout";
+
+    const MEMBERS: &'static [(&'static str, &'static str)] = &[
+        ("address", "address"),
+        ("peerId", "string"),
+        ("duration", "uint64"),
+    ];
+
+    fn members(&self) -> Vec<Member<'_>> {
+        vec![
+            Member::Address(&self.address),
+            Member::String(self.peer_id.as_str().into()),
+            Member::Uint(self.duration),
+        ]
+    }
+}
+
 /// List of banned users.
 /// Direct pin.
-#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Clone)]
 pub struct Bans {
     pub banned: HashSet<Address>,
 }
 
 /// List of moderators.
 /// Direct pin.
-#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Clone)]
 pub struct Moderators {
+    /// Beacon owner, can always moderate.
+    #[serde(default)]
+    pub owner: Option<Address>,
+
     pub mods: HashSet<Address>,
 }
 
+impl Moderators {
+    /// Check if this address is the owner or a moderator.
+    pub fn can_moderate(&self, address: &Address) -> bool {
+        self.owner.as_ref() == Some(address) || self.mods.contains(address)
+    }
+}
+
 /// Local cache of who is verified and/or banned.
 pub struct ChatModerationCache {
     verified: HashMap<PeerId, usize>, // Map peer IDs to indices.
@@ -42,9 +106,15 @@ pub struct ChatModerationCache {
     peers: Vec<PeerId>,      // sync
     origins: Vec<Cid>,       // sync
     addresses: Vec<Address>, // sync
-    names: Vec<String>,      // sync
+
+    names: HashMap<PeerId, String>, // Map peer IDs to display names, when cached.
 
     ban_index: usize, // Lower than this users are banned.
+
+    timeouts: HashMap<PeerId, u64>, // Map peer IDs to end of timeout in Unix time.
+
+    slow_mode: u64,                      // Minimum seconds between messages.
+    last_messages: HashMap<PeerId, u64>, // Map peer IDs to last message in Unix time.
 }
 
 impl ChatModerationCache {
@@ -55,12 +125,22 @@ impl ChatModerationCache {
             peers: Vec::with_capacity(capacity),
             origins: Vec::with_capacity(capacity),
             addresses: Vec::with_capacity(capacity),
-            names: Vec::with_capacity(name_cap),
+            names: HashMap::with_capacity(name_cap),
 
             ban_index: 0,
+
+            timeouts: HashMap::new(),
+
+            slow_mode: 0,
+            last_messages: HashMap::with_capacity(capacity),
         }
     }
 
+    /// Set the minimum number of seconds between messages of a peer. 0 to disable.
+    pub fn set_slow_mode(&mut self, seconds: u64) {
+        self.slow_mode = seconds;
+    }
+
     /// Check if this peer is banned.
     pub fn is_banned(&self, peer: &str) -> bool {
         let index = match self.verified.get(peer) {
@@ -71,6 +151,41 @@ impl ChatModerationCache {
         index < self.ban_index
     }
 
+    /// Check if this peer is timed out, forget expired timeouts.
+    pub fn is_timed_out(&mut self, peer: &str, now: u64) -> bool {
+        let until = match self.timeouts.get(peer) {
+            Some(until) => *until,
+            None => return false,
+        };
+
+        if now < until {
+            return true;
+        }
+
+        self.timeouts.remove(peer);
+
+        false
+    }
+
+    /// Check if this peer sent a message too recently, otherwise remember this one.
+    pub fn is_rate_limited(&mut self, peer: &str, now: u64) -> bool {
+        if self.slow_mode == 0 {
+            return false;
+        }
+
+        match self.last_messages.get_mut(peer) {
+            Some(last) if now < *last + self.slow_mode => true,
+            Some(last) => {
+                *last = now;
+                false
+            }
+            None => {
+                self.last_messages.insert(peer.to_owned(), now);
+                false
+            }
+        }
+    }
+
     /// Check if this peer is verified.
     pub fn is_verified(&self, peer: &str, origin: &Cid) -> bool {
         let index = match self.verified.get(peer) {
@@ -90,9 +205,7 @@ impl ChatModerationCache {
     }
 
     pub fn get_name(&self, peer: &str) -> Option<&str> {
-        let index = self.verified.get(peer)?;
-
-        let name = self.names.get(*index)?;
+        let name = self.names.get(peer)?;
 
         Some(name)
     }
@@ -110,7 +223,7 @@ impl ChatModerationCache {
         self.addresses.push(addrs);
 
         if let Some(name) = name {
-            self.names.push(name);
+            self.names.insert(peer.to_owned(), name);
         }
 
         self.verified.insert(peer.to_owned(), index);
@@ -126,25 +239,139 @@ impl ChatModerationCache {
             return;
         }
 
-        if i == self.ban_index {
-            self.ban_index += 1;
+        self.swap(i, self.ban_index);
+
+        self.ban_index += 1;
+    }
+
+    pub fn unban_peer(&mut self, peer: &str) {
+        let i = match self.verified.get(peer) {
+            Some(i) => *i,
+            None => return,
+        };
+
+        if i >= self.ban_index {
             return;
         }
 
-        let last = self.ban_index;
+        self.ban_index -= 1;
 
-        self.peers.swap(i, last);
-        self.origins.swap(i, last);
-        self.addresses.swap(i, last);
-        self.names.swap(i, last);
+        self.swap(i, self.ban_index);
+    }
 
-        let index = self.verified.get_mut(peer).unwrap();
-        *index = last;
+    /// Number of peers with a verified signature, banned or not.
+    pub fn peer_count(&self) -> usize {
+        self.peers.len()
+    }
 
-        let last_peer = &self.peers[i];
-        let index = self.verified.get_mut(last_peer).unwrap();
-        *index = i;
+    pub fn banned_count(&self) -> usize {
+        self.ban_index
+    }
 
-        self.ban_index += 1;
+    /// Mute this peer for a duration in seconds, starting now in Unix time.
+    pub fn timeout_peer(&mut self, peer: &str, now: u64, duration: u64) {
+        self.timeouts
+            .insert(peer.to_owned(), now.saturating_add(duration));
+    }
+
+    /// Swap two peers and update their indices.
+    fn swap(&mut self, i: usize, j: usize) {
+        if i == j {
+            return;
+        }
+
+        self.peers.swap(i, j);
+        self.origins.swap(i, j);
+        self.addresses.swap(i, j);
+
+        for index in [i, j].iter() {
+            let peer = &self.peers[*index];
+
+            if let Some(idx) = self.verified.get_mut(peer) {
+                *idx = *index;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ban_unban() {
+        let mut cache = ChatModerationCache::new(3, 0);
+
+        for (i, peer) in ["A", "B", "C"].iter().enumerate() {
+            cache.add_peer(peer, Cid::default(), [i as u8; 20], None);
+        }
+
+        cache.ban_peer("C");
+        cache.ban_peer("B");
+
+        assert!(!cache.is_banned("A"));
+        assert!(cache.is_banned("B"));
+        assert!(cache.is_banned("C"));
+        assert_eq!(cache.get_address("C"), Some(&[2u8; 20]));
+        assert_eq!(cache.banned_count(), 2);
+
+        cache.unban_peer("C");
+
+        assert_eq!(cache.peer_count(), 3);
+        assert_eq!(cache.banned_count(), 1);
+
+        assert!(cache.is_banned("B"));
+        assert!(!cache.is_banned("C"));
+        assert_eq!(cache.get_address("A"), Some(&[0u8; 20]));
+        assert_eq!(cache.get_address("B"), Some(&[1u8; 20]));
+        assert_eq!(cache.get_address("C"), Some(&[2u8; 20]));
+
+        cache.timeout_peer("A", 0, 100);
+
+        assert!(cache.is_timed_out("A", 99));
+        assert!(!cache.is_timed_out("A", 100));
+        assert!(!cache.is_timed_out("A", 0));
+
+        // Durations past the end of time never wrap to an expired timeout.
+        cache.timeout_peer("B", 100, u64::MAX);
+
+        assert!(cache.is_timed_out("B", 100));
+        assert!(cache.is_timed_out("B", u64::MAX - 1));
+    }
+
+    #[test]
+    fn names_follow_peers() {
+        let mut cache = ChatModerationCache::new(3, 2);
+
+        cache.add_peer("A", Cid::default(), [0u8; 20], None);
+        cache.add_peer("B", Cid::default(), [1u8; 20], Some("Bob".to_owned()));
+        cache.add_peer("C", Cid::default(), [2u8; 20], Some("Carol".to_owned()));
+
+        cache.ban_peer("C");
+
+        assert_eq!(cache.get_name("A"), None);
+        assert_eq!(cache.get_name("B"), Some("Bob"));
+        assert_eq!(cache.get_name("C"), Some("Carol"));
+
+        cache.unban_peer("C");
+
+        assert_eq!(cache.get_name("A"), None);
+        assert_eq!(cache.get_name("C"), Some("Carol"));
+    }
+
+    #[test]
+    fn slow_mode() {
+        let mut cache = ChatModerationCache::new(1, 0);
+
+        assert!(!cache.is_rate_limited("A", 0));
+        assert!(!cache.is_rate_limited("A", 0));
+
+        cache.set_slow_mode(5);
+
+        assert!(!cache.is_rate_limited("A", 10));
+        assert!(cache.is_rate_limited("A", 14));
+        assert!(!cache.is_rate_limited("A", 15));
+        assert!(!cache.is_rate_limited("B", 15));
+        assert!(cache.is_rate_limited("B", 16));
     }
 }
@@ -1,9 +1,10 @@
+use crate::eip712::TypedData;
 use crate::{keccak256, Address};
 
 use serde::{Deserialize, Serialize};
 
-use libsecp256k1::recover;
-use libsecp256k1::{Message, RecoveryId, Signature};
+use libsecp256k1::{recover, sign};
+use libsecp256k1::{Message, PublicKey, RecoveryId, SecretKey, Signature};
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
@@ -18,12 +19,63 @@ where
     pub data: T,
 
     pub signature: Vec<u8>, // Should be [u8; 65] but serde can't deal with big arrays.
+
+    /// Absent for personal_sign, messages signed before EIP-712 support.
+    #[serde(default, skip_serializing_if = "SignatureScheme::is_personal_sign")]
+    pub scheme: SignatureScheme,
+}
+
+/// How the data was hashed before signing.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum SignatureScheme {
+    /// JSON data with the Ethereum signed message prefix.
+    #[default]
+    PersonalSign,
+
+    /// EIP-712 typed structured data.
+    Eip712,
+}
+
+impl SignatureScheme {
+    pub fn is_personal_sign(&self) -> bool {
+        *self == SignatureScheme::PersonalSign
+    }
 }
 
 impl<T> SignedMessage<T>
 where
-    T: Serialize,
+    T: Serialize + TypedData,
 {
+    /// Sign data like personal_sign would.
+    pub fn sign(data: T, secret_key: &[u8; 32]) -> Result<Self> {
+        Self::sign_with(data, secret_key, SignatureScheme::PersonalSign)
+    }
+
+    /// Sign data like eth_signTypedData_v4 would.
+    pub fn sign_typed(data: T, secret_key: &[u8; 32]) -> Result<Self> {
+        Self::sign_with(data, secret_key, SignatureScheme::Eip712)
+    }
+
+    fn sign_with(data: T, secret_key: &[u8; 32], scheme: SignatureScheme) -> Result<Self> {
+        let address = eth_address(secret_key)?;
+
+        let secret_key = SecretKey::parse(secret_key)?;
+
+        let msg = message_hash(&data, scheme)?;
+
+        let (sig, rec_id) = sign(&msg, &secret_key);
+
+        let mut signature = sig.serialize().to_vec();
+        signature.push(rec_id.serialize() + 27);
+
+        Ok(Self {
+            address,
+            data,
+            signature,
+            scheme,
+        })
+    }
+
     pub fn verify(&self) -> bool {
         if self.signature.len() != 65 {
             return false;
@@ -42,15 +94,7 @@ where
     }
 
     fn public_key(&self) -> Result<[u8; 65]> {
-        let message = serde_json::to_vec(&self.data)?;
-
-        let mut eth_message =
-            format!("\x19Ethereum Signed Message:\n{}", message.len()).into_bytes();
-        eth_message.extend_from_slice(&message);
-
-        let hash = keccak256(&eth_message);
-
-        let msg = Message::parse_slice(&hash)?;
+        let msg = message_hash(&self.data, self.scheme)?;
 
         let sig = Signature::parse_standard_slice(&self.signature[0..64])?;
 
@@ -61,3 +105,96 @@ where
         Ok(data.serialize())
     }
 }
+
+/// Ethereum address of this private key.
+pub fn eth_address(secret_key: &[u8; 32]) -> Result<Address> {
+    let secret_key = SecretKey::parse(secret_key)?;
+
+    let public_key = PublicKey::from_secret_key(&secret_key).serialize();
+
+    let hash = keccak256(&public_key[1..]);
+
+    let mut address = Address::default();
+    address.copy_from_slice(&hash[12..]);
+
+    Ok(address)
+}
+
+/// Hash of the data according to the signature scheme.
+fn message_hash<T>(data: &T, scheme: SignatureScheme) -> Result<Message>
+where
+    T: Serialize + TypedData,
+{
+    if scheme == SignatureScheme::Eip712 {
+        return Ok(Message::parse(&data.signing_hash()));
+    }
+
+    let message = serde_json::to_vec(data)?;
+
+    let mut eth_message = format!("\x19Ethereum Signed Message:\n{}", message.len()).into_bytes();
+    eth_message.extend_from_slice(&message);
+
+    let hash = keccak256(&eth_message);
+
+    Ok(Message::parse_slice(&hash)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::chat::ChatId;
+
+    // Private key and address from the web3.js documentation.
+    const KEY: &str = "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+    const ADDRESS: &str = "2c7536e3605d9c16a7a3d7b1898e529396a65c23";
+
+    fn from_hex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn sign_verify() {
+        let mut key = [0u8; 32];
+        key.copy_from_slice(&from_hex(KEY));
+
+        assert_eq!(eth_address(&key).unwrap().to_vec(), from_hex(ADDRESS));
+
+        let data = ChatId {
+            name: "Alice".to_owned(),
+            peer_id: "12D3KooWAlice".to_owned(),
+        };
+
+        let mut signed = SignedMessage::sign(data, &key).unwrap();
+
+        assert_eq!(signed.signature.len(), 65);
+        assert!(signed.verify());
+
+        // Existing personal_sign messages keep the same encoding.
+        assert!(!serde_json::to_string(&signed).unwrap().contains("scheme"));
+
+        signed.data.name = "Mallory".to_owned();
+
+        assert!(!signed.verify());
+
+        let data = ChatId {
+            name: "Alice".to_owned(),
+            peer_id: "12D3KooWAlice".to_owned(),
+        };
+
+        let mut signed = SignedMessage::sign_typed(data, &key).unwrap();
+
+        assert_eq!(signed.scheme, SignatureScheme::Eip712);
+        assert!(signed.verify());
+
+        // Same signature, other scheme.
+        signed.scheme = SignatureScheme::PersonalSign;
+
+        assert!(!signed.verify());
+
+        assert!(eth_address(&[0u8; 32]).is_err());
+    }
+}
@@ -135,6 +135,11 @@ pub struct VideoNode {
     /// ../time/hour/0/minute/36/second/12/video/previous/..
     #[serde(rename = "previous")]
     pub previous: Option<IPLDLink>,
+
+    /// Length of the segments in seconds. Older nodes are one second long.
+    /// ../time/hour/0/minute/36/second/12/video/duration
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<f64>,
 }
 
 /// Contains initialization data for video stream.
@@ -15,8 +15,9 @@ use yew::Callback;
 use yew_router::prelude::{Router, Switch};
 
 use linked_data::beacon::Beacon;
-use linked_data::comments::Commentary;
+use linked_data::comments::{CommentIndex, CommentModeration, Commentary};
 use linked_data::feed::{ContentCache, FeedAnchor};
+use linked_data::filter::Blocklist;
 use linked_data::friends::Friendlies;
 use linked_data::identity::Identity;
 use linked_data::live::Live;
@@ -56,7 +57,8 @@ pub struct App {
 
     name_cb: Callback<(String, Result<Cid>)>,
 
-    beacon_set: HashSet<Cid>,
+    /// Maps CIDs to Beacons
+    beacon_set: HashMap<Cid, Beacon>,
     beacon: Option<Rc<Beacon>>,
     beacon_cb: Callback<(Cid, Result<Beacon>)>,
 
@@ -68,9 +70,29 @@ pub struct App {
     feed_set: HashMap<Cid, Cid>,
     feed_cb: Callback<CallbackResult<FeedAnchor>>,
 
+    /// Older feed pages requested
+    page_set: HashSet<Cid>,
+    page_cb: Callback<(Cid, Cid, Result<FeedAnchor>)>,
+    load_more_cb: Callback<()>,
+
     /// Maps IPNS to Commentary
     comments_set: HashMap<Cid, Cid>,
     comments_cb: Callback<CallbackResult<Commentary>>,
+    comments_tree_cb: Callback<(Cid, Cid, Result<Commentary>)>,
+
+    /// Maps beacons to their latest Commentary, trees not fetched.
+    commentaries: HashMap<Cid, Commentary>,
+
+    /// Media being viewed, only its comments are fetched.
+    viewed: Option<Cid>,
+    view_cb: Callback<Cid>,
+
+    comment_index_cid: Option<Cid>,
+    comment_index_cb: Callback<CallbackResult<CommentIndex>>,
+
+    /// Maps IPNS to CommentModeration
+    comment_moderation_set: HashMap<Cid, Cid>,
+    comment_moderation_cb: Callback<CallbackResult<CommentModeration>>,
 
     content: Rc<ContentCache>,
 
@@ -89,6 +111,10 @@ pub struct App {
     mods_cid: Option<Cid>,
     mods: Rc<Moderators>,
     mods_cb: Callback<CallbackResult<Moderators>>,
+
+    filters_cid: Option<Cid>,
+    filters: Rc<Blocklist>,
+    filters_cb: Callback<CallbackResult<Blocklist>>,
 }
 
 #[allow(clippy::large_enum_variant)]
@@ -98,11 +124,18 @@ pub enum AppMsg {
     Beacon((Cid, Result<Beacon>)),
     Identity(CallbackResult<Identity>),
     Feed(CallbackResult<FeedAnchor>),
+    LoadMore,
+    FeedPage((Cid, Cid, Result<FeedAnchor>)),
     Live(CallbackResult<Live>),
     Comments(CallbackResult<Commentary>),
+    CommentsTree((Cid, Cid, Result<Commentary>)),
+    View(Cid),
+    CommentIndex(CallbackResult<CommentIndex>),
+    CommentModeration(CallbackResult<CommentModeration>),
     Friends(CallbackResult<Friendlies>),
     Bans(CallbackResult<Bans>),
     Mods(CallbackResult<Moderators>),
+    Filters(CallbackResult<Blocklist>),
 }
 
 #[derive(Properties, Clone)]
@@ -126,7 +159,7 @@ impl Component for App {
 
             name_cb: link.callback(AppMsg::ENSResolve),
 
-            beacon_set: HashSet::with_capacity(10),
+            beacon_set: HashMap::with_capacity(10),
             beacon: None,
             beacon_cb: link.callback(AppMsg::Beacon),
 
@@ -136,8 +169,23 @@ impl Component for App {
             feed_set: HashMap::with_capacity(10),
             feed_cb: link.callback(AppMsg::Feed),
 
+            page_set: HashSet::with_capacity(10),
+            page_cb: link.callback(AppMsg::FeedPage),
+            load_more_cb: link.callback(|_| AppMsg::LoadMore),
+
             comments_set: HashMap::with_capacity(10),
             comments_cb: link.callback(AppMsg::Comments),
+            comments_tree_cb: link.callback(AppMsg::CommentsTree),
+
+            commentaries: HashMap::with_capacity(10),
+            viewed: None,
+            view_cb: link.callback(AppMsg::View),
+
+            comment_index_cid: None,
+            comment_index_cb: link.callback(AppMsg::CommentIndex),
+
+            comment_moderation_set: HashMap::with_capacity(10),
+            comment_moderation_cb: link.callback(AppMsg::CommentModeration),
 
             content: Rc::from(ContentCache::create()),
 
@@ -153,6 +201,10 @@ impl Component for App {
             mods: Rc::from(Moderators::default()),
             mods_cb: link.callback(AppMsg::Mods),
 
+            filters_cid: None,
+            filters: Rc::from(Blocklist::default()),
+            filters_cb: link.callback(AppMsg::Filters),
+
             friends_cid: None,
             friends: Rc::from(Friendlies::default()),
             friends_cb: link.callback(AppMsg::Friends),
@@ -171,11 +223,18 @@ impl Component for App {
             AppMsg::Beacon(result) => self.on_beacon(result),
             AppMsg::Identity(result) => self.on_identity(result),
             AppMsg::Feed(result) => self.on_feed(result),
+            AppMsg::LoadMore => self.get_feed_pages(),
+            AppMsg::FeedPage(result) => self.on_feed_page(result),
             AppMsg::Live(result) => self.on_live(result),
             AppMsg::Comments(result) => self.on_comments(result),
+            AppMsg::CommentsTree(result) => self.on_comments_tree(result),
+            AppMsg::View(cid) => self.on_view(cid),
+            AppMsg::CommentIndex(result) => self.on_comment_index(result),
+            AppMsg::CommentModeration(result) => self.on_comment_moderation(result),
             AppMsg::Friends(result) => self.on_friends(result),
             AppMsg::Bans(result) => self.on_ban_list(result),
             AppMsg::Mods(result) => self.on_mod_list(result),
+            AppMsg::Filters(result) => self.on_filters(result),
         }
     }
 
@@ -189,9 +248,12 @@ impl Component for App {
         let ipfs = self.props.ipfs.clone();
         let storage = self.props.storage.clone();
         let content = self.content.clone();
+        let load_more = self.load_more_cb.clone();
+        let view = self.view_cb.clone();
         //let beacon = self.beacon.clone().unwrap_or_default();
         let bans = self.bans.clone();
         let mods = self.mods.clone();
+        let filters = self.filters.clone();
         let live = self.live.clone();
         //let friends = self.friends.clone();
 
@@ -200,10 +262,10 @@ impl Component for App {
                 <Router<AppRoute>
                     render = Router::render(move |switch: AppRoute| {
                         match switch {
-                            AppRoute::Content(cid) => html! { <Content ipfs=ipfs.clone() cid=cid content=content.clone() /> },
+                            AppRoute::Content(cid) => html! { <Content ipfs=ipfs.clone() web3=web3.clone() storage=storage.clone() cid=cid content=content.clone() view=view.clone() /> },
                             AppRoute::Settings => html! { <Settings storage=storage.clone() peer_id=peer_id.clone() /> },
-                            AppRoute::Live => html! { <LivePage peer_id=peer_id.clone() ipfs=ipfs.clone() web3=web3.clone() storage=storage.clone() live=live.clone() bans=bans.clone() mods=mods.clone() /> },
-                            AppRoute::Feed => html! { <ContentFeed ipfs=ipfs.clone() storage=storage.clone() content=content.clone() peer_id=peer_id.clone() /> },
+                            AppRoute::Live => html! { <LivePage peer_id=peer_id.clone() ipfs=ipfs.clone() web3=web3.clone() storage=storage.clone() live=live.clone() bans=bans.clone() mods=mods.clone() filters=filters.clone() /> },
+                            AppRoute::Feed => html! { <ContentFeed ipfs=ipfs.clone() storage=storage.clone() content=content.clone() peer_id=peer_id.clone() load_more=load_more.clone() /> },
                             AppRoute::Home => html! { <Home /> },
                         }
                     })
@@ -278,7 +340,7 @@ impl App {
             }
         };
 
-        if self.beacon_set.contains(&beacon_cid) {
+        if self.beacon_set.contains_key(&beacon_cid) {
             return false;
         }
 
@@ -344,13 +406,20 @@ impl App {
             }
         };
 
-        if !self.beacon_set.insert(beacon_cid) {
+        if self.beacon_set.contains_key(&beacon_cid) {
             return false;
         }
 
+        self.beacon_set.insert(beacon_cid, beacon.clone());
+
         self.resolve_content(beacon_cid, Some(beacon.identity), &self.identity_cb);
         self.resolve_content(beacon_cid, beacon.content_feed, &self.feed_cb);
         self.resolve_content(beacon_cid, beacon.comments, &self.comments_cb);
+        self.resolve_content(
+            beacon_cid,
+            beacon.comment_moderation,
+            &self.comment_moderation_cb,
+        );
 
         if self.beacon.is_some() {
             //Prevent resolving live, bans, mods of your friend's beacon.
@@ -361,6 +430,8 @@ impl App {
         self.resolve_content(beacon_cid, beacon.live, &self.live_cb);
         self.resolve_content(beacon_cid, beacon.bans, &self.bans_cb);
         self.resolve_content(beacon_cid, beacon.mods, &self.mods_cb);
+        self.resolve_content(beacon_cid, beacon.filters, &self.filters_cb);
+        self.resolve_content(beacon_cid, beacon.comment_index, &self.comment_index_cb);
 
         if self.beacon.is_none() {
             self.beacon = Rc::from(beacon).into();
@@ -404,7 +475,7 @@ impl App {
             return false;
         }
 
-        Rc::make_mut(&mut self.content).insert_media_content(beacon_cid, feed);
+        Rc::make_mut(&mut self.content).insert_media_content(beacon_cid, feed_cid, feed);
 
         self.props.storage.set_cid(&ipns.to_string(), &feed_cid);
 
@@ -414,6 +485,47 @@ impl App {
         true
     }
 
+    /// Fetch the next older page of every content feed.
+    fn get_feed_pages(&mut self) -> bool {
+        for (beacon, page) in self.content.iter_older_pages() {
+            if !self.page_set.insert(*page) {
+                continue;
+            }
+
+            spawn_local({
+                let cb = self.page_cb.clone();
+                let ipfs = self.props.ipfs.clone();
+                let beacon = *beacon;
+                let page = *page;
+
+                async move { cb.emit((beacon, page, ipfs.dag_get(page, Option::<&str>::None).await)) }
+            });
+        }
+
+        false
+    }
+
+    /// Callback when IPFS dag get return an older content feed page.
+    fn on_feed_page(&mut self, res: (Cid, Cid, Result<FeedAnchor>)) -> bool {
+        let (beacon_cid, page_cid, page) = match res {
+            (beacon_cid, page_cid, Ok(page)) => (beacon_cid, page_cid, page),
+            (_, page_cid, Err(e)) => {
+                ConsoleService::error(&format!("{:?}", e));
+
+                // Try again on next scroll.
+                self.page_set.remove(&page_cid);
+                return false;
+            }
+        };
+
+        Rc::make_mut(&mut self.content).insert_feed_page(beacon_cid, page_cid, page);
+
+        #[cfg(debug_assertions)]
+        ConsoleService::info("App Content Feed Page Updated");
+
+        true
+    }
+
     /// Callback when IPFS dag get return any comments.
     fn on_comments(&mut self, res: CallbackResult<Commentary>) -> bool {
         let (beacon_cid, ipns, comments_cid, comments) = match on_node(res) {
@@ -425,16 +537,116 @@ impl App {
             return false;
         }
 
-        Rc::make_mut(&mut self.content).insert_comments(beacon_cid, comments);
-
         self.props.storage.set_cid(&ipns.to_string(), &comments_cid);
 
+        if let Some(media) = self.viewed {
+            self.get_media_comments(beacon_cid, media, &comments);
+        }
+
+        self.commentaries.insert(beacon_cid, comments);
+
+        false
+    }
+
+    /// Fetch the comments of every beacon on the media being viewed.
+    fn on_view(&mut self, media: Cid) -> bool {
+        if Some(media) == self.viewed {
+            return false;
+        }
+
+        self.viewed = Some(media);
+
+        for (beacon_cid, comments) in self.commentaries.iter() {
+            self.get_media_comments(*beacon_cid, media, comments);
+        }
+
+        false
+    }
+
+    /// Fetch only the comment tree nodes on the path of this media.
+    fn get_media_comments(&self, beacon_cid: Cid, media: Cid, comments: &Commentary) {
+        spawn_local({
+            let cb = self.comments_tree_cb.clone();
+            let ipfs = self.props.ipfs.clone();
+            let comments = comments.clone();
+
+            async move {
+                match comments.load_content(&ipfs, &media).await {
+                    Ok(comments) => cb.emit((beacon_cid, media, Ok(comments))),
+                    Err(e) => cb.emit((beacon_cid, media, Err(e.into()))),
+                }
+            }
+        });
+    }
+
+    /// Callback when the comment trees of a user are fetched for a media.
+    fn on_comments_tree(&mut self, res: (Cid, Cid, Result<Commentary>)) -> bool {
+        let (beacon_cid, media, comments) = match res {
+            (beacon_cid, media, Ok(comments)) => (beacon_cid, media, comments),
+            (_, _, Err(e)) => {
+                ConsoleService::error(&format!("{:?}", e));
+                return false;
+            }
+        };
+
+        if !self.beacon_set.contains_key(&beacon_cid) {
+            return false;
+        }
+
+        Rc::make_mut(&mut self.content).insert_media_comments(beacon_cid, media, comments);
+
         #[cfg(debug_assertions)]
         ConsoleService::info("App Comments Updated");
 
         true
     }
 
+    /// Callback when IPFS dag get return any comment moderation.
+    fn on_comment_moderation(&mut self, res: CallbackResult<CommentModeration>) -> bool {
+        let (beacon_cid, ipns, moderation_cid, moderation) = match on_node(res) {
+            Some(res) => res,
+            None => return false,
+        };
+
+        if Some(moderation_cid) == self.comment_moderation_set.insert(ipns, moderation_cid) {
+            return false;
+        }
+
+        Rc::make_mut(&mut self.content).insert_comment_moderation(beacon_cid, moderation);
+
+        self.props
+            .storage
+            .set_cid(&ipns.to_string(), &moderation_cid);
+
+        #[cfg(debug_assertions)]
+        ConsoleService::info("App Comment Moderation Updated");
+
+        true
+    }
+
+    /// Callback when IPFS dag get return comments on your content.
+    fn on_comment_index(&mut self, res: CallbackResult<CommentIndex>) -> bool {
+        let (_, ipns, index_cid, index) = match on_node(res) {
+            Some(res) => res,
+            None => return false,
+        };
+
+        if Some(index_cid) == self.comment_index_cid {
+            return false;
+        }
+
+        Rc::make_mut(&mut self.content).insert_comment_index(index);
+
+        self.props.storage.set_cid(&ipns.to_string(), &index_cid);
+
+        self.comment_index_cid = index_cid.into();
+
+        #[cfg(debug_assertions)]
+        ConsoleService::info("App Comment Index Updated");
+
+        true
+    }
+
     /// Callback when IPFS dag get return your friend list.
     fn on_friends(&mut self, res: CallbackResult<Friendlies>) -> bool {
         let (_, ipns, friends_cid, friends) = match on_node(res) {
@@ -446,6 +658,17 @@ impl App {
             return false;
         }
 
+        let unfollowed: Vec<Cid> = self
+            .friends
+            .friends
+            .difference(&friends.friends)
+            .filter_map(|friend| friend.friend.as_ref().right().map(|ipld| ipld.link))
+            .collect();
+
+        for beacon_cid in unfollowed {
+            self.remove_beacon(beacon_cid);
+        }
+
         for friend in friends.friends.iter() {
             match &friend.friend {
                 Either::Right(ipld) => {
@@ -476,6 +699,32 @@ impl App {
         true
     }
 
+    /// Forget an unfollowed beacon and its content.
+    fn remove_beacon(&mut self, beacon_cid: Cid) {
+        let beacon = match self.beacon_set.remove(&beacon_cid) {
+            Some(beacon) => beacon,
+            None => return,
+        };
+
+        let ipns_records = [
+            Some(beacon.identity),
+            beacon.content_feed,
+            beacon.comments,
+            beacon.comment_moderation,
+        ];
+
+        for ipns in ipns_records.iter().flatten() {
+            self.identity_set.remove(ipns);
+            self.feed_set.remove(ipns);
+            self.comments_set.remove(ipns);
+            self.comment_moderation_set.remove(ipns);
+        }
+
+        self.commentaries.remove(&beacon_cid);
+
+        Rc::make_mut(&mut self.content).remove_beacon(&beacon_cid);
+    }
+
     /// Callback when IPFS dag get return your live data.
     fn on_live(&mut self, res: CallbackResult<Live>) -> bool {
         let (_, ipns, live_cid, live) = match on_node(res) {
@@ -541,6 +790,28 @@ impl App {
 
         true
     }
+
+    /// Callback when IPFS dag get return your chat filters.
+    fn on_filters(&mut self, res: CallbackResult<Blocklist>) -> bool {
+        let (_, ipns, filters_cid, filters) = match on_node(res) {
+            Some(res) => res,
+            None => return false,
+        };
+
+        if Some(filters_cid) == self.filters_cid {
+            return false;
+        }
+
+        self.props.storage.set_cid(&ipns.to_string(), &filters_cid);
+
+        self.filters_cid = filters_cid.into();
+        self.filters = Rc::from(filters);
+
+        #[cfg(debug_assertions)]
+        ConsoleService::info("App Chat Filters Updated");
+
+        true
+    }
 }
 
 fn on_node<T>(res: CallbackResult<T>) -> Option<(Cid, Cid, Cid, T)> {
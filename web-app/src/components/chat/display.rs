@@ -16,8 +16,9 @@ use yew::services::ConsoleService;
 use yew::Callback;
 
 use linked_data::chat::{ChatId, Message, MessageType};
+use linked_data::filter::{Blocklist, ChatFilter};
 use linked_data::live::Live;
-use linked_data::moderation::{Ban, Bans, ChatModerationCache, Moderators};
+use linked_data::moderation::{Ban, Bans, ChatModerationCache, Moderator, Moderators, Timeout};
 use linked_data::signature::SignedMessage;
 use linked_data::PeerId;
 
@@ -39,6 +40,12 @@ pub struct Display {
 
     mod_db: ChatModerationCache,
 
+    filter: ChatFilter,
+
+    /// Local copies, updated by moderation messages.
+    bans: Bans,
+    mods: Moderators,
+
     chat_element: Option<Element>,
 
     next_id: usize,
@@ -57,6 +64,7 @@ pub struct Props {
     pub live: Rc<Live>,
     pub mods: Rc<Moderators>,
     pub bans: Rc<Bans>,
+    pub filters: Rc<Blocklist>,
 }
 
 impl Component for Display {
@@ -90,6 +98,14 @@ impl Component for Display {
         #[cfg(debug_assertions)]
         ConsoleService::info("Chat Display Created");
 
+        let bans = (*props.bans).clone();
+        let mods = (*props.mods).clone();
+
+        let filter = create_filter(&props.filters);
+
+        let mut mod_db = ChatModerationCache::new(100, 100);
+        mod_db.set_slow_mode(props.live.slow_mode);
+
         Self {
             props,
 
@@ -102,7 +118,12 @@ impl Component for Display {
 
             img_gen,
 
-            mod_db: ChatModerationCache::new(100, 100),
+            mod_db,
+
+            filter,
+
+            bans,
+            mods,
 
             chat_element: None,
 
@@ -119,10 +140,26 @@ impl Component for Display {
     }
 
     fn change(&mut self, props: Self::Properties) -> ShouldRender {
-        if !Rc::ptr_eq(&self.props.live, &props.live) {
+        if !Rc::ptr_eq(&self.props.bans, &props.bans) {
+            self.bans = (*props.bans).clone();
+        }
+
+        if !Rc::ptr_eq(&self.props.mods, &props.mods) {
+            self.mods = (*props.mods).clone();
+        }
+
+        if !Rc::ptr_eq(&self.props.filters, &props.filters) {
+            self.filter = create_filter(&props.filters);
+        }
+
+        let live_changed = !Rc::ptr_eq(&self.props.live, &props.live);
+
+        self.props = props;
+
+        if live_changed {
             self.handle.abort();
 
-            self.props = props;
+            self.mod_db.set_slow_mode(self.props.live.slow_mode);
 
             if !self.props.live.chat_topic.is_empty() {
                 let (handle, regis) = AbortHandle::new_pair();
@@ -225,7 +262,9 @@ impl Display {
         #[cfg(debug_assertions)]
         ConsoleService::info(&format!("Sender => {}", from));
 
-        if self.mod_db.is_banned(&from) {
+        let now = chrono::Utc::now().timestamp() as u64;
+
+        if self.mod_db.is_banned(&from) || self.mod_db.is_timed_out(&from, now) {
             return false;
         }
 
@@ -299,7 +338,7 @@ impl Display {
         #[cfg(debug_assertions)]
         ConsoleService::info("Verifiable => true");
 
-        if self.props.bans.banned.contains(&sign_msg.address) {
+        if self.bans.banned.contains(&sign_msg.address) {
             self.mod_db.ban_peer(&peer);
             return false;
         }
@@ -309,9 +348,23 @@ impl Display {
 
     fn process_msg(&mut self, peer: PeerId, msg: Message) -> bool {
         match msg.msg {
-            MessageType::Chat(msg) => self.update_display(&peer, &msg),
+            MessageType::Chat(msg) => {
+                let now = chrono::Utc::now().timestamp() as u64;
+
+                if self.mod_db.is_rate_limited(&peer, now) {
+                    return false;
+                }
+
+                match self.filter.apply(&peer, &msg) {
+                    Some(msg) => self.update_display(&peer, &msg),
+                    None => false,
+                }
+            }
             MessageType::Ban(ban) => self.update_bans(&peer, ban),
-            MessageType::Mod(_) => false, //TODO
+            MessageType::Unban(ban) => self.remove_ban(&peer, ban),
+            MessageType::Mod(moderator) => self.update_mods(&peer, moderator),
+            MessageType::Unmod(moderator) => self.remove_mod(&peer, moderator),
+            MessageType::Timeout(timeout) => self.timeout(&peer, timeout),
         }
     }
 
@@ -356,22 +409,80 @@ impl Display {
         true
     }
 
-    fn update_bans(&mut self, peer: &str, ban: Ban) -> bool {
-        let address = match self.mod_db.get_address(peer) {
-            Some(addrs) => addrs,
+    /// Check that the sender is the beacon owner or a moderator.
+    fn can_moderate(&self, peer: &str) -> bool {
+        match self.mod_db.get_address(peer) {
+            Some(address) => self.mods.can_moderate(address),
             None => {
                 #[cfg(debug_assertions)]
                 ConsoleService::error("No Address");
-                return false;
+                false
             }
-        };
+        }
+    }
 
-        if !self.props.mods.mods.contains(address) {
+    fn update_bans(&mut self, peer: &str, ban: Ban) -> bool {
+        if !self.can_moderate(peer) {
             return false;
         }
 
         self.mod_db.ban_peer(&ban.peer_id);
+        self.bans.banned.insert(ban.address);
 
         false
     }
+
+    fn remove_ban(&mut self, peer: &str, ban: Ban) -> bool {
+        if !self.can_moderate(peer) {
+            return false;
+        }
+
+        self.mod_db.unban_peer(&ban.peer_id);
+        self.bans.banned.remove(&ban.address);
+
+        false
+    }
+
+    fn update_mods(&mut self, peer: &str, moderator: Moderator) -> bool {
+        if !self.can_moderate(peer) {
+            return false;
+        }
+
+        self.mods.mods.insert(moderator.moderator);
+
+        false
+    }
+
+    fn remove_mod(&mut self, peer: &str, moderator: Moderator) -> bool {
+        if !self.can_moderate(peer) {
+            return false;
+        }
+
+        self.mods.mods.remove(&moderator.moderator);
+
+        false
+    }
+
+    fn timeout(&mut self, peer: &str, timeout: Timeout) -> bool {
+        if !self.can_moderate(peer) {
+            return false;
+        }
+
+        let now = chrono::Utc::now().timestamp() as u64;
+
+        self.mod_db
+            .timeout_peer(&timeout.peer_id, now, timeout.duration);
+
+        false
+    }
+}
+
+fn create_filter(blocklist: &Blocklist) -> ChatFilter {
+    match ChatFilter::new(blocklist) {
+        Ok(filter) => filter,
+        Err(e) => {
+            ConsoleService::error(&format!("{:?}", e));
+            ChatFilter::default()
+        }
+    }
 }
@@ -12,7 +12,7 @@ use cid::Cid;
 
 use linked_data::chat::{ChatId, Message, MessageType};
 use linked_data::live::Live;
-use linked_data::signature::SignedMessage;
+use linked_data::signature::{SignatureScheme, SignedMessage};
 
 use web3::types::Address;
 
@@ -42,6 +42,7 @@ pub struct Inputs {
     name: Option<String>,
     sign_msg_content: Option<ChatId>,
     sign_msg_cid: Option<Cid>,
+    scheme: SignatureScheme,
 }
 
 pub enum Msg {
@@ -52,6 +53,7 @@ pub enum Msg {
     Account(Result<Address>),
     AccountName(Result<String>),
     SetName(String),
+    SetTypedData(bool),
     SubmitName,
     Signed(Result<[u8; 65]>),
     Minted(Result<Cid>),
@@ -93,6 +95,7 @@ impl Component for Inputs {
             name: None,
             sign_msg_content: None,
             sign_msg_cid,
+            scheme: SignatureScheme::PersonalSign,
         }
     }
 
@@ -105,6 +108,7 @@ impl Component for Inputs {
             Msg::Account(res) => self.on_account_connected(res),
             Msg::AccountName(res) => self.on_account_name(res),
             Msg::SetName(name) => self.on_name_input(name),
+            Msg::SetTypedData(typed) => self.on_typed_data_input(typed),
             Msg::SubmitName => self.on_name_submit(),
             Msg::Signed(res) => self.on_signature(res),
             Msg::Minted(res) => self.on_sign_msg_minted(res),
@@ -158,6 +162,13 @@ impl Inputs {
                         <ybc::Input name="chat_name" value=name.to_owned() update=self.link.callback(Msg::SetName) />
                     </ybc::Control>
                 </ybc::Field>
+                <ybc::Field>
+                    <ybc::Control>
+                        <ybc::Checkbox name="typed_data" checked={self.scheme == SignatureScheme::Eip712} update=self.link.callback(Msg::SetTypedData) >
+                            { " Sign as readable typed data (EIP-712)" }
+                        </ybc::Checkbox>
+                    </ybc::Control>
+                </ybc::Field>
                 <ybc::Field label="Confirm your name by signing it".to_owned() >
                     <ybc::Control>
                         <ybc::Button classes=classes!("is-primary") onclick=self.link.callback(|_| Msg::SubmitName)>
@@ -337,6 +348,16 @@ impl Inputs {
         false
     }
 
+    fn on_typed_data_input(&mut self, typed: bool) -> bool {
+        self.scheme = if typed {
+            SignatureScheme::Eip712
+        } else {
+            SignatureScheme::PersonalSign
+        };
+
+        true
+    }
+
     fn on_name_submit(&mut self) -> bool {
         #[cfg(debug_assertions)]
         ConsoleService::info("Name Submitted");
@@ -375,7 +396,16 @@ impl Inputs {
             let web3 = self.props.web3.clone();
             let data = data.clone();
 
-            async move { cb.emit(web3.eth_sign(address, data).await) }
+            let scheme = self.scheme;
+
+            async move {
+                let response = match scheme {
+                    SignatureScheme::Eip712 => web3.eth_sign_typed_data(address, &data).await,
+                    SignatureScheme::PersonalSign => web3.eth_sign(address, data).await,
+                };
+
+                cb.emit(response)
+            }
         });
 
         self.sign_msg_content = Some(data);
@@ -418,6 +448,7 @@ impl Inputs {
             address,
             data,
             signature,
+            scheme: self.scheme,
         };
 
         #[cfg(debug_assertions)]
@@ -1,6 +1,8 @@
 mod display;
 mod inputs;
 mod message;
+mod replay;
 mod window;
 
+pub use replay::ChatReplay;
 pub use window::ChatWindow;
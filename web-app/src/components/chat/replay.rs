@@ -0,0 +1,299 @@
+use std::collections::{BTreeMap, HashSet};
+
+use crate::components::chat::message::{MessageData, UIMessage};
+use crate::utils::{seconds_to_timecode, IpfsService};
+
+use wasm_bindgen_futures::spawn_local;
+use web_sys::Element;
+
+use yew::prelude::{html, Component, ComponentLink, Html, Properties, ShouldRender};
+use yew::services::ConsoleService;
+use yew::Callback;
+
+use linked_data::chat::{ArchivedMessage, ChatId, MessageType};
+use linked_data::signature::SignedMessage;
+use linked_data::IPLDLink;
+
+use blockies::Ethereum;
+
+use cid::Cid;
+
+const MAX_MESSAGES: usize = 20;
+
+/// Chat messages archived with a video, replayed in sync with playback.
+pub struct ChatReplay {
+    props: Props,
+
+    chat_cb: Callback<(u32, Vec<(ArchivedMessage, SignedMessage<ChatId>)>)>,
+
+    img_gen: Ethereum,
+
+    chat_element: Option<Element>,
+
+    /// Seconds of video already fetched.
+    fetched: HashSet<u32>,
+
+    next_id: usize,
+    chat_messages: BTreeMap<u32, Vec<MessageData>>,
+}
+
+pub enum Msg {
+    Chat((u32, Vec<(ArchivedMessage, SignedMessage<ChatId>)>)),
+}
+
+#[derive(Properties, Clone)]
+pub struct Props {
+    pub ipfs: IpfsService,
+
+    /// Timecode CID of the video.
+    pub timecode: Cid,
+
+    /// Time of the last loaded segment in seconds.
+    pub segment: f64,
+
+    /// Current playback time in seconds.
+    pub time: f64,
+}
+
+impl Component for ChatReplay {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let img_gen = Ethereum {
+            size: 8,
+            scale: 4,
+            color: None,
+            background_color: None,
+            spot_color: None,
+        };
+
+        let mut comp = Self {
+            props,
+
+            chat_cb: link.callback(Msg::Chat),
+
+            img_gen,
+
+            chat_element: None,
+
+            fetched: HashSet::with_capacity(100),
+
+            next_id: 0,
+            chat_messages: BTreeMap::new(),
+        };
+
+        comp.get_chat();
+
+        #[cfg(debug_assertions)]
+        ConsoleService::info("Chat Replay Created");
+
+        comp
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::Chat((second, messages)) => self.on_chat(second, messages),
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if props.timecode != self.props.timecode {
+            self.props = props;
+
+            self.fetched.clear();
+            self.chat_messages.clear();
+
+            self.get_chat();
+
+            return true;
+        }
+
+        let old_second = timecode_index(self.props.time);
+
+        self.props = props;
+
+        self.get_chat();
+
+        timecode_index(self.props.time) != old_second
+    }
+
+    fn view(&self) -> Html {
+        let current = timecode_index(self.props.time);
+
+        let messages: Vec<&MessageData> = self
+            .chat_messages
+            .range(..=current)
+            .flat_map(|(_, messages)| messages.iter())
+            .collect();
+
+        let start = messages.len().saturating_sub(MAX_MESSAGES);
+
+        html! {
+            <div id="chat_replay" class="box" style="overflow-y: scroll;height: 60vh;scroll-behavior: smooth;" >
+            {
+                for messages[start..].iter().map(|cm| html! {
+                    <UIMessage key=cm.id.to_string() message_data=(*cm).clone() />
+                })
+            }
+            </div>
+        }
+    }
+
+    fn rendered(&mut self, first_render: bool) {
+        if !first_render {
+            if let Some(element) = self.chat_element.as_mut() {
+                element.set_scroll_top(element.scroll_height());
+            }
+
+            return;
+        }
+
+        let window = match web_sys::window() {
+            Some(window) => window,
+            None => {
+                #[cfg(debug_assertions)]
+                ConsoleService::error("No Window Object");
+                return;
+            }
+        };
+
+        let document = match window.document() {
+            Some(document) => document,
+            None => {
+                #[cfg(debug_assertions)]
+                ConsoleService::error("No Document Object");
+                return;
+            }
+        };
+
+        let element = match document.get_element_by_id("chat_replay") {
+            Some(document) => document,
+            None => {
+                #[cfg(debug_assertions)]
+                ConsoleService::error("No Element by Id");
+                return;
+            }
+        };
+
+        self.chat_element = Some(element);
+    }
+}
+
+impl ChatReplay {
+    /// IPFS dag get the chat messages of the last loaded segment.
+    fn get_chat(&mut self) {
+        let index = timecode_index(self.props.segment);
+
+        if !self.fetched.insert(index) {
+            return;
+        }
+
+        let (hours, minutes, seconds) = seconds_to_timecode(self.props.segment);
+
+        spawn_local({
+            let cb = self.chat_cb.clone();
+            let ipfs = self.props.ipfs.clone();
+            let cid = self.props.timecode;
+
+            let path = format!(
+                "/time/hour/{}/minute/{}/second/{}/chat",
+                hours, minutes, seconds
+            );
+
+            async move {
+                let links: Vec<IPLDLink> = match ipfs.dag_get(cid, Some(path)).await {
+                    Ok(links) => links,
+                    Err(e) => {
+                        ConsoleService::error(&format!("{:?}", e));
+                        return;
+                    }
+                };
+
+                let mut messages = Vec::with_capacity(links.len());
+
+                for ipld in links {
+                    let msg: ArchivedMessage =
+                        match ipfs.dag_get(ipld.link, Option::<String>::None).await {
+                            Ok(msg) => msg,
+                            Err(e) => {
+                                ConsoleService::error(&format!("{:?}", e));
+                                continue;
+                            }
+                        };
+
+                    let sign_msg: SignedMessage<ChatId> =
+                        match ipfs.dag_get(msg.sig.link, Option::<String>::None).await {
+                            Ok(sign_msg) => sign_msg,
+                            Err(e) => {
+                                ConsoleService::error(&format!("{:?}", e));
+                                continue;
+                            }
+                        };
+
+                    messages.push((msg, sign_msg));
+                }
+
+                cb.emit((index, messages))
+            }
+        });
+    }
+
+    /// Callback when all messages of a second were fetched.
+    fn on_chat(
+        &mut self,
+        second: u32,
+        mut messages: Vec<(ArchivedMessage, SignedMessage<ChatId>)>,
+    ) -> bool {
+        messages.sort_by_key(|(msg, _)| msg.timestamp);
+
+        let mut chat = Vec::with_capacity(messages.len());
+
+        for (msg, sign_msg) in messages {
+            if msg.peer != sign_msg.data.peer_id || !sign_msg.verify() {
+                #[cfg(debug_assertions)]
+                ConsoleService::info("Verifiable => false");
+
+                continue;
+            }
+
+            let text = match msg.msg {
+                MessageType::Chat(text) => text,
+                _ => continue,
+            };
+
+            let mut data = Vec::new();
+
+            if let Err(e) = self.img_gen.create_icon(&mut data, &sign_msg.address) {
+                ConsoleService::error(&format!("{:?}", e));
+            }
+
+            chat.push(MessageData::new(
+                self.next_id,
+                &data,
+                &sign_msg.data.name,
+                &text,
+            ));
+
+            self.next_id += 1;
+        }
+
+        if chat.is_empty() {
+            return false;
+        }
+
+        self.chat_messages.insert(second, chat);
+
+        #[cfg(debug_assertions)]
+        ConsoleService::info("Chat Replay Updated");
+
+        second <= timecode_index(self.props.time)
+    }
+}
+
+/// Total number of seconds, rounded like timecodes are.
+fn timecode_index(seconds: f64) -> u32 {
+    let (hours, minutes, seconds) = seconds_to_timecode(seconds);
+
+    hours as u32 * 3600 + minutes as u32 * 60 + seconds as u32
+}
@@ -6,6 +6,7 @@ use crate::utils::{IpfsService, LocalStorage, Web3Service};
 
 use yew::prelude::{html, Component, ComponentLink, Html, Properties, ShouldRender};
 
+use linked_data::filter::Blocklist;
 use linked_data::live::Live;
 use linked_data::moderation::{Bans, Moderators};
 
@@ -18,6 +19,7 @@ pub struct ChatWindow {
     pub live: Rc<Live>,
     pub mods: Rc<Moderators>,
     pub bans: Rc<Bans>,
+    pub filters: Rc<Blocklist>,
 }
 
 impl Component for ChatWindow {
@@ -36,6 +38,7 @@ impl Component for ChatWindow {
         if !Rc::ptr_eq(&self.live, &props.live)
             || !Rc::ptr_eq(&self.mods, &props.mods)
             || !Rc::ptr_eq(&self.bans, &props.bans)
+            || !Rc::ptr_eq(&self.filters, &props.filters)
         {
             *self = props;
 
@@ -48,7 +51,7 @@ impl Component for ChatWindow {
     fn view(&self) -> Html {
         html! {
         <>
-            <Display ipfs=self.ipfs.clone() live=self.live.clone() bans=self.bans.clone() mods=self.mods.clone() />
+            <Display ipfs=self.ipfs.clone() live=self.live.clone() bans=self.bans.clone() mods=self.mods.clone() filters=self.filters.clone() />
             <Inputs ipfs=self.ipfs.clone() web3=self.web3.clone() storage=self.storage.clone() live=self.live.clone() />
         </>
         }
@@ -11,6 +11,7 @@ pub struct Comment {
     pub cid: Cid,
     pub name: Rc<str>,
     pub comment: Rc<linked_data::comments::Comment>,
+    pub verified: bool,
 }
 
 impl Component for Comment {
@@ -26,7 +27,10 @@ impl Component for Comment {
     }
 
     fn change(&mut self, props: Self::Properties) -> ShouldRender {
-        if !Rc::ptr_eq(&self.comment, &props.comment) || !Rc::ptr_eq(&self.name, &props.name) {
+        if !Rc::ptr_eq(&self.comment, &props.comment)
+            || !Rc::ptr_eq(&self.name, &props.name)
+            || self.verified != props.verified
+        {
             *self = props;
 
             return true;
@@ -38,6 +42,12 @@ impl Component for Comment {
     fn view(&self) -> Html {
         let dt = crate::utils::timestamp_to_datetime(self.comment.timestamp);
 
+        let badge = if self.verified {
+            html! { <span class="tag is-success"> { "Verified" } </span> }
+        } else {
+            html! { <span class="tag is-light"> { "Unverified" } </span> }
+        };
+
         html! {
             <ybc::Box>
                 <ybc::Media>
@@ -54,6 +64,9 @@ impl Component for Comment {
                                 <span> { dt } </span>
                             </span>
                         </ybc::Block>
+                        <ybc::Block>
+                            { badge }
+                        </ybc::Block>
                         <ybc::Block>
                             <ExploreCid cid=self.cid />
                         </ybc::Block>
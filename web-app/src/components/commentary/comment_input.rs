@@ -0,0 +1,288 @@
+use std::rc::Rc;
+use std::str::FromStr;
+
+use crate::utils::{IpfsService, LocalStorage, Web3Service};
+
+use wasm_bindgen_futures::spawn_local;
+
+use yew::prelude::{classes, html, Component, ComponentLink, Html, Properties, ShouldRender};
+use yew::services::ConsoleService;
+
+use linked_data::beacon::Beacon;
+use linked_data::comments::{Comment, Commentary};
+use linked_data::feed::ContentCache;
+use linked_data::signature::{SignatureScheme, SignedMessage};
+
+use cid::Cid;
+
+const AUTHOR_KEY: &str = "comment_author";
+const COMMENTS_KEY: &str = "comments";
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+enum DisplayState {
+    Editing,
+    Posting,
+    Posted(Cid),
+    Error(String),
+}
+
+/// Sign a comment and add it to the comment list of the local IPFS node.
+pub struct CommentInput {
+    props: Props,
+    link: ComponentLink<Self>,
+
+    state: DisplayState,
+
+    author: String,
+    comment: String,
+}
+
+#[derive(Properties, Clone)]
+pub struct Props {
+    pub ipfs: IpfsService,
+    pub web3: Web3Service,
+    pub storage: LocalStorage,
+
+    /// Content being commented on.
+    pub origin: Cid,
+
+    pub content: Rc<ContentCache>,
+}
+
+pub enum Msg {
+    SetAuthor(String),
+    SetComment(String),
+    Post,
+    Posted(Result<Cid>),
+}
+
+impl Component for CommentInput {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let author = match props.storage.get_cid(AUTHOR_KEY) {
+            Some(cid) => cid.to_string(),
+            None => String::default(),
+        };
+
+        #[cfg(debug_assertions)]
+        ConsoleService::info("Comment Input Created");
+
+        Self {
+            props,
+            link,
+
+            state: DisplayState::Editing,
+
+            author,
+            comment: String::default(),
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::SetAuthor(author) => self.on_author_input(author),
+            Msg::SetComment(comment) => self.on_comment_input(comment),
+            Msg::Post => self.post_comment(),
+            Msg::Posted(res) => self.on_posted(res),
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if props.origin != self.props.origin {
+            self.props = props;
+            self.state = DisplayState::Editing;
+
+            return true;
+        }
+
+        self.props = props;
+
+        false
+    }
+
+    fn view(&self) -> Html {
+        let status = match &self.state {
+            DisplayState::Editing => html! {},
+            DisplayState::Posting => html! { <p class="help"> { "Posting..." } </p> },
+            DisplayState::Posted(cid) => {
+                html! { <p class="help is-success"> { format!("Comment {} Posted", cid) } </p> }
+            }
+            DisplayState::Error(e) => html! { <p class="help is-danger"> { e } </p> },
+        };
+
+        html! {
+            <ybc::Section>
+                <ybc::Container>
+                    <ybc::Box>
+                        <ybc::Field label="Your Beacon CID".to_owned() >
+                            <ybc::Control>
+                                <ybc::Input name="comment_author" value=self.author.clone() update=self.link.callback(Msg::SetAuthor) />
+                            </ybc::Control>
+                        </ybc::Field>
+                        <ybc::Field>
+                            <ybc::Control>
+                                <ybc::TextArea name="comment" value=String::default() update=self.link.callback(Msg::SetComment) rows=3 fixed_size=true />
+                            </ybc::Control>
+                        </ybc::Field>
+                        <ybc::Field>
+                            <ybc::Control>
+                                <ybc::Button classes=classes!("is-primary") loading={matches!(self.state, DisplayState::Posting)} onclick=self.link.callback(|_| Msg::Post) >
+                                    { "Sign & Post" }
+                                </ybc::Button>
+                            </ybc::Control>
+                            { status }
+                        </ybc::Field>
+                    </ybc::Box>
+                </ybc::Container>
+            </ybc::Section>
+        }
+    }
+}
+
+impl CommentInput {
+    fn on_author_input(&mut self, author: String) -> bool {
+        self.author = author;
+
+        false
+    }
+
+    fn on_comment_input(&mut self, comment: String) -> bool {
+        self.comment = comment;
+
+        false
+    }
+
+    fn post_comment(&mut self) -> bool {
+        if matches!(self.state, DisplayState::Posting) {
+            return false;
+        }
+
+        let author = match Cid::from_str(self.author.trim()) {
+            Ok(cid) => cid,
+            Err(e) => {
+                self.state = DisplayState::Error(format!("Invalid Beacon CID. {}", e));
+                return true;
+            }
+        };
+
+        let comment = self.comment.trim().to_owned();
+
+        if comment.is_empty() {
+            return false;
+        }
+
+        self.props.storage.set_cid(AUTHOR_KEY, &author);
+
+        let comment = Comment::create(author, self.props.origin, comment);
+
+        let creator = self
+            .props
+            .content
+            .media_content_beacon(&self.props.origin)
+            .copied();
+
+        spawn_local({
+            let cb = self.link.callback_once(Msg::Posted);
+            let ipfs = self.props.ipfs.clone();
+            let web3 = self.props.web3.clone();
+
+            async move {
+                let result = sign_and_publish(ipfs.clone(), web3, comment).await;
+
+                if let (Ok(comment), Some(beacon)) = (result.as_ref(), creator) {
+                    if let Err(e) = announce(&ipfs, beacon, *comment).await {
+                        ConsoleService::error(&format!("{:?}", e));
+                    }
+                }
+
+                cb.emit(result)
+            }
+        });
+
+        self.state = DisplayState::Posting;
+
+        true
+    }
+
+    fn on_posted(&mut self, response: Result<Cid>) -> bool {
+        self.state = match response {
+            Ok(cid) => {
+                self.comment.clear();
+
+                DisplayState::Posted(cid)
+            }
+            Err(e) => {
+                ConsoleService::error(&format!("{:?}", e));
+
+                DisplayState::Error(e.to_string())
+            }
+        };
+
+        true
+    }
+}
+
+/// Sign the comment as typed data then update the comment list.
+async fn sign_and_publish(ipfs: IpfsService, web3: Web3Service, data: Comment) -> Result<Cid> {
+    let address = web3.get_eth_accounts().await?;
+
+    let signature = web3.eth_sign_typed_data(address, &data).await?;
+
+    let signed_comment = SignedMessage {
+        address: address.to_fixed_bytes(),
+        data,
+        signature: signature.to_vec(),
+        scheme: SignatureScheme::Eip712,
+    };
+
+    let origin = signed_comment.data.origin.link;
+    let reply_to = signed_comment.data.reply_to.map(|ipld| ipld.link);
+
+    let comment_cid = ipfs.dag_put(&signed_comment).await?;
+
+    ipfs.pin_add(comment_cid, false).await?;
+
+    let keys = ipfs.key_list().await?;
+
+    let ipns = *keys.get(COMMENTS_KEY).ok_or("Comments Key Not Found")?;
+
+    let (old_list_cid, mut list) = ipfs.resolve_and_dag_get::<Commentary>(ipns).await?;
+
+    let changes = list
+        .add_comment(&ipfs, origin, comment_cid, reply_to)
+        .await?;
+
+    for cid in changes.added {
+        ipfs.pin_add(cid, false).await?;
+    }
+
+    let list_cid = ipfs.dag_put(&list).await?;
+
+    ipfs.pin_add(list_cid, false).await?;
+
+    ipfs.name_publish(list_cid, COMMENTS_KEY).await?;
+
+    for cid in changes
+        .removed
+        .into_iter()
+        .chain(std::iter::once(old_list_cid))
+    {
+        if let Err(e) = ipfs.pin_rm(cid, false).await {
+            ConsoleService::error(&format!("{:?}", e));
+        }
+    }
+
+    Ok(comment_cid)
+}
+
+/// Publish the comment CID on the creator's comments topic.
+async fn announce(ipfs: &IpfsService, beacon: Cid, comment: Cid) -> Result<()> {
+    let beacon: Beacon = ipfs.dag_get(beacon, Option::<&str>::None).await?;
+
+    let topic = beacon.comments_topic.ok_or("No Comments Topic")?;
+
+    ipfs.pubsub_pub(topic, comment.to_string()).await
+}
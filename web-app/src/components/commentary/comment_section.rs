@@ -11,19 +11,30 @@ use yew::Callback;
 
 use linked_data::comments::Comment;
 use linked_data::feed::ContentCache;
+use linked_data::signature::SignedMessage;
 
 use cid::Cid;
 
+use serde::Deserialize;
+
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
+/// Older comments are not signed.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum CommentNode {
+    Signed(SignedMessage<Comment>),
+    Unsigned(Comment),
+}
+
 /// Page displaying the content of any media.
 pub struct CommentSection {
     props: Props,
 
-    comments_cb: Callback<(Cid, Result<Comment>)>,
+    comments_cb: Callback<(Cid, Result<CommentNode>)>,
 
     comments_set: HashSet<Cid>,
-    comments: Vec<(Cid, Rc<str>, Rc<Comment>)>,
+    comments: Vec<(Cid, Rc<str>, Rc<Comment>, bool)>,
 }
 
 #[derive(Clone, Properties)]
@@ -36,7 +47,7 @@ pub struct Props {
 }
 
 pub enum Msg {
-    Comment((Cid, Result<Comment>)),
+    Comment((Cid, Result<CommentNode>)),
 }
 
 impl Component for CommentSection {
@@ -72,21 +83,21 @@ impl Component for CommentSection {
             self.props = props;
 
             self.get_comments();
+
+            // Comments may have been hidden or removed.
+            return true;
         }
 
         false
     }
 
     fn view(&self) -> Html {
-        use crate::components::Comment;
-
         html! {
             <ybc::Section>
                 <ybc::Container>
                 {
-                    for self.comments.iter().rev().map(|(cid, name, comment)| {
-                        html! { <Comment cid=*cid name=name.clone() comment=comment.clone() /> }
-                    })
+                    // Newest threads first, replies are not shown without their parent.
+                    for self.comments.iter().rev().filter(|(cid, ..)| self.is_thread_root(cid)).map(|(cid, ..)| self.render_thread(cid))
                 }
                 </ybc::Container>
             </ybc::Section>
@@ -95,8 +106,53 @@ impl Component for CommentSection {
 }
 
 impl CommentSection {
+    fn is_thread_root(&self, cid: &Cid) -> bool {
+        if self.props.content.is_comment_hidden(cid) {
+            return false;
+        }
+
+        match self.props.content.comment_parent(cid) {
+            // Replies to hidden comments are hidden too.
+            Some(parent) if self.props.content.is_comment_hidden(parent) => false,
+            Some(parent) => !self.comments.iter().any(|(probe, ..)| probe == parent),
+            None => true,
+        }
+    }
+
+    /// Render a comment followed by its replies, oldest first.
+    fn render_thread(&self, cid: &Cid) -> Html {
+        use crate::components::Comment;
+
+        let (name, comment, verified) = match self.comments.iter().find(|(probe, ..)| probe == cid)
+        {
+            Some((_, name, comment, verified)) => (name, comment, verified),
+            None => return html! {},
+        };
+
+        let replies: Vec<&Cid> = self.props.content.iter_replies(cid).collect();
+
+        html! {
+            <>
+                <Comment cid=*cid name=name.clone() comment=comment.clone() verified=*verified />
+                <div class="ml-6">
+                {
+                    for self.comments.iter().filter(|(probe, ..)| replies.contains(&probe) && !self.props.content.is_comment_hidden(probe)).map(|(reply, ..)| self.render_thread(reply))
+                }
+                </div>
+            </>
+        }
+    }
+
     /// IPFS dag get all comments starting by newest.
     fn get_comments(&mut self) {
+        let cache = self.props.content.clone();
+
+        // Drop comments deleted by their author.
+        self.comments_set
+            .retain(|cid| cache.comment_beacon(cid).is_some());
+        self.comments
+            .retain(|(cid, ..)| cache.comment_beacon(cid).is_some());
+
         if let Some(iterator) = self.props.content.iter_comments(&self.props.cid) {
             for ipld in iterator {
                 if self.comments_set.insert(*ipld) {
@@ -112,8 +168,8 @@ impl CommentSection {
         }
     }
 
-    fn on_comment(&mut self, response: (Cid, Result<Comment>)) -> bool {
-        let (cid, comment) = match response {
+    fn on_comment(&mut self, response: (Cid, Result<CommentNode>)) -> bool {
+        let (cid, node) = match response {
             (cid, Ok(node)) => (cid, node),
             (_, Err(e)) => {
                 ConsoleService::error(&format!("{:?}", e));
@@ -121,9 +177,14 @@ impl CommentSection {
             }
         };
 
-        /* if !signed_comment.verify() {
-            return false;
-        } */
+        let (comment, verified) = match node {
+            CommentNode::Signed(signed) => {
+                let verified = self.is_verified(&cid, &signed);
+
+                (signed.data, verified)
+            }
+            CommentNode::Unsigned(comment) => (comment, false),
+        };
 
         let name = match self.props.content.comment_author(&cid) {
             Some(name) => name,
@@ -132,15 +193,28 @@ impl CommentSection {
 
         let index = self
             .comments
-            .binary_search_by_key(&comment.timestamp, |(_, _, probe)| probe.timestamp)
+            .binary_search_by_key(&comment.timestamp, |(_, _, probe, _)| probe.timestamp)
             .unwrap_or_else(|x| x);
 
         self.comments
-            .insert(index, (cid, Rc::from(name), Rc::from(comment)));
+            .insert(index, (cid, Rc::from(name), Rc::from(comment), verified));
 
         #[cfg(debug_assertions)]
         ConsoleService::info("Content Comments Updated");
 
         true
     }
+
+    /// Signed by the address of the author identity and published by the author beacon.
+    fn is_verified(&self, cid: &Cid, signed: &SignedMessage<Comment>) -> bool {
+        if !signed.verify() {
+            return false;
+        }
+
+        if self.props.content.comment_author_address(cid) != Some(&signed.address) {
+            return false;
+        }
+
+        self.props.content.comment_beacon(cid) == Some(&signed.data.author.link)
+    }
 }
@@ -1,5 +1,7 @@
 mod comment;
+mod comment_input;
 mod comment_section;
 
 pub use comment::Comment;
+pub use comment_input::CommentInput;
 pub use comment_section::CommentSection;
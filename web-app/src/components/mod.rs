@@ -10,9 +10,9 @@ mod navbar;
 mod thumbnail;
 mod video_player;
 
-pub use chat::ChatWindow;
+pub use chat::{ChatReplay, ChatWindow};
 //pub use cid_clipboard::CidClipboard;
-pub use commentary::{Comment, CommentSection};
+pub use commentary::{Comment, CommentInput, CommentSection};
 pub use errors::{IPFSConnectionError, IPFSPubSubError};
 pub use explore_cid::ExploreCid;
 pub use image::Image;
@@ -20,4 +20,4 @@ pub use loading::Loading;
 pub use md_renderer::Markdown;
 pub use navbar::Navbar;
 pub use thumbnail::Thumbnail;
-pub use video_player::VideoPlayer;
+pub use video_player::{PlaybackEvent, VideoPlayer};
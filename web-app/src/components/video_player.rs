@@ -19,8 +19,10 @@ use yew::prelude::{classes, html, Component, ComponentLink, Html, Properties, Sh
 use yew::services::ConsoleService;
 use yew::Callback;
 
-use linked_data::live::Live;
+use linked_data::live::{Live, VideoAnnouncement};
+use linked_data::signature::SignedMessage;
 use linked_data::video::{SetupNode, Track, VideoMetadata};
+use linked_data::Address;
 
 use either::Either;
 
@@ -55,13 +57,87 @@ struct LiveStream {
     pubsub_cb: Callback<Result<(String, Vec<u8>)>>,
     buffer: VecDeque<Cid>,
 
+    /// Timestamp and sequence of the last signed announcement.
+    last_announcement: Option<(u64, u64)>,
+
     handle: AbortHandle,
 }
 
+impl LiveStream {
+    /// Video node CID if the announcement was signed by this address and is newer than the last one.
+    fn signed_announcement(&mut self, address: Address, data: &[u8]) -> Option<Cid> {
+        let signed: SignedMessage<VideoAnnouncement> = match serde_json::from_slice(data) {
+            Ok(signed) => signed,
+            Err(e) => {
+                ConsoleService::error(&format!("{:?}", e));
+                return None;
+            }
+        };
+
+        if signed.address != address || !signed.verify() {
+            #[cfg(debug_assertions)]
+            ConsoleService::warn("Unauthorized Announcement");
+            return None;
+        }
+
+        // Timestamp first since sequences restart with each stream.
+        let order = (signed.data.timestamp, signed.data.sequence);
+
+        if matches!(self.last_announcement, Some(last) if order <= last) {
+            #[cfg(debug_assertions)]
+            ConsoleService::warn("Outdated Announcement");
+            return None;
+        }
+
+        self.last_announcement = Some(order);
+
+        Some(signed.data.video.link)
+    }
+
+    /// Video node CID if sent by the streaming node.
+    fn sender_announcement(&self, from: &str, data: &[u8]) -> Option<Cid> {
+        if from != self.data.peer_id {
+            #[cfg(debug_assertions)]
+            ConsoleService::warn("Unauthorized Sender");
+            return None;
+        }
+
+        let data = match str::from_utf8(data) {
+            Ok(data) => data,
+            Err(e) => {
+                ConsoleService::error(&format!("{:?}", e));
+                return None;
+            }
+        };
+
+        #[cfg(debug_assertions)]
+        ConsoleService::info(&format!("Message => {}", data));
+
+        match Cid::from_str(data) {
+            Ok(cid) => Some(cid),
+            Err(e) => {
+                ConsoleService::error(&format!("{:?}", e));
+                None
+            }
+        }
+    }
+}
+
+/// Progress of on demand playback in seconds.
+pub enum PlaybackEvent {
+    /// Timecode of a segment being loaded.
+    Segment(f64),
+
+    /// Current time of the video element.
+    Time(f64),
+}
+
 /// Video player for live streams and on demand.
 pub struct VideoPlayer {
     ipfs: IpfsService,
 
+    playback_cb: Option<Callback<PlaybackEvent>>,
+
     error: bool,
 
     player_type: Either<LiveStream, Rc<VideoMetadata>>,
@@ -105,6 +181,9 @@ pub enum Msg {
 pub struct Props {
     pub ipfs: IpfsService,
     pub beacon_or_metadata: Either<Rc<Live>, Rc<VideoMetadata>>,
+
+    #[prop_or_default]
+    pub on_playback: Option<Callback<PlaybackEvent>>,
 }
 
 impl Component for VideoPlayer {
@@ -115,6 +194,7 @@ impl Component for VideoPlayer {
         let Props {
             ipfs,
             beacon_or_metadata,
+            on_playback,
         } = props;
 
         let ema = ExponentialMovingAverage::new();
@@ -148,6 +228,7 @@ impl Component for VideoPlayer {
                     data,
                     pubsub_cb: link.callback(Msg::PubSub),
                     buffer: VecDeque::with_capacity(5),
+                    last_announcement: None,
                     handle,
                 };
 
@@ -169,6 +250,8 @@ impl Component for VideoPlayer {
         Self {
             ipfs,
 
+            playback_cb: on_playback,
+
             error: false,
 
             player_type,
@@ -385,29 +468,14 @@ impl VideoPlayer {
         #[cfg(debug_assertions)]
         ConsoleService::info(&format!("Sender => {}", from));
 
-        if from != live.data.peer_id {
-            #[cfg(debug_assertions)]
-            ConsoleService::warn("Unauthorized Sender");
-            return false;
-        }
-
-        let data = match str::from_utf8(&data) {
-            Ok(data) => data,
-            Err(e) => {
-                ConsoleService::error(&format!("{:?}", e));
-                return false;
-            }
+        let cid = match live.data.address {
+            Some(address) => live.signed_announcement(address, &data),
+            None => live.sender_announcement(&from, &data),
         };
 
-        #[cfg(debug_assertions)]
-        ConsoleService::info(&format!("Message => {}", data));
-
-        let cid = match Cid::from_str(data) {
-            Ok(cid) => cid,
-            Err(e) => {
-                ConsoleService::error(&format!("{:?}", e));
-                return false;
-            }
+        let cid = match cid {
+            Some(cid) => cid,
+            None => return false,
         };
 
         live.buffer.push_back(cid);
@@ -437,6 +505,10 @@ impl VideoPlayer {
         #[cfg(debug_assertions)]
         ConsoleService::info("On Seeking");
 
+        if let (Some(cb), Some(media_element)) = (&self.playback_cb, &self.media_element) {
+            cb.emit(PlaybackEvent::Time(media_element.current_time()));
+        }
+
         self.state = MachineState::Flush;
     }
 
@@ -718,9 +790,12 @@ impl VideoPlayer {
                 }
             };
 
-            if current_time > 1.0 {
-                buff_end = current_time - 1.0;
-            }
+            // Every second of the timecode links the segment covering it.
+            buff_end = current_time.trunc();
+        }
+
+        if let Some(cb) = self.playback_cb.as_ref() {
+            cb.emit(PlaybackEvent::Segment(buff_end));
         }
 
         let (hours, minutes, seconds) = seconds_to_timecode(buff_end);
@@ -867,6 +942,10 @@ impl VideoPlayer {
             }
         };
 
+        if let Some(cb) = self.playback_cb.as_ref() {
+            cb.emit(PlaybackEvent::Time(current_time));
+        }
+
         if current_time < buff_start {
             let new_time = buff_start + ((buff_end - buff_start) / 2.0);
 
@@ -890,7 +969,8 @@ impl VideoPlayer {
         }
 
         if let Either::Right(metadata) = &self.player_type {
-            if buff_end >= metadata.duration {
+            // Buffered ranges are not exactly on the second.
+            if buff_end.round() >= metadata.duration.round() {
                 #[cfg(debug_assertions)]
                 ConsoleService::info("End Of Video");
                 return;
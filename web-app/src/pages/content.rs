@@ -1,9 +1,10 @@
 use std::rc::Rc;
 
 use crate::components::{
-    CommentSection, ExploreCid, IPFSConnectionError, Image, Loading, Markdown, Navbar, VideoPlayer,
+    ChatReplay, CommentInput, CommentSection, ExploreCid, IPFSConnectionError, Image, Loading,
+    Markdown, Navbar, PlaybackEvent, VideoPlayer,
 };
-use crate::utils::{timestamp_to_datetime, IpfsService};
+use crate::utils::{timestamp_to_datetime, IpfsService, LocalStorage, Web3Service};
 
 use wasm_bindgen_futures::spawn_local;
 
@@ -33,22 +34,35 @@ pub struct Content {
     props: Props,
 
     content_cb: Callback<Result<Media>>,
+    playback_cb: Callback<PlaybackEvent>,
 
     state: State,
     author: Rc<str>,
+
+    /// Timecode of the last video segment loaded.
+    segment: f64,
+
+    /// Current video playback time.
+    time: f64,
 }
 
 #[derive(Clone, Properties)]
 pub struct Props {
     pub ipfs: IpfsService,
+    pub web3: Web3Service,
+    pub storage: LocalStorage,
 
     pub cid: Cid,
 
     pub content: Rc<ContentCache>,
+
+    /// Request the comments on this content.
+    pub view: Callback<Cid>,
 }
 
 pub enum Msg {
     Metadata(Result<Media>),
+    Playback(PlaybackEvent),
 }
 
 impl Component for Content {
@@ -60,13 +74,19 @@ impl Component for Content {
             props,
 
             content_cb: link.callback(Msg::Metadata),
+            playback_cb: link.callback(Msg::Playback),
 
             state: State::Loading,
             author: Rc::from(String::default()),
+
+            segment: 0.0,
+            time: 0.0,
         };
 
         comp.get_content();
 
+        comp.props.view.emit(comp.props.cid);
+
         #[cfg(debug_assertions)]
         ConsoleService::info("Content Page Created");
 
@@ -76,10 +96,15 @@ impl Component for Content {
     fn update(&mut self, msg: Self::Message) -> ShouldRender {
         match msg {
             Msg::Metadata(result) => self.on_metadata(result),
+            Msg::Playback(event) => self.on_playback(event),
         }
     }
 
     fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if props.cid != self.props.cid {
+            props.view.emit(props.cid);
+        }
+
         if !Rc::ptr_eq(&props.content, &self.props.content) {
             #[cfg(debug_assertions)]
             {
@@ -119,6 +144,7 @@ impl Component for Content {
                     }
                     </ybc::Container>
                 </ybc::Section>
+                <CommentInput ipfs=self.props.ipfs.clone() web3=self.props.web3.clone() storage=self.props.storage.clone() origin=self.props.cid content=self.props.content.clone() />
                 <CommentSection ipfs=self.props.ipfs.clone() cid=self.props.cid content=self.props.content.clone() />
             </>
         }
@@ -132,7 +158,14 @@ impl Content {
                 <ybc::Title>
                     { &metadata.title }
                 </ybc::Title>
-                <VideoPlayer ipfs=self.props.ipfs.clone() beacon_or_metadata=Either::Right(Rc::from(metadata.clone()))/*TODO find a way to fix this weird clonning issue*/ />
+                <ybc::Columns>
+                    <ybc::Column>
+                        <VideoPlayer ipfs=self.props.ipfs.clone() beacon_or_metadata=Either::Right(Rc::from(metadata.clone()))/*TODO find a way to fix this weird clonning issue*/ on_playback=Some(self.playback_cb.clone()) />
+                    </ybc::Column>
+                    <ybc::Column classes=classes!("is-one-fifth") >
+                        <ChatReplay ipfs=self.props.ipfs.clone() timecode=metadata.video.link segment=self.segment time=self.time />
+                    </ybc::Column>
+                </ybc::Columns>
                 <ybc::Level>
                     <ybc::LevelLeft>
                         <ybc::LevelItem>
@@ -227,6 +260,9 @@ impl Content {
     }
 
     fn get_content(&mut self) {
+        self.segment = 0.0;
+        self.time = 0.0;
+
         spawn_local({
             let cb = self.content_cb.clone();
             let ipfs = self.props.ipfs.clone();
@@ -241,6 +277,15 @@ impl Content {
         };
     }
 
+    fn on_playback(&mut self, event: PlaybackEvent) -> bool {
+        match event {
+            PlaybackEvent::Segment(time) => self.segment = time,
+            PlaybackEvent::Time(time) => self.time = time,
+        }
+
+        true
+    }
+
     fn on_metadata(&mut self, response: Result<Media>) -> bool {
         self.state = match response {
             Ok(md) => State::Ready(md),
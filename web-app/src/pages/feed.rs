@@ -4,6 +4,8 @@ use std::rc::Rc;
 use crate::components::{IPFSConnectionError, Loading, Navbar, Thumbnail};
 use crate::utils::{IpfsService, LocalStorage};
 
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::JsCast;
 use wasm_bindgen_futures::spawn_local;
 
 use yew::prelude::{classes, html, Component, ComponentLink, Html, Properties, ShouldRender};
@@ -16,6 +18,9 @@ use cid::Cid;
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
+/// Distance in pixels from the bottom of the page at which older content is loaded.
+const SCROLL_THRESHOLD: f64 = 500.0;
+
 pub enum MachineState {
     Connecting,
     Loading,
@@ -45,11 +50,14 @@ pub struct ContentFeed {
     blog_filter_cb: Callback<MouseEvent>,
     micro_filter_cb: Callback<MouseEvent>,
     filter: FilterType,
+
+    scroll_closure: Option<Closure<dyn Fn()>>,
 }
 
 pub enum Msg {
     Metadata((Cid, Result<Media>)),
     Filter(FilterType),
+    Scroll,
 }
 
 #[derive(Properties, Clone)]
@@ -58,6 +66,9 @@ pub struct Props {
     pub storage: LocalStorage,
     pub content: Rc<ContentCache>,
     pub peer_id: Rc<Option<String>>,
+
+    /// Request older pages of the content feeds.
+    pub load_more: Callback<()>,
 }
 
 impl Component for ContentFeed {
@@ -80,8 +91,18 @@ impl Component for ContentFeed {
             micro_filter_cb: link
                 .callback(|_event: MouseEvent| Msg::Filter(FilterType::Statements)),
             filter: FilterType::None,
+
+            scroll_closure: None,
         };
 
+        if let Some(window) = web_sys::window() {
+            let cb = link.callback(|_| Msg::Scroll);
+            let closure = Closure::wrap(Box::new(move || cb.emit(())) as Box<dyn Fn()>);
+            window.set_onscroll(Some(closure.as_ref().unchecked_ref()));
+
+            feed.scroll_closure = Some(closure);
+        }
+
         feed.get_content();
 
         feed
@@ -99,6 +120,7 @@ impl Component for ContentFeed {
 
                 false
             }
+            Msg::Scroll => self.on_scroll(),
         }
     }
 
@@ -113,6 +135,9 @@ impl Component for ContentFeed {
             self.props = props;
 
             self.get_content();
+
+            // Show or hide the load more button.
+            return matches!(self.state, MachineState::Loaded);
         }
 
         false
@@ -165,14 +190,44 @@ impl Component for ContentFeed {
                             </li>
                         </ybc::Tabs>
                         { content }
+                        { self.render_load_more() }
                     </ybc::Container>
                 </ybc::Section>
             </>
         }
     }
+
+    fn destroy(&mut self) {
+        if let Some(window) = web_sys::window() {
+            window.set_onscroll(None);
+        }
+
+        self.scroll_closure = None;
+    }
 }
 
 impl ContentFeed {
+    /// Fallback for when the content does not fill the page and it can't be scrolled.
+    fn render_load_more(&self) -> Html {
+        if !matches!(self.state, MachineState::Loaded)
+            || self.props.content.iter_older_pages().next().is_none()
+        {
+            return html! {};
+        }
+
+        let cb = self.props.load_more.reform(|_: MouseEvent| ());
+
+        html! {
+            <ybc::Level>
+                <ybc::LevelItem>
+                    <ybc::Button onclick=cb >
+                        { "Load More" }
+                    </ybc::Button>
+                </ybc::LevelItem>
+            </ybc::Level>
+        }
+    }
+
     fn render_thumbnails(&self) -> Html {
         html! {
             <>
@@ -193,6 +248,14 @@ impl ContentFeed {
 
     /// IPFS dag get all metadata from content feed starting by newest.
     fn get_content(&mut self) {
+        let cache = self.props.content.clone();
+
+        // Drop content removed from the feeds.
+        self.content_set
+            .retain(|cid| cache.media_content_beacon(cid).is_some());
+        self.content
+            .retain(|(cid, ..)| cache.media_content_beacon(cid).is_some());
+
         for cid in self.props.content.iter_media_content() {
             if self.content_set.insert(*cid) {
                 spawn_local({
@@ -206,6 +269,32 @@ impl ContentFeed {
         }
     }
 
+    /// Request older content when the bottom of the page is near.
+    fn on_scroll(&self) -> bool {
+        let window = match web_sys::window() {
+            Some(window) => window,
+            None => return false,
+        };
+
+        let element = match window.document().and_then(|doc| doc.document_element()) {
+            Some(element) => element,
+            None => return false,
+        };
+
+        let scroll_y = window.scroll_y().unwrap_or_default();
+
+        let height = match window.inner_height().ok().and_then(|value| value.as_f64()) {
+            Some(height) => height,
+            None => return false,
+        };
+
+        if scroll_y + height >= element.scroll_height() as f64 - SCROLL_THRESHOLD {
+            self.props.load_more.emit(());
+        }
+
+        false
+    }
+
     /// Callback when IPFS dag get returns a Media node.
     fn on_metadata(&mut self, response: (Cid, Result<Media>)) -> bool {
         let (cid, metadata) = match response {
@@ -8,6 +8,7 @@ use yew::prelude::{classes, html, Component, ComponentLink, Html, Properties, Sh
 #[cfg(debug_assertions)]
 use yew::services::ConsoleService;
 
+use linked_data::filter::Blocklist;
 use linked_data::live::Live;
 use linked_data::moderation::{Bans, Moderators};
 
@@ -23,6 +24,7 @@ pub struct LivePage {
     pub live: Rc<Live>,
     pub mods: Rc<Moderators>,
     pub bans: Rc<Bans>,
+    pub filters: Rc<Blocklist>,
 }
 
 impl Component for LivePage {
@@ -44,6 +46,7 @@ impl Component for LivePage {
         if !Rc::ptr_eq(&props.live, &self.live)
             || !Rc::ptr_eq(&props.bans, &self.bans)
             || !Rc::ptr_eq(&props.mods, &self.mods)
+            || !Rc::ptr_eq(&props.filters, &self.filters)
             || !Rc::ptr_eq(&props.peer_id, &self.peer_id)
         {
             *self = props;
@@ -74,7 +77,7 @@ impl Component for LivePage {
                                 </ybc::Box>
                             </ybc::Column>
                             <ybc::Column classes=classes!("is-one-fifth") >
-                                <ChatWindow ipfs=self.ipfs.clone() web3=self.web3.clone() storage=self.storage.clone() live=self.live.clone() bans=self.bans.clone() mods=self.mods.clone() />
+                                <ChatWindow ipfs=self.ipfs.clone() web3=self.web3.clone() storage=self.storage.clone() live=self.live.clone() bans=self.bans.clone() mods=self.mods.clone() filters=self.filters.clone() />
                             </ybc::Column>
                         </ybc::Columns>
                         }
@@ -1,5 +1,6 @@
 use core::fmt;
 use std::borrow::Cow;
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::rc::Rc;
 
@@ -10,6 +11,9 @@ use futures::join;
 use futures::stream::AbortRegistration;
 use futures_util::{AsyncBufReadExt, TryStreamExt};
 
+use linked_data::dag_cbor;
+use linked_data::ipfs::{self, IpfsError, PubSubMessage};
+
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 
@@ -19,7 +23,7 @@ use yew::Callback;
 use cid::multibase::Base;
 use cid::Cid;
 
-use reqwest::multipart::Form;
+use reqwest::multipart::{Form, Part};
 use reqwest::{Client, Url};
 
 pub const DEFAULT_URI: &str = "http://127.0.0.1:5001/api/v0/";
@@ -106,7 +110,7 @@ impl IpfsService {
         Ok((audio_data.to_vec(), video_data.to_vec()))
     }
 
-    /// Serialize then add dag node to IPFS. Return a CID.
+    /// Serialize to DAG-CBOR then add dag node to IPFS. Return a CID.
     pub async fn dag_put<T>(&self, node: &T) -> Result<Cid>
     where
         T: ?Sized + Serialize,
@@ -117,14 +121,19 @@ impl IpfsService {
             serde_json::to_string(node).unwrap()
         ));
 
-        let data = serde_json::to_string(node)?;
+        let data = dag_cbor::to_vec(node)?;
 
-        //Reqwest was hacked to properly format multipart request with text ONLY
-        let form = Form::new().text("object data", data);
+        let form = Form::new().part("object data", Part::bytes(data));
 
         let url = self.base_url.join("dag/put")?;
 
-        let res = self.client.post(url).multipart(form).send().await?;
+        let res = self
+            .client
+            .post(url)
+            .query(&[("store-codec", "dag-cbor"), ("input-codec", "dag-cbor")])
+            .multipart(form)
+            .send()
+            .await?;
 
         let res = match res.json::<DagPutResponse>().await {
             Ok(res) => res,
@@ -139,22 +148,44 @@ impl IpfsService {
         Ok(cid)
     }
 
-    /// Deserialize dag node from IPFS path. Return dag node.
+    /// Deserialize dag node from IPFS path, decoded according to the CID codec. Return dag node.
     pub async fn dag_get<U, T>(&self, cid: Cid, path: Option<U>) -> Result<T>
     where
         U: Into<Cow<'static, str>>,
         T: ?Sized + DeserializeOwned,
     {
-        let mut origin = cid.to_string();
-
-        if let Some(path) = path {
-            origin.push_str(&path.into());
-        }
+        let (cid, rem_path) = match path {
+            Some(path) => self.dag_resolve(cid, &path.into()).await?,
+            None => (cid, String::new()),
+        };
 
         #[cfg(debug_assertions)]
-        ConsoleService::info(&format!("IPFS: dag get => {}", origin));
+        ConsoleService::info(&format!("IPFS: dag get => {}/{}", cid, rem_path));
+
+        let url = self.base_url.join("block/get")?;
+
+        let data = self
+            .client
+            .post(url)
+            .query(&[("arg", &cid.to_string())])
+            .send()
+            .await?
+            .bytes()
+            .await?;
+
+        let value = ipfs::decode_block(&cid, &data)?;
+        let value = ipfs::select(value, &rem_path)?;
 
-        let url = self.base_url.join("dag/get")?;
+        let node = serde_json::from_value(value)?;
+
+        Ok(node)
+    }
+
+    /// Follow links along the path. Return the last CID and the path remaining inside it.
+    async fn dag_resolve(&self, cid: Cid, path: &str) -> Result<(Cid, String)> {
+        let origin = format!("{}{}", cid, path);
+
+        let url = self.base_url.join("dag/resolve")?;
 
         let res = self
             .client
@@ -163,9 +194,14 @@ impl IpfsService {
             .send()
             .await?;
 
-        let node = res.json::<T>().await?;
+        let res = match res.json::<DagResolveResponse>().await {
+            Ok(res) => res,
+            Err(e) => return Err(e.into()),
+        };
 
-        Ok(node)
+        let cid = Cid::try_from(res.cid.cid_string)?;
+
+        Ok((cid, res.rem_path))
     }
 
     /// Resolve IPNS link then dag get. Return IPNS link, CID & Node.
@@ -173,6 +209,15 @@ impl IpfsService {
     where
         T: ?Sized + DeserializeOwned,
     {
+        let cid = self.name_resolve(ipns).await?;
+
+        let node = self.dag_get(cid, Option::<&str>::None).await?;
+
+        Ok((cid, node))
+    }
+
+    /// Resolve IPNS link. Return CID.
+    pub async fn name_resolve(&self, ipns: Cid) -> Result<Cid> {
         let url = self.base_url.join("name/resolve")?;
 
         let res = self
@@ -192,9 +237,73 @@ impl IpfsService {
         #[cfg(debug_assertions)]
         ConsoleService::info(&format!("IPFS: name resolve {} \n to {}", ipns, cid));
 
-        let node = self.dag_get(cid, Option::<&str>::None).await?;
+        Ok(cid)
+    }
 
-        Ok((cid, node))
+    pub async fn pin_add(&self, cid: Cid, recursive: bool) -> Result<()> {
+        let url = self.base_url.join("pin/add")?;
+
+        self.client
+            .post(url)
+            .query(&[
+                ("arg", cid.to_string()),
+                ("recursive", recursive.to_string()),
+            ])
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    pub async fn pin_rm(&self, cid: Cid, recursive: bool) -> Result<()> {
+        let url = self.base_url.join("pin/rm")?;
+
+        self.client
+            .post(url)
+            .query(&[
+                ("arg", cid.to_string()),
+                ("recursive", recursive.to_string()),
+            ])
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    /// Return names of the node keys mapped to IPNS addresses.
+    pub async fn key_list(&self) -> Result<HashMap<String, Cid>> {
+        let url = self.base_url.join("key/list")?;
+
+        let res = self.client.post(url).send().await?;
+
+        let res = match res.json::<KeyListResponse>().await {
+            Ok(res) => res,
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut keys = HashMap::with_capacity(res.keys.len());
+
+        for KeyPair { name, id } in res.keys {
+            keys.insert(name, Cid::try_from(id)?);
+        }
+
+        Ok(keys)
+    }
+
+    /// Publish the CID under the IPNS address of this key.
+    pub async fn name_publish(&self, cid: Cid, key: &str) -> Result<()> {
+        let url = self.base_url.join("name/publish")?;
+
+        self.client
+            .post(url)
+            .query(&[("arg", &cid.to_string()), ("key", &key.to_owned())])
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
     }
 
     pub async fn ipfs_node_id(&self) -> Result<String> {
@@ -257,34 +366,38 @@ impl IpfsService {
             .send()
             .await?;
 
-        let stream = response.bytes_stream();
-
-        let line_stream = stream.err_into().into_async_read().lines();
+        let line_stream = response.bytes_stream().err_into().into_async_read().lines();
 
         let mut abortable_stream = Abortable::new(line_stream, regis);
 
         while let Some(line) = abortable_stream.try_next().await? {
-            if let Ok(response) = serde_json::from_str::<PubsubSubResponse>(&line) {
-                let PubsubSubResponse { from, data } = response;
+            match pubsub_message(&line) {
+                Ok(PubSubMessage { from, data }) => cb.emit(Ok((from, data))),
+                Err(e) => cb.emit(Err(e.into())),
+            }
+        }
 
-                let from = Base::decode(&Base::Base64Pad, from)?;
-                let data = Base::decode(&Base::Base64Pad, data)?;
+        Ok(())
+    }
+}
 
-                //This is the most common encoding for PeerIds
-                let from = Base::encode(&Base::Base58Btc, from);
+/// Parse one line of the pubsub/sub output.
+fn pubsub_message(line: &str) -> ipfs::Result<PubSubMessage> {
+    if let Ok(response) = serde_json::from_str::<PubsubSubResponse>(line) {
+        let PubsubSubResponse { from, data } = response;
 
-                cb.emit(Ok((from, data)));
+        let from = Base::decode(&Base::Base64Pad, from).map_err(IpfsError::new)?;
+        let data = Base::decode(&Base::Base64Pad, data).map_err(IpfsError::new)?;
 
-                continue;
-            }
+        //This is the most common encoding for PeerIds
+        let from = Base::encode(&Base::Base58Btc, from);
 
-            let ipfs_error = serde_json::from_str::<IPFSError>(&line)?;
+        return Ok(PubSubMessage { from, data });
+    }
 
-            cb.emit(Err(ipfs_error.into()));
-        }
+    let ipfs_error = serde_json::from_str::<IPFSError>(line)?;
 
-        Ok(())
-    }
+    Err(IpfsError::new(ipfs_error))
 }
 
 #[derive(Deserialize)]
@@ -299,6 +412,15 @@ struct DagPutResponse {
     pub cid: CidString,
 }
 
+#[derive(Deserialize)]
+struct DagResolveResponse {
+    #[serde(rename = "Cid")]
+    pub cid: CidString,
+
+    #[serde(rename = "RemPath")]
+    pub rem_path: String,
+}
+
 #[derive(Deserialize)]
 struct CidString {
     #[serde(rename = "/")]
@@ -311,6 +433,21 @@ struct NameResolveResponse {
     pub path: String,
 }
 
+#[derive(Deserialize)]
+struct KeyListResponse {
+    #[serde(rename = "Keys")]
+    pub keys: Vec<KeyPair>,
+}
+
+#[derive(Deserialize)]
+struct KeyPair {
+    #[serde(rename = "Name")]
+    pub name: String,
+
+    #[serde(rename = "Id")]
+    pub id: String,
+}
+
 #[derive(Deserialize)]
 struct IdResponse {
     #[serde(rename = "ID")]
@@ -339,3 +476,272 @@ impl fmt::Display for IPFSError {
         }
     }
 }
+
+/// Only on WASM since the service is not thread safe.
+#[cfg(target_arch = "wasm32")]
+mod backend {
+    use super::{pubsub_message, IpfsService, KeyPair};
+
+    use std::collections::HashMap;
+    use std::convert::TryFrom;
+
+    use futures::stream::{self, TryStream};
+    use futures_util::{AsyncBufReadExt, StreamExt, TryStreamExt};
+
+    use async_trait::async_trait;
+
+    use linked_data::ipfs::{self, codec_name, IpfsBackend, IpfsError, PubSubStream};
+    use linked_data::{IPNSAddress, PeerId};
+
+    use serde::de::DeserializeOwned;
+    use serde::{Deserialize, Serialize};
+
+    use cid::Cid;
+
+    use reqwest::multipart::{Form, Part};
+
+    #[async_trait(?Send)]
+    impl IpfsBackend for IpfsService {
+        async fn dag_put<T>(&self, node: &T) -> ipfs::Result<Cid>
+        where
+            T: ?Sized + Serialize + Sync,
+        {
+            IpfsService::dag_put(self, node)
+                .await
+                .map_err(IpfsError::new)
+        }
+
+        async fn dag_get<T>(&self, cid: Cid, path: Option<&str>) -> ipfs::Result<T>
+        where
+            T: DeserializeOwned + Send,
+        {
+            let path = path.map(|path| path.to_owned());
+
+            IpfsService::dag_get(self, cid, path)
+                .await
+                .map_err(IpfsError::new)
+        }
+
+        async fn add(&self, data: Vec<u8>) -> ipfs::Result<Cid> {
+            let url = self.base_url.join("add").map_err(IpfsError::new)?;
+
+            let form = Form::new().part("file", Part::bytes(data));
+
+            let res = self
+                .client
+                .post(url)
+                .query(&[("cid-version", "1"), ("pin", "false")])
+                .multipart(form)
+                .send()
+                .await
+                .map_err(IpfsError::new)?;
+
+            let res = res.json::<AddResponse>().await.map_err(IpfsError::new)?;
+
+            let cid = Cid::try_from(res.hash)?;
+
+            Ok(cid)
+        }
+
+        async fn cat(&self, cid: Cid, path: Option<&str>) -> ipfs::Result<Vec<u8>> {
+            let url = self.base_url.join("cat").map_err(IpfsError::new)?;
+
+            let mut origin = cid.to_string();
+
+            if let Some(path) = path {
+                origin.push_str(path);
+            }
+
+            let bytes = self
+                .client
+                .post(url)
+                .query(&[("arg", &origin)])
+                .send()
+                .await
+                .map_err(IpfsError::new)?
+                .bytes()
+                .await
+                .map_err(IpfsError::new)?;
+
+            Ok(bytes.to_vec())
+        }
+
+        async fn block_get(&self, cid: Cid) -> ipfs::Result<Vec<u8>> {
+            let url = self.base_url.join("block/get").map_err(IpfsError::new)?;
+
+            let bytes = self
+                .client
+                .post(url)
+                .query(&[("arg", &cid.to_string())])
+                .send()
+                .await
+                .map_err(IpfsError::new)?
+                .bytes()
+                .await
+                .map_err(IpfsError::new)?;
+
+            Ok(bytes.to_vec())
+        }
+
+        async fn block_put(&self, codec: u64, data: Vec<u8>) -> ipfs::Result<Cid> {
+            let codec = match codec_name(codec) {
+                Some(name) => name,
+                None => return Err(IpfsError::new(format!("unknown codec {:#x}", codec))),
+            };
+
+            let url = self.base_url.join("block/put").map_err(IpfsError::new)?;
+
+            let form = Form::new().part("file", Part::bytes(data));
+
+            let res = self
+                .client
+                .post(url)
+                .query(&[("cid-codec", codec), ("mhtype", "sha2-256")])
+                .multipart(form)
+                .send()
+                .await
+                .map_err(IpfsError::new)?;
+
+            let res = res
+                .json::<BlockPutResponse>()
+                .await
+                .map_err(IpfsError::new)?;
+
+            let cid = Cid::try_from(res.key)?;
+
+            Ok(cid)
+        }
+
+        async fn pin_add(&self, cid: Cid, recursive: bool) -> ipfs::Result<()> {
+            IpfsService::pin_add(self, cid, recursive)
+                .await
+                .map_err(IpfsError::new)
+        }
+
+        async fn pin_rm(&self, cid: Cid, recursive: bool) -> ipfs::Result<()> {
+            IpfsService::pin_rm(self, cid, recursive)
+                .await
+                .map_err(IpfsError::new)
+        }
+
+        async fn pin_ls(&self) -> ipfs::Result<Vec<Cid>> {
+            let url = self.base_url.join("pin/ls").map_err(IpfsError::new)?;
+
+            let res = self
+                .client
+                .post(url)
+                .query(&[("type", "recursive")])
+                .send()
+                .await
+                .map_err(IpfsError::new)?;
+
+            let res = res.json::<PinLsResponse>().await.map_err(IpfsError::new)?;
+
+            let mut pins = Vec::with_capacity(res.keys.len());
+
+            for key in res.keys.into_keys() {
+                pins.push(Cid::try_from(key)?);
+            }
+
+            Ok(pins)
+        }
+
+        async fn key_list(&self) -> ipfs::Result<HashMap<String, IPNSAddress>> {
+            IpfsService::key_list(self).await.map_err(IpfsError::new)
+        }
+
+        async fn key_gen(&self, name: &str) -> ipfs::Result<IPNSAddress> {
+            let url = self.base_url.join("key/gen").map_err(IpfsError::new)?;
+
+            let res = self
+                .client
+                .post(url)
+                .query(&[("arg", name), ("type", "ed25519")])
+                .send()
+                .await
+                .map_err(IpfsError::new)?;
+
+            let res = res.json::<KeyPair>().await.map_err(IpfsError::new)?;
+
+            let address = Cid::try_from(res.id)?;
+
+            Ok(address)
+        }
+
+        async fn name_publish(&self, cid: Cid, key: &str) -> ipfs::Result<()> {
+            IpfsService::name_publish(self, cid, key)
+                .await
+                .map_err(IpfsError::new)
+        }
+
+        async fn name_resolve(&self, ipns: IPNSAddress) -> ipfs::Result<Cid> {
+            IpfsService::name_resolve(self, ipns)
+                .await
+                .map_err(IpfsError::new)
+        }
+
+        async fn peer_id(&self) -> ipfs::Result<PeerId> {
+            self.ipfs_node_id().await.map_err(IpfsError::new)
+        }
+
+        async fn pubsub_pub(&self, topic: &str, msg: &str) -> ipfs::Result<()> {
+            IpfsService::pubsub_pub(self, topic.to_owned(), msg.to_owned())
+                .await
+                .map_err(IpfsError::new)
+        }
+
+        fn pubsub_sub(&self, topic: &str) -> PubSubStream<'_> {
+            let topic = topic.to_owned();
+
+            let stream = stream::once(async move { self.pubsub_lines(&topic).await })
+                .try_flatten()
+                .map(|result| result.and_then(|line| pubsub_message(&line)));
+
+            Box::pin(stream)
+        }
+    }
+
+    impl IpfsService {
+        async fn pubsub_lines(
+            &self,
+            topic: &str,
+        ) -> ipfs::Result<impl TryStream<Ok = String, Error = IpfsError>> {
+            let url = self.base_url.join("pubsub/sub").map_err(IpfsError::new)?;
+
+            let response = self
+                .client
+                .post(url)
+                .query(&[("arg", topic)])
+                .send()
+                .await
+                .map_err(IpfsError::new)?;
+
+            let line_stream = response
+                .bytes_stream()
+                .err_into()
+                .into_async_read()
+                .lines()
+                .map_err(IpfsError::new);
+
+            Ok(line_stream)
+        }
+    }
+
+    #[derive(Deserialize)]
+    struct AddResponse {
+        #[serde(rename = "Hash")]
+        pub hash: String,
+    }
+
+    #[derive(Deserialize)]
+    struct BlockPutResponse {
+        #[serde(rename = "Key")]
+        pub key: String,
+    }
+
+    #[derive(Deserialize)]
+    struct PinLsResponse {
+        #[serde(rename = "Keys")]
+        pub keys: HashMap<String, serde_json::Value>,
+    }
+}
@@ -2,12 +2,15 @@ use std::borrow::Cow;
 use std::convert::TryFrom;
 
 use web3::transports::eip_1193::{Eip1193, Provider};
-use web3::types::Address;
-use web3::Web3;
+use web3::types::{Address, Bytes};
+use web3::{Transport, Web3};
 
 use yew::services::ConsoleService;
 
 use serde::Serialize;
+use serde_json::Value;
+
+use linked_data::eip712::TypedData;
 
 use cid::Cid;
 
@@ -101,6 +104,40 @@ impl Web3Service {
         Ok(sign.to_fixed_bytes())
     }
 
+    //https://docs.metamask.io/guide/signing-data.html#sign-typed-data-v4
+    pub async fn eth_sign_typed_data<T>(&self, addrs: Address, content: &T) -> Result<[u8; 65]>
+    where
+        T: TypedData,
+    {
+        let client = match &self.client {
+            Some(clt) => clt,
+            None => return Err(NoWeb3.into()),
+        };
+
+        let typed_data = serde_json::to_string(&content.typed_data_json())?;
+
+        let params = vec![
+            Value::String(format!("{:?}", addrs)),
+            Value::String(typed_data),
+        ];
+
+        let response = client
+            .transport()
+            .execute("eth_signTypedData_v4", params)
+            .await?;
+
+        let sign: Bytes = serde_json::from_value(response)?;
+
+        if sign.0.len() != 65 {
+            return Err(InvalidSignature.into());
+        }
+
+        let mut signature = [0u8; 65];
+        signature.copy_from_slice(&sign.0);
+
+        Ok(signature)
+    }
+
     //https://eips.ethereum.org/EIPS/eip-181
     pub async fn get_name(&self, addrs: Address) -> Result<String> {
         let client = match &self.client {
@@ -125,6 +162,17 @@ impl std::fmt::Display for NotIPFSStorage {
 
 impl std::error::Error for NotIPFSStorage {}
 
+#[derive(Debug)]
+struct InvalidSignature;
+
+impl std::fmt::Display for InvalidSignature {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Invalid signature length")
+    }
+}
+
+impl std::error::Error for InvalidSignature {}
+
 #[derive(Debug)]
 struct NoWeb3;
 